@@ -0,0 +1,7 @@
+//! Aggregates that accelerate ray intersection tests against large numbers of primitives.
+
+mod bvh;
+mod kdtree;
+
+pub use bvh::*;
+pub use kdtree::*;
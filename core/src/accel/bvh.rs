@@ -0,0 +1,723 @@
+use std::fmt::{self, Display, Formatter};
+use std::mem;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use rayon::prelude::*;
+use crate::bounds::Bounds3;
+use crate::interaction::SurfaceInteraction;
+use crate::material::Material;
+use crate::{Bounds3f, Point3f, Primitive, Ray, Vector3, Vector3f};
+
+/// Strategy used to partition primitives when building a [`BvhAccel`].
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum SplitMethod {
+    /// Splits primitives at the plane that minimizes the surface area heuristic.
+    Sah,
+    /// Splits primitives at the midpoint of their centroid bounds.
+    Middle,
+    /// Splits primitives into two equally sized subsets.
+    EqualCounts,
+    /// Builds a linear BVH from primitives sorted by the Morton codes of their centroids in parallel, and joins the
+    /// resulting treelets with the surface area heuristic. Much faster to build than [`SplitMethod::Sah`] at the
+    /// cost of slightly slower traversal.
+    Hlbvh
+}
+
+impl const Default for SplitMethod {
+    fn default() -> Self {
+        SplitMethod::Sah
+    }
+}
+
+/// Bounding volume hierarchy over a set of primitives.
+///
+/// The tree is flattened into a depth-first ordered array of nodes after construction, so that the first child of
+/// an interior node is stored directly after its parent.
+pub struct BvhAccel {
+    max_prims_in_node: usize,
+    split_method: SplitMethod,
+    primitives: Vec<Arc<dyn Primitive>>,
+    nodes: Vec<LinearBvhNode>,
+    stats: BvhStats
+}
+
+/// Statistics collected while building a [`BvhAccel`].
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct BvhStats {
+    pub build_time: Duration,
+    pub primitives: usize,
+    pub interior_nodes: usize,
+    pub leaf_nodes: usize,
+    pub max_depth: usize,
+    /// Average number of primitives per leaf
+    pub avg_leaf_primitives: f32,
+    /// Sum of the surface area heuristic costs of all nodes, relative to the surface area of the root
+    pub sah_cost: f32,
+    /// Size of the flattened node array in bytes
+    pub node_bytes: usize
+}
+
+impl Display for BvhStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "BVH over {} primitives built in {:.3?}: {} interior nodes, {} leaves ({:.2} primitives/leaf), max depth {}, SAH cost {:.2}, {} KiB",
+            self.primitives,
+            self.build_time,
+            self.interior_nodes,
+            self.leaf_nodes,
+            self.avg_leaf_primitives,
+            self.max_depth,
+            self.sah_cost,
+            self.node_bytes / 1024
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct BvhPrimitiveInfo {
+    primitive_number: usize,
+    bounds: Bounds3f,
+    centroid: Point3f
+}
+
+impl BvhPrimitiveInfo {
+    fn new(primitive_number: usize, bounds: Bounds3f) -> Self {
+        BvhPrimitiveInfo { primitive_number, bounds, centroid: bounds.centroid() }
+    }
+}
+
+enum BvhBuildNode {
+    Leaf {
+        bounds: Bounds3f,
+        first_prim_offset: usize,
+        n_primitives: usize
+    },
+    Interior {
+        bounds: Bounds3f,
+        split_axis: usize,
+        children: [Box<BvhBuildNode>; 2]
+    }
+}
+
+impl BvhBuildNode {
+    fn interior(split_axis: usize, c0: Box<BvhBuildNode>, c1: Box<BvhBuildNode>) -> Box<BvhBuildNode> {
+        let bounds = c0.bounds().union(c1.bounds());
+        Box::new(BvhBuildNode::Interior { bounds, split_axis, children: [c0, c1] })
+    }
+
+    fn bounds(&self) -> &Bounds3f {
+        match self {
+            BvhBuildNode::Leaf { bounds, .. } => bounds,
+            BvhBuildNode::Interior { bounds, .. } => bounds
+        }
+    }
+}
+
+/// Node of the flattened tree, laid out to fit in 32 bytes.
+#[repr(C, align(32))]
+#[derive(Debug, Copy, Clone)]
+struct LinearBvhNode {
+    bounds: Bounds3f,
+    /// Index of the first primitive for leaves, index of the second child for interior nodes
+    offset: u32,
+    n_primitives: u16,
+    axis: u8
+}
+
+const SAH_BUCKETS: usize = 12;
+
+impl BvhAccel {
+    /// Builds a new hierarchy over `primitives`.
+    ///
+    /// `max_prims_in_node` is clamped to 255.
+    pub fn new(primitives: Vec<Arc<dyn Primitive>>, max_prims_in_node: usize, split_method: SplitMethod) -> Self {
+        let start = Instant::now();
+        let mut accel = BvhAccel {
+            max_prims_in_node: max_prims_in_node.clamp(1, 255),
+            split_method,
+            primitives: Vec::new(),
+            nodes: Vec::new(),
+            stats: BvhStats::default()
+        };
+        if primitives.is_empty() {
+            return accel
+        }
+
+        let mut primitive_info: Vec<BvhPrimitiveInfo> = primitives
+            .par_iter()
+            .enumerate()
+            .map(|(i, p)| BvhPrimitiveInfo::new(i, p.world_bound()))
+            .collect();
+        let mut total_nodes = 0;
+        let mut ordered_prims = Vec::with_capacity(primitives.len());
+        let root = match split_method {
+            SplitMethod::Hlbvh => accel.hlbvh_build(&primitive_info, &mut total_nodes, &primitives, &mut ordered_prims),
+            _ => accel.recursive_build(&mut primitive_info, &mut total_nodes, &primitives, &mut ordered_prims)
+        };
+        accel.primitives = ordered_prims;
+
+        accel.nodes = Vec::with_capacity(total_nodes);
+        flatten_bvh_tree(&root, &mut accel.nodes);
+        debug_assert_eq!(accel.nodes.len(), total_nodes);
+        accel.stats = accel.compute_stats(start.elapsed());
+        accel
+    }
+
+    /// Returns statistics about the construction and shape of the hierarchy.
+    #[inline]
+    pub fn stats(&self) -> &BvhStats {
+        &self.stats
+    }
+
+    #[inline]
+    pub fn split_method(&self) -> SplitMethod {
+        self.split_method
+    }
+
+    #[inline]
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn recursive_build(
+        &self,
+        primitive_info: &mut [BvhPrimitiveInfo],
+        total_nodes: &mut usize,
+        primitives: &[Arc<dyn Primitive>],
+        ordered_prims: &mut Vec<Arc<dyn Primitive>>
+    ) -> Box<BvhBuildNode> {
+        *total_nodes += 1;
+        let bounds = primitive_info.iter().fold(Bounds3::empty(), |b, info| b.union(&info.bounds));
+        let n_primitives = primitive_info.len();
+
+        let mut create_leaf = |primitive_info: &[BvhPrimitiveInfo]| {
+            let first_prim_offset = ordered_prims.len();
+            ordered_prims.extend(primitive_info.iter().map(|info| primitives[info.primitive_number].clone()));
+            Box::new(BvhBuildNode::Leaf { bounds, first_prim_offset, n_primitives })
+        };
+
+        if n_primitives == 1 {
+            return create_leaf(primitive_info)
+        }
+
+        let centroid_bounds = primitive_info
+            .iter()
+            .fold(Bounds3::empty(), |b, info| b.union_point(&info.centroid));
+        let dim = centroid_bounds.maximum_extent() as usize;
+        // If all centroids are at the same position, splitting would not help unless the leaf gets too large
+        let coincident = centroid_bounds.max[dim] == centroid_bounds.min[dim];
+        if coincident && n_primitives <= self.max_prims_in_node {
+            return create_leaf(primitive_info)
+        }
+
+        let mid = match self.split_method {
+            _ if coincident => n_primitives / 2,
+            SplitMethod::Middle => {
+                let p_mid = 0.5 * (centroid_bounds.min[dim] + centroid_bounds.max[dim]);
+                let mid = partition(primitive_info, |info| info.centroid[dim] < p_mid);
+                if mid == 0 || mid == n_primitives {
+                    split_equal_counts(primitive_info, dim)
+                } else {
+                    mid
+                }
+            },
+            SplitMethod::EqualCounts => split_equal_counts(primitive_info, dim),
+            SplitMethod::Sah | SplitMethod::Hlbvh => {
+                if n_primitives <= 2 {
+                    split_equal_counts(primitive_info, dim)
+                } else {
+                    match sah_split(primitive_info, &bounds, &centroid_bounds, dim, self.max_prims_in_node) {
+                        Some(mid) => mid,
+                        None => return create_leaf(primitive_info)
+                    }
+                }
+            }
+        };
+
+        let (left, right) = primitive_info.split_at_mut(mid);
+        BvhBuildNode::interior(
+            dim,
+            self.recursive_build(left, total_nodes, primitives, ordered_prims),
+            self.recursive_build(right, total_nodes, primitives, ordered_prims)
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct MortonPrimitive {
+    primitive_index: usize,
+    morton_code: u32
+}
+
+const MORTON_BITS: u32 = 10;
+/// Number of high Morton code bits that determine the treelet a primitive falls into
+const TREELET_BITS: u32 = 12;
+
+impl BvhAccel {
+    fn hlbvh_build(
+        &self,
+        primitive_info: &[BvhPrimitiveInfo],
+        total_nodes: &mut usize,
+        primitives: &[Arc<dyn Primitive>],
+        ordered_prims: &mut Vec<Arc<dyn Primitive>>
+    ) -> Box<BvhBuildNode> {
+        let bounds = primitive_info
+            .par_iter()
+            .fold(Bounds3f::empty, |b, info| b.union_point(&info.centroid))
+            .reduce(Bounds3f::empty, |a, b| a.union(&b));
+
+        // Quantize the centroids to a 2^10 grid and sort them along the Z-order curve
+        let morton_scale = (1 << MORTON_BITS) as f32;
+        let mut morton_prims: Vec<MortonPrimitive> = primitive_info
+            .par_iter()
+            .map(|info| {
+                let offset = bounds.offset(&info.centroid) * morton_scale;
+                MortonPrimitive { primitive_index: info.primitive_number, morton_code: encode_morton3(&offset) }
+            })
+            .collect();
+        morton_prims.par_sort_unstable_by_key(|p| p.morton_code);
+
+        // Find the intervals of primitives that share the same treelet
+        let mask = ((1 << TREELET_BITS) - 1) << (3 * MORTON_BITS - TREELET_BITS);
+        let mut treelets = Vec::new();
+        let mut start = 0;
+        for end in 1..=morton_prims.len() {
+            if end == morton_prims.len() || (morton_prims[start].morton_code & mask) != (morton_prims[end].morton_code & mask) {
+                treelets.push(start..end);
+                start = end;
+            }
+        }
+
+        // Build the treelets in parallel. Leaves emit primitives in Morton order, so every primitive's position in
+        // the ordered primitive list is its position in the sorted array.
+        let first_bit_index = (3 * MORTON_BITS - TREELET_BITS) as i32 - 1;
+        let treelet_roots: Vec<(Box<BvhBuildNode>, usize)> = treelets
+            .into_par_iter()
+            .map(|range| {
+                let mut nodes = 0;
+                let root = self.emit_lbvh(primitive_info, &morton_prims[range.clone()], range.start, &mut nodes, first_bit_index);
+                (root, nodes)
+            })
+            .collect();
+        ordered_prims.extend(morton_prims.iter().map(|p| primitives[p.primitive_index].clone()));
+
+        let mut roots = Vec::with_capacity(treelet_roots.len());
+        for (root, nodes) in treelet_roots {
+            *total_nodes += nodes;
+            roots.push(*root);
+        }
+        build_upper_sah(roots, total_nodes)
+    }
+
+    fn emit_lbvh(
+        &self,
+        primitive_info: &[BvhPrimitiveInfo],
+        morton_prims: &[MortonPrimitive],
+        first_prim_offset: usize,
+        total_nodes: &mut usize,
+        bit_index: i32
+    ) -> Box<BvhBuildNode> {
+        let n_primitives = morton_prims.len();
+        if n_primitives < self.max_prims_in_node || (bit_index == -1 && n_primitives <= self.max_prims_in_node) {
+            *total_nodes += 1;
+            let bounds = morton_prims
+                .iter()
+                .fold(Bounds3f::empty(), |b, p| b.union(&primitive_info[p.primitive_index].bounds));
+            return Box::new(BvhBuildNode::Leaf { bounds, first_prim_offset, n_primitives })
+        }
+
+        let split = if bit_index == -1 {
+            // The primitives share their Morton code, so split them evenly to keep the leaves small
+            n_primitives / 2
+        } else {
+            let mask = 1 << bit_index;
+            if (morton_prims[0].morton_code & mask) == (morton_prims[n_primitives - 1].morton_code & mask) {
+                // All primitives lie on the same side of this split plane
+                return self.emit_lbvh(primitive_info, morton_prims, first_prim_offset, total_nodes, bit_index - 1)
+            }
+            // Find the first primitive on the far side of the split plane
+            morton_prims.partition_point(|p| p.morton_code & mask == morton_prims[0].morton_code & mask)
+        };
+        *total_nodes += 1;
+        let (left, right) = morton_prims.split_at(split);
+        let next_bit = (bit_index - 1).max(-1);
+        BvhBuildNode::interior(
+            bit_index.rem_euclid(3) as usize,
+            self.emit_lbvh(primitive_info, left, first_prim_offset, total_nodes, next_bit),
+            self.emit_lbvh(primitive_info, right, first_prim_offset + split, total_nodes, next_bit)
+        )
+    }
+
+    fn compute_stats(&self, build_time: Duration) -> BvhStats {
+        fn visit(accel: &BvhAccel, index: usize, depth: usize, stats: &mut BvhStats) {
+            let node = &accel.nodes[index];
+            stats.max_depth = stats.max_depth.max(depth);
+            stats.sah_cost += node.bounds.surface_area() * if node.n_primitives > 0 { node.n_primitives as f32 } else { 0.125 };
+            if node.n_primitives > 0 {
+                stats.leaf_nodes += 1;
+            } else {
+                stats.interior_nodes += 1;
+                visit(accel, index + 1, depth + 1, stats);
+                visit(accel, node.offset as usize, depth + 1, stats);
+            }
+        }
+
+        let mut stats = BvhStats {
+            build_time,
+            primitives: self.primitives.len(),
+            node_bytes: self.nodes.len() * mem::size_of::<LinearBvhNode>(),
+            ..Default::default()
+        };
+        if !self.nodes.is_empty() {
+            visit(self, 0, 0, &mut stats);
+            stats.avg_leaf_primitives = stats.primitives as f32 / stats.leaf_nodes as f32;
+            let root_area = self.nodes[0].bounds.surface_area();
+            if root_area > 0.0 {
+                stats.sah_cost /= root_area;
+            }
+        }
+        stats
+    }
+}
+
+/// Spreads the lower 10 bits of `x` so that there are two zero bits between each of them.
+#[inline]
+fn left_shift3(mut x: u32) -> u32 {
+    debug_assert!(x <= 1 << MORTON_BITS);
+    if x == 1 << MORTON_BITS {
+        x -= 1;
+    }
+    x = (x | (x << 16)) & 0b00000011000000000000000011111111;
+    x = (x | (x << 8)) & 0b00000011000000001111000000001111;
+    x = (x | (x << 4)) & 0b00000011000011000011000011000011;
+    x = (x | (x << 2)) & 0b00001001001001001001001001001001;
+    x
+}
+
+#[inline]
+fn encode_morton3(v: &Vector3f) -> u32 {
+    (left_shift3(v.z as u32) << 2) | (left_shift3(v.y as u32) << 1) | left_shift3(v.x as u32)
+}
+
+/// Joins the treelet roots into a single tree, splitting them with the surface area heuristic.
+fn build_upper_sah(mut roots: Vec<BvhBuildNode>, total_nodes: &mut usize) -> Box<BvhBuildNode> {
+    if roots.len() == 1 {
+        return Box::new(roots.pop().unwrap())
+    }
+    *total_nodes += 1;
+
+    let bounds = roots.iter().fold(Bounds3f::empty(), |b, node| b.union(node.bounds()));
+    let centroid_bounds = roots.iter().fold(Bounds3f::empty(), |b, node| b.union_point(&node.bounds().centroid()));
+    let dim = centroid_bounds.maximum_extent() as usize;
+
+    let bucket_index = |node: &BvhBuildNode| {
+        let b = (SAH_BUCKETS as f32 * centroid_bounds.offset(&node.bounds().centroid())[dim]) as usize;
+        b.min(SAH_BUCKETS - 1)
+    };
+    let mut counts = [0usize; SAH_BUCKETS];
+    let mut bucket_bounds = [Bounds3f::empty(); SAH_BUCKETS];
+    for node in &roots {
+        let b = bucket_index(node);
+        counts[b] += 1;
+        bucket_bounds[b] = bucket_bounds[b].union(node.bounds());
+    }
+    let mut min_cost = f32::INFINITY;
+    let mut min_cost_bucket = 0;
+    for i in 0..SAH_BUCKETS - 1 {
+        let (b0, count0) = (0..=i).fold((Bounds3f::empty(), 0), |(b, c), j| (b.union(&bucket_bounds[j]), c + counts[j]));
+        let (b1, count1) = (i + 1..SAH_BUCKETS).fold((Bounds3f::empty(), 0), |(b, c), j| (b.union(&bucket_bounds[j]), c + counts[j]));
+        let area0 = if count0 > 0 { b0.surface_area() } else { 0.0 };
+        let area1 = if count1 > 0 { b1.surface_area() } else { 0.0 };
+        let cost = 0.125 + (count0 as f32 * area0 + count1 as f32 * area1) / bounds.surface_area();
+        if cost < min_cost {
+            min_cost = cost;
+            min_cost_bucket = i;
+        }
+    }
+
+    let mut mid = partition(&mut roots, |node| bucket_index(node) <= min_cost_bucket);
+    if mid == 0 || mid == roots.len() {
+        // Degenerate centroid distribution, fall back to an even split
+        mid = roots.len() / 2;
+    }
+    let right = roots.split_off(mid);
+    BvhBuildNode::interior(dim, build_upper_sah(roots, total_nodes), build_upper_sah(right, total_nodes))
+}
+
+/// Partitions the primitives around their median centroid along `dim` and returns the index of the split.
+fn split_equal_counts(primitive_info: &mut [BvhPrimitiveInfo], dim: usize) -> usize {
+    let mid = primitive_info.len() / 2;
+    // Non-finite bounds can produce NaN centroids, which the total order sorts to the ends
+    primitive_info.select_nth_unstable_by(mid, |a, b| a.centroid[dim].total_cmp(&b.centroid[dim]));
+    mid
+}
+
+/// Finds the split with the lowest cost according to the surface area heuristic by binning centroids into buckets.
+///
+/// Returns `None` if creating a leaf is cheaper than any split.
+fn sah_split(
+    primitive_info: &mut [BvhPrimitiveInfo],
+    bounds: &Bounds3f,
+    centroid_bounds: &Bounds3f,
+    dim: usize,
+    max_prims_in_node: usize
+) -> Option<usize> {
+    let bucket_index = |info: &BvhPrimitiveInfo| {
+        let b = (SAH_BUCKETS as f32 * centroid_bounds.offset(&info.centroid)[dim]) as usize;
+        b.min(SAH_BUCKETS - 1)
+    };
+
+    let mut counts = [0usize; SAH_BUCKETS];
+    let mut bucket_bounds = [Bounds3f::empty(); SAH_BUCKETS];
+    for info in primitive_info.iter() {
+        let b = bucket_index(info);
+        counts[b] += 1;
+        bucket_bounds[b] = bucket_bounds[b].union(&info.bounds);
+    }
+
+    // Cost of splitting after each bucket, relative to the cost of a primitive intersection
+    let mut min_cost = f32::INFINITY;
+    let mut min_cost_bucket = 0;
+    for i in 0..SAH_BUCKETS - 1 {
+        let (b0, count0) = (0..=i).fold((Bounds3f::empty(), 0), |(b, c), j| (b.union(&bucket_bounds[j]), c + counts[j]));
+        let (b1, count1) = (i + 1..SAH_BUCKETS).fold((Bounds3f::empty(), 0), |(b, c), j| (b.union(&bucket_bounds[j]), c + counts[j]));
+        let area0 = if count0 > 0 { b0.surface_area() } else { 0.0 };
+        let area1 = if count1 > 0 { b1.surface_area() } else { 0.0 };
+        let cost = 0.125 + (count0 as f32 * area0 + count1 as f32 * area1) / bounds.surface_area();
+        if cost < min_cost {
+            min_cost = cost;
+            min_cost_bucket = i;
+        }
+    }
+
+    let leaf_cost = primitive_info.len() as f32;
+    if primitive_info.len() > max_prims_in_node || min_cost < leaf_cost {
+        Some(partition(primitive_info, |info| bucket_index(info) <= min_cost_bucket))
+    } else {
+        None
+    }
+}
+
+/// Reorders the slice so that all elements satisfying the predicate precede those that do not,
+/// and returns the number of elements satisfying it.
+pub(crate) fn partition<T, F: FnMut(&T) -> bool>(slice: &mut [T], mut pred: F) -> usize {
+    let mut first = 0;
+    for i in 0..slice.len() {
+        if pred(&slice[i]) {
+            slice.swap(first, i);
+            first += 1;
+        }
+    }
+    first
+}
+
+fn flatten_bvh_tree(node: &BvhBuildNode, nodes: &mut Vec<LinearBvhNode>) -> usize {
+    let offset = nodes.len();
+    match node {
+        BvhBuildNode::Leaf { bounds, first_prim_offset, n_primitives } => {
+            // Leaves are split during the build to hold at most `max_prims_in_node` primitives
+            assert!(*n_primitives <= u16::MAX as usize, "BVH leaf with {} primitives", n_primitives);
+            nodes.push(LinearBvhNode {
+                bounds: *bounds,
+                offset: *first_prim_offset as u32,
+                n_primitives: *n_primitives as u16,
+                axis: 0
+            });
+        },
+        BvhBuildNode::Interior { bounds, split_axis, children } => {
+            nodes.push(LinearBvhNode {
+                bounds: *bounds,
+                offset: 0,
+                n_primitives: 0,
+                axis: *split_axis as u8
+            });
+            flatten_bvh_tree(&children[0], nodes);
+            let second_child_offset = flatten_bvh_tree(&children[1], nodes);
+            nodes[offset].offset = second_child_offset as u32;
+        }
+    }
+    offset
+}
+
+impl BvhAccel {
+    /// Visits the nodes whose bounds are intersected by the ray in front-to-back order and calls `visit` with the
+    /// primitives of each leaf. Traversal stops early when `visit` returns `true`.
+    #[inline]
+    fn traverse<F: FnMut(&[Arc<dyn Primitive>]) -> bool>(&self, ray: &Ray, mut visit: F) {
+        if self.nodes.is_empty() {
+            return
+        }
+        let inv_dir = Vector3::new(1.0 / ray.d.x, 1.0 / ray.d.y, 1.0 / ray.d.z);
+        let dir_is_neg = [(inv_dir.x < 0.0) as usize, (inv_dir.y < 0.0) as usize, (inv_dir.z < 0.0) as usize];
+
+        let mut to_visit = [0usize; 64];
+        let mut to_visit_offset = 0;
+        let mut current = 0;
+        loop {
+            let node = &self.nodes[current];
+            if node.bounds.intersect_p_with_inv_dir(ray, &inv_dir, dir_is_neg) {
+                if node.n_primitives > 0 {
+                    let first = node.offset as usize;
+                    if visit(&self.primitives[first..first + node.n_primitives as usize]) {
+                        return
+                    }
+                    if to_visit_offset == 0 {
+                        return
+                    }
+                    to_visit_offset -= 1;
+                    current = to_visit[to_visit_offset];
+                } else if dir_is_neg[node.axis as usize] == 1 {
+                    // Visit the second child first
+                    to_visit[to_visit_offset] = current + 1;
+                    to_visit_offset += 1;
+                    current = node.offset as usize;
+                } else {
+                    to_visit[to_visit_offset] = node.offset as usize;
+                    to_visit_offset += 1;
+                    current += 1;
+                }
+            } else {
+                if to_visit_offset == 0 {
+                    return
+                }
+                to_visit_offset -= 1;
+                current = to_visit[to_visit_offset];
+            }
+        }
+    }
+}
+
+impl Primitive for BvhAccel {
+    fn world_bound(&self) -> Bounds3f {
+        self.nodes.first().map_or(Bounds3f::empty(), |root| root.bounds)
+    }
+
+    fn intersect(&self, r: &Ray, interaction: &mut SurfaceInteraction) -> bool {
+        let mut hit = false;
+        self.traverse(r, |primitives| {
+            for primitive in primitives {
+                // Primitives shrink `r.tmax` on a hit, culling nodes behind the closest hit found so far
+                hit |= primitive.intersect(r, interaction);
+            }
+            false
+        });
+        hit
+    }
+
+    fn intersect_p(&self, r: &Ray) -> bool {
+        let mut hit = false;
+        self.traverse(r, |primitives| {
+            hit = primitives.iter().any(|primitive| primitive.intersect_p(r));
+            hit
+        });
+        hit
+    }
+
+    /// Aggregates have no material of their own, the intersected primitive sets it on the interaction instead.
+    fn material(&self) -> Option<&dyn Material> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitive::GeometricPrimitive;
+    use crate::shape::Sphere;
+    use crate::{Point3, Transform, vec3};
+
+    fn spheres() -> Vec<Arc<dyn Primitive>> {
+        let mut primitives: Vec<Arc<dyn Primitive>> = Vec::new();
+        for i in 0..10 {
+            for j in 0..10 {
+                let sphere = Sphere::full(Transform::translate(vec3(i as f32 * 3.0, j as f32 * 3.0, 10.0)), false, 1.0);
+                primitives.push(Arc::new(GeometricPrimitive::new(Arc::new(sphere), None)));
+            }
+        }
+        primitives
+    }
+
+    #[test]
+    fn test_bvh_matches_linear_search() {
+        for split_method in [SplitMethod::Sah, SplitMethod::Middle, SplitMethod::EqualCounts, SplitMethod::Hlbvh] {
+            let primitives = spheres();
+            let bvh = BvhAccel::new(primitives.clone(), 4, split_method);
+            for i in 0..30 {
+                let o = Point3::new(i as f32 * 0.9 - 0.5, i as f32 * 0.7, 0.0);
+                let d = vec3(0.05 * i as f32, 0.02, 1.0);
+
+                let ray = Ray::new(o, d);
+                let mut interaction = SurfaceInteraction::default();
+                let hit = bvh.intersect(&ray, &mut interaction);
+
+                let expected = Ray::new(o, d);
+                let mut expected_interaction = SurfaceInteraction::default();
+                let expected_hit = primitives.iter().fold(false, |hit, p| p.intersect(&expected, &mut expected_interaction) | hit);
+
+                assert_eq!(hit, expected_hit);
+                assert_eq!(bvh.intersect_p(&Ray::new(o, d)), expected_hit);
+                if hit {
+                    assert_eq!(ray.tmax.get(), expected.tmax.get());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bvh_stats() {
+        for split_method in [SplitMethod::Sah, SplitMethod::Hlbvh] {
+            let bvh = BvhAccel::new(spheres(), 4, split_method);
+            let stats = bvh.stats();
+            assert_eq!(stats.primitives, 100);
+            assert_eq!(stats.interior_nodes + stats.leaf_nodes, bvh.node_count());
+            assert_eq!(stats.leaf_nodes, stats.interior_nodes + 1);
+        }
+    }
+
+    #[test]
+    fn test_coincident_primitives_are_split() {
+        let primitives: Vec<Arc<dyn Primitive>> = (0..20)
+            .map(|_| {
+                let sphere = Sphere::full(Transform::translate(vec3(0.0, 0.0, 10.0)), false, 1.0);
+                Arc::new(GeometricPrimitive::new(Arc::new(sphere), None)) as Arc<dyn Primitive>
+            })
+            .collect();
+        for split_method in [SplitMethod::Sah, SplitMethod::Middle, SplitMethod::EqualCounts, SplitMethod::Hlbvh] {
+            let bvh = BvhAccel::new(primitives.clone(), 4, split_method);
+            assert!(bvh.nodes.iter().all(|node| node.n_primitives <= 4));
+            assert_eq!(bvh.stats().primitives, 20);
+            let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0));
+            assert!(bvh.intersect(&ray, &mut SurfaceInteraction::default()));
+            assert!((ray.tmax.get() - 9.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_morton_code_interleaves_bits() {
+        assert_eq!(encode_morton3(&vec3(1.0, 0.0, 0.0)), 0b001);
+        assert_eq!(encode_morton3(&vec3(0.0, 1.0, 0.0)), 0b010);
+        assert_eq!(encode_morton3(&vec3(0.0, 0.0, 1.0)), 0b100);
+        assert_eq!(encode_morton3(&vec3(3.0, 0.0, 0.0)), 0b001001);
+    }
+
+    #[test]
+    fn test_bvh_world_bound() {
+        let bvh = BvhAccel::new(spheres(), 4, SplitMethod::Sah);
+        let bounds = bvh.world_bound();
+        assert_eq!(bounds.min, Point3::new(-1.0, -1.0, 9.0));
+        assert_eq!(bounds.max, Point3::new(28.0, 28.0, 11.0));
+    }
+
+    #[test]
+    fn test_split_equal_counts_orders_nan_centroids() {
+        let mut primitive_info: Vec<BvhPrimitiveInfo> = [2.0, 0.0, 1.0, 3.0].iter().enumerate().map(|(i, &x)| {
+            BvhPrimitiveInfo::new(i, Bounds3f::from((Point3::new(x, 0.0, 0.0), Point3::new(x + 1.0, 1.0, 1.0))))
+        }).collect();
+        // Bounds with infinite extent have a NaN centroid
+        primitive_info[3].centroid = Point3 { x: f32::NAN, y: 0.5, z: 0.5 };
+        let mid = split_equal_counts(&mut primitive_info, 0);
+        assert_eq!(mid, 2);
+        assert!(primitive_info[..mid].iter().all(|info| info.centroid.x < 2.0));
+    }
+}
@@ -0,0 +1,376 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+use crate::bounds::{Axis3, Bounds3};
+use crate::interaction::SurfaceInteraction;
+use crate::material::Material;
+use crate::{Bounds3f, Primitive, Ray, Vector3};
+
+/// Kd-tree over a set of primitives, built with the surface area heuristic.
+///
+/// Unlike a BVH, the kd-tree partitions space rather than primitives, so primitives that straddle a split plane
+/// are referenced from both sides.
+pub struct KdTreeAccel {
+    isect_cost: f32,
+    traversal_cost: f32,
+    empty_bonus: f32,
+    max_prims: usize,
+    primitives: Vec<Arc<dyn Primitive>>,
+    primitive_indices: Vec<u32>,
+    nodes: Vec<KdAccelNode>,
+    bounds: Bounds3f
+}
+
+#[derive(Debug, Copy, Clone)]
+enum KdAccelNode {
+    Leaf {
+        /// Index of the first primitive of the leaf in `primitive_indices`
+        first: u32,
+        n_prims: u32
+    },
+    /// The child below the split plane is stored directly after its parent.
+    Interior {
+        axis: Axis3,
+        split: f32,
+        above_child: u32
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
+enum EdgeType {
+    Start,
+    End
+}
+
+#[derive(Debug, Copy, Clone)]
+struct BoundEdge {
+    t: f32,
+    prim_num: u32,
+    edge_type: EdgeType
+}
+
+#[derive(Debug, Copy, Clone)]
+struct KdToDo {
+    node: usize,
+    t_min: f32,
+    t_max: f32
+}
+
+const MAX_TODO: usize = 64;
+
+impl KdTreeAccel {
+    /// Builds a new kd-tree over `primitives`.
+    ///
+    /// `isect_cost` and `traversal_cost` are the estimated relative costs of a primitive intersection and of a node
+    /// traversal step, and `empty_bonus` is the fraction by which the cost of a split that leaves one side empty is
+    /// reduced. If `max_depth` is `None`, it is derived from the number of primitives. The depth is limited to
+    /// `MAX_TODO`, which bounds the nodes that traversal defers.
+    pub fn new(
+        primitives: Vec<Arc<dyn Primitive>>,
+        isect_cost: f32,
+        traversal_cost: f32,
+        empty_bonus: f32,
+        max_prims: usize,
+        max_depth: Option<usize>
+    ) -> Self {
+        let n = primitives.len();
+        let max_depth = max_depth.unwrap_or_else(|| {
+            let log2 = if n > 0 { (usize::BITS - 1 - n.leading_zeros()) as f32 } else { 0.0 };
+            (8.0 + 1.3 * log2).round() as usize
+        }).min(MAX_TODO);
+
+        let prim_bounds: Vec<Bounds3f> = primitives.iter().map(|p| p.world_bound()).collect();
+        let bounds = prim_bounds.iter().fold(Bounds3::empty(), |b, pb| b.union(pb));
+
+        let mut accel = KdTreeAccel {
+            isect_cost,
+            traversal_cost,
+            empty_bonus,
+            max_prims,
+            primitives,
+            primitive_indices: Vec::new(),
+            nodes: Vec::new(),
+            bounds
+        };
+        if n > 0 {
+            let mut edges = [Vec::with_capacity(2 * n), Vec::with_capacity(2 * n), Vec::with_capacity(2 * n)];
+            let prim_nums: Vec<u32> = (0..n as u32).collect();
+            accel.build_tree(&bounds, &prim_bounds, &prim_nums, max_depth, &mut edges, 0);
+        }
+        accel
+    }
+
+    /// Creates a kd-tree with the default cost model.
+    pub fn with_defaults(primitives: Vec<Arc<dyn Primitive>>) -> Self {
+        Self::new(primitives, 80.0, 1.0, 0.5, 1, None)
+    }
+
+    #[inline]
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn push_leaf(&mut self, prim_nums: &[u32]) {
+        self.nodes.push(KdAccelNode::Leaf {
+            first: self.primitive_indices.len() as u32,
+            n_prims: prim_nums.len() as u32
+        });
+        self.primitive_indices.extend_from_slice(prim_nums);
+    }
+
+    fn build_tree(
+        &mut self,
+        node_bounds: &Bounds3f,
+        all_prim_bounds: &[Bounds3f],
+        prim_nums: &[u32],
+        depth: usize,
+        edges: &mut [Vec<BoundEdge>; 3],
+        mut bad_refines: u32
+    ) {
+        let n_primitives = prim_nums.len();
+        if n_primitives <= self.max_prims || depth == 0 {
+            return self.push_leaf(prim_nums)
+        }
+
+        // Choose the split with the lowest cost, trying the axis with the largest extent first
+        let mut best: Option<(Axis3, usize)> = None;
+        let mut best_cost = f32::INFINITY;
+        let old_cost = self.isect_cost * n_primitives as f32;
+        let inv_total_sa = 1.0 / node_bounds.surface_area();
+        let d = node_bounds.diagonal();
+        let mut axis = node_bounds.maximum_extent();
+        for _ in 0..3 {
+            let a = axis as usize;
+            let axis_edges = &mut edges[a];
+            axis_edges.clear();
+            for &prim_num in prim_nums {
+                let bounds = &all_prim_bounds[prim_num as usize];
+                axis_edges.push(BoundEdge { t: bounds.min[a], prim_num, edge_type: EdgeType::Start });
+                axis_edges.push(BoundEdge { t: bounds.max[a], prim_num, edge_type: EdgeType::End });
+            }
+            axis_edges.sort_unstable_by(|e0, e1| {
+                e0.t.partial_cmp(&e1.t).unwrap_or(Ordering::Equal).then(e0.edge_type.cmp(&e1.edge_type))
+            });
+
+            // Sweep over the edges to compute the cost of splitting at each of them
+            let mut n_below = 0;
+            let mut n_above = n_primitives;
+            let (other0, other1) = ((a + 1) % 3, (a + 2) % 3);
+            for (i, edge) in axis_edges.iter().enumerate() {
+                if edge.edge_type == EdgeType::End {
+                    n_above -= 1;
+                }
+                let edge_t = edge.t;
+                if edge_t > node_bounds.min[a] && edge_t < node_bounds.max[a] {
+                    let below_sa = 2.0 * (d[other0] * d[other1] + (edge_t - node_bounds.min[a]) * (d[other0] + d[other1]));
+                    let above_sa = 2.0 * (d[other0] * d[other1] + (node_bounds.max[a] - edge_t) * (d[other0] + d[other1]));
+                    let p_below = below_sa * inv_total_sa;
+                    let p_above = above_sa * inv_total_sa;
+                    let eb = if n_above == 0 || n_below == 0 { self.empty_bonus } else { 0.0 };
+                    let cost = self.traversal_cost +
+                        self.isect_cost * (1.0 - eb) * (p_below * n_below as f32 + p_above * n_above as f32);
+                    if cost < best_cost {
+                        best_cost = cost;
+                        best = Some((axis, i));
+                    }
+                }
+                if edge.edge_type == EdgeType::Start {
+                    n_below += 1;
+                }
+            }
+            debug_assert!(n_below == n_primitives && n_above == 0);
+
+            if best.is_some() {
+                break
+            }
+            // No useful split along this axis, try the next one
+            axis = axis.next();
+        }
+
+        if best_cost > old_cost {
+            bad_refines += 1;
+        }
+        let (best_axis, best_offset) = match best {
+            Some(best) if !((best_cost > 4.0 * old_cost && n_primitives < 16) || bad_refines == 3) => best,
+            _ => return self.push_leaf(prim_nums)
+        };
+
+        // Classify the primitives with respect to the split
+        let split_edges = &edges[best_axis as usize];
+        let prims0: Vec<u32> = split_edges[..best_offset]
+            .iter()
+            .filter(|e| e.edge_type == EdgeType::Start)
+            .map(|e| e.prim_num)
+            .collect();
+        let prims1: Vec<u32> = split_edges[best_offset + 1..]
+            .iter()
+            .filter(|e| e.edge_type == EdgeType::End)
+            .map(|e| e.prim_num)
+            .collect();
+
+        let t_split = split_edges[best_offset].t;
+        let (mut bounds0, mut bounds1) = (*node_bounds, *node_bounds);
+        bounds0.max[best_axis as usize] = t_split;
+        bounds1.min[best_axis as usize] = t_split;
+
+        let node_num = self.nodes.len();
+        self.nodes.push(KdAccelNode::Interior { axis: best_axis, split: t_split, above_child: 0 });
+        self.build_tree(&bounds0, all_prim_bounds, &prims0, depth - 1, edges, bad_refines);
+        let above = self.nodes.len() as u32;
+        if let KdAccelNode::Interior { above_child, .. } = &mut self.nodes[node_num] {
+            *above_child = above;
+        }
+        self.build_tree(&bounds1, all_prim_bounds, &prims1, depth - 1, edges, bad_refines);
+    }
+
+    /// Visits the leaves along the ray in front-to-back order and calls `visit` with the primitive indices of each
+    /// leaf. Traversal stops early when `visit` returns `true`.
+    #[inline]
+    fn traverse<F: FnMut(&[u32]) -> bool>(&self, ray: &Ray, mut visit: F) {
+        if self.nodes.is_empty() {
+            return
+        }
+        let (mut t_min, mut t_max) = match self.bounds.intersect_p(ray) {
+            Some(range) => range,
+            None => return
+        };
+        let inv_dir = Vector3::new(1.0 / ray.d.x, 1.0 / ray.d.y, 1.0 / ray.d.z);
+
+        let mut todo = [KdToDo { node: 0, t_min: 0.0, t_max: 0.0 }; MAX_TODO];
+        let mut todo_pos = 0;
+        let mut node = 0;
+        loop {
+            // Stop once the closest hit found so far is in front of the current node
+            if ray.tmax.get() < t_min {
+                return
+            }
+            match self.nodes[node] {
+                KdAccelNode::Interior { axis, split, above_child } => {
+                    let a = axis as usize;
+                    let t_plane = (split - ray.o[a]) * inv_dir[a];
+                    let below_first = ray.o[a] < split || (ray.o[a] == split && ray.d[a] <= 0.0);
+                    let (first, second) = if below_first {
+                        (node + 1, above_child as usize)
+                    } else {
+                        (above_child as usize, node + 1)
+                    };
+                    if t_plane > t_max || t_plane <= 0.0 {
+                        node = first;
+                    } else if t_plane < t_min {
+                        node = second;
+                    } else {
+                        todo[todo_pos] = KdToDo { node: second, t_min: t_plane, t_max };
+                        todo_pos += 1;
+                        node = first;
+                        t_max = t_plane;
+                    }
+                },
+                KdAccelNode::Leaf { first, n_prims } => {
+                    if visit(&self.primitive_indices[first as usize..(first + n_prims) as usize]) {
+                        return
+                    }
+                    if todo_pos == 0 {
+                        return
+                    }
+                    todo_pos -= 1;
+                    KdToDo { node, t_min, t_max } = todo[todo_pos];
+                }
+            }
+        }
+    }
+}
+
+impl Primitive for KdTreeAccel {
+    fn world_bound(&self) -> Bounds3f {
+        self.bounds
+    }
+
+    fn intersect(&self, r: &Ray, interaction: &mut SurfaceInteraction) -> bool {
+        let mut hit = false;
+        self.traverse(r, |indices| {
+            for &i in indices {
+                hit |= self.primitives[i as usize].intersect(r, interaction);
+            }
+            false
+        });
+        hit
+    }
+
+    fn intersect_p(&self, r: &Ray) -> bool {
+        let mut hit = false;
+        self.traverse(r, |indices| {
+            hit = indices.iter().any(|&i| self.primitives[i as usize].intersect_p(r));
+            hit
+        });
+        hit
+    }
+
+    /// Aggregates have no material of their own, the intersected primitive sets it on the interaction instead.
+    fn material(&self) -> Option<&dyn Material> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitive::GeometricPrimitive;
+    use crate::shape::Sphere;
+    use crate::{Point3, Transform, vec3};
+
+    fn spheres() -> Vec<Arc<dyn Primitive>> {
+        let mut primitives: Vec<Arc<dyn Primitive>> = Vec::new();
+        for i in 0..10 {
+            for j in 0..10 {
+                let sphere = Sphere::full(Transform::translate(vec3(i as f32 * 3.0, j as f32 * 3.0, (i + j) as f32)), false, 1.0);
+                primitives.push(Arc::new(GeometricPrimitive::new(Arc::new(sphere), None)));
+            }
+        }
+        primitives
+    }
+
+    #[test]
+    fn test_kdtree_matches_linear_search() {
+        let primitives = spheres();
+        let kdtree = KdTreeAccel::with_defaults(primitives.clone());
+        assert!(kdtree.node_count() > 1);
+        for i in 0..30 {
+            let o = Point3::new(i as f32 * 0.9 - 0.5, i as f32 * 0.7, -10.0);
+            let d = vec3(0.05 * i as f32, 0.02, 1.0);
+
+            let ray = Ray::new(o, d);
+            let mut interaction = SurfaceInteraction::default();
+            let hit = kdtree.intersect(&ray, &mut interaction);
+
+            let expected = Ray::new(o, d);
+            let mut expected_interaction = SurfaceInteraction::default();
+            let expected_hit = primitives.iter().fold(false, |hit, p| p.intersect(&expected, &mut expected_interaction) | hit);
+
+            assert_eq!(hit, expected_hit);
+            assert_eq!(kdtree.intersect_p(&Ray::new(o, d)), expected_hit);
+            if hit {
+                assert_eq!(ray.tmax.get(), expected.tmax.get());
+            }
+        }
+    }
+
+    fn depth(kdtree: &KdTreeAccel, node: usize) -> usize {
+        match kdtree.nodes[node] {
+            KdAccelNode::Leaf { .. } => 0,
+            KdAccelNode::Interior { above_child, .. } =>
+                1 + usize::max(depth(kdtree, node + 1), depth(kdtree, above_child as usize))
+        }
+    }
+
+    #[test]
+    fn test_max_depth_is_clamped() {
+        // Spheres that shrink towards a corner make every split peel off a single primitive, which would need a
+        // deeper tree than traversal supports
+        let primitives: Vec<Arc<dyn Primitive>> = (0..90).map(|i| {
+            let r = 2f32.powi(50 - i);
+            let sphere = Sphere::full(Transform::translate(vec3(0.75 * r, 0.75 * r, 0.75 * r)), false, 0.25 * r);
+            Arc::new(GeometricPrimitive::new(Arc::new(sphere), None)) as Arc<dyn Primitive>
+        }).collect();
+        let kdtree = KdTreeAccel::new(primitives, 80.0, 1.0, 0.5, 1, Some(1000));
+        assert_eq!(depth(&kdtree, 0), MAX_TODO);
+    }
+}
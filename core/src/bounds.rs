@@ -1,6 +1,6 @@
-use std::cmp;
 use std::fmt::Debug;
-use crate::{Bounded, Scalar, Vector3};
+use crate::{Bounded, Ray, Scalar, Vector3};
+use crate::math::gamma;
 use crate::math::Lerp;
 use crate::types::Field;
 use crate::{Point2, Point3};
@@ -11,10 +11,10 @@ pub struct Bounds2<T: Scalar> {
     pub max: Point2<T>
 }
 
-impl<T: Scalar + Ord> Bounds2<T> {
+impl<T: Scalar + PartialOrd> Bounds2<T> {
     pub fn new(p1: Point2<T>, p2: Point2<T>) -> Self {
-        let min = Point2::new(cmp::min(p1.x, p2.x), cmp::min(p1.y, p2.y));
-        let max = Point2::new(cmp::max(p1.x, p2.x), cmp::max(p1.x, p2.y));
+        let min = Point2::new(partial_min(p1.x, p2.x), partial_min(p1.y, p2.y));
+        let max = Point2::new(partial_max(p1.x, p2.x), partial_max(p1.y, p2.y));
         Bounds2 { min, max }
     }
 
@@ -22,6 +22,22 @@ impl<T: Scalar + Ord> Bounds2<T> {
         let d = self.max - self.min;
         d.x * d.y
     }
+
+    /// Returns the overlap of two bounds, which is degenerate if they do not overlap.
+    pub fn intersect(&self, other: &Self) -> Self {
+        let min = Point2::new(partial_max(self.min.x, other.min.x), partial_max(self.min.y, other.min.y));
+        let max = Point2::new(partial_min(self.max.x, other.max.x), partial_min(self.max.y, other.max.y));
+        Bounds2 { min, max }
+    }
+
+    /// Checks if `p` is inside the bounds, excluding the upper boundary, as used for discrete pixel bounds.
+    pub fn inside_exclusive(&self, p: &Point2<T>) -> bool {
+        p.x >= self.min.x && p.x < self.max.x && p.y >= self.min.y && p.y < self.max.y
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x >= self.max.x || self.min.y >= self.max.y
+    }
 }
 
 impl<T: Scalar + Bounded> const Default for Bounds2<T> {
@@ -53,6 +69,16 @@ pub struct Bounds3<T: Scalar> {
     pub max: Point3<T>
 }
 
+impl<T: Scalar + Bounded> Bounds3<T> {
+    /// Returns an empty bounding box, which is the identity element of [`Bounds3::union`].
+    #[inline]
+    pub const fn empty() -> Self {
+        let min = Point3::new(T::MAX, T::MAX, T::MAX);
+        let max = Point3::new(T::MIN, T::MIN, T::MIN);
+        Bounds3 { min, max }
+    }
+}
+
 impl<T: Scalar + Bounded> const Default for Bounds3<T> {
     #[inline]
     fn default() -> Self {
@@ -62,13 +88,53 @@ impl<T: Scalar + Bounded> const Default for Bounds3<T> {
     }
 }
 
-impl<T: Scalar + Ord + Field> Bounds3<T> {
+impl<T: Scalar + PartialOrd> Bounds3<T> {
     pub fn new(p1: Point3<T>, p2: Point3<T>) -> Self {
-        let min = Point3::new(cmp::min(p1.x, p2.x), cmp::min(p1.y, p2.y), cmp::min(p1.z, p2.z));
-        let max = Point3::new(cmp::max(p1.x, p2.x), cmp::max(p1.x, p2.y), cmp::max(p1.z, p2.z));
+        let min = Point3::new(partial_min(p1.x, p2.x), partial_min(p1.y, p2.y), partial_min(p1.z, p2.z));
+        let max = Point3::new(partial_max(p1.x, p2.x), partial_max(p1.y, p2.y), partial_max(p1.z, p2.z));
         Bounds3 { min, max }
     }
 
+    /// Returns the bounding box that contains both `self` and the point `p`.
+    pub fn union_point(&self, p: &Point3<T>) -> Self {
+        let min = Point3::new(partial_min(self.min.x, p.x), partial_min(self.min.y, p.y), partial_min(self.min.z, p.z));
+        let max = Point3::new(partial_max(self.max.x, p.x), partial_max(self.max.y, p.y), partial_max(self.max.z, p.z));
+        Bounds3 { min, max }
+    }
+
+    /// Returns the bounding box that contains both `self` and `other`.
+    pub fn union(&self, other: &Self) -> Self {
+        let min = Point3::new(
+            partial_min(self.min.x, other.min.x),
+            partial_min(self.min.y, other.min.y),
+            partial_min(self.min.z, other.min.z)
+        );
+        let max = Point3::new(
+            partial_max(self.max.x, other.max.x),
+            partial_max(self.max.y, other.max.y),
+            partial_max(self.max.z, other.max.z)
+        );
+        Bounds3 { min, max }
+    }
+
+    pub fn inside(&self, p: &Point3<T>) -> bool {
+        p.x >= self.min.x && p.x <= self.max.x &&
+            p.y >= self.min.y && p.y <= self.max.y &&
+            p.z >= self.min.z && p.z <= self.max.z
+    }
+
+    /// Returns the coordinates of one of the eight corners of the bounding box.
+    pub fn corner(&self, i: usize) -> Point3<T> {
+        debug_assert!(i < 8);
+        Point3::new(
+            if i & 1 == 0 { self.min.x } else { self.max.x },
+            if i & 2 == 0 { self.min.y } else { self.max.y },
+            if i & 4 == 0 { self.min.z } else { self.max.z }
+        )
+    }
+}
+
+impl<T: Scalar + PartialOrd + Field> Bounds3<T> {
     pub const fn diagonal(&self) -> Vector3<T> {
         self.max - self.min
     }
@@ -79,7 +145,18 @@ impl<T: Scalar + Ord + Field> Bounds3<T> {
     }
 
     pub fn surface_area(&self) -> T {
-        todo!()
+        let d = self.diagonal();
+        let (xy, xz, yz) = (d.x * d.y, d.x * d.z, d.y * d.z);
+        xy + xy + xz + xz + yz + yz
+    }
+
+    /// Returns the position of `p` relative to the corners of the box, where `min` is at `(0, 0, 0)` and `max` is at `(1, 1, 1)`.
+    pub fn offset(&self, p: &Point3<T>) -> Vector3<T> {
+        let mut o = *p - self.min;
+        if self.max.x > self.min.x { o.x /= self.max.x - self.min.x; }
+        if self.max.y > self.min.y { o.y /= self.max.y - self.min.y; }
+        if self.max.z > self.min.z { o.z /= self.max.z - self.min.z; }
+        o
     }
 
     pub fn maximum_extent(&self) -> Axis3 {
@@ -103,6 +180,70 @@ impl<T: Scalar + Ord + Field> Bounds3<T> {
 }
 
 
+impl Bounds3<f32> {
+    #[inline]
+    pub fn centroid(&self) -> Point3<f32> {
+        Point3::new(
+            0.5 * (self.min.x + self.max.x),
+            0.5 * (self.min.y + self.max.y),
+            0.5 * (self.min.z + self.max.z)
+        )
+    }
+
+    /// Intersects the ray with the bounding box and returns the parametric range of the ray that overlaps the box.
+    pub fn intersect_p(&self, ray: &Ray) -> Option<(f32, f32)> {
+        let mut t0 = 0.0;
+        let mut t1 = ray.tmax.get();
+        for i in 0..3 {
+            let inv_dir = 1.0 / ray.d[i];
+            let mut t_near = (self.min[i] - ray.o[i]) * inv_dir;
+            let mut t_far = (self.max[i] - ray.o[i]) * inv_dir;
+            if t_near > t_far {
+                std::mem::swap(&mut t_near, &mut t_far);
+            }
+            // Make the far bound conservative to account for rounding errors
+            t_far *= 1.0 + 2.0 * gamma(3);
+            t0 = if t_near > t0 { t_near } else { t0 };
+            t1 = if t_far < t1 { t_far } else { t1 };
+            if t0 > t1 {
+                return None
+            }
+        }
+        Some((t0, t1))
+    }
+
+    /// Tests the ray for intersection with the bounding box using a precomputed reciprocal of the ray direction.
+    ///
+    /// `dir_is_neg` holds `1` for each axis along which the ray direction is negative and `0` otherwise.
+    #[inline]
+    pub fn intersect_p_with_inv_dir(&self, ray: &Ray, inv_dir: &Vector3<f32>, dir_is_neg: [usize; 3]) -> bool {
+        let bounds = [self.min, self.max];
+        let mut t_min = (bounds[dir_is_neg[0]].x - ray.o.x) * inv_dir.x;
+        let mut t_max = (bounds[1 - dir_is_neg[0]].x - ray.o.x) * inv_dir.x;
+        let ty_min = (bounds[dir_is_neg[1]].y - ray.o.y) * inv_dir.y;
+        let mut ty_max = (bounds[1 - dir_is_neg[1]].y - ray.o.y) * inv_dir.y;
+
+        t_max *= 1.0 + 2.0 * gamma(3);
+        ty_max *= 1.0 + 2.0 * gamma(3);
+        if t_min > ty_max || ty_min > t_max {
+            return false
+        }
+        if ty_min > t_min { t_min = ty_min; }
+        if ty_max < t_max { t_max = ty_max; }
+
+        let tz_min = (bounds[dir_is_neg[2]].z - ray.o.z) * inv_dir.z;
+        let mut tz_max = (bounds[1 - dir_is_neg[2]].z - ray.o.z) * inv_dir.z;
+        tz_max *= 1.0 + 2.0 * gamma(3);
+        if t_min > tz_max || tz_min > t_max {
+            return false
+        }
+        if tz_min > t_min { t_min = tz_min; }
+        if tz_max < t_max { t_max = tz_max; }
+
+        t_min < ray.tmax.get() && t_max > 0.0
+    }
+}
+
 impl<T: Scalar> From<Point3<T>> for Bounds3<T> {
     #[inline]
     fn from(p: Point3<T>) -> Self {
@@ -120,4 +261,25 @@ impl<T: Scalar> From<(Point3<T>, Point3<T>)> for Bounds3<T> {
 #[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
 pub enum Axis3 {
     X, Y, Z
-}
\ No newline at end of file
+}
+
+impl Axis3 {
+    /// Returns the axis following this one in cyclic order.
+    #[inline]
+    pub const fn next(self) -> Axis3 {
+        match self {
+            Axis3::X => Axis3::Y,
+            Axis3::Y => Axis3::Z,
+            Axis3::Z => Axis3::X
+        }
+    }
+}
+#[inline]
+fn partial_min<T: PartialOrd>(a: T, b: T) -> T {
+    if b < a { b } else { a }
+}
+
+#[inline]
+fn partial_max<T: PartialOrd>(a: T, b: T) -> T {
+    if b > a { b } else { a }
+}
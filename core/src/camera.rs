@@ -0,0 +1,121 @@
+//! Camera models that generate primary rays from film samples.
+
+mod orthographic;
+mod perspective;
+
+pub use orthographic::*;
+pub use perspective::*;
+
+use crate::math::Lerp;
+use crate::{point2, vec3, Bounds2f, Point2, Point2f, Point2i, Point3, Point3f, Ray, RayDifferential, Transform};
+
+/// The film, lens and time sample values needed to generate a camera ray.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct CameraSample {
+    /// Position on the film in raster space.
+    pub p_film: Point2f,
+    /// Position on the lens in `[0, 1)^2`.
+    pub p_lens: Point2f,
+    /// Time in `[0, 1)` that is mapped to the shutter interval.
+    pub time: f32
+}
+
+pub trait Camera: Send + Sync {
+    /// Generates a world space ray for the given sample and returns its radiance weight.
+    ///
+    /// A weight of zero means that the sample does not contribute to the image.
+    fn generate_ray(&self, sample: &CameraSample, ray: &mut Ray) -> f32;
+
+    /// Generates a world space ray together with the rays for samples one pixel over in x and y on the film.
+    ///
+    /// The default implementation calls `generate_ray` for each of the three samples.
+    fn generate_ray_differential(&self, sample: &CameraSample, ray: &mut RayDifferential) -> f32 {
+        let weight = self.generate_ray(sample, &mut ray.ray);
+        if weight == 0.0 {
+            return 0.0
+        }
+
+        let mut rx = Ray::default();
+        let sample_x = CameraSample { p_film: point2(sample.p_film.x + 1.0, sample.p_film.y), ..*sample };
+        if self.generate_ray(&sample_x, &mut rx) == 0.0 {
+            return 0.0
+        }
+        let mut ry = Ray::default();
+        let sample_y = CameraSample { p_film: point2(sample.p_film.x, sample.p_film.y + 1.0), ..*sample };
+        if self.generate_ray(&sample_y, &mut ry) == 0.0 {
+            return 0.0
+        }
+
+        ray.rx_origin = rx.o;
+        ray.rx_direction = rx.d;
+        ray.ry_origin = ry.o;
+        ray.ry_direction = ry.d;
+        ray.has_differentials = true;
+        weight
+    }
+}
+
+/// State shared by cameras that project the scene onto the film plane with a 4x4 projection.
+#[derive(Debug, Clone)]
+pub(crate) struct ProjectiveCamera {
+    pub camera_to_world: Transform,
+    pub raster_to_camera: Transform,
+    pub shutter_open: f32,
+    pub shutter_close: f32,
+    pub lens_radius: f32,
+    pub focal_distance: f32
+}
+
+impl ProjectiveCamera {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        camera_to_world: Transform,
+        camera_to_screen: Transform,
+        screen_window: &Bounds2f,
+        full_resolution: Point2i,
+        shutter_open: f32,
+        shutter_close: f32,
+        lens_radius: f32,
+        focal_distance: f32
+    ) -> Self {
+        let screen_to_raster = Transform::scale(full_resolution.x as f32, full_resolution.y as f32, 1.0) *
+            Transform::scale(
+                1.0 / (screen_window.max.x - screen_window.min.x),
+                1.0 / (screen_window.min.y - screen_window.max.y),
+                1.0
+            ) *
+            Transform::translate(vec3(-screen_window.min.x, -screen_window.max.y, 0.0));
+        let raster_to_camera = camera_to_screen.inverse() * screen_to_raster.inverse();
+        ProjectiveCamera {
+            camera_to_world,
+            raster_to_camera,
+            shutter_open,
+            shutter_close,
+            lens_radius,
+            focal_distance
+        }
+    }
+
+    /// Maps a sample time in `[0, 1)` to the shutter interval.
+    #[inline]
+    pub fn time(&self, sample: &CameraSample) -> f32 {
+        Lerp::lerp(sample.time, self.shutter_open, self.shutter_close)
+    }
+
+    /// Returns the camera space position of a raster space film sample.
+    #[inline]
+    pub fn film_to_camera(&self, p_film: &Point2f) -> Point3f {
+        self.raster_to_camera.transform_point(&Point3::new(p_film.x, p_film.y, 0.0))
+    }
+}
+
+/// Returns the default screen window for an image of the given resolution, spanning `[-1, 1]` along the shorter
+/// image axis.
+pub fn default_screen_window(full_resolution: Point2i) -> Bounds2f {
+    let frame = full_resolution.x as f32 / full_resolution.y as f32;
+    if frame > 1.0 {
+        Bounds2f::from((Point2::new(-frame, -1.0), Point2::new(frame, 1.0)))
+    } else {
+        Bounds2f::from((Point2::new(-1.0, -1.0 / frame), Point2::new(1.0, 1.0 / frame)))
+    }
+}
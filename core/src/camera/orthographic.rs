@@ -0,0 +1,169 @@
+use crate::camera::{Camera, CameraSample, ProjectiveCamera};
+use crate::sampling::concentric_sample_disk;
+use crate::{Bounds2f, Point2i, Point3, Point3f, Ray, RayDifferential, Transform, Vector3, Vector3f};
+
+/// A camera with an orthographic projection, optionally with a thin lens for depth of field.
+#[derive(Debug, Clone)]
+pub struct OrthographicCamera {
+    projective: ProjectiveCamera,
+    dx_camera: Vector3f,
+    dy_camera: Vector3f
+}
+
+impl OrthographicCamera {
+    /// Creates a new orthographic camera.
+    ///
+    /// The screen window gives the extent of the film in camera space units. A `lens_radius` of zero gives
+    /// parallel rays; otherwise the camera is in focus at `focal_distance`.
+    pub fn new(
+        camera_to_world: Transform,
+        screen_window: &Bounds2f,
+        full_resolution: Point2i,
+        shutter_open: f32,
+        shutter_close: f32,
+        lens_radius: f32,
+        focal_distance: f32
+    ) -> Self {
+        let projective = ProjectiveCamera::new(
+            camera_to_world,
+            Transform::orthographic(0.0, 1.0),
+            screen_window,
+            full_resolution,
+            shutter_open,
+            shutter_close,
+            lens_radius,
+            focal_distance
+        );
+        let dx_camera = projective.raster_to_camera.transform_vector(&Vector3::new(1.0, 0.0, 0.0));
+        let dy_camera = projective.raster_to_camera.transform_vector(&Vector3::new(0.0, 1.0, 0.0));
+        OrthographicCamera { projective, dx_camera, dy_camera }
+    }
+
+    /// Returns the point on the lens for a ray that would start at `p_camera` without a lens.
+    fn lens_point(&self, p_camera: &Point3f, sample: &CameraSample) -> Point3f {
+        let p_lens = concentric_sample_disk(&sample.p_lens);
+        Point3::new(
+            p_camera.x + p_lens.x * self.projective.lens_radius,
+            p_camera.y + p_lens.y * self.projective.lens_radius,
+            0.0
+        )
+    }
+
+    /// Returns the direction from `p_lens` through the point on the plane of focus that the unfocused ray from
+    /// `p_camera` passes through.
+    fn focus(&self, p_camera: &Point3f, p_lens: &Point3f) -> Vector3f {
+        let p_focus = Point3::new(p_camera.x, p_camera.y, self.projective.focal_distance);
+        (p_focus - *p_lens).normalize()
+    }
+}
+
+impl Camera for OrthographicCamera {
+    fn generate_ray(&self, sample: &CameraSample, ray: &mut Ray) -> f32 {
+        let p_camera = self.projective.film_to_camera(&sample.p_film);
+        let mut r = Ray::new(p_camera, Vector3::new(0.0, 0.0, 1.0));
+
+        if self.projective.lens_radius > 0.0 {
+            let p_lens = self.lens_point(&p_camera, sample);
+            r.d = self.focus(&p_camera, &p_lens);
+            r.o = p_lens;
+        }
+
+        r.time = self.projective.time(sample);
+        *ray = self.projective.camera_to_world.transform_ray(&r);
+        1.0
+    }
+
+    fn generate_ray_differential(&self, sample: &CameraSample, ray: &mut RayDifferential) -> f32 {
+        let p_camera = self.projective.film_to_camera(&sample.p_film);
+        let mut r = RayDifferential::new(Ray::new(p_camera, Vector3::new(0.0, 0.0, 1.0)));
+
+        if self.projective.lens_radius > 0.0 {
+            let p_lens = self.lens_point(&p_camera, sample);
+            r.d = self.focus(&p_camera, &p_lens);
+            r.o = p_lens;
+            let (p_dx, p_dy) = (p_camera + self.dx_camera, p_camera + self.dy_camera);
+            r.rx_origin = self.lens_point(&p_dx, sample);
+            r.ry_origin = self.lens_point(&p_dy, sample);
+            r.rx_direction = self.focus(&p_dx, &r.rx_origin);
+            r.ry_direction = self.focus(&p_dy, &r.ry_origin);
+        } else {
+            r.rx_origin = r.o + self.dx_camera;
+            r.ry_origin = r.o + self.dy_camera;
+            r.rx_direction = r.d;
+            r.ry_direction = r.d;
+        }
+
+        r.time = self.projective.time(sample);
+        r.has_differentials = true;
+        *ray = self.projective.camera_to_world.transform_ray_differential(&r);
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::default_screen_window;
+    use crate::{point2, vec3};
+
+    fn camera(lens_radius: f32, focal_distance: f32) -> OrthographicCamera {
+        let resolution = Point2i::new(64, 32);
+        OrthographicCamera::new(
+            Transform::translate(vec3(1.0, 2.0, 3.0)),
+            &default_screen_window(resolution),
+            resolution,
+            0.0,
+            2.0,
+            lens_radius,
+            focal_distance
+        )
+    }
+
+    #[test]
+    fn test_center_ray_looks_down_z() {
+        let camera = camera(0.0, 1e6);
+        let sample = CameraSample { p_film: point2(32.0, 16.0), p_lens: point2(0.5, 0.5), time: 0.25 };
+        let mut ray = RayDifferential::default();
+        assert_eq!(camera.generate_ray_differential(&sample, &mut ray), 1.0);
+        assert!((ray.o.x - 1.0).abs() < 1e-5 && (ray.o.y - 2.0).abs() < 1e-5 && (ray.o.z - 3.0).abs() < 1e-5);
+        assert_eq!((ray.d.x, ray.d.y, ray.d.z), (0.0, 0.0, 1.0));
+        assert!((ray.time - 0.5).abs() < 1e-6);
+        // The screen window is 4 units wide over 64 pixels, and raster y points down
+        assert!((ray.rx_origin.x - ray.o.x - 1.0 / 16.0).abs() < 1e-5 && ray.rx_origin.y == ray.o.y);
+        assert!((ray.ry_origin.y - ray.o.y + 1.0 / 16.0).abs() < 1e-5 && ray.ry_origin.x == ray.o.x);
+        assert!(ray.rx_direction == ray.d && ray.ry_direction == ray.d);
+    }
+
+    #[test]
+    fn test_corner_ray_starts_at_screen_window_corner() {
+        let camera = camera(0.0, 1e6);
+        let sample = CameraSample { p_film: point2(0.0, 0.0), p_lens: point2(0.5, 0.5), time: 0.0 };
+        let mut ray = Ray::default();
+        camera.generate_ray(&sample, &mut ray);
+        // The top left corner of the film is at the minimum x and maximum y of the screen window
+        assert!((ray.o.x - (1.0 - 2.0)).abs() < 1e-5 && (ray.o.y - (2.0 + 1.0)).abs() < 1e-5);
+        assert!((ray.o.z - 3.0).abs() < 1e-5);
+        assert_eq!((ray.d.x, ray.d.y, ray.d.z), (0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_lens_rays_converge_on_plane_of_focus() {
+        let camera = camera(0.5, 10.0);
+        let p_film = point2(40.0, 8.0);
+        // The unfocused ray starts at (0.5, 0.5) in camera space, so it passes through (1.5, 2.5, 13) in world space
+        let focus = Point3::new(1.5, 2.5, 13.0);
+        for p_lens in [point2(0.1, 0.2), point2(0.9, 0.5), point2(0.4, 0.95)] {
+            let sample = CameraSample { p_film, p_lens, time: 0.0 };
+            let mut ray = RayDifferential::default();
+            camera.generate_ray_differential(&sample, &mut ray);
+            assert!((ray.o.z - 3.0).abs() < 1e-5);
+            assert!((ray.o.x - 1.5).hypot(ray.o.y - 2.5) <= 0.5 + 1e-5);
+            let p = ray.o + ray.d * ((focus.z - ray.o.z) / ray.d.z);
+            assert!((p.x - focus.x).abs() < 1e-4 && (p.y - focus.y).abs() < 1e-4, "{:?}", p);
+
+            // The differential rays focus one pixel over
+            let rx = ray.rx_origin + ray.rx_direction * ((focus.z - ray.rx_origin.z) / ray.rx_direction.z);
+            assert!((rx.x - focus.x - 1.0 / 16.0).abs() < 1e-4 && (rx.y - focus.y).abs() < 1e-4, "{:?}", rx);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::camera::{Camera, CameraSample, ProjectiveCamera};
+use crate::sampling::concentric_sample_disk;
+use crate::{Bounds2f, Point2i, Point3, Point3f, Ray, RayDifferential, Transform, Vector3, Vector3f};
+
+/// A pinhole or thin lens camera with a perspective projection.
+#[derive(Debug, Clone)]
+pub struct PerspectiveCamera {
+    projective: ProjectiveCamera,
+    dx_camera: Vector3f,
+    dy_camera: Vector3f
+}
+
+impl PerspectiveCamera {
+    /// Creates a new perspective camera.
+    ///
+    /// `fov` is the field of view in degrees along the shorter image axis of the default screen window. A
+    /// `lens_radius` of zero gives a pinhole camera; otherwise the camera is in focus at `focal_distance`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        camera_to_world: Transform,
+        screen_window: &Bounds2f,
+        full_resolution: Point2i,
+        shutter_open: f32,
+        shutter_close: f32,
+        lens_radius: f32,
+        focal_distance: f32,
+        fov: f32
+    ) -> Self {
+        let projective = ProjectiveCamera::new(
+            camera_to_world,
+            Transform::perspective(fov, 1e-2, 1000.0),
+            screen_window,
+            full_resolution,
+            shutter_open,
+            shutter_close,
+            lens_radius,
+            focal_distance
+        );
+        let origin = projective.raster_to_camera.transform_point(&Point3::new(0.0, 0.0, 0.0));
+        let dx_camera = projective.raster_to_camera.transform_point(&Point3::new(1.0, 0.0, 0.0)) - origin;
+        let dy_camera = projective.raster_to_camera.transform_point(&Point3::new(0.0, 1.0, 0.0)) - origin;
+        PerspectiveCamera { projective, dx_camera, dy_camera }
+    }
+
+    /// Returns the camera space point on the lens and the direction from it through the plane of focus for a ray
+    /// that would leave a pinhole camera in direction `d`.
+    fn focus(&self, p_lens: &Point3f, d: &Vector3f) -> Vector3f {
+        let ft = self.projective.focal_distance / d.z;
+        let p_focus = Point3::new(0.0, 0.0, 0.0) + *d * ft;
+        (p_focus - *p_lens).normalize()
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn generate_ray(&self, sample: &CameraSample, ray: &mut Ray) -> f32 {
+        let p_camera = self.projective.film_to_camera(&sample.p_film);
+        let mut r = Ray::new(Point3::new(0.0, 0.0, 0.0), Vector3::from(p_camera).normalize());
+
+        if self.projective.lens_radius > 0.0 {
+            let p_lens = concentric_sample_disk(&sample.p_lens);
+            let p_lens = Point3::new(p_lens.x * self.projective.lens_radius, p_lens.y * self.projective.lens_radius, 0.0);
+            r.d = self.focus(&p_lens, &r.d);
+            r.o = p_lens;
+        }
+
+        r.time = self.projective.time(sample);
+        *ray = self.projective.camera_to_world.transform_ray(&r);
+        1.0
+    }
+
+    fn generate_ray_differential(&self, sample: &CameraSample, ray: &mut RayDifferential) -> f32 {
+        let p_camera = self.projective.film_to_camera(&sample.p_film);
+        let d = Vector3::from(p_camera);
+        let mut r = RayDifferential::new(Ray::new(Point3::new(0.0, 0.0, 0.0), d.normalize()));
+
+        if self.projective.lens_radius > 0.0 {
+            let p_lens = concentric_sample_disk(&sample.p_lens);
+            let p_lens = Point3::new(p_lens.x * self.projective.lens_radius, p_lens.y * self.projective.lens_radius, 0.0);
+            r.d = self.focus(&p_lens, &r.d);
+            r.o = p_lens;
+            r.rx_origin = p_lens;
+            r.ry_origin = p_lens;
+            r.rx_direction = self.focus(&p_lens, &(d + self.dx_camera).normalize());
+            r.ry_direction = self.focus(&p_lens, &(d + self.dy_camera).normalize());
+        } else {
+            r.rx_origin = r.o;
+            r.ry_origin = r.o;
+            r.rx_direction = (d + self.dx_camera).normalize();
+            r.ry_direction = (d + self.dy_camera).normalize();
+        }
+
+        r.time = self.projective.time(sample);
+        r.has_differentials = true;
+        *ray = self.projective.camera_to_world.transform_ray_differential(&r);
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::default_screen_window;
+    use crate::{point2, vec3};
+
+    #[test]
+    fn test_center_ray_looks_down_z() {
+        let resolution = Point2i::new(64, 32);
+        let camera = PerspectiveCamera::new(
+            Transform::translate(vec3(1.0, 2.0, 3.0)),
+            &default_screen_window(resolution),
+            resolution,
+            0.0,
+            2.0,
+            0.0,
+            1e6,
+            90.0
+        );
+        let sample = CameraSample { p_film: point2(32.0, 16.0), p_lens: point2(0.5, 0.5), time: 0.25 };
+        let mut ray = RayDifferential::default();
+        assert_eq!(camera.generate_ray_differential(&sample, &mut ray), 1.0);
+        assert!((ray.o.x - 1.0).abs() < 1e-5 && (ray.o.y - 2.0).abs() < 1e-5 && (ray.o.z - 3.0).abs() < 1e-5);
+        assert!(ray.d.z > 0.9999);
+        assert!((ray.time - 0.5).abs() < 1e-6);
+        // Raster y points down, so the differential in y tilts the ray towards negative camera space y
+        assert!(ray.rx_direction.x > 0.0 && ray.ry_direction.y < 0.0);
+
+        // With a 90 degree field of view the top left corner of the film lies at 45 degrees along the shorter axis
+        let corner = CameraSample { p_film: point2(16.0, 0.0), ..sample };
+        let mut r = Ray::default();
+        camera.generate_ray(&corner, &mut r);
+        assert!((r.d.y / r.d.z - 1.0).abs() < 1e-4);
+    }
+}
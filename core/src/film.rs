@@ -0,0 +1,341 @@
+//! Film that reconstructs the image from filtered samples and writes it to disk.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::Mutex;
+use openexr::core::attr::{Box2i, Compression, PixelType};
+use openexr::core::context::WriteContext;
+use openexr::core::frame_buffer::{Slice, SliceData};
+use crate::filter::Filter;
+use crate::spectrum::{xyz_to_rgb, SampledWavelengths, SpectrumSample};
+use crate::{point2, Bounds2f, Bounds2i, Point2, Point2f, Point2i, Vector2, Vector2f};
+
+/// Number of entries of the precomputed filter table along each axis.
+const FILTER_TABLE_WIDTH: usize = 16;
+
+#[derive(Debug, Copy, Clone, Default)]
+struct Pixel {
+    contrib_sum: [f32; 3],
+    filter_weight_sum: f32
+}
+
+impl Pixel {
+    fn add(&mut self, other: &Pixel) {
+        for (sum, c) in self.contrib_sum.iter_mut().zip(other.contrib_sum) {
+            *sum += c;
+        }
+        self.filter_weight_sum += other.filter_weight_sum;
+    }
+}
+
+/// The film of a camera.
+///
+/// Samples are accumulated into [`FilmTile`]s that cover a region of the image and can be filled independently
+/// from worker threads before being merged into the film.
+pub struct Film {
+    full_resolution: Point2i,
+    cropped_pixel_bounds: Bounds2i,
+    filter: Box<dyn Filter>,
+    /// Values of the filter over the positive quadrant of its support, sampled at the center of each entry.
+    filter_table: Box<[f32]>,
+    scale: f32,
+    pixels: Mutex<Vec<Pixel>>
+}
+
+impl Film {
+    /// Creates a new film.
+    ///
+    /// `crop_window` is given in normalized device coordinates, i.e. `[0, 1]^2` for the full image, and `scale`
+    /// is applied to all pixel values when the image is written.
+    pub fn new(full_resolution: Point2i, crop_window: &Bounds2f, filter: Box<dyn Filter>, scale: f32) -> Self {
+        let (res_x, res_y) = (full_resolution.x as f32, full_resolution.y as f32);
+        let cropped_pixel_bounds = Bounds2i::from((
+            Point2::new((res_x * crop_window.min.x).ceil() as i32, (res_y * crop_window.min.y).ceil() as i32),
+            Point2::new((res_x * crop_window.max.x).ceil() as i32, (res_y * crop_window.max.y).ceil() as i32)
+        ));
+
+        let radius = filter.radius();
+        let mut filter_table = Vec::with_capacity(FILTER_TABLE_WIDTH * FILTER_TABLE_WIDTH);
+        for y in 0..FILTER_TABLE_WIDTH {
+            for x in 0..FILTER_TABLE_WIDTH {
+                let p = point2(
+                    (x as f32 + 0.5) * radius.x / FILTER_TABLE_WIDTH as f32,
+                    (y as f32 + 0.5) * radius.y / FILTER_TABLE_WIDTH as f32
+                );
+                filter_table.push(filter.evaluate(&p));
+            }
+        }
+
+        let pixel_count = cropped_pixel_bounds.area().max(0) as usize;
+        Film {
+            full_resolution,
+            cropped_pixel_bounds,
+            filter,
+            filter_table: filter_table.into_boxed_slice(),
+            scale,
+            pixels: Mutex::new(vec![Pixel::default(); pixel_count])
+        }
+    }
+
+    #[inline]
+    pub fn full_resolution(&self) -> Point2i {
+        self.full_resolution
+    }
+
+    /// Returns the pixels of the crop window, excluding the upper bound.
+    #[inline]
+    pub fn cropped_pixel_bounds(&self) -> Bounds2i {
+        self.cropped_pixel_bounds
+    }
+
+    #[inline]
+    pub fn filter(&self) -> &dyn Filter {
+        self.filter.as_ref()
+    }
+
+    /// Returns the range of pixels that must be sampled to reconstruct all pixels of the crop window, which
+    /// extends past the crop window by the filter radius.
+    pub fn sample_bounds(&self) -> Bounds2i {
+        let radius = self.filter.radius();
+        let min = self.cropped_pixel_bounds.min;
+        let max = self.cropped_pixel_bounds.max;
+        Bounds2i::from((
+            Point2::new((min.x as f32 + 0.5 - radius.x).floor() as i32, (min.y as f32 + 0.5 - radius.y).floor() as i32),
+            Point2::new((max.x as f32 - 0.5 + radius.x).ceil() as i32, (max.y as f32 - 0.5 + radius.y).ceil() as i32)
+        ))
+    }
+
+    /// Creates a tile for the pixels that samples within `sample_bounds` contribute to.
+    pub fn film_tile(&self, sample_bounds: &Bounds2i) -> FilmTile<'_> {
+        let radius = self.filter.radius();
+        let p0 = Point2::new(
+            (sample_bounds.min.x as f32 - 0.5 - radius.x).ceil() as i32,
+            (sample_bounds.min.y as f32 - 0.5 - radius.y).ceil() as i32
+        );
+        let p1 = Point2::new(
+            (sample_bounds.max.x as f32 - 0.5 + radius.x).floor() as i32 + 1,
+            (sample_bounds.max.y as f32 - 0.5 + radius.y).floor() as i32 + 1
+        );
+        let pixel_bounds = Bounds2i::from((p0, p1)).intersect(&self.cropped_pixel_bounds);
+        FilmTile::new(pixel_bounds, radius, &self.filter_table)
+    }
+
+    /// Adds the contributions accumulated in a tile to the film.
+    pub fn merge_film_tile(&self, tile: FilmTile<'_>) {
+        let mut pixels = self.pixels.lock().unwrap();
+        let bounds = tile.pixel_bounds;
+        for y in bounds.min.y..bounds.max.y {
+            for x in bounds.min.x..bounds.max.x {
+                let p = Point2::new(x, y);
+                pixels[self.pixel_offset(&p)].add(&tile.pixels[tile.pixel_offset(&p)]);
+            }
+        }
+    }
+
+    #[inline]
+    fn pixel_offset(&self, p: &Point2i) -> usize {
+        debug_assert!(self.cropped_pixel_bounds.inside_exclusive(p));
+        let width = self.cropped_pixel_bounds.max.x - self.cropped_pixel_bounds.min.x;
+        ((p.y - self.cropped_pixel_bounds.min.y) * width + (p.x - self.cropped_pixel_bounds.min.x)) as usize
+    }
+
+    /// Returns the reconstructed RGBA pixels of the crop window in scanline order.
+    ///
+    /// Alpha is one for pixels that received samples with a nonzero filter weight and zero otherwise.
+    pub fn rgba(&self) -> Vec<f32> {
+        let pixels = self.pixels.lock().unwrap();
+        let mut rgba = Vec::with_capacity(pixels.len() * 4);
+        for pixel in pixels.iter() {
+            if pixel.filter_weight_sum != 0.0 {
+                let inv_weight = 1.0 / pixel.filter_weight_sum;
+                for c in pixel.contrib_sum {
+                    rgba.push(f32::max(0.0, c * inv_weight) * self.scale);
+                }
+                rgba.push(1.0);
+            } else {
+                rgba.extend_from_slice(&[0.0; 4]);
+            }
+        }
+        rgba
+    }
+
+    /// Writes the image as an OpenEXR file with `R`, `G`, `B` and optionally `A` channels of the given pixel type.
+    ///
+    /// The crop window is written as the data window and the full resolution as the display window.
+    pub fn write_image<P: AsRef<Path>>(&self, path: P, pixel_type: PixelType, alpha: bool) -> Result<(), openexr::Error> {
+        let bounds = self.cropped_pixel_bounds;
+        let data_window = Box2i::new([bounds.min.x, bounds.min.y], [bounds.max.x - 1, bounds.max.y - 1]);
+        let display_window = Box2i::with_size(self.full_resolution.x, self.full_resolution.y);
+        let width = data_window.width() as usize;
+        let rgba = self.rgba();
+
+        let mut exr = WriteContext::new(BufWriter::new(File::create(path)?))?;
+        let part = exr.add_scanline_part(None, data_window, display_window, Compression::Zip)?;
+        let channels: &[&str] = if alpha { &["R", "G", "B", "A"] } else { &["R", "G", "B"] };
+        for name in channels {
+            exr.add_channel(part, name, pixel_type, false)?;
+        }
+        exr.write_header()?;
+        let slices: Vec<_> = channels.iter()
+            .enumerate()
+            .map(|(i, name)| Slice::interleaved(name, SliceData::Float(&rgba), width, 4, i))
+            .collect();
+        exr.write_scanlines(part, &slices)?;
+        exr.finish()
+    }
+}
+
+/// A region of the film that accumulates samples independently of other tiles.
+pub struct FilmTile<'a> {
+    pixel_bounds: Bounds2i,
+    filter_radius: Vector2f,
+    inv_filter_radius: Vector2f,
+    filter_table: &'a [f32],
+    pixels: Vec<Pixel>
+}
+
+impl<'a> FilmTile<'a> {
+    fn new(pixel_bounds: Bounds2i, filter_radius: Vector2f, filter_table: &'a [f32]) -> Self {
+        FilmTile {
+            pixel_bounds,
+            filter_radius,
+            inv_filter_radius: Vector2::new(1.0 / filter_radius.x, 1.0 / filter_radius.y),
+            filter_table,
+            pixels: vec![Pixel::default(); pixel_bounds.area().max(0) as usize]
+        }
+    }
+
+    /// Returns the pixels covered by this tile, excluding the upper bound.
+    #[inline]
+    pub fn pixel_bounds(&self) -> Bounds2i {
+        self.pixel_bounds
+    }
+
+    /// Adds the radiance `rgb` of a sample at the continuous raster position `p_film` to all pixels within the
+    /// filter radius, weighted by `sample_weight` and the filter.
+    pub fn add_sample(&mut self, p_film: &Point2f, rgb: [f32; 3], sample_weight: f32) {
+        // Pixel centers are at half-integer positions
+        let p = point2(p_film.x - 0.5, p_film.y - 0.5);
+        let p0 = Point2::new(
+            i32::max((p.x - self.filter_radius.x).ceil() as i32, self.pixel_bounds.min.x),
+            i32::max((p.y - self.filter_radius.y).ceil() as i32, self.pixel_bounds.min.y)
+        );
+        let p1 = Point2::new(
+            i32::min((p.x + self.filter_radius.x).floor() as i32 + 1, self.pixel_bounds.max.x),
+            i32::min((p.y + self.filter_radius.y).floor() as i32 + 1, self.pixel_bounds.max.y)
+        );
+        if p0.x >= p1.x || p0.y >= p1.y {
+            return
+        }
+
+        let table_offset = |d: f32, inv_radius: f32| {
+            usize::min((d * inv_radius * FILTER_TABLE_WIDTH as f32).abs().floor() as usize, FILTER_TABLE_WIDTH - 1)
+        };
+        let ifx: Vec<usize> = (p0.x..p1.x).map(|x| table_offset(x as f32 - p.x, self.inv_filter_radius.x)).collect();
+        for y in p0.y..p1.y {
+            let ify = table_offset(y as f32 - p.y, self.inv_filter_radius.y);
+            for x in p0.x..p1.x {
+                let weight = self.filter_table[ify * FILTER_TABLE_WIDTH + ifx[(x - p0.x) as usize]];
+                let offset = self.pixel_offset(&Point2::new(x, y));
+                let pixel = &mut self.pixels[offset];
+                for (sum, c) in pixel.contrib_sum.iter_mut().zip(rgb) {
+                    *sum += c * sample_weight * weight;
+                }
+                pixel.filter_weight_sum += weight;
+            }
+        }
+    }
+
+    /// Adds a sample of the radiance `l` carried at the wavelengths `lambda`, converting it to linear sRGB through
+    /// CIE XYZ.
+    pub fn add_spectral_sample(
+        &mut self,
+        p_film: &Point2f,
+        l: &SpectrumSample,
+        lambda: &SampledWavelengths,
+        sample_weight: f32
+    ) {
+        self.add_sample(p_film, xyz_to_rgb(l.to_xyz(lambda)), sample_weight);
+    }
+
+    #[inline]
+    fn pixel_offset(&self, p: &Point2i) -> usize {
+        debug_assert!(self.pixel_bounds.inside_exclusive(p));
+        let width = self.pixel_bounds.max.x - self.pixel_bounds.min.x;
+        ((p.y - self.pixel_bounds.min.y) * width + (p.x - self.pixel_bounds.min.x)) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::prelude::*;
+    use crate::filter::{BoxFilter, TriangleFilter};
+
+    fn full_window() -> Bounds2f {
+        Bounds2f::from((point2(0.0, 0.0), point2(1.0, 1.0)))
+    }
+
+    #[test]
+    fn test_box_filtered_samples_are_averaged() {
+        let film = Film::new(Point2::new(4, 2), &full_window(), Box::new(BoxFilter::default()), 1.0);
+        let mut tile = film.film_tile(&film.sample_bounds());
+        tile.add_sample(&point2(1.25, 0.5), [1.0, 2.0, 3.0], 1.0);
+        tile.add_sample(&point2(1.75, 0.5), [3.0, 2.0, 1.0], 1.0);
+        film.merge_film_tile(tile);
+        let rgba = film.rgba();
+        assert_eq!(&rgba[4..8], &[2.0, 2.0, 2.0, 1.0]);
+        assert_eq!(&rgba[0..4], &[0.0; 4]);
+    }
+
+    #[test]
+    fn test_tiles_from_threads_merge() {
+        let crop = Bounds2f::from((point2(0.25, 0.0), point2(1.0, 1.0)));
+        let film = Film::new(Point2::new(8, 8), &crop, Box::new(TriangleFilter::default()), 2.0);
+        assert_eq!(film.cropped_pixel_bounds(), Bounds2i::from((Point2::new(2, 0), Point2::new(8, 8))));
+        (0..8).into_par_iter().for_each(|y| {
+            let sample_bounds = Bounds2i::from((Point2::new(0, y), Point2::new(8, y + 1)));
+            let mut tile = film.film_tile(&sample_bounds);
+            for x in 0..8 {
+                tile.add_sample(&point2(x as f32 + 0.5, y as f32 + 0.5), [0.5, 0.5, 0.5], 1.0);
+            }
+            film.merge_film_tile(tile);
+        });
+        // A constant signal is reconstructed exactly regardless of the filter
+        assert!(film.rgba().iter().all(|&v| (v - 1.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_write_image_exr() {
+        use openexr::core::context::{Part, ReadContext};
+        use crate::image::Image;
+
+        let crop = Bounds2f::from((point2(0.25, 0.0), point2(1.0, 0.5)));
+        let film = Film::new(Point2::new(4, 4), &crop, Box::new(BoxFilter::default()), 1.0);
+        let mut tile = film.film_tile(&film.sample_bounds());
+        for y in 0..4 {
+            for x in 0..4 {
+                tile.add_sample(&point2(x as f32 + 0.5, y as f32 + 0.5), [x as f32, y as f32, 0.5], 1.0);
+            }
+        }
+        film.merge_film_tile(tile);
+
+        let path = std::env::temp_dir().join(format!("pbr-film-{}.exr", std::process::id()));
+        film.write_image(&path, PixelType::Float, true).unwrap();
+        let exr = ReadContext::open(&path).unwrap();
+        let channels: Vec<String> = exr.channels(Part::new(0)).unwrap().into_iter().map(|c| c.name).collect();
+        let data_window = exr.data_window(Part::new(0)).unwrap();
+        let image = Image::read(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        // The channels are sorted by name and the crop window becomes the data window
+        assert_eq!(channels, ["A", "B", "G", "R"]);
+        assert_eq!(data_window, Box2i::new([1, 0], [3, 1]));
+        let image = image.unwrap();
+        assert_eq!(image.resolution(), Point2::new(3, 2));
+        let rgb: Vec<f32> = film.rgba().chunks_exact(4).flat_map(|p| p[..3].to_vec()).collect();
+        assert_eq!(image.pixels(), &rgb[..]);
+        assert_eq!(&image.pixels()[..3], &[1.0, 0.0, 0.5]);
+    }
+}
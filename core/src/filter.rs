@@ -0,0 +1,202 @@
+//! Pixel reconstruction filters.
+
+use std::f32::consts::PI;
+use crate::{Point2f, Vector2, Vector2f};
+
+/// A reconstruction filter that weights the contribution of an image sample to nearby pixels.
+pub trait Filter: Send + Sync {
+    /// Extent of the filter support in each direction from its center.
+    fn radius(&self) -> Vector2f;
+
+    /// Evaluates the filter at `p` relative to its center.
+    fn evaluate(&self, p: &Point2f) -> f32;
+}
+
+/// A filter that weights all samples within its radius equally.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BoxFilter {
+    radius: Vector2f
+}
+
+impl BoxFilter {
+    pub const fn new(radius: Vector2f) -> Self {
+        BoxFilter { radius }
+    }
+}
+
+impl const Default for BoxFilter {
+    fn default() -> Self {
+        BoxFilter::new(Vector2::new(0.5, 0.5))
+    }
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> Vector2f {
+        self.radius
+    }
+
+    fn evaluate(&self, _p: &Point2f) -> f32 {
+        1.0
+    }
+}
+
+/// A filter whose weight falls off linearly from its center to its radius.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TriangleFilter {
+    radius: Vector2f
+}
+
+impl TriangleFilter {
+    pub const fn new(radius: Vector2f) -> Self {
+        TriangleFilter { radius }
+    }
+}
+
+impl const Default for TriangleFilter {
+    fn default() -> Self {
+        TriangleFilter::new(Vector2::new(2.0, 2.0))
+    }
+}
+
+impl Filter for TriangleFilter {
+    fn radius(&self) -> Vector2f {
+        self.radius
+    }
+
+    fn evaluate(&self, p: &Point2f) -> f32 {
+        f32::max(0.0, self.radius.x - p.x.abs()) * f32::max(0.0, self.radius.y - p.y.abs())
+    }
+}
+
+/// A Gaussian filter that is offset so that it goes to zero at its radius.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GaussianFilter {
+    radius: Vector2f,
+    alpha: f32,
+    exp_x: f32,
+    exp_y: f32
+}
+
+impl GaussianFilter {
+    /// Creates a Gaussian filter with the falloff rate `alpha`; larger values give a narrower filter.
+    pub fn new(radius: Vector2f, alpha: f32) -> Self {
+        GaussianFilter {
+            radius,
+            alpha,
+            exp_x: (-alpha * radius.x * radius.x).exp(),
+            exp_y: (-alpha * radius.y * radius.y).exp()
+        }
+    }
+
+    #[inline]
+    fn gaussian(&self, d: f32, exp_v: f32) -> f32 {
+        f32::max(0.0, (-self.alpha * d * d).exp() - exp_v)
+    }
+}
+
+impl Default for GaussianFilter {
+    fn default() -> Self {
+        GaussianFilter::new(Vector2::new(1.5, 1.5), 2.0)
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> Vector2f {
+        self.radius
+    }
+
+    fn evaluate(&self, p: &Point2f) -> f32 {
+        self.gaussian(p.x, self.exp_x) * self.gaussian(p.y, self.exp_y)
+    }
+}
+
+/// The Mitchell-Netravali cubic filter with the free parameters `b` and `c`, which trade off blurring against
+/// ringing.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MitchellFilter {
+    radius: Vector2f,
+    inv_radius: Vector2f,
+    b: f32,
+    c: f32
+}
+
+impl MitchellFilter {
+    pub fn new(radius: Vector2f, b: f32, c: f32) -> Self {
+        MitchellFilter { radius, inv_radius: Vector2::new(1.0 / radius.x, 1.0 / radius.y), b, c }
+    }
+
+    /// Evaluates the one-dimensional filter over `[-1, 1]`.
+    fn mitchell_1d(&self, x: f32) -> f32 {
+        let (b, c) = (self.b, self.c);
+        let x = (2.0 * x).abs();
+        if x > 1.0 {
+            ((-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x + (-12.0 * b - 48.0 * c) * x +
+                (8.0 * b + 24.0 * c)) * (1.0 / 6.0)
+        } else {
+            ((12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x + (6.0 - 2.0 * b)) *
+                (1.0 / 6.0)
+        }
+    }
+}
+
+impl Default for MitchellFilter {
+    fn default() -> Self {
+        MitchellFilter::new(Vector2::new(2.0, 2.0), 1.0 / 3.0, 1.0 / 3.0)
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> Vector2f {
+        self.radius
+    }
+
+    fn evaluate(&self, p: &Point2f) -> f32 {
+        self.mitchell_1d(p.x * self.inv_radius.x) * self.mitchell_1d(p.y * self.inv_radius.y)
+    }
+}
+
+/// A sinc filter windowed by a Lanczos window with `tau` cycles of the sinc function.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LanczosSincFilter {
+    radius: Vector2f,
+    tau: f32
+}
+
+impl LanczosSincFilter {
+    pub const fn new(radius: Vector2f, tau: f32) -> Self {
+        LanczosSincFilter { radius, tau }
+    }
+
+    fn windowed_sinc(&self, x: f32, radius: f32) -> f32 {
+        let x = x.abs();
+        if x > radius {
+            return 0.0
+        }
+        sinc(x) * sinc(x / self.tau)
+    }
+}
+
+impl const Default for LanczosSincFilter {
+    fn default() -> Self {
+        LanczosSincFilter::new(Vector2::new(4.0, 4.0), 3.0)
+    }
+}
+
+impl Filter for LanczosSincFilter {
+    fn radius(&self) -> Vector2f {
+        self.radius
+    }
+
+    fn evaluate(&self, p: &Point2f) -> f32 {
+        self.windowed_sinc(p.x, self.radius.x) * self.windowed_sinc(p.y, self.radius.y)
+    }
+}
+
+#[inline]
+fn sinc(x: f32) -> f32 {
+    let x = x.abs();
+    if x < 1e-5 {
+        return 1.0
+    }
+    (PI * x).sin() / (PI * x)
+}
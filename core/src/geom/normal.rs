@@ -74,6 +74,13 @@ impl<T: Scalar> const From<Vector3<T>> for Normal3<T> {
     }
 }
 
+impl<T: Scalar> const From<Normal3<T>> for Vector3<T> {
+    #[inline]
+    fn from(n: Normal3<T>) -> Self {
+        Vector3::new(n.x, n.y, n.z)
+    }
+}
+
 //#region Operators
 impl<T: Scalar + ~const Add<Output=T>> const Add for Normal3<T> {
     type Output = Normal3<T>;
@@ -199,6 +206,19 @@ impl<T: Float> Normal3<T> {
     pub fn normalize(&self) -> Normal3<T> {
         self / self.length()
     }
+
+    /// Flips the normal so that it lies in the same hemisphere as `v`.
+    #[inline]
+    pub fn face_forward<U>(self, v: &U) -> Normal3<T> where Self: DotProduct<U, Output=T> {
+        if DotProduct::dot(&self, v) < T::default() { -self } else { self }
+    }
+}
+
+impl Normal3<f32> {
+    #[inline]
+    pub fn abs(self) -> Normal3<f32> {
+        Normal3::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
 }
 
 impl<T: Scalar + ~const Add<Output=T> + ~const Mul<Output=T>> const DotProduct for Normal3<T> {
@@ -1,5 +1,5 @@
 use std::fmt::{Debug, Formatter};
-use std::ops::{Add, Sub};
+use std::ops::{Add, Index, IndexMut, Sub};
 use bytemuck::{Pod, Zeroable};
 use crate::{Scalar, Vector2, Vector3};
 use crate::types::Float;
@@ -159,6 +159,59 @@ impl<T: Scalar> const From<Point2<T>> for (T, T) {
 }
 impl<T: Scalar> const From<Point3<T>> for (T, T, T) {
     fn from(p: Point3<T>) -> Self {
-        (p.x, p.y, p.x)
+        (p.x, p.y, p.z)
     }
-}
\ No newline at end of file
+}
+impl<T: Scalar> const From<Vector3<T>> for Point3<T> {
+    #[inline]
+    fn from(v: Vector3<T>) -> Self {
+        Point3::new(v.x, v.y, v.z)
+    }
+}
+
+impl<T: Scalar> const From<Point3<T>> for Vector3<T> {
+    #[inline]
+    fn from(p: Point3<T>) -> Self {
+        Vector3::new(p.x, p.y, p.z)
+    }
+}
+
+impl<T: Scalar> Point3<T> {
+    #[inline]
+    pub fn permute(self, x: usize, y: usize, z: usize) -> Self {
+        Point3::new(self[x], self[y], self[z])
+    }
+}
+
+impl Point3<f32> {
+    #[inline]
+    pub fn abs(self) -> Point3<f32> {
+        Point3::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+}
+
+impl<T: Scalar> Index<usize> for Point3<T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, i: usize) -> &T {
+        match i {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("index out of bounds: Point3 has 3 components but the index is {i}")
+        }
+    }
+}
+
+impl<T: Scalar> IndexMut<usize> for Point3<T> {
+    #[inline]
+    fn index_mut(&mut self, i: usize) -> &mut T {
+        match i {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("index out of bounds: Point3 has 3 components but the index is {i}")
+        }
+    }
+}
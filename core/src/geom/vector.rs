@@ -321,7 +321,12 @@ macro_rules! impl_vector_struct {
             /// Returns the L2 (Euclidean) norm of this vector.
             #[inline]
             pub fn length(self) -> T {
-                (strip_plus!($(+ (self.$field * self.$field))+)).sqrt()
+                self.length_squared().sqrt()
+            }
+
+            #[inline]
+            pub fn length_squared(self) -> T {
+                strip_plus!($(+ (self.$field * self.$field))+)
             }
 
             #[inline]
@@ -394,6 +399,72 @@ impl<T> Vector3<T> {
     }
 }
 
+impl<T: Float> Vector3<T> {
+    #[inline]
+    pub fn max_component(self) -> T {
+        let m = if self.x > self.y { self.x } else { self.y };
+        if m > self.z { m } else { self.z }
+    }
+
+    /// Returns the index of the component with the largest value.
+    #[inline]
+    pub fn max_dimension(self) -> usize {
+        if self.x > self.y {
+            if self.x > self.z { 0 } else { 2 }
+        } else if self.y > self.z {
+            1
+        } else {
+            2
+        }
+    }
+
+    #[inline]
+    pub fn permute(self, x: usize, y: usize, z: usize) -> Self {
+        Vector3::new(self[x], self[y], self[z])
+    }
+}
+
+impl Vector3<f32> {
+    /// Constructs two vectors that form an orthogonal coordinate system together with `self`.
+    ///
+    /// `self` is expected to be normalized.
+    pub fn coordinate_system(self) -> (Vector3<f32>, Vector3<f32>) {
+        let v2 = if self.x.abs() > self.y.abs() {
+            Vector3::new(-self.z, 0.0, self.x) / (self.x * self.x + self.z * self.z).sqrt()
+        } else {
+            Vector3::new(0.0, self.z, -self.y) / (self.y * self.y + self.z * self.z).sqrt()
+        };
+        let v3 = self.cross(&v2);
+        (v2, v3)
+    }
+}
+
+impl<T> Index<usize> for Vector3<T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, i: usize) -> &T {
+        match i {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("index out of bounds: Vector3 has 3 components but the index is {i}")
+        }
+    }
+}
+
+impl<T> IndexMut<usize> for Vector3<T> {
+    #[inline]
+    fn index_mut(&mut self, i: usize) -> &mut T {
+        match i {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("index out of bounds: Vector3 has 3 components but the index is {i}")
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
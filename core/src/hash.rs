@@ -0,0 +1,74 @@
+//! Hash functions for deriving decorrelated seeds and permutations from pixel coordinates and sample indices.
+
+/// Scrambles the bits of `v` so that small changes of the input change about half of the output bits, in the
+/// style of the 64-bit finalizer of MurmurHash3.
+#[inline]
+pub const fn mix_bits(mut v: u64) -> u64 {
+    v ^= v >> 31;
+    v = v.wrapping_mul(0x7fb5d329728ea185);
+    v ^= v >> 27;
+    v = v.wrapping_mul(0x81dadef4bc2dd44d);
+    v ^= v >> 33;
+    v
+}
+
+/// Combines `values` into a single well-mixed hash.
+#[inline]
+pub fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x9e3779b97f4a7c15, |h, &v| mix_bits(h ^ mix_bits(v)))
+}
+
+/// Returns element `i` of a pseudo-random permutation of `0..n` selected by `seed`, without storing the permutation.
+///
+/// See Kensler, "Correlated Multi-Jittered Sampling" (2013).
+pub fn permutation_element(mut i: u32, n: u32, seed: u32) -> u32 {
+    debug_assert!(i < n);
+    let mut w = n - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    // Cycle walk until the permuted value falls into [0, n)
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            break
+        }
+    }
+    (i.wrapping_add(seed)) % n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permutation_element_is_permutation() {
+        for n in [1, 2, 7, 64, 100] {
+            let mut seen = vec![false; n as usize];
+            for i in 0..n {
+                let p = permutation_element(i, n, 0xdeadbeef);
+                assert!(!seen[p as usize]);
+                seen[p as usize] = true;
+            }
+        }
+    }
+}
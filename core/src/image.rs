@@ -0,0 +1,414 @@
+//! In-memory images with an arbitrary number of floating point channels per pixel.
+
+use std::f32::consts::PI;
+use std::fs;
+use std::io;
+use std::path::Path;
+use openexr::core::attr::{Channel, Storage};
+use openexr::core::context::{Part, ReadContext};
+use openexr::core::frame_buffer::{SliceDataMut, SliceMut};
+use crate::{Point2f, Point2i};
+
+/// Determines the pixels that lookups outside of an image refer to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum WrapMode {
+    /// The image repeats periodically.
+    Repeat,
+    /// Lookups return the closest pixel at the edge of the image.
+    Clamp,
+    /// The image is surrounded by black pixels.
+    Black,
+    /// The image is an octahedral map of the sphere, whose edges are folded back onto themselves, see Clarberg,
+    /// "Fast Equal-Area Mapping of the (Hemi)Sphere using SIMD" (2008).
+    OctahedralSphere
+}
+
+impl WrapMode {
+    /// Maps the pixel coordinates `p` into an image with resolution `res`, returning `None` if they refer to a black
+    /// pixel outside of it.
+    #[inline]
+    pub fn remap(self, p: Point2i, res: Point2i) -> Option<Point2i> {
+        match self {
+            WrapMode::Repeat => Some(Point2i::new(p.x.rem_euclid(res.x), p.y.rem_euclid(res.y))),
+            WrapMode::Clamp => Some(Point2i::new(p.x.clamp(0, res.x - 1), p.y.clamp(0, res.y - 1))),
+            WrapMode::Black => {
+                if p.x < 0 || p.x >= res.x || p.y < 0 || p.y >= res.y {
+                    return None
+                }
+                Some(p)
+            }
+            WrapMode::OctahedralSphere => {
+                // Crossing an edge of the map continues on the same edge, mirrored about its center
+                let (mut x, mut y) = (p.x, p.y);
+                if x < 0 {
+                    x = -x - 1;
+                    y = res.y - 1 - y;
+                } else if x >= res.x {
+                    x = 2 * res.x - 1 - x;
+                    y = res.y - 1 - y;
+                }
+                if y < 0 {
+                    x = res.x - 1 - x;
+                    y = -y - 1;
+                } else if y >= res.y {
+                    x = res.x - 1 - x;
+                    y = 2 * res.y - 1 - y;
+                }
+                Some(Point2i::new(x.clamp(0, res.x - 1), y.clamp(0, res.y - 1)))
+            }
+        }
+    }
+}
+
+/// An image that stores the channels of its pixels interleaved in scanline order, starting at the top left corner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    resolution: Point2i,
+    n_channels: usize,
+    pixels: Vec<f32>
+}
+
+impl Image {
+    /// Creates an image of the given resolution from the interleaved channel values of its pixels.
+    pub fn new(resolution: Point2i, n_channels: usize, pixels: Vec<f32>) -> Self {
+        assert!(resolution.x > 0 && resolution.y > 0 && n_channels > 0);
+        assert_eq!(pixels.len(), resolution.x as usize * resolution.y as usize * n_channels);
+        Image { resolution, n_channels, pixels }
+    }
+
+    #[inline]
+    pub fn resolution(&self) -> Point2i {
+        self.resolution
+    }
+
+    #[inline]
+    pub fn n_channels(&self) -> usize {
+        self.n_channels
+    }
+
+    /// Returns the interleaved channel values of the pixels in scanline order.
+    #[inline]
+    pub fn pixels(&self) -> &[f32] {
+        &self.pixels
+    }
+
+    /// Returns channel `c` of the pixel at `p`, where coordinates outside of the image are handled according to
+    /// `wrap`.
+    #[inline]
+    pub fn get_channel(&self, p: Point2i, c: usize, wrap: WrapMode) -> f32 {
+        match wrap.remap(p, self.resolution) {
+            Some(p) => self.pixels[(p.y as usize * self.resolution.x as usize + p.x as usize) * self.n_channels + c],
+            None => 0.0
+        }
+    }
+
+    /// Bilinearly interpolates channel `c` at the continuous coordinates `p` in `[0, 1]^2`, where pixel centers are
+    /// at half-integer offsets.
+    pub fn bilerp_channel(&self, p: Point2f, c: usize, wrap: WrapMode) -> f32 {
+        let x = p.x * self.resolution.x as f32 - 0.5;
+        let y = p.y * self.resolution.y as f32 - 0.5;
+        let (xi, yi) = (x.floor(), y.floor());
+        let (dx, dy) = (x - xi, y - yi);
+        let (xi, yi) = (xi as i32, yi as i32);
+        (1.0 - dx) * (1.0 - dy) * self.get_channel(Point2i::new(xi, yi), c, wrap)
+            + dx * (1.0 - dy) * self.get_channel(Point2i::new(xi + 1, yi), c, wrap)
+            + (1.0 - dx) * dy * self.get_channel(Point2i::new(xi, yi + 1), c, wrap)
+            + dx * dy * self.get_channel(Point2i::new(xi + 1, yi + 1), c, wrap)
+    }
+
+    /// Resamples the image to a higher `resolution` with a Lanczos filter, where lookups outside of the image are
+    /// handled according to `wrap`.
+    pub fn resize_up(&self, resolution: Point2i, wrap: WrapMode) -> Image {
+        assert!(resolution.x >= self.resolution.x && resolution.y >= self.resolution.y);
+        let nc = self.n_channels;
+
+        // Resample along x, then along y
+        let x_weights = resample_weights(self.resolution.x, resolution.x);
+        let mut wide = vec![0.0; resolution.x as usize * self.resolution.y as usize * nc];
+        for y in 0..self.resolution.y {
+            for (x, w) in x_weights.iter().enumerate() {
+                for c in 0..nc {
+                    let v = (0..4).map(|j| {
+                        w.weight[j] * self.get_channel(Point2i::new(w.first_pixel + j as i32, y), c, wrap)
+                    }).sum::<f32>();
+                    wide[(y as usize * resolution.x as usize + x) * nc + c] = v;
+                }
+            }
+        }
+        let wide = Image::new(Point2i::new(resolution.x, self.resolution.y), nc, wide);
+
+        let y_weights = resample_weights(self.resolution.y, resolution.y);
+        let mut pixels = vec![0.0; resolution.x as usize * resolution.y as usize * nc];
+        for (y, w) in y_weights.iter().enumerate() {
+            for x in 0..resolution.x {
+                for c in 0..nc {
+                    let v = (0..4).map(|j| {
+                        w.weight[j] * wide.get_channel(Point2i::new(x, w.first_pixel + j as i32), c, wrap)
+                    }).sum::<f32>();
+                    // The negative lobes of the filter may ring below zero
+                    pixels[(y * resolution.x as usize + x as usize) * nc + c] = v.max(0.0);
+                }
+            }
+        }
+        Image::new(resolution, nc, pixels)
+    }
+
+    /// Reads the image in the file at `path`, whose format is determined by its extension:
+    ///
+    /// * `.exr`: the first part of an OpenEXR file, which may be stored as scanlines or tiles. Only the full
+    ///   resolution level of MIP mapped files is read. The image has the red, green and blue channels of the part if
+    ///   it has them, otherwise its luminance channel `Y` or its only channel.
+    /// * `.pfm`: a Portable FloatMap with one or three channels.
+    ///
+    /// Other formats are rejected with [`io::ErrorKind::InvalidInput`].
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Image> {
+        let path = path.as_ref();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("exr") => Self::read_exr(path),
+            Some("pfm") => Self::read_pfm(&fs::read(path)?),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unknown image format: {}", path.display())))
+        }
+    }
+
+    fn read_exr(path: &Path) -> io::Result<Image> {
+        let ctxt = ReadContext::open(path).map_err(exr_error)?;
+        let part = Part::new(0);
+        let channels = select_exr_channels(ctxt.channels(part).map_err(exr_error)?)?;
+        let data_window = ctxt.data_window(part).map_err(exr_error)?;
+        let (width, height) = (data_window.width() as usize, data_window.height() as usize);
+
+        // Each channel is read to a separate plane and interleaved afterwards
+        let mut planes = vec![vec![0.0; width * height]; channels.len()];
+        match ctxt.storage(part).map_err(exr_error)? {
+            Storage::Scanline => {
+                let mut slices = exr_slices(&channels, &mut planes, 0, width);
+                ctxt.read_scanlines(part, &mut slices).map_err(exr_error)?;
+            }
+            Storage::Tiled => {
+                let tiles = ctxt.tile_description(part).map_err(exr_error)?;
+                let (tile_width, tile_height) = (tiles.x_size as usize, tiles.y_size as usize);
+                for y in (0..height).step_by(tile_height) {
+                    for x in (0..width).step_by(tile_width) {
+                        let tile = ((x / tile_width) as i32, (y / tile_height) as i32);
+                        let mut slices = exr_slices(&channels, &mut planes, y * width + x, width);
+                        ctxt.read_tile(part, tile.0, tile.1, 0, 0, &mut slices).map_err(exr_error)?;
+                    }
+                }
+            }
+            Storage::DeepScanline | Storage::DeepTiled => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "deep images are not supported"))
+            }
+        }
+
+        let pixels = (0..width * height).flat_map(|i| planes.iter().map(move |plane| plane[i])).collect();
+        Ok(Image::new(Point2i::new(width as i32, height as i32), channels.len(), pixels))
+    }
+
+    fn read_pfm(bytes: &[u8]) -> io::Result<Image> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        // The header consists of three whitespace-separated tokens after the magic number
+        let mut pos = 0;
+        let mut token = || {
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            let start = pos;
+            while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            std::str::from_utf8(&bytes[start..pos]).map_err(|_| invalid("invalid PFM header"))
+        };
+        let n_channels = match token()? {
+            "PF" => 3,
+            "Pf" => 1,
+            _ => return Err(invalid("not a PFM file"))
+        };
+        let width: i32 = token()?.parse().map_err(|_| invalid("invalid PFM width"))?;
+        let height: i32 = token()?.parse().map_err(|_| invalid("invalid PFM height"))?;
+        let scale: f32 = token()?.parse().map_err(|_| invalid("invalid PFM scale"))?;
+        if width <= 0 || height <= 0 {
+            return Err(invalid("invalid PFM resolution"))
+        }
+        // A single whitespace character separates the header from the data
+        if pos >= bytes.len() {
+            return Err(invalid("truncated PFM file"))
+        }
+        let data = &bytes[pos + 1..];
+
+        let n = width as usize * height as usize * n_channels;
+        if data.len() < 4 * n {
+            return Err(invalid("truncated PFM file"))
+        }
+        let little_endian = scale < 0.0;
+        let values: Vec<f32> = data[..4 * n].chunks_exact(4).map(|b| {
+            let b = [b[0], b[1], b[2], b[3]];
+            if little_endian { f32::from_le_bytes(b) } else { f32::from_be_bytes(b) }
+        }).collect();
+
+        // Scanlines are stored from the bottom up
+        let row = width as usize * n_channels;
+        let pixels = values.chunks_exact(row).rev().flatten().map(|v| v * scale.abs()).collect();
+        Ok(Image::new(Point2i::new(width, height), n_channels, pixels))
+    }
+}
+
+/// Chooses the channels of an EXR part that make up an image: red, green and blue if the part has them, otherwise
+/// its luminance channel `Y` or its only channel.
+pub(crate) fn select_exr_channels(channels: Vec<Channel>) -> io::Result<Vec<String>> {
+    let names: Vec<String> = channels.into_iter().map(|c| c.name).collect();
+    let has = |name: &str| names.iter().any(|n| n == name);
+    if has("R") && has("G") && has("B") {
+        return Ok(vec!["R".to_string(), "G".to_string(), "B".to_string()])
+    }
+    if has("Y") {
+        return Ok(vec!["Y".to_string()])
+    }
+    if names.len() == 1 {
+        return Ok(names)
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, "image has neither RGB, luminance nor a single channel"))
+}
+
+/// Creates the slices for reading the channels `names` to separate planes of samples that are `width` pixels wide,
+/// starting at sample `offset` of each plane.
+pub(crate) fn exr_slices<'a>(names: &'a [String], planes: &'a mut [Vec<f32>], offset: usize,
+                             width: usize) -> Vec<SliceMut<'a>> {
+    names.iter()
+        .zip(planes)
+        .map(|(name, plane)| {
+            SliceMut { name, data: SliceDataMut::Float(&mut plane[offset..]), x_stride: 1, y_stride: width }
+        })
+        .collect()
+}
+
+pub(crate) fn exr_error(err: openexr::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err.to_string())
+}
+
+/// The weights of the four pixels that contribute to a pixel of a resampled image.
+struct ResampleWeight {
+    first_pixel: i32,
+    weight: [f32; 4]
+}
+
+/// Computes the weights of a Lanczos filter for resampling `old_res` pixels to `new_res` pixels.
+fn resample_weights(old_res: i32, new_res: i32) -> Vec<ResampleWeight> {
+    const FILTER_WIDTH: f32 = 2.0;
+    (0..new_res).map(|i| {
+        let center = (i as f32 + 0.5) * old_res as f32 / new_res as f32;
+        let first_pixel = (center - FILTER_WIDTH + 0.5).floor() as i32;
+        let mut weight = [0.0; 4];
+        for (j, w) in weight.iter_mut().enumerate() {
+            let pos = (first_pixel + j as i32) as f32 + 0.5;
+            *w = windowed_sinc((pos - center) / FILTER_WIDTH, 2.0);
+        }
+        let sum: f32 = weight.iter().sum();
+        ResampleWeight { first_pixel, weight: weight.map(|w| w / sum) }
+    }).collect()
+}
+
+/// A sinc function with `tau` lobes that is windowed by the central lobe of a wider sinc, for `x` in `[-1, 1]`.
+fn windowed_sinc(x: f32, tau: f32) -> f32 {
+    let x = x.abs();
+    if x < 1e-5 {
+        return 1.0
+    }
+    if x > 1.0 {
+        return 0.0
+    }
+    let x = x * PI;
+    let sinc = (x * tau).sin() / (x * tau);
+    let lanczos = x.sin() / x;
+    sinc * lanczos
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::point2;
+    use super::*;
+
+    #[test]
+    fn test_bilerp_channel() {
+        let image = Image::new(Point2i::new(2, 1), 2, vec![0.0, 1.0, 1.0, 3.0]);
+        assert_eq!(image.bilerp_channel(point2(0.25, 0.5), 0, WrapMode::Repeat), 0.0);
+        assert_eq!(image.bilerp_channel(point2(0.5, 0.5), 0, WrapMode::Repeat), 0.5);
+        assert_eq!(image.bilerp_channel(point2(0.5, 0.5), 1, WrapMode::Repeat), 2.0);
+        // Lookups wrap around at the edges
+        assert_eq!(image.bilerp_channel(point2(1.0, 0.5), 1, WrapMode::Repeat), 2.0);
+        assert_eq!(image.get_channel(Point2i::new(-1, 3), 1, WrapMode::Repeat), 3.0);
+    }
+
+    #[test]
+    fn test_wrap_modes() {
+        let image = Image::new(Point2i::new(2, 2), 1, vec![1.0, 2.0, 3.0, 4.0]);
+        let at = |x, y, wrap| image.get_channel(Point2i::new(x, y), 0, wrap);
+        assert_eq!(at(-1, 0, WrapMode::Repeat), 2.0);
+        assert_eq!(at(-1, 0, WrapMode::Clamp), 1.0);
+        assert_eq!(at(5, 1, WrapMode::Clamp), 4.0);
+        assert_eq!(at(-1, 0, WrapMode::Black), 0.0);
+        assert_eq!(at(1, 1, WrapMode::Black), 4.0);
+        // Octahedral maps fold back onto the mirrored edge
+        assert_eq!(at(-1, 0, WrapMode::OctahedralSphere), 3.0);
+        assert_eq!(at(2, 0, WrapMode::OctahedralSphere), 4.0);
+        assert_eq!(at(0, -1, WrapMode::OctahedralSphere), 2.0);
+        assert_eq!(at(0, 2, WrapMode::OctahedralSphere), 4.0);
+    }
+
+    #[test]
+    fn test_resize_up() {
+        let constant = Image::new(Point2i::new(3, 5), 2, vec![0.5; 30]);
+        let resized = constant.resize_up(Point2i::new(4, 8), WrapMode::Clamp);
+        assert_eq!(resized.resolution(), Point2i::new(4, 8));
+        assert!(resized.pixels().iter().all(|&v| (v - 0.5).abs() < 1e-5));
+
+        // A ramp stays monotonic away from the edges
+        let ramp = Image::new(Point2i::new(3, 1), 1, vec![0.0, 1.0, 2.0]);
+        let resized = ramp.resize_up(Point2i::new(4, 1), WrapMode::Clamp);
+        assert!(resized.pixels().windows(2).all(|w| w[0] < w[1]), "{:?}", resized.pixels());
+    }
+
+    #[test]
+    fn test_read_pfm() {
+        let mut bytes = b"Pf\n2 2\n-1.0\n".to_vec();
+        for v in [1.0f32, 2.0, 3.0, 4.0] {
+            bytes.extend_from_slice(&v.to_le_bytes());
+        }
+        let image = Image::read_pfm(&bytes).unwrap();
+        assert_eq!(image.resolution(), Point2i::new(2, 2));
+        assert_eq!(image.n_channels(), 1);
+        // The bottom row comes first in the file
+        assert_eq!(image.pixels(), &[3.0, 4.0, 1.0, 2.0]);
+
+        assert_eq!(Image::read_pfm(&bytes[..20]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        // The file may end right after the header
+        assert_eq!(Image::read_pfm(b"Pf\n2 2\n-1.0").unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(Image::read("texture.png").unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_read_exr() {
+        use std::fs::File;
+        use openexr::core::attr::{Box2i, Compression, PixelType};
+        use openexr::core::context::WriteContext;
+        use openexr::core::frame_buffer::{Slice, SliceData};
+
+        // A luminance image whose data window does not start at the origin
+        let path = std::env::temp_dir().join(format!("pbr-image-{}.exr", std::process::id()));
+        let pixels = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let mut exr = WriteContext::new(File::create(&path).unwrap()).unwrap();
+        let part = exr.add_scanline_part(None, Box2i::new([1, 2], [3, 3]), Box2i::with_size(4, 4), Compression::Zip)
+            .unwrap();
+        exr.add_channel(part, "Y", PixelType::Half, false).unwrap();
+        exr.write_header().unwrap();
+        exr.write_scanlines(part, &[Slice::interleaved("Y", SliceData::Float(&pixels), 3, 1, 0)]).unwrap();
+        exr.finish().unwrap();
+
+        let image = Image::read(&path);
+        fs::remove_file(&path).unwrap();
+        let image = image.unwrap();
+        assert_eq!(image.resolution(), Point2i::new(3, 2));
+        assert_eq!(image.n_channels(), 1);
+        assert_eq!(image.pixels(), &pixels);
+    }
+}
@@ -1,17 +1,64 @@
 use std::cell::Cell;
 use std::sync::Arc;
-use crate::geom::Normal3;
-use crate::{Normal3f, Point2f, Point3f, Ray, Vector3f};
+use crate::geom::{DotProduct, Normal3};
+use crate::{point2, vec3, Normal3f, Point2f, Point3f, Ray, RayDifferential, Vector3f};
+use crate::image::{Image, WrapMode};
+use crate::math::{next_float_down, next_float_up, solve_linear_system_2x2};
+use crate::material::Material;
+use crate::reflection::{Bsdf, TransportMode};
 use crate::shape::Shape;
+use crate::texture::Texture;
 
 pub trait Interaction {
+    fn p(&self) -> &Point3f;
+    fn p_error(&self) -> &Vector3f;
+    fn time(&self) -> f32;
     fn normal(&self) -> &Normal3f;
 
     fn is_surface_interaction(&self) -> bool {
         self.normal() == &Normal3::new(0.0, 0.0, 0.0)
     }
+
+    /// Spawns a ray leaving the interaction point in direction `d`.
+    ///
+    /// The origin is offset along the normal by the error bounds of the interaction point so that the
+    /// ray does not reintersect the surface it leaves from.
+    fn spawn_ray(&self, d: &Vector3f) -> Ray {
+        let o = offset_ray_origin(self.p(), self.p_error(), self.normal(), d);
+        Ray { o, d: *d, tmax: Cell::new(f32::INFINITY), time: self.time() }
+    }
+
+    /// Spawns a ray from the interaction point towards `p`, stopping just before reaching it.
+    fn spawn_ray_to(&self, p: &Point3f) -> Ray {
+        let o = offset_ray_origin(self.p(), self.p_error(), self.normal(), &(p - self.p()));
+        let d = p - &o;
+        Ray { o, d, tmax: Cell::new(1.0 - SHADOW_EPSILON), time: self.time() }
+    }
 }
 
+const SHADOW_EPSILON: f32 = 0.0001;
+
+/// Offsets a ray origin along the normal `n` so that it lies outside the error bounds of `p`
+/// on the side that `w` points towards.
+pub fn offset_ray_origin(p: &Point3f, p_error: &Vector3f, n: &Normal3f, w: &Vector3f) -> Point3f {
+    let d = n.abs().dot(p_error);
+    let mut offset = Vector3f::from(*n) * d;
+    if w.dot(n) < 0.0 {
+        offset = -offset;
+    }
+    let mut po = *p + offset;
+    // Round offset point away from p
+    for i in 0..3 {
+        if offset[i] > 0.0 {
+            po[i] = next_float_up(po[i]);
+        } else if offset[i] < 0.0 {
+            po[i] = next_float_down(po[i]);
+        }
+    }
+    po
+}
+
+#[derive(Default, Copy, Clone)]
 pub struct Shading {
     pub n: Normal3f,
     pub dpdu: Vector3f,
@@ -20,6 +67,7 @@ pub struct Shading {
     pub dndv: Normal3f
 }
 
+#[derive(Default)]
 pub struct SurfaceInteraction {
     //#region Common Interaction fields
     pub p: Point3f,
@@ -36,18 +84,353 @@ pub struct SurfaceInteraction {
     pub dndv: Normal3f,
     pub shape: Option<Arc<dyn Shape>>,
     pub shading: Shading,
-    // Primitive, BSDF, BSSRDF
+    /// The material of the intersected primitive, if it has one.
+    pub material: Option<Arc<dyn Material>>,
+    // BSSRDF
+    /// The scattering at the interaction, set by the material of the intersected primitive.
+    pub bsdf: Option<Bsdf>,
+    /// Change in surface position for a one pixel step in x and y on the film.
     pub dpdx: Cell<Vector3f>,
     pub dpdy: Cell<Vector3f>,
+    /// Change in `u` for a one pixel step in x and y, i.e. `(du/dx, du/dy)`.
     pub dud: Cell<(f32, f32)>,
+    /// Change in `v` for a one pixel step in x and y, i.e. `(dv/dx, dv/dy)`.
     pub dvd: Cell<(f32, f32)>
 }
 
+impl SurfaceInteraction {
+    /// Creates a new surface interaction from the local differential geometry at `p`.
+    ///
+    /// The geometric normal is computed from `dpdu` and `dpdv` and flipped if `flip_normal` is set, which shapes
+    /// use when their orientation is reversed or their transformation swaps the handedness of the coordinate system.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        p: Point3f,
+        p_error: Vector3f,
+        uv: Point2f,
+        wo: Vector3f,
+        dpdu: Vector3f,
+        dpdv: Vector3f,
+        dndu: Normal3f,
+        dndv: Normal3f,
+        time: f32,
+        flip_normal: bool
+    ) -> Self {
+        let mut n = Normal3f::from(dpdu.cross(&dpdv).normalize());
+        if flip_normal {
+            n = -n;
+        }
+        SurfaceInteraction {
+            p,
+            time,
+            p_error,
+            wo,
+            n,
+            uv,
+            dpdu,
+            dpdv,
+            dndu,
+            dndv,
+            shape: None,
+            shading: Shading { n, dpdu, dpdv, dndu, dndv },
+            ..Default::default()
+        }
+    }
+
+    /// Estimates the screen space derivatives of the surface position and `(u, v)` at the interaction by
+    /// intersecting the auxiliary rays of `ray` with the tangent plane at `p`.
+    ///
+    /// The derivatives are set to zero if the ray has no differentials or they can not be computed.
+    pub fn compute_differentials(&self, ray: &RayDifferential) {
+        if !self.try_compute_differentials(ray) {
+            self.dpdx.set(Vector3f::new(0.0, 0.0, 0.0));
+            self.dpdy.set(Vector3f::new(0.0, 0.0, 0.0));
+            self.dud.set((0.0, 0.0));
+            self.dvd.set((0.0, 0.0));
+        }
+    }
+
+    /// Computes the differentials of the interaction for `ray` and lets the material of the intersected primitive set
+    /// up the scattering functions, leaving `bsdf` unset if there is no material.
+    pub fn compute_scattering_functions(&mut self, ray: &RayDifferential, mode: TransportMode) {
+        self.compute_differentials(ray);
+        if let Some(material) = self.material.clone() {
+            material.compute_scattering_functions(self, mode);
+        }
+    }
+
+    /// Sets the shading geometry to the shading normal `ns` and the given partial derivatives.
+    ///
+    /// If `orientation_is_authoritative` is set, the geometric normal is flipped into the hemisphere of `ns`,
+    /// otherwise `ns` is flipped into the hemisphere of the geometric normal.
+    pub fn set_shading_geometry(&mut self, ns: Normal3f, dpdus: Vector3f, dpdvs: Vector3f, dndus: Normal3f,
+                                dndvs: Normal3f, orientation_is_authoritative: bool) {
+        let n = if orientation_is_authoritative {
+            self.n = self.n.face_forward(&ns);
+            ns
+        } else {
+            ns.face_forward(&self.n)
+        };
+        // Bump maps can produce huge partial derivatives, whose products would overflow
+        let (mut dpdu, mut dpdv) = (dpdus, dpdvs);
+        while dpdu.length_squared() > 1e16 || dpdv.length_squared() > 1e16 {
+            dpdu *= 1e-8;
+            dpdv *= 1e-8;
+        }
+        self.shading = Shading { n, dpdu, dpdv, dndu: dndus, dndv: dndvs };
+    }
+
+    /// Perturbs the shading geometry by offsetting the surface along the shading normal by `displacement`.
+    ///
+    /// The partial derivatives of the displacement are estimated with forward differences over half the change in
+    /// `u` and `v` from one pixel to the next, which requires the differentials of the interaction.
+    pub fn bump_map(&mut self, displacement: &dyn Texture<f32>) {
+        let (dudx, dudy) = self.dud.get();
+        let (dvdx, dvdy) = self.dvd.get();
+        let mut du = 0.5 * (dudx.abs() + dudy.abs());
+        if du == 0.0 {
+            du = 0.0005;
+        }
+        let mut dv = 0.5 * (dvdx.abs() + dvdy.abs());
+        if dv == 0.0 {
+            dv = 0.0005;
+        }
+        let u_displace = displacement.evaluate(&self.shifted(self.shading.dpdu * du, du, 0.0));
+        let v_displace = displacement.evaluate(&self.shifted(self.shading.dpdv * dv, 0.0, dv));
+        let displace = displacement.evaluate(self);
+
+        let n = Vector3f::from(self.shading.n);
+        let dpdu = self.shading.dpdu + n * ((u_displace - displace) / du)
+            + Vector3f::from(self.shading.dndu) * displace;
+        let dpdv = self.shading.dpdv + n * ((v_displace - displace) / dv)
+            + Vector3f::from(self.shading.dndv) * displace;
+        let mut ns = Normal3f::from(dpdu.cross(&dpdv).normalize());
+        // Flip the normal in the same cases in which shapes flip their geometric normal
+        if let Some(shape) = &self.shape {
+            if shape.reverse_orientation() ^ shape.transform_swaps_handedness() {
+                ns = -ns;
+            }
+        }
+        self.set_shading_geometry(ns, dpdu, dpdv, self.shading.dndu, self.shading.dndv, false);
+    }
+
+    /// Replaces the shading normal with the tangent space normal stored in the first three channels of
+    /// `normal_map` at `(u, v)`, which map `[0, 1]` to the `[-1, 1]` components along the shading `dpdu`, the
+    /// bitangent and the shading normal.
+    pub fn normal_map(&mut self, normal_map: &Image) {
+        debug_assert!(normal_map.n_channels() >= 3);
+        // Images start at the top, while v increases upwards
+        let st = point2(self.uv.x, 1.0 - self.uv.y);
+        let c = |i| 2.0 * normal_map.bilerp_channel(st, i, WrapMode::Repeat) - 1.0;
+        let ns = vec3(c(0), c(1), c(2)).normalize();
+
+        // Transform the normal from the tangent space of the shading geometry
+        let z = Vector3f::from(self.shading.n);
+        let x = (self.shading.dpdu - z * z.dot(&self.shading.dpdu)).normalize();
+        let y = z.cross(&x);
+        let ns = x * ns.x + y * ns.y + z * ns.z;
+
+        let dpdu = (self.shading.dpdu - ns * ns.dot(&self.shading.dpdu)).normalize() * self.shading.dpdu.length();
+        let dpdv = ns.cross(&dpdu).normalize() * self.shading.dpdv.length();
+        self.set_shading_geometry(Normal3f::from(ns), dpdu, dpdv, self.shading.dndu, self.shading.dndv, false);
+    }
+
+    /// Returns a copy of the interaction that is offset by `dp` in position and `(du, dv)` in its parameterization,
+    /// at which textures can be evaluated.
+    fn shifted(&self, dp: Vector3f, du: f32, dv: f32) -> SurfaceInteraction {
+        SurfaceInteraction {
+            p: self.p + dp,
+            time: self.time,
+            p_error: self.p_error,
+            wo: self.wo,
+            n: self.n,
+            uv: point2(self.uv.x + du, self.uv.y + dv),
+            dpdu: self.dpdu,
+            dpdv: self.dpdv,
+            dndu: self.dndu,
+            dndv: self.dndv,
+            shape: self.shape.clone(),
+            shading: self.shading,
+            material: self.material.clone(),
+            bsdf: None,
+            dpdx: self.dpdx.clone(),
+            dpdy: self.dpdy.clone(),
+            dud: self.dud.clone(),
+            dvd: self.dvd.clone()
+        }
+    }
+
+    fn try_compute_differentials(&self, ray: &RayDifferential) -> bool {
+        if !ray.has_differentials {
+            return false
+        }
+
+        // Intersect the offset rays with the tangent plane
+        let d = self.n.dot(&Vector3f::from(self.p));
+        let tx = -(self.n.dot(&Vector3f::from(ray.rx_origin)) - d) / self.n.dot(&ray.rx_direction);
+        let ty = -(self.n.dot(&Vector3f::from(ray.ry_origin)) - d) / self.n.dot(&ray.ry_direction);
+        if !tx.is_finite() || !ty.is_finite() {
+            return false
+        }
+        let px = ray.rx_origin + ray.rx_direction * tx;
+        let py = ray.ry_origin + ray.ry_direction * ty;
+        let dpdx = px - self.p;
+        let dpdy = py - self.p;
+
+        // Solve for (du, dv) in the two dimensions where the projection of the tangent plane is largest
+        let (d0, d1) = if self.n.x.abs() > self.n.y.abs() && self.n.x.abs() > self.n.z.abs() {
+            (1, 2)
+        } else if self.n.y.abs() > self.n.z.abs() {
+            (0, 2)
+        } else {
+            (0, 1)
+        };
+        let a = [[self.dpdu[d0], self.dpdv[d0]], [self.dpdu[d1], self.dpdv[d1]]];
+        let (dudx, dvdx) = solve_linear_system_2x2(a, [dpdx[d0], dpdx[d1]]).unwrap_or((0.0, 0.0));
+        let (dudy, dvdy) = solve_linear_system_2x2(a, [dpdy[d0], dpdy[d1]]).unwrap_or((0.0, 0.0));
+        self.dpdx.set(dpdx);
+        self.dpdy.set(dpdy);
+        self.dud.set((dudx, dudy));
+        self.dvd.set((dvdx, dvdy));
+        true
+    }
+}
+
 impl Interaction for SurfaceInteraction {
+    #[inline]
+    fn p(&self) -> &Point3f {
+        &self.p
+    }
+
+    #[inline]
+    fn p_error(&self) -> &Vector3f {
+        &self.p_error
+    }
+
+    #[inline]
+    fn time(&self) -> f32 {
+        self.time
+    }
+
     #[inline]
     fn normal(&self) -> &Normal3f {
         &self.n
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shape::Sphere;
+    use crate::{Point2i, Point3, Transform};
 
+    #[test]
+    fn test_compute_differentials_on_plane() {
+        // The plane z = 0 parameterized with u = x / 2 and v = y / 4
+        let si = SurfaceInteraction::new(
+            Point3::new(0.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 0.0),
+            point2(0.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+            vec3(2.0, 0.0, 0.0),
+            vec3(0.0, 4.0, 0.0),
+            Normal3::new(0.0, 0.0, 0.0),
+            Normal3::new(0.0, 0.0, 0.0),
+            0.0,
+            false
+        );
+        let mut ray = RayDifferential::new(Ray::new(Point3::new(0.0, 0.0, 1.0), vec3(0.0, 0.0, -1.0)));
+        si.compute_differentials(&ray);
+        assert_eq!(si.dud.get(), (0.0, 0.0));
 
-}
\ No newline at end of file
+        ray.has_differentials = true;
+        ray.rx_origin = Point3::new(0.0, 0.0, 1.0);
+        ray.ry_origin = Point3::new(0.0, 0.0, 1.0);
+        ray.rx_direction = vec3(0.1, 0.0, -1.0);
+        ray.ry_direction = vec3(0.0, 0.2, -1.0);
+        si.compute_differentials(&ray);
+        let (dudx, dudy) = si.dud.get();
+        let (dvdx, dvdy) = si.dvd.get();
+        assert!((si.dpdx.get().x - 0.1).abs() < 1e-6);
+        assert!((dudx - 0.05).abs() < 1e-6 && dudy.abs() < 1e-6);
+        assert!(dvdx.abs() < 1e-6 && (dvdy - 0.05).abs() < 1e-6);
+    }
+
+    fn xy_plane() -> SurfaceInteraction {
+        SurfaceInteraction::new(
+            Point3::new(0.0, 0.0, 0.0),
+            vec3(0.0, 0.0, 0.0),
+            point2(0.25, 0.25),
+            vec3(0.0, 0.0, 1.0),
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            Normal3::new(0.0, 0.0, 0.0),
+            Normal3::new(0.0, 0.0, 0.0),
+            0.0,
+            false
+        )
+    }
+
+    #[test]
+    fn test_set_shading_geometry() {
+        let mut si = xy_plane();
+        let (dpdu, dpdv) = (si.dpdu, si.dpdv);
+        si.set_shading_geometry(Normal3::new(0.0, 0.6, -0.8), dpdu, dpdv, si.dndu, si.dndv, false);
+        assert_eq!(si.shading.n, Normal3::new(-0.0, -0.6, 0.8));
+        assert_eq!(si.n, Normal3::new(0.0, 0.0, 1.0));
+
+        si.set_shading_geometry(Normal3::new(0.0, 0.6, -0.8), dpdu, dpdv, si.dndu, si.dndv, true);
+        assert_eq!(si.shading.n, Normal3::new(0.0, 0.6, -0.8));
+        assert_eq!(si.n, Normal3::new(-0.0, -0.0, -1.0));
+    }
+
+    struct Ramp;
+
+    impl Texture<f32> for Ramp {
+        fn evaluate(&self, si: &SurfaceInteraction) -> f32 {
+            0.1 * si.uv.x
+        }
+    }
+
+    #[test]
+    fn test_bump_map() {
+        // Displacing the plane by 0.1 u tilts it towards -x
+        let mut si = xy_plane();
+        si.bump_map(&Ramp);
+        let expected = vec3(-0.1, 0.0, 1.0).normalize();
+        assert!((Vector3f::from(si.shading.n) - expected).length() < 1e-3, "{:?}", si.shading.n);
+        assert!((si.shading.dpdu - vec3(1.0, 0.0, 0.1)).length() < 1e-3);
+        assert_eq!(si.n, Normal3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_bump_map_reverse_orientation() {
+        // Mirroring a sphere turns its normals inwards, and so the shading normal
+        let sphere: Arc<dyn Shape> = Arc::new(Sphere::full(Transform::scale(1.0, 1.0, -1.0), false, 1.0));
+        let ray = Ray::new(Point3::new(2.0, 0.0, 0.0), vec3(-1.0, 0.0, 0.0));
+        let mut si = SurfaceInteraction::default();
+        assert!(sphere.intersect(&ray, &mut 0.0, &mut si, false));
+        si.shape = Some(sphere);
+        let n = si.n;
+        assert!(n.x < -0.99, "{:?}", n);
+        si.bump_map(&Ramp);
+        assert!(Vector3f::from(si.shading.n).dot(&Vector3f::from(n)) > 0.99, "{:?} {:?}", si.shading.n, n);
+    }
+
+    #[test]
+    fn test_normal_map() {
+        // A flat normal map keeps the shading frame
+        let flat = Image::new(Point2i::new(1, 1), 3, vec![0.5, 0.5, 1.0]);
+        let mut si = xy_plane();
+        si.normal_map(&flat);
+        assert!((Vector3f::from(si.shading.n) - vec3(0.0, 0.0, 1.0)).length() < 1e-6);
+
+        // A normal tilted towards u in tangent space
+        let s = std::f32::consts::FRAC_1_SQRT_2;
+        let tilted = Image::new(Point2i::new(1, 1), 3, vec![0.5 + 0.5 * s, 0.5, 0.5 + 0.5 * s]);
+        si.normal_map(&tilted);
+        assert!((Vector3f::from(si.shading.n) - vec3(s, 0.0, s)).length() < 1e-6, "{:?}", si.shading.n);
+        assert!(si.shading.dpdu.dot(&Vector3f::from(si.shading.n)).abs() < 1e-6);
+        assert!((si.shading.dpdv - vec3(0.0, 1.0, 0.0)).length() < 1e-6);
+    }
+}
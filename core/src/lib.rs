@@ -15,6 +15,8 @@
 #![feature(const_fn_floating_point_arithmetic)]
 #![feature(unboxed_closures)]
 #![feature(fn_traits)]
+#![feature(once_cell)]
+#![feature(total_cmp)]
 
 extern crate core;
 
@@ -27,7 +29,22 @@ pub mod transform;
 pub mod spectrum;
 pub mod ray;
 pub mod interaction;
+pub mod reflection;
+pub mod material;
+pub mod texture;
 pub mod primitive;
+pub mod accel;
+pub mod sampling;
+pub mod camera;
+pub mod filter;
+pub mod film;
+pub mod image;
+pub mod mipmap;
+pub mod texcache;
+pub mod hash;
+pub mod rng;
+pub mod lowdiscrepancy;
+pub mod sampler;
 
 mod macros;
 
@@ -39,7 +56,7 @@ pub use interaction::Interaction;
 pub use primitive::*;
 
 pub use types::{Scalar, Bounded};
-use crate::bounds::Bounds3;
+use crate::bounds::{Bounds2, Bounds3};
 use crate::geom::Normal3;
 
 pub type Vector2f = Vector2<f32>;
@@ -54,6 +71,9 @@ pub type Point2i = Point2<i32>;
 pub type Point3f = Point3<f32>;
 pub type Point3i = Point3<i32>;
 
+pub type Bounds2f = Bounds2<f32>;
+pub type Bounds2i = Bounds2<i32>;
+
 pub type Bounds3f = Bounds3<f32>;
 pub type Bounds3i = Bounds3<i32>;
 
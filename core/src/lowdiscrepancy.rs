@@ -0,0 +1,343 @@
+//! Low-discrepancy sequences and their randomization.
+
+use std::lazy::SyncLazy;
+use crate::hash::{hash, mix_bits, permutation_element};
+use crate::math::ONE_MINUS_EPSILON;
+
+/// Number of primes available as bases of radical inverses.
+pub const PRIME_TABLE_SIZE: usize = 1000;
+
+/// The first [`PRIME_TABLE_SIZE`] prime numbers.
+pub static PRIMES: SyncLazy<Vec<u32>> = SyncLazy::new(|| {
+    let mut primes: Vec<u32> = Vec::with_capacity(PRIME_TABLE_SIZE);
+    let mut n = 2;
+    while primes.len() < PRIME_TABLE_SIZE {
+        if primes.iter().take_while(|&&p| p * p <= n).all(|&p| n % p != 0) {
+            primes.push(n);
+        }
+        n += 1;
+    }
+    primes
+});
+
+/// Computes the radical inverse of `a` in the prime base with index `base_index`, i.e. mirrors its digits around
+/// the decimal point.
+pub fn radical_inverse(base_index: usize, mut a: u64) -> f32 {
+    let base = PRIMES[base_index] as u64;
+    // Stop before the reversed digits overflow
+    let limit = u64::MAX / base - base;
+    let inv_base = 1.0 / base as f32;
+    let mut inv_base_m = 1.0;
+    let mut reversed_digits = 0;
+    while a != 0 && reversed_digits < limit {
+        let next = a / base;
+        let digit = a - next * base;
+        reversed_digits = reversed_digits * base + digit;
+        inv_base_m *= inv_base;
+        a = next;
+    }
+    f32::min(reversed_digits as f32 * inv_base_m, ONE_MINUS_EPSILON)
+}
+
+/// Inverts the radical inverse for the integer `inverse` formed by its first `n_digits` digits in `base`.
+pub fn inverse_radical_inverse(mut inverse: u64, base: u64, n_digits: u32) -> u64 {
+    let mut index = 0;
+    for _ in 0..n_digits {
+        let digit = inverse % base;
+        inverse /= base;
+        index = index * base + digit;
+    }
+    index
+}
+
+/// Random permutations of the digits of a base, one for each digit that is representable in an `f32`.
+#[derive(Debug, Clone)]
+pub struct DigitPermutation {
+    base: u32,
+    n_digits: usize,
+    permutations: Vec<u16>
+}
+
+impl DigitPermutation {
+    pub fn new(base: u32, seed: u64) -> Self {
+        assert!(base < 65536);
+        let inv_base = 1.0 / base as f32;
+        let mut n_digits = 0;
+        let mut inv_base_m = 1.0;
+        while 1.0 - (base - 1) as f32 * inv_base_m < 1.0 {
+            n_digits += 1;
+            inv_base_m *= inv_base;
+        }
+        let mut permutations = vec![0; n_digits * base as usize];
+        for digit_index in 0..n_digits {
+            let digit_seed = hash(&[base as u64, digit_index as u64, seed]) as u32;
+            for digit_value in 0..base {
+                permutations[digit_index * base as usize + digit_value as usize] =
+                    permutation_element(digit_value, base, digit_seed) as u16;
+            }
+        }
+        DigitPermutation { base, n_digits, permutations }
+    }
+
+    #[inline]
+    pub fn permute(&self, digit_index: usize, digit_value: u32) -> u32 {
+        debug_assert!(digit_index < self.n_digits && digit_value < self.base);
+        self.permutations[digit_index * self.base as usize + digit_value as usize] as u32
+    }
+}
+
+/// Creates the digit permutations for all bases of the prime table.
+pub fn compute_radical_inverse_permutations(seed: u64) -> Vec<DigitPermutation> {
+    PRIMES.iter().map(|&base| DigitPermutation::new(base, seed)).collect()
+}
+
+/// Computes the radical inverse of `a` in the prime base with index `base_index` after permuting each digit with
+/// `permutation`.
+///
+/// Unlike the plain radical inverse, trailing zero digits are permuted as well, so that the result is randomized
+/// over all representable digits.
+pub fn scrambled_radical_inverse(base_index: usize, mut a: u64, permutation: &DigitPermutation) -> f32 {
+    let base = PRIMES[base_index];
+    debug_assert_eq!(base, permutation.base);
+    let limit = u64::MAX / base as u64 - base as u64;
+    let inv_base = 1.0 / base as f32;
+    let mut inv_base_m = 1.0;
+    let mut reversed_digits = 0u64;
+    let mut digit_index = 0;
+    while 1.0 - (base - 1) as f32 * inv_base_m < 1.0 && reversed_digits < limit {
+        let next = a / base as u64;
+        let digit_value = (a - next * base as u64) as u32;
+        reversed_digits = reversed_digits * base as u64 + permutation.permute(digit_index, digit_value) as u64;
+        inv_base_m *= inv_base;
+        digit_index += 1;
+        a = next;
+    }
+    f32::min(inv_base_m * reversed_digits as f32, ONE_MINUS_EPSILON)
+}
+
+/// Applies a nested uniform scramble to the bits of the binary fraction `v`, where the decision to flip each bit
+/// depends on all higher bits.
+///
+/// See Owen, "Randomly Permuted (t,m,s)-Nets and (t,s)-Sequences" (1995), and Laine and Karras, "Stratified
+/// Sampling for Stochastic Transparency" (2011) for the hashing approach.
+#[inline]
+pub fn owen_scramble(mut v: u32, seed: u32) -> u32 {
+    if seed & 1 != 0 {
+        v ^= 1 << 31;
+    }
+    for b in 1..32 {
+        let mask = !0u32 << (32 - b);
+        if (mix_bits(((v & mask) ^ seed) as u64) as u32) & (1 << b) != 0 {
+            v ^= 1 << (31 - b);
+        }
+    }
+    v
+}
+
+/// Returns `x` such that `a * x = 1 (mod n)`.
+pub fn multiplicative_inverse(a: i64, n: i64) -> u64 {
+    fn extended_gcd(a: i64, b: i64) -> (i64, i64) {
+        if b == 0 {
+            return (1, 0)
+        }
+        let d = a / b;
+        let (xp, yp) = extended_gcd(b, a % b);
+        (yp, xp - d * yp)
+    }
+    let (x, _) = extended_gcd(a, n);
+    x.rem_euclid(n) as u64
+}
+
+/// Number of dimensions of the Sobol sequence, which are limited to the tabulated initial direction numbers.
+pub const N_SOBOL_DIMENSIONS: usize = SOBOL_INITIAL_DIRECTIONS.len() + 1;
+/// Number of bits of the points of the Sobol sequence.
+pub const SOBOL_MATRIX_SIZE: usize = 32;
+
+/// Degree, coefficients and initial direction numbers of the generator matrices for dimensions 1 to 15, from Joe
+/// and Kuo, "Constructing Sobol Sequences with Better Two-Dimensional Projections" (2008).
+const SOBOL_INITIAL_DIRECTIONS: [(u32, u32, &[u32]); 15] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49])
+];
+
+/// Checks if the polynomial over GF(2) with coefficient bits `p` of degree `degree` is primitive, i.e. `x`
+/// generates the multiplicative group of `GF(2)[x] / p`.
+fn is_primitive_polynomial(p: u32, degree: u32) -> bool {
+    if degree == 1 {
+        return p == 0b11
+    }
+    let mul_mod = |a: u32, b: u32| {
+        let mut result = 0u32;
+        let mut a = a;
+        for i in 0..degree {
+            if b & (1 << i) != 0 {
+                result ^= a;
+            }
+            a <<= 1;
+            if a & (1 << degree) != 0 {
+                a ^= p;
+            }
+        }
+        result
+    };
+    let pow_x = |mut e: u32| {
+        let (mut result, mut base) = (1u32, 2u32);
+        while e > 0 {
+            if e & 1 != 0 {
+                result = mul_mod(result, base);
+            }
+            base = mul_mod(base, base);
+            e >>= 1;
+        }
+        result
+    };
+    let order = (1u32 << degree) - 1;
+    if pow_x(order) != 1 {
+        return false
+    }
+    let mut m = order;
+    let mut q = 2;
+    while q * q <= m {
+        if m % q == 0 {
+            if pow_x(order / q) == 1 {
+                return false
+            }
+            while m % q == 0 {
+                m /= q;
+            }
+        }
+        q += 1;
+    }
+    m == 1 || pow_x(order / m) != 1
+}
+
+/// Generator matrices of the Sobol sequence, stored as one column per bit of the sample index.
+pub static SOBOL_MATRICES: SyncLazy<Vec<[u32; SOBOL_MATRIX_SIZE]>> = SyncLazy::new(|| {
+    let mut matrices = Vec::with_capacity(N_SOBOL_DIMENSIONS);
+    // The first dimension is the van der Corput sequence
+    let mut identity = [0; SOBOL_MATRIX_SIZE];
+    for (j, column) in identity.iter_mut().enumerate() {
+        *column = 1 << (31 - j);
+    }
+    matrices.push(identity);
+
+    let mut degree = 1;
+    let mut a = 0;
+    while matrices.len() < N_SOBOL_DIMENSIONS {
+        if a == 1 << (degree - 1) {
+            degree += 1;
+            a = 0;
+        }
+        let p = (1 << degree) | (a << 1) | 1;
+        if !is_primitive_polynomial(p, degree) {
+            a += 1;
+            continue
+        }
+        let dimension = matrices.len();
+        let mut m = [0u32; SOBOL_MATRIX_SIZE];
+        let (s, coefficients, initial) = SOBOL_INITIAL_DIRECTIONS[dimension - 1];
+        debug_assert!(s == degree && coefficients == a);
+        m[..initial.len()].copy_from_slice(initial);
+        let s = degree as usize;
+        for k in s..SOBOL_MATRIX_SIZE {
+            m[k] = m[k - s] ^ (m[k - s] << s);
+            for j in 1..s {
+                if (a >> (s - 1 - j)) & 1 != 0 {
+                    m[k] ^= m[k - j] << j;
+                }
+            }
+        }
+        let mut matrix = [0; SOBOL_MATRIX_SIZE];
+        for (k, column) in matrix.iter_mut().enumerate() {
+            *column = m[k] << (31 - k);
+        }
+        matrices.push(matrix);
+        a += 1;
+    }
+    matrices
+});
+
+/// Returns dimension `dimension` of point `a` of the Sobol sequence after applying `scramble` to its bits.
+#[inline]
+pub fn sobol_sample(mut a: u64, dimension: usize, scramble: impl Fn(u32) -> u32) -> f32 {
+    debug_assert!(dimension < N_SOBOL_DIMENSIONS);
+    let matrix = &SOBOL_MATRICES[dimension];
+    let mut v = 0u32;
+    let mut i = 0;
+    while a != 0 {
+        if a & 1 != 0 {
+            v ^= matrix[i];
+        }
+        a >>= 1;
+        i += 1;
+    }
+    v = scramble(v);
+    f32::min(v as f32 * (1.0 / 4294967296.0), ONE_MINUS_EPSILON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_radical_inverse() {
+        assert_eq!(radical_inverse(0, 1), 0.5);
+        assert_eq!(radical_inverse(0, 6), 0.375);
+        assert!((radical_inverse(1, 5) - (2.0 / 3.0 + 1.0 / 9.0)).abs() < 1e-6);
+        assert_eq!(inverse_radical_inverse(0b011, 2, 3), 0b110);
+        assert_eq!(multiplicative_inverse(27, 32) * 27 % 32, 1);
+    }
+
+    #[test]
+    fn test_primitive_polynomials() {
+        assert!(is_primitive_polynomial(0b111, 2));
+        assert!(is_primitive_polynomial(0b1011, 3));
+        // x^4 + x^3 + x^2 + x + 1 is irreducible but not primitive
+        assert!(!is_primitive_polynomial(0b11111, 4));
+        assert_eq!(SOBOL_MATRICES.len(), N_SOBOL_DIMENSIONS);
+    }
+
+    /// Checks that the first 2^m points of a pair of dimensions form a (0, m, 2)-net.
+    fn is_net(dimensions: (usize, usize), m: u32, scramble: impl Fn(u32) -> u32 + Copy) -> bool {
+        let n = 1u64 << m;
+        (0..=m).all(|k| {
+            let (cells_x, cells_y) = (1u64 << k, 1u64 << (m - k));
+            let mut seen = vec![false; n as usize];
+            (0..n).all(|i| {
+                let x = (sobol_sample(i, dimensions.0, scramble) * cells_x as f32) as u64;
+                let y = (sobol_sample(i, dimensions.1, scramble) * cells_y as f32) as u64;
+                !std::mem::replace(&mut seen[(y * cells_x + x) as usize], true)
+            })
+        })
+    }
+
+    #[test]
+    fn test_sobol_stratification() {
+        assert!(is_net((0, 1), 8, |v| v));
+        assert!(is_net((0, 1), 8, |v| owen_scramble(v, 0x12345678)));
+    }
+
+    #[test]
+    fn test_scrambled_radical_inverse_stratifies() {
+        let permutations = compute_radical_inverse_permutations(7);
+        let mut seen = [false; 9];
+        for i in 0..9 {
+            let v = scrambled_radical_inverse(1, i, &permutations[1]);
+            seen[(v * 9.0) as usize] = true;
+        }
+        assert!(seen.iter().all(|&s| s));
+    }
+}
@@ -0,0 +1,62 @@
+//! Materials, which describe the appearance of surfaces by the scattering functions they produce at points on them.
+
+mod coated;
+mod glass;
+mod hair;
+mod matte;
+mod measured;
+mod metal;
+mod mirror;
+mod mix;
+mod plastic;
+mod substrate;
+mod translucent;
+
+pub use coated::*;
+pub use glass::*;
+pub use hair::*;
+pub use matte::*;
+pub use measured::*;
+pub use metal::*;
+pub use mirror::*;
+pub use mix::*;
+pub use plastic::*;
+pub use substrate::*;
+pub use translucent::*;
+
+use std::sync::Arc;
+use crate::image::Image;
+use crate::interaction::SurfaceInteraction;
+use crate::reflection::{TransportMode, TrowbridgeReitzDistribution};
+use crate::texture::Texture;
+
+/// Describes how light scatters at the surfaces of the primitives that use the material.
+pub trait Material: Send + Sync {
+    /// Computes the scattering functions at `si` and stores them in `si.bsdf`.
+    ///
+    /// `mode` tells whether the path that reached the interaction carries radiance or importance. Materials may
+    /// perturb the shading geometry of `si` before they set up the scattering functions in its frame.
+    fn compute_scattering_functions(&self, si: &mut SurfaceInteraction, mode: TransportMode);
+}
+
+/// Perturbs the shading geometry of `si` by the `displacement` of a material, or by its normal map if it has no
+/// displacement.
+fn perturb_shading(si: &mut SurfaceInteraction, displacement: &Option<Arc<dyn Texture<f32>>>,
+                   normal_map: &Option<Arc<Image>>) {
+    if let Some(displacement) = displacement {
+        si.bump_map(displacement.as_ref());
+    } else if let Some(normal_map) = normal_map {
+        si.normal_map(normal_map);
+    }
+}
+
+/// Creates the microfacet distribution of a surface with the given roughness along `dpdu` and `dpdv`, which is
+/// mapped to the `alpha` parameters of the distribution if `remap` is set.
+fn roughness_distribution(u_roughness: f32, v_roughness: f32, remap: bool) -> TrowbridgeReitzDistribution {
+    if remap {
+        TrowbridgeReitzDistribution::new(TrowbridgeReitzDistribution::roughness_to_alpha(u_roughness),
+                                         TrowbridgeReitzDistribution::roughness_to_alpha(v_roughness))
+    } else {
+        TrowbridgeReitzDistribution::new(u_roughness, v_roughness)
+    }
+}
@@ -0,0 +1,148 @@
+use std::sync::Arc;
+use crate::image::Image;
+use crate::interaction::SurfaceInteraction;
+use crate::material::{perturb_shading, roughness_distribution, Material};
+use crate::reflection::{Bsdf, Bxdf, ConductorBxdf, DielectricBxdf, LambertianReflection, LayeredBxdf,
+                        TransportMode};
+use crate::texture::{ConstantTexture, Texture};
+use crate::Spectrum;
+
+/// The dielectric coating of [`CoatedDiffuseMaterial`] and [`CoatedConductorMaterial`], together with the medium
+/// between the coating and the base.
+#[derive(Clone)]
+pub struct Coating {
+    /// Index of refraction of the coating.
+    pub eta: f32,
+    pub u_roughness: Arc<dyn Texture<f32>>,
+    pub v_roughness: Arc<dyn Texture<f32>>,
+    /// Thickness of the medium, relative to its unit attenuation coefficient.
+    pub thickness: Arc<dyn Texture<f32>>,
+    /// Single-scattering albedo of the medium, or black for a purely absorbing medium.
+    pub albedo: Arc<dyn Texture<Spectrum>>,
+    /// Asymmetry parameter of the Henyey–Greenstein phase function of the medium.
+    pub g: Arc<dyn Texture<f32>>,
+    /// Maximum number of scattering events of each random walk through the layers.
+    pub max_depth: u32,
+    /// Number of random walks that are averaged by each evaluation.
+    pub n_samples: u32
+}
+
+impl Default for Coating {
+    /// A thin, smooth clear coat.
+    fn default() -> Self {
+        Coating {
+            eta: 1.5,
+            u_roughness: Arc::new(ConstantTexture::new(0.0)),
+            v_roughness: Arc::new(ConstantTexture::new(0.0)),
+            thickness: Arc::new(ConstantTexture::new(0.01)),
+            albedo: Arc::new(ConstantTexture::new(Spectrum::new(0.0))),
+            g: Arc::new(ConstantTexture::new(0.0)),
+            max_depth: 10,
+            n_samples: 1
+        }
+    }
+}
+
+impl Coating {
+    /// Puts the coating with its parameters at `si` over `base`.
+    fn over<B: Bxdf>(&self, si: &SurfaceInteraction, base: B, remap_roughness: bool,
+                     mode: TransportMode) -> LayeredBxdf<B> {
+        let distrib = roughness_distribution(self.u_roughness.evaluate(si), self.v_roughness.evaluate(si),
+                                             remap_roughness);
+        let interface = DielectricBxdf::new(self.eta, Box::new(distrib), mode);
+        LayeredBxdf::new(interface, base, self.thickness.evaluate(si), self.albedo.evaluate(si),
+                         self.g.evaluate(si), self.max_depth, self.n_samples)
+    }
+}
+
+/// A diffuse surface with the given `reflectance` under a dielectric coating, such as lacquered wood or glazed
+/// ceramics.
+pub struct CoatedDiffuseMaterial {
+    reflectance: Arc<dyn Texture<Spectrum>>,
+    coating: Coating,
+    remap_roughness: bool,
+    displacement: Option<Arc<dyn Texture<f32>>>,
+    normal_map: Option<Arc<Image>>
+}
+
+impl CoatedDiffuseMaterial {
+    pub fn new(reflectance: Arc<dyn Texture<Spectrum>>, coating: Coating, remap_roughness: bool,
+               displacement: Option<Arc<dyn Texture<f32>>>, normal_map: Option<Arc<Image>>) -> Self {
+        CoatedDiffuseMaterial { reflectance, coating, remap_roughness, displacement, normal_map }
+    }
+}
+
+impl Material for CoatedDiffuseMaterial {
+    fn compute_scattering_functions(&self, si: &mut SurfaceInteraction, mode: TransportMode) {
+        perturb_shading(si, &self.displacement, &self.normal_map);
+        let base = LambertianReflection::new(self.reflectance.evaluate(si).clamp(0.0, 1.0));
+        let mut bsdf = Bsdf::new(si, 1.0);
+        bsdf.add(Box::new(self.coating.over(si, base, self.remap_roughness, mode)));
+        si.bsdf = Some(bsdf);
+    }
+}
+
+/// A metal with the complex index of refraction `eta + i k` relative to the coating under a dielectric coating,
+/// such as car paint.
+pub struct CoatedConductorMaterial {
+    eta: Arc<dyn Texture<Spectrum>>,
+    k: Arc<dyn Texture<Spectrum>>,
+    u_roughness: Arc<dyn Texture<f32>>,
+    v_roughness: Arc<dyn Texture<f32>>,
+    coating: Coating,
+    remap_roughness: bool,
+    displacement: Option<Arc<dyn Texture<f32>>>,
+    normal_map: Option<Arc<Image>>
+}
+
+impl CoatedConductorMaterial {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(eta: Arc<dyn Texture<Spectrum>>, k: Arc<dyn Texture<Spectrum>>, u_roughness: Arc<dyn Texture<f32>>,
+               v_roughness: Arc<dyn Texture<f32>>, coating: Coating, remap_roughness: bool,
+               displacement: Option<Arc<dyn Texture<f32>>>, normal_map: Option<Arc<Image>>) -> Self {
+        CoatedConductorMaterial { eta, k, u_roughness, v_roughness, coating, remap_roughness, displacement, normal_map }
+    }
+}
+
+impl Material for CoatedConductorMaterial {
+    fn compute_scattering_functions(&self, si: &mut SurfaceInteraction, mode: TransportMode) {
+        perturb_shading(si, &self.displacement, &self.normal_map);
+        let distrib = roughness_distribution(self.u_roughness.evaluate(si), self.v_roughness.evaluate(si),
+                                             self.remap_roughness);
+        let base = ConductorBxdf::new(Box::new(distrib), self.eta.evaluate(si), self.k.evaluate(si));
+        let mut bsdf = Bsdf::new(si, 1.0);
+        bsdf.add(Box::new(self.coating.over(si, base, self.remap_roughness, mode)));
+        si.bsdf = Some(bsdf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interaction::Shading;
+    use crate::reflection::BxdfFlags;
+    use crate::{vec3, Normal3f};
+    use super::*;
+
+    #[test]
+    fn test_coating_darkens_base() {
+        let n = Normal3f::new(0.0, 0.0, 1.0);
+        let mut si = SurfaceInteraction {
+            n,
+            shading: Shading { n, dpdu: vec3(1.0, 0.0, 0.0), ..Default::default() },
+            ..Default::default()
+        };
+        let roughness: Arc<dyn Texture<f32>> = Arc::new(ConstantTexture::new(0.3));
+        let coating = Coating { u_roughness: roughness.clone(), v_roughness: roughness, ..Default::default() };
+        CoatedDiffuseMaterial::new(Arc::new(ConstantTexture::new(Spectrum::new(0.5))), coating, true, None, None)
+            .compute_scattering_functions(&mut si, TransportMode::Radiance);
+        let bsdf = si.bsdf.take().unwrap();
+        assert_eq!(bsdf.num_components(BxdfFlags::ALL), 1);
+
+        // Away from the highlight, the coating reflects some light away from the diffuse base
+        let wo = vec3(0.0, 0.6, 0.8);
+        let wi = vec3(0.6, 0.0, 0.8);
+        let f = bsdf.f(&wo, &wi, BxdfFlags::ALL)[0];
+        assert!(f > 0.0 && f < 0.5 * std::f32::consts::FRAC_1_PI, "{}", f);
+        assert!(bsdf.f(&wo, &-wi, BxdfFlags::ALL).is_black());
+    }
+}
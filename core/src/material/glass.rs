@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use crate::image::Image;
+use crate::interaction::SurfaceInteraction;
+use crate::material::{perturb_shading, roughness_distribution, Material};
+use crate::reflection::{Bsdf, DielectricBxdf, TransportMode};
+use crate::texture::Texture;
+
+/// A dielectric such as glass or water with the index of refraction `eta`, which reflects and transmits light
+/// according to the Fresnel equations.
+///
+/// Smooth glass chooses between reflection and transmission according to the Fresnel reflectance when it is
+/// sampled.
+pub struct GlassMaterial {
+    eta: f32,
+    u_roughness: Arc<dyn Texture<f32>>,
+    v_roughness: Arc<dyn Texture<f32>>,
+    remap_roughness: bool,
+    displacement: Option<Arc<dyn Texture<f32>>>,
+    normal_map: Option<Arc<Image>>
+}
+
+impl GlassMaterial {
+    pub fn new(eta: f32, u_roughness: Arc<dyn Texture<f32>>, v_roughness: Arc<dyn Texture<f32>>,
+               remap_roughness: bool, displacement: Option<Arc<dyn Texture<f32>>>,
+               normal_map: Option<Arc<Image>>) -> Self {
+        GlassMaterial { eta, u_roughness, v_roughness, remap_roughness, displacement, normal_map }
+    }
+}
+
+impl Material for GlassMaterial {
+    fn compute_scattering_functions(&self, si: &mut SurfaceInteraction, mode: TransportMode) {
+        perturb_shading(si, &self.displacement, &self.normal_map);
+        let mut bsdf = Bsdf::new(si, self.eta);
+        let distrib = roughness_distribution(self.u_roughness.evaluate(si), self.v_roughness.evaluate(si),
+                                             self.remap_roughness);
+        bsdf.add(Box::new(DielectricBxdf::new(self.eta, Box::new(distrib), mode)));
+        si.bsdf = Some(bsdf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interaction::Shading;
+    use crate::reflection::BxdfFlags;
+    use crate::texture::ConstantTexture;
+    use crate::{point2, vec3, Normal3f, Vector3f};
+    use super::*;
+
+    fn interaction() -> SurfaceInteraction {
+        let n = Normal3f::new(0.0, 0.0, 1.0);
+        SurfaceInteraction {
+            n,
+            shading: Shading { n, dpdu: vec3(1.0, 0.0, 0.0), ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    fn glass(roughness: f32) -> GlassMaterial {
+        let roughness = Arc::new(ConstantTexture::new(roughness));
+        GlassMaterial::new(1.5, roughness.clone(), roughness, false, None, None)
+    }
+
+    #[test]
+    fn test_smooth_glass_is_specular() {
+        let mut si = interaction();
+        glass(0.0).compute_scattering_functions(&mut si, TransportMode::Importance);
+        let bsdf = si.bsdf.unwrap();
+        assert_eq!(bsdf.eta, 1.5);
+        assert_eq!(bsdf.num_components(BxdfFlags::ALL), 1);
+        let wo = vec3(0.6, 0.0, 0.8);
+        for uc in [0.01, 0.5, 0.99] {
+            let mut wi = Vector3f::default();
+            let mut pdf = 0.0;
+            let mut sampled_type = BxdfFlags::empty();
+            let f = bsdf.sample_f(&wo, &mut wi, uc, &point2(0.5, 0.5), &mut pdf, BxdfFlags::ALL, &mut sampled_type);
+            // Without the scaling of radiance, every sample carries all of the energy
+            assert!(sampled_type.is_specular());
+            assert!((f[0] * wi.z.abs() / pdf - 1.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_rough_glass_reflects_and_transmits() {
+        let mut si = interaction();
+        glass(0.3).compute_scattering_functions(&mut si, TransportMode::Radiance);
+        let bsdf = si.bsdf.unwrap();
+        assert_eq!(bsdf.num_components(BxdfFlags::REFLECTION | BxdfFlags::TRANSMISSION | BxdfFlags::GLOSSY), 1);
+        assert_eq!(bsdf.num_components(BxdfFlags::REFLECTION | BxdfFlags::TRANSMISSION | BxdfFlags::SPECULAR), 0);
+        let wo = vec3(0.6, 0.0, 0.8);
+        assert!(!bsdf.f(&wo, &vec3(-0.6, 0.0, 0.8), BxdfFlags::ALL).is_black());
+        assert!(!bsdf.f(&wo, &vec3(-0.3, 0.0, -0.95), BxdfFlags::ALL).is_black());
+    }
+}
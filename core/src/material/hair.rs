@@ -0,0 +1,37 @@
+use std::sync::Arc;
+use crate::interaction::SurfaceInteraction;
+use crate::material::Material;
+use crate::reflection::{Bsdf, HairBxdf, TransportMode};
+use crate::texture::Texture;
+use crate::Spectrum;
+
+/// Hair or fur fibers with the absorption coefficient `sigma_a`, for use with curve shapes.
+///
+/// The absorption is usually derived from the melanin concentrations of the fibers or from their desired color,
+/// see [`HairBxdf::sigma_a_from_concentration`] and [`HairBxdf::sigma_a_from_reflectance`]. The remaining
+/// parameters are passed to [`HairBxdf::new`].
+pub struct HairMaterial {
+    sigma_a: Arc<dyn Texture<Spectrum>>,
+    eta: f32,
+    beta_m: Arc<dyn Texture<f32>>,
+    beta_n: Arc<dyn Texture<f32>>,
+    alpha: Arc<dyn Texture<f32>>
+}
+
+impl HairMaterial {
+    pub fn new(sigma_a: Arc<dyn Texture<Spectrum>>, eta: f32, beta_m: Arc<dyn Texture<f32>>,
+               beta_n: Arc<dyn Texture<f32>>, alpha: Arc<dyn Texture<f32>>) -> Self {
+        HairMaterial { sigma_a, eta, beta_m, beta_n, alpha }
+    }
+}
+
+impl Material for HairMaterial {
+    fn compute_scattering_functions(&self, si: &mut SurfaceInteraction, _mode: TransportMode) {
+        let mut bsdf = Bsdf::new(si, self.eta);
+        // Curves parameterize the offset across the fiber by `v`
+        let h = (-1.0 + 2.0 * si.uv.y).clamp(-1.0, 1.0);
+        bsdf.add(Box::new(HairBxdf::new(h, self.eta, self.sigma_a.evaluate(si), self.beta_m.evaluate(si),
+                                        self.beta_n.evaluate(si), self.alpha.evaluate(si))));
+        si.bsdf = Some(bsdf);
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+use crate::image::Image;
+use crate::interaction::SurfaceInteraction;
+use crate::material::{perturb_shading, Material};
+use crate::reflection::{Bsdf, LambertianReflection, OrenNayar, TransportMode};
+use crate::texture::Texture;
+use crate::Spectrum;
+
+/// A purely diffuse surface with reflectance `kd`, which follows the Oren–Nayar model for rough surfaces if the
+/// standard deviation `sigma` of the microfacet orientation angles in degrees is positive.
+pub struct MatteMaterial {
+    kd: Arc<dyn Texture<Spectrum>>,
+    sigma: Arc<dyn Texture<f32>>,
+    displacement: Option<Arc<dyn Texture<f32>>>,
+    normal_map: Option<Arc<Image>>
+}
+
+impl MatteMaterial {
+    pub fn new(kd: Arc<dyn Texture<Spectrum>>, sigma: Arc<dyn Texture<f32>>,
+               displacement: Option<Arc<dyn Texture<f32>>>, normal_map: Option<Arc<Image>>) -> Self {
+        MatteMaterial { kd, sigma, displacement, normal_map }
+    }
+}
+
+impl Material for MatteMaterial {
+    fn compute_scattering_functions(&self, si: &mut SurfaceInteraction, _mode: TransportMode) {
+        perturb_shading(si, &self.displacement, &self.normal_map);
+        let mut bsdf = Bsdf::new(si, 1.0);
+        let r = self.kd.evaluate(si).clamp(0.0, f32::INFINITY);
+        let sigma = self.sigma.evaluate(si).clamp(0.0, 90.0);
+        if !r.is_black() {
+            if sigma == 0.0 {
+                bsdf.add(Box::new(LambertianReflection::new(r)));
+            } else {
+                bsdf.add(Box::new(OrenNayar::new(r, sigma)));
+            }
+        }
+        si.bsdf = Some(bsdf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interaction::Shading;
+    use crate::reflection::BxdfFlags;
+    use crate::texture::ConstantTexture;
+    use crate::{vec3, Normal3f, Point2i};
+    use super::*;
+
+    fn interaction() -> SurfaceInteraction {
+        let n = Normal3f::new(0.0, 0.0, 1.0);
+        SurfaceInteraction {
+            n,
+            shading: Shading { n, dpdu: vec3(1.0, 0.0, 0.0), dpdv: vec3(0.0, 1.0, 0.0), ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    fn matte(kd: f32, sigma: f32, normal_map: Option<Arc<Image>>) -> MatteMaterial {
+        MatteMaterial::new(Arc::new(ConstantTexture::new(Spectrum::new(kd))), Arc::new(ConstantTexture::new(sigma)),
+                           None, normal_map)
+    }
+
+    #[test]
+    fn test_matte() {
+        let mut si = interaction();
+        matte(0.5, 0.0, None).compute_scattering_functions(&mut si, TransportMode::Radiance);
+        let bsdf = si.bsdf.take().unwrap();
+        assert_eq!(bsdf.num_components(BxdfFlags::REFLECTION | BxdfFlags::DIFFUSE), 1);
+        let f = bsdf.f(&vec3(0.0, 0.6, 0.8), &vec3(0.0, 0.0, 1.0), BxdfFlags::ALL);
+        assert!((f[0] - 0.5 * std::f32::consts::FRAC_1_PI).abs() < 1e-6);
+
+        // A black surface does not scatter at all
+        matte(0.0, 20.0, None).compute_scattering_functions(&mut si, TransportMode::Radiance);
+        assert_eq!(si.bsdf.unwrap().num_components(BxdfFlags::ALL), 0);
+    }
+
+    #[test]
+    fn test_normal_map_perturbs_bsdf_frame() {
+        // A normal tilted towards dpdu, encoded in [0, 1]
+        let normal_map = Image::new(Point2i::new(1, 1), 3, vec![0.8, 0.5, 0.9]);
+        let mut si = interaction();
+        matte(0.5, 0.0, Some(Arc::new(normal_map))).compute_scattering_functions(&mut si, TransportMode::Radiance);
+        assert!((si.shading.n.x - 0.6).abs() < 1e-5 && (si.shading.n.z - 0.8).abs() < 1e-5, "{:?}", si.shading.n);
+        let bsdf = si.bsdf.unwrap();
+        let n = bsdf.local_to_world(&vec3(0.0, 0.0, 1.0));
+        assert!((n.x - 0.6).abs() < 1e-5 && (n.z - 0.8).abs() < 1e-5, "{:?}", n);
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::io;
+use std::lazy::SyncLazy;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use crate::image::Image;
+use crate::interaction::SurfaceInteraction;
+use crate::material::{perturb_shading, Material};
+use crate::reflection::{Bsdf, MeasuredBrdf, MeasuredBxdf, TransportMode};
+use crate::texture::Texture;
+
+/// The measured BRDFs that have been read, by the path of their file.
+static BRDFS: SyncLazy<Mutex<HashMap<PathBuf, Arc<MeasuredBrdf>>>> = SyncLazy::new(Default::default);
+
+/// A material whose reflection was measured, stored in one of the file formats of [`MeasuredBrdf`].
+pub struct MeasuredMaterial {
+    brdf: Arc<MeasuredBrdf>,
+    displacement: Option<Arc<dyn Texture<f32>>>,
+    normal_map: Option<Arc<Image>>
+}
+
+impl MeasuredMaterial {
+    /// Creates a material with the measured BRDF in the file at `path`, which is only read once for all materials
+    /// that refer to it.
+    pub fn new<P: AsRef<Path>>(path: P, displacement: Option<Arc<dyn Texture<f32>>>,
+                               normal_map: Option<Arc<Image>>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut brdfs = BRDFS.lock().unwrap();
+        let brdf = match brdfs.get(path) {
+            Some(brdf) => brdf.clone(),
+            None => {
+                let brdf = Arc::new(MeasuredBrdf::read(path)?);
+                brdfs.insert(path.to_path_buf(), brdf.clone());
+                brdf
+            }
+        };
+        Ok(MeasuredMaterial { brdf, displacement, normal_map })
+    }
+}
+
+impl Material for MeasuredMaterial {
+    fn compute_scattering_functions(&self, si: &mut SurfaceInteraction, _mode: TransportMode) {
+        perturb_shading(si, &self.displacement, &self.normal_map);
+        let mut bsdf = Bsdf::new(si, 1.0);
+        bsdf.add(Box::new(MeasuredBxdf::new(self.brdf.clone())));
+        si.bsdf = Some(bsdf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_files() {
+        let err = MeasuredMaterial::new("brdfs/missing.binary", None, None).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        let err = MeasuredMaterial::new("brdfs/gold.png", None, None).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,37 @@
+use std::sync::Arc;
+use crate::image::Image;
+use crate::interaction::SurfaceInteraction;
+use crate::material::{perturb_shading, roughness_distribution, Material};
+use crate::reflection::{Bsdf, ConductorBxdf, TransportMode};
+use crate::texture::Texture;
+use crate::Spectrum;
+
+/// A metal with the complex index of refraction `eta + i k`, which reflects glossily if it is rough.
+pub struct MetalMaterial {
+    eta: Arc<dyn Texture<Spectrum>>,
+    k: Arc<dyn Texture<Spectrum>>,
+    u_roughness: Arc<dyn Texture<f32>>,
+    v_roughness: Arc<dyn Texture<f32>>,
+    remap_roughness: bool,
+    displacement: Option<Arc<dyn Texture<f32>>>,
+    normal_map: Option<Arc<Image>>
+}
+
+impl MetalMaterial {
+    pub fn new(eta: Arc<dyn Texture<Spectrum>>, k: Arc<dyn Texture<Spectrum>>, u_roughness: Arc<dyn Texture<f32>>,
+               v_roughness: Arc<dyn Texture<f32>>, remap_roughness: bool,
+               displacement: Option<Arc<dyn Texture<f32>>>, normal_map: Option<Arc<Image>>) -> Self {
+        MetalMaterial { eta, k, u_roughness, v_roughness, remap_roughness, displacement, normal_map }
+    }
+}
+
+impl Material for MetalMaterial {
+    fn compute_scattering_functions(&self, si: &mut SurfaceInteraction, _mode: TransportMode) {
+        perturb_shading(si, &self.displacement, &self.normal_map);
+        let mut bsdf = Bsdf::new(si, 1.0);
+        let distrib = roughness_distribution(self.u_roughness.evaluate(si), self.v_roughness.evaluate(si),
+                                             self.remap_roughness);
+        bsdf.add(Box::new(ConductorBxdf::new(Box::new(distrib), self.eta.evaluate(si), self.k.evaluate(si))));
+        si.bsdf = Some(bsdf);
+    }
+}
@@ -0,0 +1,33 @@
+use std::sync::Arc;
+use crate::image::Image;
+use crate::interaction::SurfaceInteraction;
+use crate::material::{perturb_shading, Material};
+use crate::reflection::{Bsdf, FresnelNoOp, SpecularReflection, TransportMode};
+use crate::texture::Texture;
+use crate::Spectrum;
+
+/// A perfect mirror that reflects the fraction `kr` of light at all angles.
+pub struct MirrorMaterial {
+    kr: Arc<dyn Texture<Spectrum>>,
+    displacement: Option<Arc<dyn Texture<f32>>>,
+    normal_map: Option<Arc<Image>>
+}
+
+impl MirrorMaterial {
+    pub fn new(kr: Arc<dyn Texture<Spectrum>>, displacement: Option<Arc<dyn Texture<f32>>>,
+               normal_map: Option<Arc<Image>>) -> Self {
+        MirrorMaterial { kr, displacement, normal_map }
+    }
+}
+
+impl Material for MirrorMaterial {
+    fn compute_scattering_functions(&self, si: &mut SurfaceInteraction, _mode: TransportMode) {
+        perturb_shading(si, &self.displacement, &self.normal_map);
+        let mut bsdf = Bsdf::new(si, 1.0);
+        let r = self.kr.evaluate(si).clamp(0.0, f32::INFINITY);
+        if !r.is_black() {
+            bsdf.add(Box::new(SpecularReflection::new(r, Box::new(FresnelNoOp))));
+        }
+        si.bsdf = Some(bsdf);
+    }
+}
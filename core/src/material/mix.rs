@@ -0,0 +1,109 @@
+use std::sync::Arc;
+use crate::interaction::SurfaceInteraction;
+use crate::material::Material;
+use crate::reflection::{Bsdf, FramedBxdf, ScaledBxdf, TransportMode};
+use crate::texture::Texture;
+use crate::{vec3, Spectrum};
+
+/// A blend of two materials, where `amount` is the weight of the second material and the first is weighted by
+/// the remainder.
+///
+/// Each material applies its own bump or normal map, and its BxDFs keep the shading frame it perturbed.
+pub struct MixMaterial {
+    m1: Arc<dyn Material>,
+    m2: Arc<dyn Material>,
+    amount: Arc<dyn Texture<Spectrum>>
+}
+
+impl MixMaterial {
+    pub fn new(m1: Arc<dyn Material>, m2: Arc<dyn Material>, amount: Arc<dyn Texture<Spectrum>>) -> Self {
+        MixMaterial { m1, m2, amount }
+    }
+}
+
+impl Material for MixMaterial {
+    fn compute_scattering_functions(&self, si: &mut SurfaceInteraction, mode: TransportMode) {
+        let s2 = self.amount.evaluate(si).clamp(0.0, 1.0);
+        let s1 = (Spectrum::new(1.0) - s2).clamp(0.0, 1.0);
+
+        // Both materials start from the original shading geometry, which they may perturb
+        let shading = si.shading;
+        self.m1.compute_scattering_functions(si, mode);
+        let bsdf1 = si.bsdf.take();
+        si.shading = shading;
+        self.m2.compute_scattering_functions(si, mode);
+        let bsdf2 = si.bsdf.take();
+        si.shading = shading;
+
+        // The BxDFs of both materials are evaluated in their own shading frames, rotated into the original one
+        let mut bsdf = Bsdf::new(si, bsdf1.as_ref().map_or(1.0, |b| b.eta));
+        for (b, s) in [(bsdf1, s1), (bsdf2, s2)] {
+            let b = match b {
+                Some(b) => b,
+                None => continue
+            };
+            let axis = |v| bsdf.world_to_local(&b.local_to_world(&v));
+            let (x, y, z) = (axis(vec3(1.0, 0.0, 0.0)), axis(vec3(0.0, 1.0, 0.0)), axis(vec3(0.0, 0.0, 1.0)));
+            for bxdf in b.into_bxdfs() {
+                bsdf.add(Box::new(ScaledBxdf::new(Box::new(FramedBxdf::new(bxdf, x, y, z)), s)));
+            }
+        }
+        si.bsdf = Some(bsdf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_1_PI;
+    use crate::interaction::Shading;
+    use crate::material::MatteMaterial;
+    use crate::reflection::BxdfFlags;
+    use crate::image::Image;
+    use crate::texture::ConstantTexture;
+    use crate::{Normal3f, Point2i};
+    use super::*;
+
+    fn interaction() -> SurfaceInteraction {
+        let n = Normal3f::new(0.0, 0.0, 1.0);
+        SurfaceInteraction {
+            n,
+            shading: Shading { n, dpdu: vec3(1.0, 0.0, 0.0), dpdv: vec3(0.0, 1.0, 0.0), ..Default::default() },
+            ..Default::default()
+        }
+    }
+
+    fn matte(kd: f32, normal_map: Option<Arc<Image>>) -> Arc<MatteMaterial> {
+        Arc::new(MatteMaterial::new(Arc::new(ConstantTexture::new(Spectrum::new(kd))),
+                                    Arc::new(ConstantTexture::new(0.0)), None, normal_map))
+    }
+
+    #[test]
+    fn test_mix_weights_materials() {
+        let mut si = interaction();
+        let matte = |kd| matte(kd, None);
+        let mix = MixMaterial::new(matte(0.2), matte(0.8), Arc::new(ConstantTexture::new(Spectrum::new(0.25))));
+        mix.compute_scattering_functions(&mut si, TransportMode::Radiance);
+        let bsdf = si.bsdf.unwrap();
+        assert_eq!(bsdf.num_components(BxdfFlags::ALL), 2);
+        let f = bsdf.f(&vec3(0.0, 0.6, 0.8), &vec3(0.0, 0.0, 1.0), BxdfFlags::ALL);
+        assert!((f[0] - (0.75 * 0.2 + 0.25 * 0.8) * FRAC_1_PI).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_mix_keeps_perturbed_frames() {
+        // The second material tilts its shading normal to (0.6, 0, 0.8), so that it does not reflect light arriving
+        // from far along the -x axis
+        let normal_map = Image::new(Point2i::new(1, 1), 3, vec![0.8, 0.5, 0.9]);
+        let mix = MixMaterial::new(matte(0.0, None), matte(0.8, Some(Arc::new(normal_map))),
+                                   Arc::new(ConstantTexture::new(Spectrum::new(1.0))));
+        let mut si = interaction();
+        mix.compute_scattering_functions(&mut si, TransportMode::Radiance);
+        assert_eq!(si.shading.n, Normal3f::new(0.0, 0.0, 1.0));
+        let bsdf = si.bsdf.unwrap();
+        let wo = vec3(0.0, 0.0, 1.0);
+        let wi = vec3(-0.9, 0.0, 0.1).normalize();
+        assert!(bsdf.f(&wo, &wi, BxdfFlags::ALL).is_black());
+        let f = bsdf.f(&wo, &vec3(0.9, 0.0, 0.1).normalize(), BxdfFlags::ALL);
+        assert!((f[0] - 0.8 * FRAC_1_PI).abs() < 1e-6);
+    }
+}
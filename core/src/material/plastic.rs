@@ -0,0 +1,48 @@
+use std::sync::Arc;
+use crate::image::Image;
+use crate::interaction::SurfaceInteraction;
+use crate::material::{perturb_shading, roughness_distribution, Material};
+use crate::reflection::{Bsdf, ConductorBxdf, LambertianReflection, ScaledBxdf, TransportMode};
+use crate::texture::Texture;
+use crate::Spectrum;
+
+/// A diffuse surface with reflectance `kd` under a glossy dielectric coating whose reflection is scaled by `ks`.
+///
+/// The coating is modeled as an independent lobe, unlike [`CoatedDiffuseMaterial`](super::CoatedDiffuseMaterial),
+/// so it is much cheaper to evaluate but does not account for the light that is reflected back and forth under it.
+pub struct PlasticMaterial {
+    kd: Arc<dyn Texture<Spectrum>>,
+    ks: Arc<dyn Texture<Spectrum>>,
+    roughness: Arc<dyn Texture<f32>>,
+    remap_roughness: bool,
+    displacement: Option<Arc<dyn Texture<f32>>>,
+    normal_map: Option<Arc<Image>>
+}
+
+impl PlasticMaterial {
+    pub fn new(kd: Arc<dyn Texture<Spectrum>>, ks: Arc<dyn Texture<Spectrum>>, roughness: Arc<dyn Texture<f32>>,
+               remap_roughness: bool, displacement: Option<Arc<dyn Texture<f32>>>,
+               normal_map: Option<Arc<Image>>) -> Self {
+        PlasticMaterial { kd, ks, roughness, remap_roughness, displacement, normal_map }
+    }
+}
+
+impl Material for PlasticMaterial {
+    fn compute_scattering_functions(&self, si: &mut SurfaceInteraction, _mode: TransportMode) {
+        perturb_shading(si, &self.displacement, &self.normal_map);
+        let mut bsdf = Bsdf::new(si, 1.0);
+        let kd = self.kd.evaluate(si).clamp(0.0, f32::INFINITY);
+        if !kd.is_black() {
+            bsdf.add(Box::new(LambertianReflection::new(kd)));
+        }
+        let ks = self.ks.evaluate(si).clamp(0.0, f32::INFINITY);
+        if !ks.is_black() {
+            let roughness = self.roughness.evaluate(si);
+            let distrib = roughness_distribution(roughness, roughness, self.remap_roughness);
+            // Without absorption, the Fresnel reflectance of a conductor is the one of a dielectric
+            let coating = ConductorBxdf::new(Box::new(distrib), Spectrum::new(1.5), Spectrum::new(0.0));
+            bsdf.add(Box::new(ScaledBxdf::new(Box::new(coating), ks)));
+        }
+        si.bsdf = Some(bsdf);
+    }
+}
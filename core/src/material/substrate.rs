@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use crate::image::Image;
+use crate::interaction::SurfaceInteraction;
+use crate::material::{perturb_shading, roughness_distribution, Material};
+use crate::reflection::{Bsdf, FresnelBlend, TransportMode};
+use crate::texture::Texture;
+use crate::Spectrum;
+
+/// A diffuse substrate with reflectance `kd` under a glossy coating with the reflectance `ks` at normal incidence,
+/// whose reflection increases towards grazing angles at the expense of the substrate.
+///
+/// The roughness is expected to be positive.
+pub struct SubstrateMaterial {
+    kd: Arc<dyn Texture<Spectrum>>,
+    ks: Arc<dyn Texture<Spectrum>>,
+    u_roughness: Arc<dyn Texture<f32>>,
+    v_roughness: Arc<dyn Texture<f32>>,
+    remap_roughness: bool,
+    displacement: Option<Arc<dyn Texture<f32>>>,
+    normal_map: Option<Arc<Image>>
+}
+
+impl SubstrateMaterial {
+    pub fn new(kd: Arc<dyn Texture<Spectrum>>, ks: Arc<dyn Texture<Spectrum>>, u_roughness: Arc<dyn Texture<f32>>,
+               v_roughness: Arc<dyn Texture<f32>>, remap_roughness: bool,
+               displacement: Option<Arc<dyn Texture<f32>>>, normal_map: Option<Arc<Image>>) -> Self {
+        SubstrateMaterial { kd, ks, u_roughness, v_roughness, remap_roughness, displacement, normal_map }
+    }
+}
+
+impl Material for SubstrateMaterial {
+    fn compute_scattering_functions(&self, si: &mut SurfaceInteraction, _mode: TransportMode) {
+        perturb_shading(si, &self.displacement, &self.normal_map);
+        let mut bsdf = Bsdf::new(si, 1.0);
+        let kd = self.kd.evaluate(si).clamp(0.0, f32::INFINITY);
+        let ks = self.ks.evaluate(si).clamp(0.0, f32::INFINITY);
+        if !kd.is_black() || !ks.is_black() {
+            let distrib = roughness_distribution(self.u_roughness.evaluate(si), self.v_roughness.evaluate(si),
+                                                 self.remap_roughness);
+            bsdf.add(Box::new(FresnelBlend::new(kd, ks, Box::new(distrib))));
+        }
+        si.bsdf = Some(bsdf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interaction::Shading;
+    use crate::reflection::BxdfFlags;
+    use crate::texture::ConstantTexture;
+    use crate::{vec3, Normal3f};
+    use super::*;
+
+    #[test]
+    fn test_coating_reflects_more_at_grazing_angles() {
+        let n = Normal3f::new(0.0, 0.0, 1.0);
+        let mut si = SurfaceInteraction {
+            n,
+            shading: Shading { n, dpdu: vec3(1.0, 0.0, 0.0), ..Default::default() },
+            ..Default::default()
+        };
+        let roughness: Arc<dyn Texture<f32>> = Arc::new(ConstantTexture::new(0.1));
+        let material = SubstrateMaterial::new(Arc::new(ConstantTexture::new(Spectrum::new(0.0))),
+                                              Arc::new(ConstantTexture::new(Spectrum::new(0.04))), roughness.clone(),
+                                              roughness, false, None, None);
+        material.compute_scattering_functions(&mut si, TransportMode::Radiance);
+        let bsdf = si.bsdf.unwrap();
+        // Over a black substrate, only the coating reflects
+        let mirror = |theta: f32| {
+            let (sin, cos) = theta.sin_cos();
+            bsdf.f(&vec3(sin, 0.0, cos), &vec3(-sin, 0.0, cos), BxdfFlags::ALL)[0] * cos
+        };
+        assert!(mirror(1.3) > mirror(0.2), "{} <= {}", mirror(1.3), mirror(0.2));
+    }
+}
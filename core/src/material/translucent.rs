@@ -0,0 +1,62 @@
+use std::sync::Arc;
+use crate::image::Image;
+use crate::interaction::SurfaceInteraction;
+use crate::material::{perturb_shading, roughness_distribution, Material};
+use crate::reflection::{Bsdf, DielectricBxdf, LambertianReflection, LambertianTransmission, ScaledBxdf,
+                        TransportMode};
+use crate::texture::Texture;
+use crate::Spectrum;
+
+/// A thin surface such as leaves or paper that scatters diffusely with `kd` and glossily with `ks` on both sides,
+/// where `reflect` and `transmit` are the fractions of the scattered light that are reflected and transmitted.
+pub struct TranslucentMaterial {
+    kd: Arc<dyn Texture<Spectrum>>,
+    ks: Arc<dyn Texture<Spectrum>>,
+    reflect: Arc<dyn Texture<Spectrum>>,
+    transmit: Arc<dyn Texture<Spectrum>>,
+    roughness: Arc<dyn Texture<f32>>,
+    remap_roughness: bool,
+    displacement: Option<Arc<dyn Texture<f32>>>,
+    normal_map: Option<Arc<Image>>
+}
+
+impl TranslucentMaterial {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(kd: Arc<dyn Texture<Spectrum>>, ks: Arc<dyn Texture<Spectrum>>, reflect: Arc<dyn Texture<Spectrum>>,
+               transmit: Arc<dyn Texture<Spectrum>>, roughness: Arc<dyn Texture<f32>>, remap_roughness: bool,
+               displacement: Option<Arc<dyn Texture<f32>>>, normal_map: Option<Arc<Image>>) -> Self {
+        TranslucentMaterial { kd, ks, reflect, transmit, roughness, remap_roughness, displacement, normal_map }
+    }
+}
+
+impl Material for TranslucentMaterial {
+    fn compute_scattering_functions(&self, si: &mut SurfaceInteraction, mode: TransportMode) {
+        perturb_shading(si, &self.displacement, &self.normal_map);
+        let eta = 1.5;
+        let mut bsdf = Bsdf::new(si, eta);
+        let r = self.reflect.evaluate(si).clamp(0.0, f32::INFINITY);
+        let t = self.transmit.evaluate(si).clamp(0.0, f32::INFINITY);
+        if r.is_black() && t.is_black() {
+            si.bsdf = Some(bsdf);
+            return
+        }
+
+        let kd = self.kd.evaluate(si).clamp(0.0, f32::INFINITY);
+        if !kd.is_black() {
+            if !r.is_black() {
+                bsdf.add(Box::new(LambertianReflection::new(r * kd)));
+            }
+            if !t.is_black() {
+                bsdf.add(Box::new(LambertianTransmission::new(t * kd)));
+            }
+        }
+        let ks = self.ks.evaluate(si).clamp(0.0, f32::INFINITY);
+        if !ks.is_black() {
+            let roughness = self.roughness.evaluate(si);
+            let distrib = roughness_distribution(roughness, roughness, self.remap_roughness);
+            let interface = DielectricBxdf::new(eta, Box::new(distrib), mode);
+            bsdf.add(Box::new(ScaledBxdf::split(Box::new(interface), r * ks, t * ks)));
+        }
+        si.bsdf = Some(bsdf);
+    }
+}
@@ -51,4 +51,117 @@ impl Lerp for f64 {
             (1.0 - t) * v0 + t * v1
         }
     }
-}
\ No newline at end of file
+}
+/// Half of the machine epsilon of `f32`, i.e. the maximum relative error of a single rounded operation.
+pub const MACHINE_EPSILON: f32 = f32::EPSILON * 0.5;
+
+/// The largest `f32` less than one, used to keep sample values in `[0, 1)`.
+pub const ONE_MINUS_EPSILON: f32 = 1.0 - MACHINE_EPSILON;
+
+/// Returns a conservative bound for the relative error accumulated over `n` floating-point operations.
+#[inline]
+pub const fn gamma(n: i32) -> f32 {
+    (n as f32 * MACHINE_EPSILON) / (1.0 - n as f32 * MACHINE_EPSILON)
+}
+
+/// Returns the next representable `f32` greater than `v`.
+#[inline]
+pub fn next_float_up(v: f32) -> f32 {
+    if v.is_infinite() && v > 0.0 {
+        return v
+    }
+    // Skip over negative zero so that -0.0 and 0.0 behave the same
+    let v = if v == -0.0 { 0.0 } else { v };
+    let bits = v.to_bits();
+    let bits = if v >= 0.0 { bits + 1 } else { bits - 1 };
+    f32::from_bits(bits)
+}
+
+/// Returns the next representable `f32` less than `v`.
+#[inline]
+pub fn next_float_down(v: f32) -> f32 {
+    if v.is_infinite() && v < 0.0 {
+        return v
+    }
+    let v = if v == 0.0 { -0.0 } else { v };
+    let bits = v.to_bits();
+    let bits = if v > 0.0 { bits - 1 } else { bits + 1 };
+    f32::from_bits(bits)
+}
+
+#[inline]
+pub fn safe_sqrt(x: f32) -> f32 {
+    debug_assert!(x >= -1e-3);
+    f32::sqrt(f32::max(0.0, x))
+}
+
+#[inline]
+pub fn safe_acos(x: f32) -> f32 {
+    f32::acos(x.clamp(-1.0, 1.0))
+}
+
+#[inline]
+pub fn safe_asin(x: f32) -> f32 {
+    f32::asin(x.clamp(-1.0, 1.0))
+}
+
+/// Solves the quadratic equation `a * t^2 + b * t + c = 0`, returning the roots in ascending order.
+///
+/// The roots are computed with double precision and the numerically stable form of the quadratic formula
+/// to avoid catastrophic cancellation when `b^2` is close to `4ac`.
+pub fn quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None
+    }
+    let root = discriminant.sqrt();
+    let q = if b < 0.0 { -0.5 * (b - root) } else { -0.5 * (b + root) };
+    let t0 = q / a;
+    let t1 = c / q;
+    if t0 > t1 { Some((t1, t0)) } else { Some((t0, t1)) }
+}
+
+/// Solves the linear system `a * x = b` for a 2x2 matrix `a`, returning `None` if `a` is singular.
+pub fn solve_linear_system_2x2(a: [[f32; 2]; 2], b: [f32; 2]) -> Option<(f32, f32)> {
+    let det = a[0][0] * a[1][1] - a[0][1] * a[1][0];
+    if det.abs() < 1e-10 {
+        return None
+    }
+    let x0 = (a[1][1] * b[0] - a[0][1] * b[1]) / det;
+    let x1 = (a[0][0] * b[1] - a[1][0] * b[0]) / det;
+    if x0.is_nan() || x1.is_nan() {
+        return None
+    }
+    Some((x0, x1))
+}
+
+/// Approximates the error function with a maximum error of 1.5e-7, after Abramowitz and Stegun, formula 7.1.26.
+#[allow(clippy::excessive_precision)]
+pub fn erf(x: f32) -> f32 {
+    const A: [f32; 5] = [0.254829592, -0.284496736, 1.421413741, -1.453152027, 1.061405429];
+    const P: f32 = 0.3275911;
+
+    let sign = x.signum();
+    let x = x.abs();
+    let t = 1.0 / (1.0 + P * x);
+    let poly = A.iter().rev().fold(0.0, |acc, &a| (acc + a) * t);
+    sign * (1.0 - poly * (-x * x).exp())
+}
+
+/// Approximates the inverse of the error function, after Giles, "Approximating the erfinv function" (2010).
+#[allow(clippy::excessive_precision)]
+pub fn erf_inv(x: f32) -> f32 {
+    const P_CENTRAL: [f32; 9] = [
+        2.81022636e-08, 3.43273939e-07, -3.5233877e-06, -4.39150654e-06, 0.00021858087, -0.00125372503,
+        -0.00417768164, 0.246640727, 1.50140941
+    ];
+    const P_TAIL: [f32; 9] = [
+        -0.000200214257, 0.000100950558, 0.00134934322, -0.00367342844, 0.00573950773, -0.0076224613,
+        0.00943887047, 1.00167406, 2.83297682
+    ];
+
+    let x = x.clamp(-0.99999, 0.99999);
+    let w = -((1.0 - x) * (1.0 + x)).ln();
+    let (w, p) = if w < 5.0 { (w - 2.5, &P_CENTRAL) } else { (w.sqrt() - 3.0, &P_TAIL) };
+    p.iter().fold(0.0, |acc, &c| acc * w + c) * x
+}
@@ -0,0 +1,357 @@
+//! Image pyramids for filtered texture lookups.
+
+use std::lazy::SyncLazy;
+use std::ops::{Add, AddAssign, Mul};
+use crate::image::{Image, WrapMode};
+use crate::{Point2f, Point2i, RgbSpectrum, Vector2f};
+
+/// How [`MipMap`] lookups filter the texels under the footprint of a lookup.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FilterFunction {
+    /// The closest texel of the pyramid level whose texels match the width of the footprint.
+    Point,
+    /// Bilinear interpolation in the pyramid level whose texels match the width of the footprint.
+    Bilinear,
+    /// Bilinear interpolation between the two pyramid levels whose texels are closest to the width of the
+    /// footprint, which blurs anisotropic footprints along their shorter axis.
+    Trilinear,
+    /// An elliptically weighted average over the footprint, see Heckbert, "Fundamentals of Texture Mapping and
+    /// Image Warping" (1989).
+    Ewa
+}
+
+/// Options of the filtering of [`MipMap`] lookups.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MipMapFilterOptions {
+    pub filter: FilterFunction,
+    /// The largest ratio of the axes of the footprint of an EWA lookup, beyond which the shorter axis is lengthened
+    /// to bound the number of texels that are filtered.
+    pub max_anisotropy: f32
+}
+
+impl Default for MipMapFilterOptions {
+    fn default() -> Self {
+        MipMapFilterOptions { filter: FilterFunction::Bilinear, max_anisotropy: 8.0 }
+    }
+}
+
+/// Values that can be looked up in a [`MipMap`].
+pub trait Texel: Copy + Default + Add<Output = Self> + AddAssign + Mul<f32, Output = Self> {
+    /// Returns the value of the texel at `p` in `image`.
+    fn texel(image: &Image, p: Point2i, wrap: WrapMode) -> Self;
+}
+
+impl Texel for f32 {
+    /// Returns the single channel of the texel, or the average of the color channels of RGB images.
+    #[inline]
+    fn texel(image: &Image, p: Point2i, wrap: WrapMode) -> Self {
+        if image.n_channels() < 3 {
+            return image.get_channel(p, 0, wrap)
+        }
+        (0..3).map(|c| image.get_channel(p, c, wrap)).sum::<f32>() / 3.0
+    }
+}
+
+impl Texel for RgbSpectrum {
+    /// Returns the color channels of the texel, or the single channel as gray of monochrome images.
+    #[inline]
+    fn texel(image: &Image, p: Point2i, wrap: WrapMode) -> Self {
+        if image.n_channels() < 3 {
+            return RgbSpectrum::new(image.get_channel(p, 0, wrap))
+        }
+        RgbSpectrum::from_coefficients([0, 1, 2].map(|c| image.get_channel(p, c, wrap)))
+    }
+}
+
+/// The number of entries of [`EWA_WEIGHTS`].
+const EWA_LUT_SIZE: usize = 128;
+
+/// Gaussian filter weights over the squared distance to the center of the ellipse, which is one on its boundary.
+static EWA_WEIGHTS: SyncLazy<[f32; EWA_LUT_SIZE]> = SyncLazy::new(|| {
+    const ALPHA: f32 = 2.0;
+    let mut weights = [0.0; EWA_LUT_SIZE];
+    for (i, w) in weights.iter_mut().enumerate() {
+        let r2 = i as f32 / (EWA_LUT_SIZE - 1) as f32;
+        *w = (-ALPHA * r2).exp() - (-ALPHA).exp();
+    }
+    weights
+});
+
+/// A pyramid of successively halved versions of an image, which allows filtered lookups over arbitrary footprints
+/// in constant time.
+///
+/// Images whose resolution is not a power of two are resampled to the next larger power of two first.
+pub struct MipMap {
+    pyramid: Vec<Image>,
+    wrap_mode: WrapMode,
+    options: MipMapFilterOptions
+}
+
+impl MipMap {
+    pub fn new(image: Image, wrap_mode: WrapMode, options: MipMapFilterOptions) -> Self {
+        let res = image.resolution();
+        let pow2 = Point2i::new((res.x as u32).next_power_of_two() as i32, (res.y as u32).next_power_of_two() as i32);
+        // Black borders would darken the resampled pixels at the edges, so the edges are extended instead
+        let resample_wrap = if wrap_mode == WrapMode::Black { WrapMode::Clamp } else { wrap_mode };
+        let image = if pow2 != res { image.resize_up(pow2, resample_wrap) } else { image };
+
+        // Box filter each level down to the next, until a single texel remains
+        let n_levels = 1 + pow2.x.max(pow2.y).trailing_zeros() as usize;
+        let mut pyramid = Vec::with_capacity(n_levels);
+        pyramid.push(image);
+        for _ in 1..n_levels {
+            let next = downsample(pyramid.last().unwrap());
+            pyramid.push(next);
+        }
+        MipMap { pyramid, wrap_mode, options }
+    }
+
+    #[inline]
+    pub fn levels(&self) -> usize {
+        self.pyramid.len()
+    }
+
+    #[inline]
+    pub fn level(&self, level: usize) -> &Image {
+        &self.pyramid[level]
+    }
+
+    #[inline]
+    pub fn wrap_mode(&self) -> WrapMode {
+        self.wrap_mode
+    }
+
+    #[inline]
+    pub fn options(&self) -> MipMapFilterOptions {
+        self.options
+    }
+
+    /// Returns the texel at `p` of the given pyramid level.
+    #[inline]
+    pub fn texel<T: Texel>(&self, level: usize, p: Point2i) -> T {
+        T::texel(&self.pyramid[level], p, self.wrap_mode)
+    }
+
+    /// Looks up the filtered value at `st` over the footprint given by the change `dst0` and `dst1` of the
+    /// coordinates for a one pixel step in x and y.
+    pub fn filter<T: Texel>(&self, st: Point2f, dst0: Vector2f, dst1: Vector2f) -> T {
+        if self.options.filter == FilterFunction::Ewa {
+            return self.ewa(st, dst0, dst1)
+        }
+
+        // Choose the level whose texels have about the width of the footprint
+        let width = 2.0 * dst0.x.abs().max(dst0.y.abs()).max(dst1.x.abs()).max(dst1.y.abs());
+        let n_levels = self.levels();
+        let level = (n_levels - 1) as f32 + width.max(1e-8).log2();
+        if level >= (n_levels - 1) as f32 {
+            return self.texel(n_levels - 1, Point2i::new(0, 0))
+        }
+        let level = level.max(0.0);
+        match self.options.filter {
+            FilterFunction::Point => {
+                let image = &self.pyramid[level.round() as usize];
+                let res = image.resolution();
+                let p = Point2i::new((st.x * res.x as f32 - 0.5).round() as i32,
+                                     (st.y * res.y as f32 - 0.5).round() as i32);
+                T::texel(image, p, self.wrap_mode)
+            }
+            FilterFunction::Bilinear => self.bilerp(level.round() as usize, st),
+            _ => {
+                let i_level = level.floor() as usize;
+                let delta = level - i_level as f32;
+                if delta == 0.0 {
+                    return self.bilerp(i_level, st)
+                }
+                self.bilerp::<T>(i_level, st) * (1.0 - delta) + self.bilerp::<T>(i_level + 1, st) * delta
+            }
+        }
+    }
+
+    /// Bilinearly interpolates the texels of the given level at `st`.
+    pub fn bilerp<T: Texel>(&self, level: usize, st: Point2f) -> T {
+        let image = &self.pyramid[level];
+        let res = image.resolution();
+        let x = st.x * res.x as f32 - 0.5;
+        let y = st.y * res.y as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (dx, dy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+        let texel = |x, y| T::texel(image, Point2i::new(x, y), self.wrap_mode);
+        texel(x0, y0) * ((1.0 - dx) * (1.0 - dy)) + texel(x0 + 1, y0) * (dx * (1.0 - dy))
+            + texel(x0, y0 + 1) * ((1.0 - dx) * dy) + texel(x0 + 1, y0 + 1) * (dx * dy)
+    }
+
+    fn ewa<T: Texel>(&self, st: Point2f, mut dst0: Vector2f, mut dst1: Vector2f) -> T {
+        // Make dst0 the major axis of the ellipse and bound its eccentricity
+        if dst0.length_squared() < dst1.length_squared() {
+            std::mem::swap(&mut dst0, &mut dst1);
+        }
+        let major_length = dst0.length();
+        let mut minor_length = dst1.length();
+        if minor_length * self.options.max_anisotropy < major_length && minor_length > 0.0 {
+            let scale = major_length / (minor_length * self.options.max_anisotropy);
+            dst1 *= scale;
+            minor_length *= scale;
+        }
+        if minor_length == 0.0 {
+            return self.bilerp(0, st)
+        }
+
+        // Filter the two levels whose texels are closest to the length of the minor axis
+        let lod = ((self.levels() - 1) as f32 + minor_length.log2()).max(0.0);
+        let i_lod = lod.floor() as usize;
+        let delta = lod - i_lod as f32;
+        let lower: T = self.ewa_level(i_lod, st, dst0, dst1);
+        if delta == 0.0 {
+            return lower
+        }
+        lower * (1.0 - delta) + self.ewa_level::<T>(i_lod + 1, st, dst0, dst1) * delta
+    }
+
+    fn ewa_level<T: Texel>(&self, level: usize, st: Point2f, dst0: Vector2f, dst1: Vector2f) -> T {
+        if level >= self.levels() {
+            return self.texel(self.levels() - 1, Point2i::new(0, 0))
+        }
+
+        // Convert the ellipse to the texel coordinates of the level
+        let image = &self.pyramid[level];
+        let res = image.resolution();
+        let s = st.x * res.x as f32 - 0.5;
+        let t = st.y * res.y as f32 - 0.5;
+        let dst0 = Vector2f::new(dst0.x * res.x as f32, dst0.y * res.y as f32);
+        let dst1 = Vector2f::new(dst1.x * res.x as f32, dst1.y * res.y as f32);
+
+        // Compute the coefficients of the implicit equation of the ellipse, which is widened by one texel so that
+        // it covers at least one texel center
+        let a = dst0.y * dst0.y + dst1.y * dst1.y + 1.0;
+        let b = -2.0 * (dst0.x * dst0.y + dst1.x * dst1.y);
+        let c = dst0.x * dst0.x + dst1.x * dst1.x + 1.0;
+        let inv_f = 1.0 / (a * c - b * b * 0.25);
+        let (a, b, c) = (a * inv_f, b * inv_f, c * inv_f);
+
+        // Find the bounding box of the ellipse in texel coordinates
+        let det = -b * b + 4.0 * a * c;
+        let inv_det = 1.0 / det;
+        let u_sqrt = (det * c).sqrt();
+        let v_sqrt = (a * det).sqrt();
+        let s0 = (s - 2.0 * inv_det * u_sqrt).ceil() as i32;
+        let s1 = (s + 2.0 * inv_det * u_sqrt).floor() as i32;
+        let t0 = (t - 2.0 * inv_det * v_sqrt).ceil() as i32;
+        let t1 = (t + 2.0 * inv_det * v_sqrt).floor() as i32;
+
+        // Sum the weighted texels inside the ellipse
+        let mut sum = T::default();
+        let mut sum_weights = 0.0;
+        for it in t0..=t1 {
+            let tt = it as f32 - t;
+            for is in s0..=s1 {
+                let ss = is as f32 - s;
+                let r2 = a * ss * ss + b * ss * tt + c * tt * tt;
+                if r2 < 1.0 {
+                    let index = ((r2 * EWA_LUT_SIZE as f32) as usize).min(EWA_LUT_SIZE - 1);
+                    let weight = EWA_WEIGHTS[index];
+                    sum += T::texel(image, Point2i::new(is, it), self.wrap_mode) * weight;
+                    sum_weights += weight;
+                }
+            }
+        }
+        if sum_weights <= 0.0 {
+            return self.bilerp(level, st)
+        }
+        sum * (1.0 / sum_weights)
+    }
+}
+
+/// Halves the resolution of `image` by averaging blocks of two by two pixels.
+///
+/// The blocks only leave the image along axes with a single pixel, which are clamped so that the wrap mode of the
+/// texture does not affect the average.
+fn downsample(image: &Image) -> Image {
+    let res = image.resolution();
+    let next = Point2i::new((res.x / 2).max(1), (res.y / 2).max(1));
+    let nc = image.n_channels();
+    let mut pixels = Vec::with_capacity(next.x as usize * next.y as usize * nc);
+    for y in 0..next.y {
+        for x in 0..next.x {
+            for c in 0..nc {
+                let texel = |dx, dy| image.get_channel(Point2i::new(2 * x + dx, 2 * y + dy), c, WrapMode::Clamp);
+                pixels.push(0.25 * (texel(0, 0) + texel(1, 0) + texel(0, 1) + texel(1, 1)));
+            }
+        }
+    }
+    Image::new(next, nc, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::point2;
+    use super::*;
+
+    /// A checkerboard of single texels, whose average is 0.5.
+    fn checkerboard(res: i32) -> Image {
+        let pixels = (0..res * res).map(|i| ((i % res + i / res) % 2) as f32).collect();
+        Image::new(Point2i::new(res, res), 1, pixels)
+    }
+
+    fn repeating(image: Image, filter: FilterFunction) -> MipMap {
+        MipMap::new(image, WrapMode::Repeat, MipMapFilterOptions { filter, ..Default::default() })
+    }
+
+    #[test]
+    fn test_pyramid() {
+        let mipmap = repeating(checkerboard(16), FilterFunction::Trilinear);
+        assert_eq!(mipmap.levels(), 5);
+        assert_eq!(mipmap.level(1).resolution(), Point2i::new(8, 8));
+        assert!(mipmap.level(1).pixels().iter().all(|&v| v == 0.5));
+        assert_eq!(mipmap.texel::<f32>(4, Point2i::new(0, 0)), 0.5);
+
+        // Other resolutions are resampled to the next power of two, down to a single texel on the longer axis
+        let mipmap = repeating(Image::new(Point2i::new(5, 3), 3, vec![0.25; 45]), FilterFunction::Trilinear);
+        assert_eq!(mipmap.level(0).resolution(), Point2i::new(8, 4));
+        assert_eq!(mipmap.levels(), 4);
+        assert_eq!(mipmap.level(3).resolution(), Point2i::new(1, 1));
+        let texel: RgbSpectrum = mipmap.texel(3, Point2i::new(0, 0));
+        assert!((texel[2] - 0.25).abs() < 1e-5);
+
+        // Black borders do not darken the levels of constant images
+        let black = |image| MipMap::new(image, WrapMode::Black, MipMapFilterOptions::default());
+        assert_eq!(black(Image::new(Point2i::new(2, 1), 1, vec![1.0; 2])).level(1).pixels(), &[1.0]);
+        let mipmap = black(Image::new(Point2i::new(3, 1), 1, vec![1.0; 3]));
+        assert!(mipmap.level(0).pixels().iter().chain(mipmap.level(2).pixels()).all(|&v| (v - 1.0).abs() < 1e-5));
+    }
+
+    #[test]
+    fn test_footprint_selects_level() {
+        let point = repeating(checkerboard(16), FilterFunction::Point);
+        let small = Vector2f::new(1e-3, 0.0);
+        let st = point2(1.5 / 16.0, 0.5 / 16.0);
+        assert_eq!(point.filter::<f32>(st, small, Vector2f::new(0.0, 1e-3)), 1.0);
+        // Footprints that cover several texels average them
+        let large = Vector2f::new(0.25, 0.0);
+        assert_eq!(point.filter::<f32>(st, large, Vector2f::new(0.0, 0.25)), 0.5);
+
+        let trilinear = repeating(checkerboard(16), FilterFunction::Trilinear);
+        assert_eq!(trilinear.filter::<f32>(point2(0.5 / 16.0, 0.5 / 16.0), small, small), 0.0);
+        let v: f32 = trilinear.filter(st, Vector2f::new(0.07, 0.0), Vector2f::new(0.0, 0.07));
+        assert!((v - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_ewa() {
+        let ewa = MipMap::new(checkerboard(64), WrapMode::Repeat,
+                              MipMapFilterOptions { filter: FilterFunction::Ewa, max_anisotropy: 8.0 });
+        let st = point2(0.3, 0.6);
+        // A tiny footprint returns the texel under it
+        let v: f32 = ewa.filter(point2(1.5 / 64.0, 0.5 / 64.0), Vector2f::new(1e-4, 0.0), Vector2f::new(0.0, 1e-4));
+        assert!((v - 1.0).abs() < 1e-3, "{}", v);
+        // Anisotropic footprints that span many texels average them
+        let v: f32 = ewa.filter(st, Vector2f::new(0.1, 0.02), Vector2f::new(-0.004, 0.02));
+        assert!((v - 0.5).abs() < 0.05, "{}", v);
+        // Filtered values stay between those of the texels
+        for i in 0..50 {
+            let d = 1e-3 * i as f32;
+            let v: f32 = ewa.filter(point2(0.01 * i as f32, 0.3), Vector2f::new(d, 0.0), Vector2f::new(0.0, 0.5 * d));
+            assert!((-1e-5..=1.0 + 1e-5).contains(&v), "{}", v);
+        }
+    }
+}
@@ -1,10 +1,56 @@
+use std::sync::Arc;
 use crate::{Bounds3f, Ray};
 use crate::interaction::SurfaceInteraction;
+use crate::material::Material;
+use crate::shape::Shape;
 
-pub trait Primitive {
+pub trait Primitive: Send + Sync {
     fn world_bound(&self) -> Bounds3f;
-    fn intersect(&self, r: &Ray, interaction: &SurfaceInteraction) -> bool;
+    fn intersect(&self, r: &Ray, interaction: &mut SurfaceInteraction) -> bool;
     fn intersect_p(&self, r: &Ray) -> bool;
     // fn area_light(&self) -> &dyn AreaLight;
-    // fn material(&self) -> &dyn Material;
-}
\ No newline at end of file
+    /// Returns the material of the primitive, or `None` if the primitive only delimits participating media.
+    fn material(&self) -> Option<&dyn Material>;
+}
+
+/// A primitive that combines a shape with its appearance properties.
+pub struct GeometricPrimitive {
+    shape: Arc<dyn Shape>,
+    material: Option<Arc<dyn Material>>
+}
+
+impl GeometricPrimitive {
+    pub fn new(shape: Arc<dyn Shape>, material: Option<Arc<dyn Material>>) -> Self {
+        GeometricPrimitive { shape, material }
+    }
+
+    #[inline]
+    pub fn shape(&self) -> &Arc<dyn Shape> {
+        &self.shape
+    }
+}
+
+impl Primitive for GeometricPrimitive {
+    fn world_bound(&self) -> Bounds3f {
+        self.shape.world_bound()
+    }
+
+    fn intersect(&self, r: &Ray, interaction: &mut SurfaceInteraction) -> bool {
+        let mut t_hit = 0.0;
+        if !self.shape.intersect(r, &mut t_hit, interaction, true) {
+            return false
+        }
+        r.tmax.set(t_hit);
+        interaction.shape = Some(self.shape.clone());
+        interaction.material = self.material.clone();
+        true
+    }
+
+    fn intersect_p(&self, r: &Ray) -> bool {
+        self.shape.intersect_p(r, true)
+    }
+
+    fn material(&self) -> Option<&dyn Material> {
+        self.material.as_deref()
+    }
+}
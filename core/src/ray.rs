@@ -1,4 +1,5 @@
 use std::cell::Cell;
+use std::ops::{Deref, DerefMut};
 use crate::{Point3, Vector3};
 
 #[derive(Debug, PartialOrd, PartialEq, Clone)]
@@ -29,4 +30,62 @@ impl Ray {
     pub fn at(&self, t: f32) -> Point3<f32> {
         self.o + self.d * t
     }
-}
\ No newline at end of file
+}
+/// A ray with two auxiliary rays offset by one pixel in x and y on the film, used to estimate the footprint of
+/// the ray on the surfaces it hits.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RayDifferential {
+    pub ray: Ray,
+    pub has_differentials: bool,
+    pub rx_origin: Point3<f32>,
+    pub ry_origin: Point3<f32>,
+    pub rx_direction: Vector3<f32>,
+    pub ry_direction: Vector3<f32>
+}
+
+impl RayDifferential {
+    /// Creates a ray differential without auxiliary rays.
+    pub const fn new(ray: Ray) -> Self {
+        RayDifferential {
+            ray,
+            has_differentials: false,
+            rx_origin: Point3::new(0.0, 0.0, 0.0),
+            ry_origin: Point3::new(0.0, 0.0, 0.0),
+            rx_direction: Vector3::new(0.0, 0.0, 0.0),
+            ry_direction: Vector3::new(0.0, 0.0, 0.0)
+        }
+    }
+}
+
+impl RayDifferential {
+    /// Scales the offsets of the auxiliary rays by `s`, which is used to account for the smaller footprint of
+    /// each sample when a pixel is sampled `n` times with `s = 1 / sqrt(n)`.
+    pub fn scale_differentials(&mut self, s: f32) {
+        self.rx_origin = self.ray.o + (self.rx_origin - self.ray.o) * s;
+        self.ry_origin = self.ray.o + (self.ry_origin - self.ray.o) * s;
+        self.rx_direction = self.ray.d + (self.rx_direction - self.ray.d) * s;
+        self.ry_direction = self.ray.d + (self.ry_direction - self.ray.d) * s;
+    }
+}
+
+impl const Default for RayDifferential {
+    fn default() -> Self {
+        RayDifferential::new(Ray::default())
+    }
+}
+
+impl Deref for RayDifferential {
+    type Target = Ray;
+
+    #[inline]
+    fn deref(&self) -> &Ray {
+        &self.ray
+    }
+}
+
+impl DerefMut for RayDifferential {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Ray {
+        &mut self.ray
+    }
+}
@@ -0,0 +1,343 @@
+//! Models of light scattering at surfaces.
+//!
+//! BxDFs are evaluated in a local shading frame in which the surface normal is the `z` axis and the shading
+//! `dpdu` is the `x` axis, so that angles to the normal follow directly from the coordinates of a direction.
+//! A [`Bsdf`] combines a set of BxDFs and transforms directions between world space and this frame.
+
+mod bsdf;
+mod conductor;
+mod dielectric;
+mod framed;
+mod fresnel;
+mod fresnel_blend;
+mod hair;
+mod lambertian;
+mod layered;
+mod measured;
+mod microfacet;
+mod oren_nayar;
+mod scaled;
+mod specular;
+
+pub use bsdf::*;
+pub use conductor::*;
+pub use dielectric::*;
+pub use framed::*;
+pub use fresnel::*;
+pub use fresnel_blend::*;
+pub use hair::*;
+pub use lambertian::*;
+pub use layered::*;
+pub use measured::*;
+pub use microfacet::*;
+pub use oren_nayar::*;
+pub use scaled::*;
+pub use specular::*;
+
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+use crate::geom::DotProduct;
+use crate::sampling::{cosine_hemisphere_pdf, cosine_sample_hemisphere};
+use crate::{Normal3f, Point2f, Spectrum, Vector3f};
+
+/// A set of properties that classify the scattering of a BxDF.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct BxdfFlags(u8);
+
+impl BxdfFlags {
+    pub const REFLECTION: BxdfFlags = BxdfFlags(1 << 0);
+    pub const TRANSMISSION: BxdfFlags = BxdfFlags(1 << 1);
+    pub const DIFFUSE: BxdfFlags = BxdfFlags(1 << 2);
+    pub const GLOSSY: BxdfFlags = BxdfFlags(1 << 3);
+    pub const SPECULAR: BxdfFlags = BxdfFlags(1 << 4);
+    pub const ALL: BxdfFlags = BxdfFlags(0b11111);
+
+    #[inline]
+    pub const fn empty() -> Self {
+        BxdfFlags(0)
+    }
+
+    #[inline]
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Checks if all flags of `other` are set.
+    #[inline]
+    pub const fn contains(self, other: BxdfFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Checks if any flag of `other` is set.
+    #[inline]
+    pub const fn intersects(self, other: BxdfFlags) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    #[inline]
+    pub const fn is_reflective(self) -> bool {
+        self.intersects(BxdfFlags::REFLECTION)
+    }
+
+    #[inline]
+    pub const fn is_transmissive(self) -> bool {
+        self.intersects(BxdfFlags::TRANSMISSION)
+    }
+
+    #[inline]
+    pub const fn is_diffuse(self) -> bool {
+        self.intersects(BxdfFlags::DIFFUSE)
+    }
+
+    #[inline]
+    pub const fn is_glossy(self) -> bool {
+        self.intersects(BxdfFlags::GLOSSY)
+    }
+
+    #[inline]
+    pub const fn is_specular(self) -> bool {
+        self.intersects(BxdfFlags::SPECULAR)
+    }
+}
+
+impl BitOr for BxdfFlags {
+    type Output = BxdfFlags;
+
+    #[inline]
+    fn bitor(self, rhs: BxdfFlags) -> BxdfFlags {
+        BxdfFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for BxdfFlags {
+    #[inline]
+    fn bitor_assign(&mut self, rhs: BxdfFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for BxdfFlags {
+    type Output = BxdfFlags;
+
+    #[inline]
+    fn bitand(self, rhs: BxdfFlags) -> BxdfFlags {
+        BxdfFlags(self.0 & rhs.0)
+    }
+}
+
+/// The quantity that is carried along a path, which determines how non-symmetric scattering is handled.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransportMode {
+    /// Paths traced from the camera that carry radiance.
+    Radiance,
+    /// Paths traced from light sources that carry importance.
+    Importance
+}
+
+/// A bidirectional distribution function that describes the scattering at a surface in the shading frame.
+///
+/// `wo` and `wi` are normalized directions pointing away from the surface, towards the viewer and the light.
+pub trait Bxdf: Send + Sync {
+    fn flags(&self) -> BxdfFlags;
+
+    /// Evaluates the distribution for the pair of directions.
+    ///
+    /// Specular BxDFs are described by delta distributions and return zero.
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum;
+
+    /// Samples an incident direction `wi` for `wo` from the uniform samples `uc` and `u` and returns the value of
+    /// the distribution for the pair together with the density `pdf` of `wi` with respect to solid angle.
+    ///
+    /// BxDFs with several lobes use `uc` to choose between them and set `sampled_type` to the kind of scattering
+    /// that was sampled. The default implementation samples the cosine-weighted hemisphere on the side of `wo`.
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, uc: f32, u: &Point2f, pdf: &mut f32,
+                sampled_type: &mut BxdfFlags) -> Spectrum {
+        let _ = (uc, sampled_type);
+        *wi = cosine_sample_hemisphere(u);
+        if wo.z < 0.0 {
+            wi.z = -wi.z;
+        }
+        *pdf = self.pdf(wo, wi);
+        self.f(wo, wi)
+    }
+
+    /// Returns the density with which [`sample_f`](Self::sample_f) samples `wi` for `wo`.
+    fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        if same_hemisphere(wo, wi) {
+            cosine_hemisphere_pdf(abs_cos_theta(wi))
+        } else {
+            0.0
+        }
+    }
+
+    /// Estimates the hemispherical-directional reflectance, i.e. the fraction of light arriving from all
+    /// directions that is scattered towards `wo`, with one sample per pair of elements of `uc` and `u`.
+    fn rho(&self, wo: &Vector3f, uc: &[f32], u: &[Point2f]) -> Spectrum {
+        estimate_rho(self, wo, uc, u)
+    }
+
+    /// Checks if `f` and `pdf` are stochastic estimates, which only agree with the values that
+    /// [`sample_f`](Self::sample_f) returns in the ratio of the two.
+    #[inline]
+    fn is_stochastic(&self) -> bool {
+        false
+    }
+
+    /// Checks if all flags of the BxDF are in `t`.
+    #[inline]
+    fn matches_flags(&self, t: BxdfFlags) -> bool {
+        t.contains(self.flags())
+    }
+}
+
+/// Estimates the hemispherical-directional reflectance of `bxdf` by sampling it, see [`Bxdf::rho`].
+pub(crate) fn estimate_rho<B: Bxdf + ?Sized>(bxdf: &B, wo: &Vector3f, uc: &[f32], u: &[Point2f]) -> Spectrum {
+    debug_assert_eq!(uc.len(), u.len());
+    let mut r = Spectrum::new(0.0);
+    for (&uc, u) in uc.iter().zip(u) {
+        let mut wi = Vector3f::default();
+        let mut pdf = 0.0;
+        let mut sampled_type = BxdfFlags::empty();
+        let f = bxdf.sample_f(wo, &mut wi, uc, u, &mut pdf, &mut sampled_type);
+        if pdf > 0.0 {
+            r += f * (abs_cos_theta(&wi) / pdf);
+        }
+    }
+    r / u.len() as f32
+}
+
+#[inline]
+pub fn cos_theta(w: &Vector3f) -> f32 {
+    w.z
+}
+
+#[inline]
+pub fn cos2_theta(w: &Vector3f) -> f32 {
+    w.z * w.z
+}
+
+#[inline]
+pub fn abs_cos_theta(w: &Vector3f) -> f32 {
+    w.z.abs()
+}
+
+#[inline]
+pub fn sin2_theta(w: &Vector3f) -> f32 {
+    f32::max(0.0, 1.0 - cos2_theta(w))
+}
+
+#[inline]
+pub fn sin_theta(w: &Vector3f) -> f32 {
+    sin2_theta(w).sqrt()
+}
+
+#[inline]
+pub fn tan_theta(w: &Vector3f) -> f32 {
+    sin_theta(w) / cos_theta(w)
+}
+
+#[inline]
+pub fn tan2_theta(w: &Vector3f) -> f32 {
+    sin2_theta(w) / cos2_theta(w)
+}
+
+#[inline]
+pub fn cos_phi(w: &Vector3f) -> f32 {
+    let sin_theta = sin_theta(w);
+    if sin_theta == 0.0 { 1.0 } else { (w.x / sin_theta).clamp(-1.0, 1.0) }
+}
+
+#[inline]
+pub fn sin_phi(w: &Vector3f) -> f32 {
+    let sin_theta = sin_theta(w);
+    if sin_theta == 0.0 { 0.0 } else { (w.y / sin_theta).clamp(-1.0, 1.0) }
+}
+
+#[inline]
+pub fn cos2_phi(w: &Vector3f) -> f32 {
+    cos_phi(w) * cos_phi(w)
+}
+
+#[inline]
+pub fn sin2_phi(w: &Vector3f) -> f32 {
+    sin_phi(w) * sin_phi(w)
+}
+
+/// Returns the cosine of the angle between the projections of `wa` and `wb` onto the tangent plane.
+pub fn cos_d_phi(wa: &Vector3f, wb: &Vector3f) -> f32 {
+    let waxy = wa.x * wa.x + wa.y * wa.y;
+    let wbxy = wb.x * wb.x + wb.y * wb.y;
+    if waxy == 0.0 || wbxy == 0.0 {
+        return 1.0
+    }
+    ((wa.x * wb.x + wa.y * wb.y) / (waxy * wbxy).sqrt()).clamp(-1.0, 1.0)
+}
+
+/// Checks if two directions in the shading frame lie on the same side of the surface.
+#[inline]
+pub fn same_hemisphere(w: &Vector3f, wp: &Vector3f) -> bool {
+    w.z * wp.z > 0.0
+}
+
+/// Reflects `wo` about the normal `n`.
+#[inline]
+pub fn reflect(wo: &Vector3f, n: &Vector3f) -> Vector3f {
+    -*wo + *n * (2.0 * wo.dot(n))
+}
+
+/// Refracts `wi` through the interface with normal `n` on the side of `wi`, where `eta` is the ratio of the index
+/// of refraction on the side of `wi` to the one on the other side.
+///
+/// Returns `None` in case of total internal reflection.
+pub fn refract(wi: &Vector3f, n: &Normal3f, eta: f32) -> Option<Vector3f> {
+    // Compute the cosine of the transmitted direction using Snell's law
+    let cos_theta_i = n.dot(wi);
+    let sin2_theta_i = f32::max(0.0, 1.0 - cos_theta_i * cos_theta_i);
+    let sin2_theta_t = eta * eta * sin2_theta_i;
+    if sin2_theta_t >= 1.0 {
+        return None
+    }
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+    Some(-*wi * eta + Vector3f::from(*n) * (eta * cos_theta_i - cos_theta_t))
+}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck::TestResult;
+    use quickcheck_macros::quickcheck;
+    use crate::sampling::uniform_sample_sphere;
+    use crate::{point2, vec3};
+    use super::*;
+
+    #[test]
+    fn test_flags() {
+        let flags = BxdfFlags::REFLECTION | BxdfFlags::DIFFUSE;
+        assert!(flags.is_reflective() && flags.is_diffuse());
+        assert!(!flags.is_transmissive() && !flags.is_specular());
+        assert!(BxdfFlags::ALL.contains(flags));
+        assert!(!flags.contains(BxdfFlags::ALL));
+        assert!((flags & BxdfFlags::TRANSMISSION).is_empty());
+    }
+
+    #[quickcheck]
+    fn refract_obeys_snells_law(u0: u16, u1: u16) -> TestResult {
+        let u = point2(u0 as f32 / 65536.0, u1 as f32 / 65536.0);
+        let wi = uniform_sample_sphere(&u);
+        if wi.z <= 1e-3 {
+            return TestResult::discard()
+        }
+        let n = Normal3f::new(0.0, 0.0, 1.0);
+        let eta = 1.0 / 1.5;
+        let wt = refract(&wi, &n, eta).unwrap();
+        let snell = (sin_theta(&wi) * eta - sin_theta(&wt)).abs() < 1e-4;
+        // Refracting back recovers the incident direction
+        let back = refract(&wt, &-n, 1.0 / eta).unwrap();
+        TestResult::from_bool(snell && wt.z < 0.0 && (back - wi).length() < 1e-3)
+    }
+
+    #[test]
+    fn test_total_internal_reflection() {
+        let wi = vec3(0.8f32, 0.0, 0.6);
+        assert!(refract(&wi, &Normal3f::new(0.0, 0.0, 1.0), 1.5).is_none());
+        assert_eq!(reflect(&wi, &vec3(0.0, 0.0, 1.0)), vec3(-0.8, 0.0, 0.6));
+    }
+}
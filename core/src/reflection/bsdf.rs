@@ -0,0 +1,258 @@
+use crate::geom::DotProduct;
+use crate::interaction::SurfaceInteraction;
+use crate::math::ONE_MINUS_EPSILON;
+use crate::reflection::{Bxdf, BxdfFlags};
+use crate::{vec3, Normal3f, Point2f, Spectrum, Vector3f};
+
+/// The scattering at a point on a surface, given by a sum of BxDFs in the shading frame of the point.
+pub struct Bsdf {
+    /// Relative index of refraction over the boundary, or one for opaque surfaces.
+    pub eta: f32,
+    ns: Normal3f,
+    ng: Normal3f,
+    ss: Vector3f,
+    ts: Vector3f,
+    bxdfs: Vec<Box<dyn Bxdf>>
+}
+
+impl Bsdf {
+    /// Creates an empty BSDF with the shading frame given by the shading normal and `dpdu` of `si`.
+    pub fn new(si: &SurfaceInteraction, eta: f32) -> Self {
+        let ns = si.shading.n;
+        let ss = si.shading.dpdu.normalize();
+        let ts = Vector3f::from(ns).cross(&ss);
+        Bsdf { eta, ns, ng: si.n, ss, ts, bxdfs: Vec::new() }
+    }
+
+    pub fn add(&mut self, bxdf: Box<dyn Bxdf>) {
+        self.bxdfs.push(bxdf);
+    }
+
+    /// Consumes the BSDF and returns its BxDFs.
+    pub fn into_bxdfs(self) -> Vec<Box<dyn Bxdf>> {
+        self.bxdfs
+    }
+
+    /// Returns the number of BxDFs whose flags are all in `flags`.
+    pub fn num_components(&self, flags: BxdfFlags) -> usize {
+        self.bxdfs.iter().filter(|b| b.matches_flags(flags)).count()
+    }
+
+    /// Transforms a direction from world space to the shading frame.
+    #[inline]
+    pub fn world_to_local(&self, v: &Vector3f) -> Vector3f {
+        vec3(v.dot(&self.ss), v.dot(&self.ts), v.dot(&self.ns))
+    }
+
+    /// Transforms a direction from the shading frame to world space.
+    #[inline]
+    pub fn local_to_world(&self, v: &Vector3f) -> Vector3f {
+        let ns = Vector3f::from(self.ns);
+        vec3(
+            self.ss.x * v.x + self.ts.x * v.y + ns.x * v.z,
+            self.ss.y * v.x + self.ts.y * v.y + ns.y * v.z,
+            self.ss.z * v.x + self.ts.z * v.y + ns.z * v.z
+        )
+    }
+
+    /// Evaluates the sum of the BxDFs matching `flags` for a pair of world space directions.
+    ///
+    /// Whether light is reflected or transmitted is decided with the geometric normal, so that shading normals do
+    /// not cause light leaks.
+    pub fn f(&self, wo_world: &Vector3f, wi_world: &Vector3f, flags: BxdfFlags) -> Spectrum {
+        let wi = self.world_to_local(wi_world);
+        let wo = self.world_to_local(wo_world);
+        if wo.z == 0.0 {
+            return Spectrum::new(0.0)
+        }
+        let reflect = wi_world.dot(&self.ng) * wo_world.dot(&self.ng) > 0.0;
+        self.sum_matching(&wo, &wi, reflect, flags, true)
+    }
+
+    /// Samples an incident direction for `wo_world` from one of the BxDFs matching `flags`, chosen uniformly with
+    /// `uc`.
+    ///
+    /// Returns the value of the BSDF for the pair of directions and sets `pdf` to the density of `wi_world` over
+    /// all matching BxDFs, unless a specular BxDF was sampled. `sampled_type` is set to the flags of the sampled
+    /// BxDF and is empty if no direction was sampled.
+    ///
+    /// The estimates of [stochastic](Bxdf::is_stochastic) BxDFs are only exact in the ratio of the value and the
+    /// density of their own samples. Their samples therefore keep both, with the density scaled by the probability
+    /// of choosing the BxDF, while the samples of the other BxDFs leave them out.
+    #[allow(clippy::too_many_arguments)]
+    pub fn sample_f(&self, wo_world: &Vector3f, wi_world: &mut Vector3f, uc: f32, u: &Point2f, pdf: &mut f32,
+                    flags: BxdfFlags, sampled_type: &mut BxdfFlags) -> Spectrum {
+        *pdf = 0.0;
+        *sampled_type = BxdfFlags::empty();
+        let matching = self.num_components(flags);
+        if matching == 0 {
+            return Spectrum::new(0.0)
+        }
+
+        // Choose a component and remap the sample to [0, 1)
+        let comp = usize::min((uc * matching as f32) as usize, matching - 1);
+        let bxdf = self.bxdfs.iter().filter(|b| b.matches_flags(flags)).nth(comp).unwrap();
+        let uc_remapped = f32::min(uc * matching as f32 - comp as f32, ONE_MINUS_EPSILON);
+
+        let wo = self.world_to_local(wo_world);
+        if wo.z == 0.0 {
+            return Spectrum::new(0.0)
+        }
+        let mut wi = Vector3f::default();
+        let mut bxdf_type = bxdf.flags();
+        let f = bxdf.sample_f(&wo, &mut wi, uc_remapped, u, pdf, &mut bxdf_type);
+        if *pdf == 0.0 {
+            return Spectrum::new(0.0)
+        }
+        *sampled_type = bxdf_type;
+        *wi_world = self.local_to_world(&wi);
+        if matching == 1 || bxdf.flags().is_specular() || bxdf.is_stochastic() {
+            *pdf /= matching as f32;
+            return f
+        }
+
+        // Account for the densities and values of the other deterministic components. They are evaluated for the
+        // direction in world space like in f() and pdf(), so that the rounding errors of the change of frame do not
+        // make them disagree.
+        let wi = self.world_to_local(wi_world);
+        *pdf = self.bxdfs.iter()
+            .filter(|b| b.matches_flags(flags) && !b.is_stochastic())
+            .fold(0.0, |pdf, b| pdf + b.pdf(&wo, &wi)) / matching as f32;
+        if *pdf == 0.0 {
+            *sampled_type = BxdfFlags::empty();
+            return Spectrum::new(0.0)
+        }
+        let reflect = wi_world.dot(&self.ng) * wo_world.dot(&self.ng) > 0.0;
+        self.sum_matching(&wo, &wi, reflect, flags, false)
+    }
+
+    /// Returns the density with which [`sample_f`](Self::sample_f) samples `wi_world` for `wo_world`.
+    pub fn pdf(&self, wo_world: &Vector3f, wi_world: &Vector3f, flags: BxdfFlags) -> f32 {
+        let wo = self.world_to_local(wo_world);
+        let wi = self.world_to_local(wi_world);
+        if wo.z == 0.0 {
+            return 0.0
+        }
+        let (pdf, matching) = self.bxdfs.iter()
+            .filter(|b| b.matches_flags(flags))
+            .fold((0.0, 0), |(pdf, n), b| (pdf + b.pdf(&wo, &wi), n + 1));
+        if matching > 0 { pdf / matching as f32 } else { 0.0 }
+    }
+
+    /// Estimates the hemispherical-directional reflectance of the BxDFs matching `flags` for `wo_world`.
+    pub fn rho(&self, wo_world: &Vector3f, uc: &[f32], u: &[Point2f], flags: BxdfFlags) -> Spectrum {
+        let wo = self.world_to_local(wo_world);
+        self.bxdfs.iter()
+            .filter(|b| b.matches_flags(flags))
+            .fold(Spectrum::new(0.0), |r, b| r + b.rho(&wo, uc, u))
+    }
+
+    /// Sums the values of the BxDFs matching `flags` that scatter to the side given by `reflect`, leaving out the
+    /// stochastic BxDFs unless `stochastic` is set.
+    fn sum_matching(&self, wo: &Vector3f, wi: &Vector3f, reflect: bool, flags: BxdfFlags,
+                    stochastic: bool) -> Spectrum {
+        self.bxdfs.iter()
+            .filter(|b| b.matches_flags(flags) && (stochastic || !b.is_stochastic()))
+            .filter(|b| (reflect && b.flags().is_reflective()) || (!reflect && b.flags().is_transmissive()))
+            .fold(Spectrum::new(0.0), |f, b| f + b.f(wo, wi))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_1_PI;
+    use quickcheck_macros::quickcheck;
+    use crate::interaction::Shading;
+    use crate::point2;
+    use crate::reflection::{CoatedDiffuseBxdf, DielectricBxdf, LambertianReflection, LambertianTransmission, OrenNayar,
+                            TransportMode, TrowbridgeReitzDistribution};
+    use crate::sampling::{sample_grid, uniform_sample_sphere};
+    use super::*;
+
+    fn interaction() -> SurfaceInteraction {
+        let n = Normal3f::from(vec3(1.0f32, 1.0, 0.0).normalize());
+        let dpdu = vec3(1.0f32, -1.0, 0.0) * 2.0;
+        SurfaceInteraction { n, shading: Shading { n, dpdu, ..Default::default() }, ..Default::default() }
+    }
+
+    #[quickcheck]
+    fn local_frame_roundtrip(u0: u16, u1: u16) -> bool {
+        let bsdf = Bsdf::new(&interaction(), 1.0);
+        let w = uniform_sample_sphere(&point2(u0 as f32 / 65536.0, u1 as f32 / 65536.0));
+        let local = bsdf.world_to_local(&w);
+        (local.z - w.dot(&bsdf.ns)).abs() < 1e-6 && (bsdf.local_to_world(&local) - w).length() < 1e-5
+    }
+
+    #[test]
+    fn test_reflection_and_transmission() {
+        let mut bsdf = Bsdf::new(&interaction(), 1.0);
+        bsdf.add(Box::new(LambertianReflection::new(Spectrum::new(0.5))));
+        bsdf.add(Box::new(LambertianTransmission::new(Spectrum::new(0.25))));
+        let wo = vec3(1.0, 0.0, 0.0);
+        let above = vec3(0.0, 1.0, 0.0);
+        let below = vec3(0.0, -1.0, 0.0);
+        assert!((bsdf.f(&wo, &above, BxdfFlags::ALL)[0] - 0.5 * FRAC_1_PI).abs() < 1e-6);
+        assert!((bsdf.f(&wo, &below, BxdfFlags::ALL)[0] - 0.25 * FRAC_1_PI).abs() < 1e-6);
+        assert!(bsdf.f(&wo, &above, BxdfFlags::TRANSMISSION | BxdfFlags::DIFFUSE).is_black());
+        assert_eq!(bsdf.num_components(BxdfFlags::REFLECTION | BxdfFlags::DIFFUSE), 1);
+    }
+
+    #[quickcheck]
+    fn sample_f_matches_f_and_pdf(u0: u16, u1: u16, uc: u16, v0: u16, v1: u16) -> bool {
+        let mut bsdf = Bsdf::new(&interaction(), 1.0);
+        bsdf.add(Box::new(LambertianReflection::new(Spectrum::new(0.3))));
+        bsdf.add(Box::new(OrenNayar::new(Spectrum::new(0.6), 25.0)));
+        let wo = uniform_sample_sphere(&point2(u0 as f32 / 65536.0, u1 as f32 / 65536.0));
+        let u = point2(v0 as f32 / 65536.0, v1 as f32 / 65536.0);
+        let mut wi = Vector3f::default();
+        let mut pdf = 0.0;
+        let mut sampled_type = BxdfFlags::empty();
+        let f = bsdf.sample_f(&wo, &mut wi, uc as f32 / 65536.0, &u, &mut pdf, BxdfFlags::ALL, &mut sampled_type);
+        if pdf == 0.0 {
+            return sampled_type.is_empty()
+        }
+        (f[0] - bsdf.f(&wo, &wi, BxdfFlags::ALL)[0]).abs() < 1e-5
+            && (pdf - bsdf.pdf(&wo, &wi, BxdfFlags::ALL)).abs() < 1e-4 * pdf.max(1.0)
+            && sampled_type == (BxdfFlags::REFLECTION | BxdfFlags::DIFFUSE)
+    }
+
+    #[test]
+    fn test_sample_f_keeps_stochastic_estimates() {
+        let n = Normal3f::new(0.0, 0.0, 1.0);
+        let si = SurfaceInteraction {
+            n,
+            shading: Shading { n, dpdu: vec3(1.0, 0.0, 0.0), ..Default::default() },
+            ..Default::default()
+        };
+        let coated = || {
+            let coating = DielectricBxdf::new(1.5, Box::new(TrowbridgeReitzDistribution::new(0.3, 0.3)),
+                                              TransportMode::Radiance);
+            Box::new(CoatedDiffuseBxdf::new(coating, LambertianReflection::new(Spectrum::new(0.8)), 0.01,
+                                            Spectrum::new(0.0), 0.0, 10, 1))
+        };
+        let wo = vec3(0.6, 0.0, 0.8);
+        let u: Vec<_> = sample_grid(128).collect();
+        let uc: Vec<_> = (0..u.len()).map(|i| (i as f32 * 0.618034).fract()).collect();
+        let rho = |bsdf: &Bsdf| {
+            let mut r = 0.0;
+            for (&uc, u) in uc.iter().zip(&u) {
+                let mut wi = Vector3f::default();
+                let mut pdf = 0.0;
+                let mut sampled_type = BxdfFlags::empty();
+                let f = bsdf.sample_f(&wo, &mut wi, uc, u, &mut pdf, BxdfFlags::ALL, &mut sampled_type);
+                if pdf > 0.0 {
+                    r += f[0] * wi.z.abs() / pdf;
+                }
+            }
+            r / u.len() as f32
+        };
+
+        // Sampling the BSDF gives the reflectance of its BxDFs, also when a stochastic BxDF is combined with others
+        let expected = coated().rho(&wo, &uc, &u)[0];
+        let mut bsdf = Bsdf::new(&si, 1.0);
+        bsdf.add(coated());
+        assert!((rho(&bsdf) - expected).abs() < 1e-3, "{} {}", rho(&bsdf), expected);
+        bsdf.add(Box::new(LambertianReflection::new(Spectrum::new(0.2))));
+        assert!((rho(&bsdf) - expected - 0.2).abs() < 1e-2, "{} {}", rho(&bsdf), expected + 0.2);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::geom::DotProduct;
+use crate::reflection::{abs_cos_theta, fr_conductor, reflect, same_hemisphere, Bxdf, BxdfFlags,
+                        MicrofacetDistribution};
+use crate::{vec3, Point2f, Spectrum, Vector3f};
+
+/// Reflection from a metal with the complex index of refraction `eta + i k` relative to the medium outside.
+///
+/// Rough surfaces use the Torrance–Sparrow model with the given microfacet distribution, while effectively smooth
+/// surfaces reflect specularly.
+pub struct ConductorBxdf {
+    distrib: Box<dyn MicrofacetDistribution>,
+    eta: Spectrum,
+    k: Spectrum
+}
+
+impl ConductorBxdf {
+    pub fn new(distrib: Box<dyn MicrofacetDistribution>, eta: Spectrum, k: Spectrum) -> Self {
+        ConductorBxdf { distrib, eta, k }
+    }
+
+    #[inline]
+    fn fresnel(&self, cos_theta_i: f32) -> Spectrum {
+        fr_conductor(cos_theta_i.abs(), &Spectrum::new(1.0), &self.eta, &self.k)
+    }
+}
+
+impl Bxdf for ConductorBxdf {
+    #[inline]
+    fn flags(&self) -> BxdfFlags {
+        if self.distrib.effectively_smooth() {
+            BxdfFlags::REFLECTION | BxdfFlags::SPECULAR
+        } else {
+            BxdfFlags::REFLECTION | BxdfFlags::GLOSSY
+        }
+    }
+
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        if !same_hemisphere(wo, wi) || self.distrib.effectively_smooth() {
+            return Spectrum::new(0.0)
+        }
+        let cos_theta_o = abs_cos_theta(wo);
+        let cos_theta_i = abs_cos_theta(wi);
+        if cos_theta_i == 0.0 || cos_theta_o == 0.0 {
+            return Spectrum::new(0.0)
+        }
+        let wm = *wi + *wo;
+        if wm.length_squared() == 0.0 {
+            return Spectrum::new(0.0)
+        }
+        let wm = wm.normalize();
+        self.fresnel(wo.dot(&wm)) * (self.distrib.d(&wm) * self.distrib.g(wo, wi) / (4.0 * cos_theta_i * cos_theta_o))
+    }
+
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, _uc: f32, u: &Point2f, pdf: &mut f32,
+                _sampled_type: &mut BxdfFlags) -> Spectrum {
+        if self.distrib.effectively_smooth() {
+            *wi = vec3(-wo.x, -wo.y, wo.z);
+            *pdf = 1.0;
+            return self.fresnel(abs_cos_theta(wi)) / abs_cos_theta(wi)
+        }
+
+        if wo.z == 0.0 {
+            return Spectrum::new(0.0)
+        }
+        let wm = self.distrib.sample_wm(wo, u);
+        *wi = reflect(wo, &wm);
+        if !same_hemisphere(wo, wi) {
+            return Spectrum::new(0.0)
+        }
+        // Account for the change of variables from the half vector to the reflected direction
+        *pdf = self.distrib.pdf(wo, &wm) / (4.0 * wo.dot(&wm).abs());
+        self.f(wo, wi)
+    }
+
+    fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        if !same_hemisphere(wo, wi) || self.distrib.effectively_smooth() {
+            return 0.0
+        }
+        let wm = *wo + *wi;
+        if wm.length_squared() == 0.0 {
+            return 0.0
+        }
+        let wm = wm.normalize();
+        let wm = if wm.z < 0.0 { -wm } else { wm };
+        self.distrib.pdf(wo, &wm) / (4.0 * wo.dot(&wm).abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::point2;
+    use crate::reflection::{BeckmannDistribution, TrowbridgeReitzDistribution};
+    use crate::sampling::{sample_grid, uniform_hemisphere_pdf, uniform_sample_hemisphere};
+    use super::*;
+
+    #[test]
+    fn test_sample_f_is_consistent() {
+        let bxdf = ConductorBxdf::new(Box::new(TrowbridgeReitzDistribution::new(0.2, 0.4)), Spectrum::new(0.2),
+                                      Spectrum::new(3.0));
+        let wo = vec3(0.3, -0.4, 0.5).normalize();
+        for u in sample_grid(32) {
+            let mut wi = Vector3f::default();
+            let mut pdf = 0.0;
+            let mut sampled_type = bxdf.flags();
+            let f = bxdf.sample_f(&wo, &mut wi, 0.5, &u, &mut pdf, &mut sampled_type);
+            if pdf == 0.0 {
+                continue
+            }
+            assert!((pdf - bxdf.pdf(&wo, &wi)).abs() <= 1e-3 * pdf, "{} {}", pdf, bxdf.pdf(&wo, &wi));
+            assert!((f[0] - bxdf.f(&wo, &wi)[0]).abs() <= 1e-4 * f[0].max(1.0));
+        }
+    }
+
+    #[test]
+    fn test_white_furnace() {
+        // A perfect reflector only loses the energy of light that would scatter more than once between microfacets,
+        // which is most of it for very rough surfaces
+        let perfect = |distrib| ConductorBxdf::new(distrib, Spectrum::new(0.0), Spectrum::new(1e4));
+        for (alpha, min_albedo) in [(0.05, 0.98), (0.3, 0.8), (1.0, 0.25)] {
+            for bxdf in [
+                perfect(Box::new(TrowbridgeReitzDistribution::new(alpha, alpha))),
+                perfect(Box::new(BeckmannDistribution::new(alpha, alpha)))
+            ] {
+                for wo in [vec3(0.0, 0.0, 1.0), vec3(0.6, 0.0, 0.8)] {
+                    let samples: Vec<_> = sample_grid(128).collect();
+                    let albedo = bxdf.rho(&wo, &vec![0.5; samples.len()], &samples)[0];
+                    // Compare with an estimate that does not depend on the sampling routine
+                    let uniform = samples.iter().map(|u| {
+                        let wi = uniform_sample_hemisphere(u);
+                        bxdf.f(&wo, &wi)[0] * abs_cos_theta(&wi) / uniform_hemisphere_pdf()
+                    }).sum::<f32>() / samples.len() as f32;
+                    assert!(albedo <= 1.0 + 1e-2 && albedo >= min_albedo, "{} {} {:?}", albedo, alpha, wo);
+                    // Uniform sampling does not resolve the narrow lobes of smooth surfaces
+                    if alpha >= 0.3 {
+                        assert!((albedo - uniform).abs() < 2e-2, "{} {}", albedo, uniform);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_smooth_conductor_is_specular() {
+        let bxdf = ConductorBxdf::new(Box::new(TrowbridgeReitzDistribution::new(0.0, 0.0)), Spectrum::new(0.2),
+                                      Spectrum::new(3.0));
+        assert!(bxdf.flags().is_specular());
+        let wo = vec3(0.6, 0.0, 0.8);
+        let mut wi = Vector3f::default();
+        let mut pdf = 0.0;
+        let f = bxdf.sample_f(&wo, &mut wi, 0.5, &point2(0.5, 0.5), &mut pdf, &mut BxdfFlags::empty());
+        assert_eq!(wi, vec3(-0.6, 0.0, 0.8));
+        assert!((f[0] * 0.8 - fr_conductor(0.8, &Spectrum::new(1.0), &Spectrum::new(0.2), &Spectrum::new(3.0))[0])
+            .abs() < 1e-5);
+        assert_eq!(bxdf.pdf(&wo, &wi), 0.0);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::geom::DotProduct;
+use crate::reflection::{abs_cos_theta, cos_theta, fr_dielectric, reflect, refract, same_hemisphere,
+                        transmission_scale, Bxdf, BxdfFlags, MicrofacetDistribution, TransportMode};
+use crate::{vec3, Normal3f, Point2f, Spectrum, Vector3f};
+
+/// Reflection and transmission at the interface between two dielectrics, where `eta` is the index of refraction
+/// below the surface relative to the one above it.
+///
+/// Rough interfaces use the microfacet model of Walter et al., "Microfacet Models for Refraction through Rough
+/// Surfaces" (2007), while effectively smooth interfaces scatter specularly. When sampling, the choice between
+/// reflection and transmission follows the Fresnel reflectance.
+pub struct DielectricBxdf {
+    eta: f32,
+    distrib: Box<dyn MicrofacetDistribution>,
+    mode: TransportMode
+}
+
+impl DielectricBxdf {
+    pub fn new(eta: f32, distrib: Box<dyn MicrofacetDistribution>, mode: TransportMode) -> Self {
+        DielectricBxdf { eta, distrib, mode }
+    }
+
+    #[inline]
+    pub fn eta(&self) -> f32 {
+        self.eta
+    }
+
+    #[inline]
+    pub fn mode(&self) -> TransportMode {
+        self.mode
+    }
+
+    /// Returns the generalized half vector of `wo` and `wi` in the upper hemisphere together with the relative
+    /// index of refraction along the path, or `None` if the microfacet is back-facing to either direction.
+    fn half_vector(&self, wo: &Vector3f, wi: &Vector3f) -> Option<(Vector3f, f32)> {
+        let cos_theta_o = cos_theta(wo);
+        let cos_theta_i = cos_theta(wi);
+        let etap = if cos_theta_i * cos_theta_o > 0.0 {
+            1.0
+        } else if cos_theta_o > 0.0 {
+            self.eta
+        } else {
+            1.0 / self.eta
+        };
+        let wm = *wi * etap + *wo;
+        if cos_theta_i == 0.0 || cos_theta_o == 0.0 || wm.length_squared() == 0.0 {
+            return None
+        }
+        let wm = wm.normalize();
+        let wm = if wm.z < 0.0 { -wm } else { wm };
+        if wm.dot(wi) * cos_theta_i < 0.0 || wm.dot(wo) * cos_theta_o < 0.0 {
+            return None
+        }
+        Some((wm, etap))
+    }
+
+    fn sample_smooth(&self, wo: &Vector3f, wi: &mut Vector3f, uc: f32, pdf: &mut f32,
+                     sampled_type: &mut BxdfFlags) -> Spectrum {
+        let r = fr_dielectric(cos_theta(wo), 1.0, self.eta);
+        let t = 1.0 - r;
+        if uc < r {
+            *wi = vec3(-wo.x, -wo.y, wo.z);
+            *sampled_type = BxdfFlags::REFLECTION | BxdfFlags::SPECULAR;
+            *pdf = r;
+            return Spectrum::new(r / abs_cos_theta(wi))
+        }
+
+        let (eta_i, eta_t) = if cos_theta(wo) > 0.0 { (1.0, self.eta) } else { (self.eta, 1.0) };
+        *wi = match refract(wo, &Normal3f::new(0.0, 0.0, 1.0).face_forward(wo), eta_i / eta_t) {
+            Some(wt) => wt,
+            None => return Spectrum::new(0.0)
+        };
+        *sampled_type = BxdfFlags::TRANSMISSION | BxdfFlags::SPECULAR;
+        *pdf = t;
+        transmission_scale(Spectrum::new(t / abs_cos_theta(wi)), eta_i, eta_t, self.mode)
+    }
+
+    fn sample_rough(&self, wo: &Vector3f, wi: &mut Vector3f, uc: f32, u: &Point2f, pdf: &mut f32,
+                    sampled_type: &mut BxdfFlags) -> Spectrum {
+        let wm = self.distrib.sample_wm(wo, u);
+        let r = fr_dielectric(wo.dot(&wm), 1.0, self.eta);
+        let t = 1.0 - r;
+        let cos_theta_o = abs_cos_theta(wo);
+        if uc < r {
+            *wi = reflect(wo, &wm);
+            if !same_hemisphere(wo, wi) {
+                return Spectrum::new(0.0)
+            }
+            *sampled_type = BxdfFlags::REFLECTION | BxdfFlags::GLOSSY;
+            *pdf = self.distrib.pdf(wo, &wm) / (4.0 * wo.dot(&wm).abs()) * r;
+            let f = self.distrib.d(&wm) * self.distrib.g(wo, wi) * r / (4.0 * abs_cos_theta(wi) * cos_theta_o);
+            return Spectrum::new(f)
+        }
+
+        let (eta_i, eta_t) = if wo.dot(&wm) > 0.0 { (1.0, self.eta) } else { (self.eta, 1.0) };
+        let n = Normal3f::from(wm).face_forward(wo);
+        *wi = match refract(wo, &n, eta_i / eta_t) {
+            Some(wt) if !same_hemisphere(wo, &wt) && wt.z != 0.0 => wt,
+            _ => return Spectrum::new(0.0)
+        };
+        *sampled_type = BxdfFlags::TRANSMISSION | BxdfFlags::GLOSSY;
+        let etap = eta_t / eta_i;
+        let denom = (wi.dot(&wm) + wo.dot(&wm) / etap).powi(2);
+        let dwm_dwi = wi.dot(&wm).abs() / denom;
+        *pdf = self.distrib.pdf(wo, &wm) * dwm_dwi * t;
+        let ft = t * self.distrib.d(&wm) * self.distrib.g(wo, wi)
+            * (wi.dot(&wm) * wo.dot(&wm) / (cos_theta(wi) * cos_theta(wo) * denom)).abs();
+        transmission_scale(Spectrum::new(ft), eta_i, eta_t, self.mode)
+    }
+}
+
+impl Bxdf for DielectricBxdf {
+    fn flags(&self) -> BxdfFlags {
+        let flags = if self.eta == 1.0 {
+            BxdfFlags::TRANSMISSION
+        } else {
+            BxdfFlags::REFLECTION | BxdfFlags::TRANSMISSION
+        };
+        if self.distrib.effectively_smooth() { flags | BxdfFlags::SPECULAR } else { flags | BxdfFlags::GLOSSY }
+    }
+
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        if self.eta == 1.0 || self.distrib.effectively_smooth() {
+            return Spectrum::new(0.0)
+        }
+        let (wm, etap) = match self.half_vector(wo, wi) {
+            Some(h) => h,
+            None => return Spectrum::new(0.0)
+        };
+        let f = fr_dielectric(wo.dot(&wm), 1.0, self.eta);
+        let d = self.distrib.d(&wm);
+        let g = self.distrib.g(wo, wi);
+        if same_hemisphere(wo, wi) {
+            return Spectrum::new(d * g * f / (4.0 * cos_theta(wi) * cos_theta(wo)).abs())
+        }
+        let denom = (wi.dot(&wm) + wo.dot(&wm) / etap).powi(2) * cos_theta(wi) * cos_theta(wo);
+        let ft = d * (1.0 - f) * g * (wi.dot(&wm) * wo.dot(&wm) / denom).abs();
+        transmission_scale(Spectrum::new(ft), 1.0, etap, self.mode)
+    }
+
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, uc: f32, u: &Point2f, pdf: &mut f32,
+                sampled_type: &mut BxdfFlags) -> Spectrum {
+        if self.eta == 1.0 || self.distrib.effectively_smooth() {
+            self.sample_smooth(wo, wi, uc, pdf, sampled_type)
+        } else {
+            self.sample_rough(wo, wi, uc, u, pdf, sampled_type)
+        }
+    }
+
+    fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        if self.eta == 1.0 || self.distrib.effectively_smooth() {
+            return 0.0
+        }
+        let (wm, etap) = match self.half_vector(wo, wi) {
+            Some(h) => h,
+            None => return 0.0
+        };
+        let r = fr_dielectric(wo.dot(&wm), 1.0, self.eta);
+        if same_hemisphere(wo, wi) {
+            self.distrib.pdf(wo, &wm) / (4.0 * wo.dot(&wm).abs()) * r
+        } else {
+            let denom = (wi.dot(&wm) + wo.dot(&wm) / etap).powi(2);
+            let dwm_dwi = wi.dot(&wm).abs() / denom;
+            self.distrib.pdf(wo, &wm) * dwm_dwi * (1.0 - r)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reflection::{BeckmannDistribution, TrowbridgeReitzDistribution};
+    use crate::sampling::{sample_grid, uniform_sample_sphere, uniform_sphere_pdf};
+    use super::*;
+
+    fn sample(bxdf: &dyn Bxdf, wo: &Vector3f, uc: f32, u: &Point2f) -> (Spectrum, Vector3f, f32, BxdfFlags) {
+        let mut wi = Vector3f::default();
+        let mut pdf = 0.0;
+        let mut sampled_type = bxdf.flags();
+        let f = bxdf.sample_f(wo, &mut wi, uc, u, &mut pdf, &mut sampled_type);
+        (f, wi, pdf, sampled_type)
+    }
+
+    fn directions() -> [Vector3f; 4] {
+        [vec3(0.0, 0.0, 1.0), vec3(0.6, 0.0, 0.8), vec3(0.2, 0.3, -0.9).normalize(), vec3(-0.9, 0.0, -0.3).normalize()]
+    }
+
+    #[test]
+    fn test_sample_f_is_consistent() {
+        for distrib in [
+            Box::new(TrowbridgeReitzDistribution::new(0.3, 0.3)) as Box<dyn MicrofacetDistribution>,
+            Box::new(BeckmannDistribution::new(0.2, 0.5))
+        ] {
+            let bxdf = DielectricBxdf::new(1.5, distrib, TransportMode::Radiance);
+            for wo in directions() {
+                for (i, u) in sample_grid(32).enumerate() {
+                    let (f, wi, pdf, sampled_type) = sample(&bxdf, &wo, (i as f32 * 0.618034).fract(), &u);
+                    if pdf == 0.0 {
+                        continue
+                    }
+                    assert_eq!(sampled_type.is_reflective(), same_hemisphere(&wo, &wi));
+                    let expected_pdf = bxdf.pdf(&wo, &wi);
+                    assert!((pdf - expected_pdf).abs() <= 1e-2 * pdf, "{} {} {:?} {:?}", pdf, expected_pdf, wo, wi);
+                    let expected_f = bxdf.f(&wo, &wi)[0];
+                    assert!((f[0] - expected_f).abs() <= 1e-2 * f[0].max(1e-3), "{} {}", f[0], expected_f);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_smooth_white_furnace() {
+        // Every sample of a smooth interface carries all of the incident importance
+        let bxdf = DielectricBxdf::new(1.5, Box::new(TrowbridgeReitzDistribution::new(0.0, 0.0)),
+                                       TransportMode::Importance);
+        assert!(bxdf.flags().is_specular());
+        for wo in directions() {
+            for (i, u) in sample_grid(16).enumerate() {
+                let (f, wi, pdf, _) = sample(&bxdf, &wo, (i as f32 * 0.618034).fract(), &u);
+                assert!(pdf > 0.0);
+                assert!((f[0] * abs_cos_theta(&wi) / pdf - 1.0).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rough_white_furnace() {
+        // Without absorption, a rough interface only loses the energy of multiple scattering between microfacets
+        for (alpha, min_albedo) in [(0.05, 0.97), (0.5, 0.6)] {
+            let bxdf = DielectricBxdf::new(1.5, Box::new(TrowbridgeReitzDistribution::new(alpha, alpha)),
+                                           TransportMode::Importance);
+            for wo in directions() {
+                let samples: Vec<_> = sample_grid(128).collect();
+                let uc: Vec<_> = (0..samples.len()).map(|i| (i as f32 * 0.618034).fract()).collect();
+                let albedo = bxdf.rho(&wo, &uc, &samples)[0];
+                assert!(albedo <= 1.0 + 1e-2 && albedo >= min_albedo, "{} {} {:?}", albedo, alpha, wo);
+                if alpha >= 0.5 && wo.z > 0.0 {
+                    // Compare with an estimate that does not depend on the sampling routine. Refraction into the
+                    // denser medium narrows the transmission lobe, which needs many uniform samples to resolve.
+                    let uniform = sample_grid(512).map(|u| {
+                        let wi = uniform_sample_sphere(&u);
+                        bxdf.f(&wo, &wi)[0] * abs_cos_theta(&wi) / uniform_sphere_pdf()
+                    }).sum::<f32>() / (512 * 512) as f32;
+                    assert!((albedo - uniform).abs() < 1.5e-2, "{} {} {:?}", albedo, uniform, wo);
+                }
+            }
+        }
+    }
+}
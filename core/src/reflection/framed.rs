@@ -0,0 +1,63 @@
+use crate::geom::DotProduct;
+use crate::reflection::{Bxdf, BxdfFlags};
+use crate::{vec3, Point2f, Spectrum, Vector3f};
+
+/// Evaluates another BxDF in a rotated shading frame, which lets materials combine BxDFs whose shading geometry
+/// was perturbed differently.
+pub struct FramedBxdf {
+    bxdf: Box<dyn Bxdf>,
+    x: Vector3f,
+    y: Vector3f,
+    z: Vector3f
+}
+
+impl FramedBxdf {
+    /// Evaluates `bxdf` in the frame with the orthonormal axes `x`, `y` and `z`, which are given in the frame of the
+    /// returned BxDF.
+    pub fn new(bxdf: Box<dyn Bxdf>, x: Vector3f, y: Vector3f, z: Vector3f) -> Self {
+        FramedBxdf { bxdf, x, y, z }
+    }
+
+    #[inline]
+    fn outer_to_local(&self, v: &Vector3f) -> Vector3f {
+        vec3(v.dot(&self.x), v.dot(&self.y), v.dot(&self.z))
+    }
+
+    #[inline]
+    fn local_to_outer(&self, v: &Vector3f) -> Vector3f {
+        self.x * v.x + self.y * v.y + self.z * v.z
+    }
+}
+
+impl Bxdf for FramedBxdf {
+    #[inline]
+    fn flags(&self) -> BxdfFlags {
+        self.bxdf.flags()
+    }
+
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        self.bxdf.f(&self.outer_to_local(wo), &self.outer_to_local(wi))
+    }
+
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, uc: f32, u: &Point2f, pdf: &mut f32,
+                sampled_type: &mut BxdfFlags) -> Spectrum {
+        let mut wi_local = Vector3f::default();
+        let f = self.bxdf.sample_f(&self.outer_to_local(wo), &mut wi_local, uc, u, pdf, sampled_type);
+        *wi = self.local_to_outer(&wi_local);
+        f
+    }
+
+    fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        // Rotations preserve solid angle
+        self.bxdf.pdf(&self.outer_to_local(wo), &self.outer_to_local(wi))
+    }
+
+    fn rho(&self, wo: &Vector3f, uc: &[f32], u: &[Point2f]) -> Spectrum {
+        self.bxdf.rho(&self.outer_to_local(wo), uc, u)
+    }
+
+    #[inline]
+    fn is_stochastic(&self) -> bool {
+        self.bxdf.is_stochastic()
+    }
+}
@@ -0,0 +1,132 @@
+use crate::Spectrum;
+
+/// Computes the Fresnel reflectance of unpolarized light at the interface between two dielectrics with indices
+/// of refraction `eta_i` on the side of the normal and `eta_t` on the other side.
+///
+/// A negative `cos_theta_i` means that light arrives from the side of `eta_t`. Returns one in case of total
+/// internal reflection.
+pub fn fr_dielectric(cos_theta_i: f32, mut eta_i: f32, mut eta_t: f32) -> f32 {
+    let mut cos_theta_i = cos_theta_i.clamp(-1.0, 1.0);
+    if cos_theta_i < 0.0 {
+        std::mem::swap(&mut eta_i, &mut eta_t);
+        cos_theta_i = -cos_theta_i;
+    }
+
+    // Compute the cosine of the transmitted direction using Snell's law
+    let sin_theta_i = f32::max(0.0, 1.0 - cos_theta_i * cos_theta_i).sqrt();
+    let sin_theta_t = eta_i / eta_t * sin_theta_i;
+    if sin_theta_t >= 1.0 {
+        return 1.0
+    }
+    let cos_theta_t = f32::max(0.0, 1.0 - sin_theta_t * sin_theta_t).sqrt();
+
+    let r_parl = (eta_t * cos_theta_i - eta_i * cos_theta_t) / (eta_t * cos_theta_i + eta_i * cos_theta_t);
+    let r_perp = (eta_i * cos_theta_i - eta_t * cos_theta_t) / (eta_i * cos_theta_i + eta_t * cos_theta_t);
+    (r_parl * r_parl + r_perp * r_perp) / 2.0
+}
+
+/// Computes the Fresnel reflectance of unpolarized light at the interface between a dielectric with index of
+/// refraction `eta_i` and a conductor with complex index of refraction `eta_t + i k`.
+pub fn fr_conductor(cos_theta_i: f32, eta_i: &Spectrum, eta_t: &Spectrum, k: &Spectrum) -> Spectrum {
+    let cos_theta_i = cos_theta_i.clamp(-1.0, 1.0);
+    let eta = *eta_t / *eta_i;
+    let eta_k = *k / *eta_i;
+
+    let cos2_theta_i = cos_theta_i * cos_theta_i;
+    let sin2_theta_i = 1.0 - cos2_theta_i;
+    let eta2 = eta * eta;
+    let eta_k2 = eta_k * eta_k;
+
+    let t0 = eta2 - eta_k2 - sin2_theta_i;
+    let a2_plus_b2 = (t0 * t0 + eta2 * eta_k2 * 4.0).sqrt();
+    let t1 = a2_plus_b2 + cos2_theta_i;
+    let a = ((a2_plus_b2 + t0) * 0.5).sqrt();
+    let t2 = a * (2.0 * cos_theta_i);
+    let rs = (t1 - t2) / (t1 + t2);
+
+    let t3 = a2_plus_b2 * cos2_theta_i + sin2_theta_i * sin2_theta_i;
+    let t4 = t2 * sin2_theta_i;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    (rp + rs) * 0.5
+}
+
+/// The fraction of light that is reflected at an interface.
+pub trait Fresnel: Send + Sync {
+    /// Returns the reflectance for light arriving at an angle with cosine `cos_theta_i` to the normal.
+    fn evaluate(&self, cos_theta_i: f32) -> Spectrum;
+}
+
+/// The Fresnel reflectance of a conductor, see [`fr_conductor`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FresnelConductor {
+    pub eta_i: Spectrum,
+    pub eta_t: Spectrum,
+    pub k: Spectrum
+}
+
+impl FresnelConductor {
+    pub fn new(eta_i: Spectrum, eta_t: Spectrum, k: Spectrum) -> Self {
+        FresnelConductor { eta_i, eta_t, k }
+    }
+}
+
+impl Fresnel for FresnelConductor {
+    fn evaluate(&self, cos_theta_i: f32) -> Spectrum {
+        fr_conductor(cos_theta_i.abs(), &self.eta_i, &self.eta_t, &self.k)
+    }
+}
+
+/// The Fresnel reflectance of a dielectric interface, see [`fr_dielectric`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FresnelDielectric {
+    pub eta_i: f32,
+    pub eta_t: f32
+}
+
+impl FresnelDielectric {
+    pub fn new(eta_i: f32, eta_t: f32) -> Self {
+        FresnelDielectric { eta_i, eta_t }
+    }
+}
+
+impl Fresnel for FresnelDielectric {
+    fn evaluate(&self, cos_theta_i: f32) -> Spectrum {
+        Spectrum::new(fr_dielectric(cos_theta_i, self.eta_i, self.eta_t))
+    }
+}
+
+/// A perfect mirror that reflects all light.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct FresnelNoOp;
+
+impl Fresnel for FresnelNoOp {
+    fn evaluate(&self, _cos_theta_i: f32) -> Spectrum {
+        Spectrum::new(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fr_dielectric() {
+        // Normal incidence gives ((eta_t - eta_i) / (eta_t + eta_i))^2 from either side
+        assert!((fr_dielectric(1.0, 1.0, 1.5) - 0.04).abs() < 1e-6);
+        assert!((fr_dielectric(-1.0, 1.0, 1.5) - 0.04).abs() < 1e-6);
+        // Grazing incidence reflects everything
+        assert!((fr_dielectric(0.0, 1.0, 1.5) - 1.0).abs() < 1e-6);
+        // Total internal reflection beyond the critical angle of about 41.8 degrees
+        assert_eq!(fr_dielectric(-0.7, 1.0, 1.5), 1.0);
+        assert!(fr_dielectric(-0.8, 1.0, 1.5) < 1.0);
+    }
+
+    #[test]
+    fn test_fr_conductor_reduces_to_dielectric() {
+        for cos_theta in [1.0, 0.7, 0.3, 0.05] {
+            let conductor = fr_conductor(cos_theta, &Spectrum::new(1.0), &Spectrum::new(1.5), &Spectrum::new(0.0));
+            let dielectric = fr_dielectric(cos_theta, 1.0, 1.5);
+            assert!((conductor[0] - dielectric).abs() < 1e-5, "{:?} {}", conductor, dielectric);
+        }
+    }
+}
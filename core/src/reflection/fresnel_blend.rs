@@ -0,0 +1,107 @@
+use std::f32::consts::PI;
+use crate::geom::DotProduct;
+use crate::reflection::{abs_cos_theta, reflect, same_hemisphere, Bxdf, BxdfFlags, MicrofacetDistribution};
+use crate::sampling::{cosine_hemisphere_pdf, cosine_sample_hemisphere};
+use crate::{Point2f, Spectrum, Vector3f};
+
+/// A diffuse substrate under a glossy specular coating, where the diffuse reflectance `rd` shows through the
+/// fraction of light that the coating with the normal incidence reflectance `rs` does not reflect.
+///
+/// See Ashikhmin and Shirley, "An Anisotropic Phong BRDF Model" (2000).
+pub struct FresnelBlend {
+    rd: Spectrum,
+    rs: Spectrum,
+    distrib: Box<dyn MicrofacetDistribution>
+}
+
+impl FresnelBlend {
+    pub fn new(rd: Spectrum, rs: Spectrum, distrib: Box<dyn MicrofacetDistribution>) -> Self {
+        FresnelBlend { rd, rs, distrib }
+    }
+
+    /// Schlick's approximation of the Fresnel reflectance of the coating.
+    #[inline]
+    fn schlick_fresnel(&self, cos_theta: f32) -> Spectrum {
+        self.rs + (Spectrum::new(1.0) - self.rs) * pow5(1.0 - cos_theta)
+    }
+}
+
+#[inline]
+fn pow5(v: f32) -> f32 {
+    (v * v) * (v * v) * v
+}
+
+impl Bxdf for FresnelBlend {
+    #[inline]
+    fn flags(&self) -> BxdfFlags {
+        BxdfFlags::REFLECTION | BxdfFlags::GLOSSY
+    }
+
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        if !same_hemisphere(wo, wi) {
+            return Spectrum::new(0.0)
+        }
+        let diffuse = self.rd * (Spectrum::new(1.0) - self.rs) * (28.0 / (23.0 * PI)
+            * (1.0 - pow5(1.0 - 0.5 * abs_cos_theta(wi))) * (1.0 - pow5(1.0 - 0.5 * abs_cos_theta(wo))));
+        let wm = *wi + *wo;
+        if wm.length_squared() == 0.0 {
+            return Spectrum::new(0.0)
+        }
+        let wm = wm.normalize();
+        let wm = if wm.z < 0.0 { -wm } else { wm };
+        let specular = self.schlick_fresnel(wi.dot(&wm).abs())
+            * (self.distrib.d(&wm) / (4.0 * wi.dot(&wm).abs() * f32::max(abs_cos_theta(wi), abs_cos_theta(wo))));
+        diffuse + specular
+    }
+
+    /// Samples the diffuse and the glossy lobe with equal probability, chosen with `uc`.
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, uc: f32, u: &Point2f, pdf: &mut f32,
+                _sampled_type: &mut BxdfFlags) -> Spectrum {
+        if uc < 0.5 {
+            *wi = cosine_sample_hemisphere(u);
+            if wo.z < 0.0 {
+                wi.z = -wi.z;
+            }
+        } else {
+            let wm = self.distrib.sample_wm(wo, u);
+            *wi = reflect(wo, &wm);
+            if !same_hemisphere(wo, wi) {
+                return Spectrum::new(0.0)
+            }
+        }
+        *pdf = self.pdf(wo, wi);
+        self.f(wo, wi)
+    }
+
+    fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        if !same_hemisphere(wo, wi) {
+            return 0.0
+        }
+        let wm = (*wo + *wi).normalize();
+        let wm = if wm.z < 0.0 { -wm } else { wm };
+        let glossy = self.distrib.pdf(wo, &wm) / (4.0 * wo.dot(&wm).abs());
+        0.5 * (cosine_hemisphere_pdf(abs_cos_theta(wi)) + glossy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reflection::TrowbridgeReitzDistribution;
+    use crate::sampling::sample_grid;
+    use crate::vec3;
+    use super::*;
+
+    #[test]
+    fn test_conserves_energy() {
+        // Even a white substrate under a perfect coating reflects at most all light
+        let bxdf = FresnelBlend::new(Spectrum::new(1.0), Spectrum::new(0.04),
+                                     Box::new(TrowbridgeReitzDistribution::new(0.3, 0.3)));
+        let n = 64;
+        let u: Vec<_> = sample_grid(n).collect();
+        let uc: Vec<_> = (0..n * n).map(|i| (i as f32 * 0.618034).fract()).collect();
+        for wo in [vec3(0.0, 0.0, 1.0), vec3(0.6, 0.0, 0.8), vec3(0.0, 0.6, -0.8)] {
+            let albedo = bxdf.rho(&wo, &uc, &u)[0];
+            assert!(albedo > 0.5 && albedo < 1.0, "{} {:?}", albedo, wo);
+        }
+    }
+}
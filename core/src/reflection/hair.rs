@@ -0,0 +1,370 @@
+use std::f32::consts::{PI, TAU};
+use crate::math::{safe_asin, safe_sqrt};
+use crate::reflection::{abs_cos_theta, fr_dielectric, Bxdf, BxdfFlags};
+use crate::{vec3, Point2f, Spectrum, SpectrumType, Vector3f};
+
+/// The number of scattering lobes that are modeled explicitly, after which all higher-order lobes are combined.
+const P_MAX: usize = 3;
+
+/// Absorption coefficients of eumelanin and pheomelanin per unit concentration, as linear sRGB colors.
+const EUMELANIN_SIGMA_A: [f32; 3] = [0.419, 0.697, 1.37];
+const PHEOMELANIN_SIGMA_A: [f32; 3] = [0.187, 0.4, 1.05];
+
+/// Scattering from a hair or fur fiber, following Chiang et al., "A Practical and Controllable Hair and Fur Model
+/// for Production Path Tracing" (2016) and d'Eon et al., "An Energy-Conserving Hair Reflectance Model" (2011).
+///
+/// The fiber is modeled as a rough dielectric cylinder with the absorption coefficient `sigma_a` inside. Light that
+/// is reflected at the cuticle (R), transmitted through the fiber (TT), reflected once inside (TRT) and all
+/// higher-order paths each form a lobe, which factors into a longitudinal part with the roughness `beta_m` and an
+/// azimuthal part with the roughness `beta_n`. The scales of the cuticle tilt the lobes by `alpha` degrees.
+///
+/// The BxDF expects the local frame of a curve, in which the `x` axis runs along the fiber and the `y` axis across
+/// it, and `h` in `[-1, 1]` is the offset across the fiber at which it was hit. For a curve shape this offset is
+/// given by `2 v - 1` for the `v` coordinate of the surface interaction.
+pub struct HairBxdf {
+    h: f32,
+    gamma_o: f32,
+    eta: f32,
+    sigma_a: Spectrum,
+    /// The variances of the longitudinal scattering of each lobe.
+    v: [f32; P_MAX + 1],
+    /// The scale of the logistic distribution of the azimuthal scattering.
+    s: f32,
+    sin_2k_alpha: [f32; 3],
+    cos_2k_alpha: [f32; 3]
+}
+
+impl HairBxdf {
+    pub fn new(h: f32, eta: f32, sigma_a: Spectrum, beta_m: f32, beta_n: f32, alpha: f32) -> Self {
+        assert!((-1.0..=1.0).contains(&h), "offset across the fiber out of range: {}", h);
+        assert!((0.0..=1.0).contains(&beta_m) && (0.0..=1.0).contains(&beta_n));
+
+        // Map the roughnesses to the variance and scale of the lobes so that they change perceptually uniformly
+        let mut v = [0.0; P_MAX + 1];
+        v[0] = sqr(0.726 * beta_m + 0.812 * sqr(beta_m) + 3.7 * beta_m.powi(20));
+        v[1] = 0.25 * v[0];
+        v[2] = 4.0 * v[0];
+        for p in 3..=P_MAX {
+            v[p] = v[2];
+        }
+        let s = (PI / 8.0).sqrt() * (0.265 * beta_n + 1.194 * sqr(beta_n) + 5.372 * beta_n.powi(22));
+
+        // Precompute the sines and cosines of the tilts of the lobes, which double for each higher-order lobe
+        let mut sin_2k_alpha = [0.0; 3];
+        let mut cos_2k_alpha = [0.0; 3];
+        sin_2k_alpha[0] = alpha.to_radians().sin();
+        cos_2k_alpha[0] = safe_sqrt(1.0 - sqr(sin_2k_alpha[0]));
+        for i in 1..3 {
+            sin_2k_alpha[i] = 2.0 * cos_2k_alpha[i - 1] * sin_2k_alpha[i - 1];
+            cos_2k_alpha[i] = sqr(cos_2k_alpha[i - 1]) - sqr(sin_2k_alpha[i - 1]);
+        }
+
+        HairBxdf { h, gamma_o: safe_asin(h), eta, sigma_a, v, s, sin_2k_alpha, cos_2k_alpha }
+    }
+
+    /// Returns the absorption coefficient of a fiber with the given concentrations of eumelanin, which makes hair
+    /// brown to black, and pheomelanin, which makes it red.
+    pub fn sigma_a_from_concentration(ce: f32, cp: f32) -> Spectrum {
+        let rgb = [0, 1, 2].map(|i| ce * EUMELANIN_SIGMA_A[i] + cp * PHEOMELANIN_SIGMA_A[i]);
+        Spectrum::from_rgb(rgb, SpectrumType::Unbounded)
+    }
+
+    /// Returns the absorption coefficient with which fibers of the azimuthal roughness `beta_n` have roughly the
+    /// color `c` after multiple scattering.
+    pub fn sigma_a_from_reflectance(c: &Spectrum, beta_n: f32) -> Spectrum {
+        let d = 5.969 - 0.215 * beta_n + 2.532 * sqr(beta_n) - 10.73 * beta_n.powi(3) + 5.574 * beta_n.powi(4)
+            + 0.245 * beta_n.powi(5);
+        Spectrum::from_coefficients(c.coefficients().map(|c| sqr(c.ln() / d)))
+    }
+
+    /// Returns the transmittance of a path through the fiber that enters it at the longitudinal angle of `wo`.
+    fn transmittance(&self, sin_theta_o: f32, cos_theta_o: f32) -> Spectrum {
+        let sin_theta_t = sin_theta_o / self.eta;
+        let cos_theta_t = safe_sqrt(1.0 - sqr(sin_theta_t));
+        let cos_gamma_t = safe_sqrt(1.0 - sqr(self.sin_gamma_t(sin_theta_o, cos_theta_o)));
+        (-self.sigma_a * (2.0 * cos_gamma_t / cos_theta_t)).exp()
+    }
+
+    /// Returns the sine of the azimuthal angle of refraction, with the modified index of refraction of Bravais that
+    /// accounts for the longitudinal angle of `wo`.
+    #[inline]
+    fn sin_gamma_t(&self, sin_theta_o: f32, cos_theta_o: f32) -> f32 {
+        let etap = (sqr(self.eta) - sqr(sin_theta_o)).sqrt() / cos_theta_o;
+        self.h / etap
+    }
+
+    /// Returns the attenuation of each lobe.
+    fn ap(&self, cos_theta_o: f32, t: &Spectrum) -> [Spectrum; P_MAX + 1] {
+        let cos_gamma_o = safe_sqrt(1.0 - sqr(self.h));
+        let f = fr_dielectric(cos_theta_o * cos_gamma_o, 1.0, self.eta);
+        let mut ap = [Spectrum::new(0.0); P_MAX + 1];
+        ap[0] = Spectrum::new(f);
+        ap[1] = *t * sqr(1.0 - f);
+        for p in 2..P_MAX {
+            ap[p] = ap[p - 1] * *t * f;
+        }
+        // The remaining lobes form a geometric series
+        ap[P_MAX] = ap[P_MAX - 1] * *t * f / (Spectrum::new(1.0) - *t * f);
+        ap
+    }
+
+    /// Returns the probabilities with which [`sample_f`](Bxdf::sample_f) chooses each lobe, which are proportional
+    /// to the luminance of their attenuation.
+    fn ap_pdf(&self, sin_theta_o: f32, cos_theta_o: f32) -> [f32; P_MAX + 1] {
+        let ap = self.ap(cos_theta_o, &self.transmittance(sin_theta_o, cos_theta_o));
+        let y = ap.map(|a| a.y());
+        let sum: f32 = y.iter().sum();
+        if sum <= 0.0 {
+            return [0.0; P_MAX + 1]
+        }
+        y.map(|y| y / sum)
+    }
+
+    /// Returns the sine and cosine of the longitudinal angle of `wo` rotated by the cuticle tilt of lobe `p`.
+    fn tilt(&self, p: usize, sin_theta_o: f32, cos_theta_o: f32) -> (f32, f32) {
+        let (sin_theta_op, cos_theta_op) = match p {
+            0 => (sin_theta_o * self.cos_2k_alpha[1] - cos_theta_o * self.sin_2k_alpha[1],
+                  cos_theta_o * self.cos_2k_alpha[1] + sin_theta_o * self.sin_2k_alpha[1]),
+            1 => (sin_theta_o * self.cos_2k_alpha[0] + cos_theta_o * self.sin_2k_alpha[0],
+                  cos_theta_o * self.cos_2k_alpha[0] - sin_theta_o * self.sin_2k_alpha[0]),
+            2 => (sin_theta_o * self.cos_2k_alpha[2] + cos_theta_o * self.sin_2k_alpha[2],
+                  cos_theta_o * self.cos_2k_alpha[2] - sin_theta_o * self.sin_2k_alpha[2]),
+            _ => (sin_theta_o, cos_theta_o)
+        };
+        (sin_theta_op, cos_theta_op.abs())
+    }
+}
+
+impl Bxdf for HairBxdf {
+    #[inline]
+    fn flags(&self) -> BxdfFlags {
+        BxdfFlags::REFLECTION | BxdfFlags::TRANSMISSION | BxdfFlags::GLOSSY
+    }
+
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        let (sin_theta_o, cos_theta_o, phi_o) = fiber_angles(wo);
+        let (sin_theta_i, cos_theta_i, phi_i) = fiber_angles(wi);
+
+        let gamma_t = safe_asin(self.sin_gamma_t(sin_theta_o, cos_theta_o));
+        let ap = self.ap(cos_theta_o, &self.transmittance(sin_theta_o, cos_theta_o));
+        let phi = phi_i - phi_o;
+        let mut f = Spectrum::new(0.0);
+        for (p, ap) in ap.iter().enumerate().take(P_MAX) {
+            let (sin_theta_op, cos_theta_op) = self.tilt(p, sin_theta_o, cos_theta_o);
+            f += *ap * (mp(cos_theta_i, cos_theta_op, sin_theta_i, sin_theta_op, self.v[p])
+                * np(phi, p, self.s, self.gamma_o, gamma_t));
+        }
+        // The higher-order lobes are spread uniformly around the fiber
+        f += ap[P_MAX] * (mp(cos_theta_i, cos_theta_o, sin_theta_i, sin_theta_o, self.v[P_MAX]) / TAU);
+
+        // Cancel out the cosine factor of the scattering equation, which the model already accounts for
+        if abs_cos_theta(wi) > 0.0 {
+            f /= abs_cos_theta(wi);
+        }
+        f
+    }
+
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, uc: f32, u: &Point2f, pdf: &mut f32,
+                _sampled_type: &mut BxdfFlags) -> Spectrum {
+        let (sin_theta_o, cos_theta_o, phi_o) = fiber_angles(wo);
+
+        // Choose a lobe and remap the sample to [0, 1)
+        let ap_pdf = self.ap_pdf(sin_theta_o, cos_theta_o);
+        let mut uc = uc;
+        let mut p = 0;
+        while p < P_MAX && uc >= ap_pdf[p] {
+            uc -= ap_pdf[p];
+            p += 1;
+        }
+        if ap_pdf[p] == 0.0 {
+            return Spectrum::new(0.0)
+        }
+        let uc = (uc / ap_pdf[p]).min(1.0);
+
+        // Sample the longitudinal scattering of the lobe
+        let (sin_theta_op, cos_theta_op) = self.tilt(p, sin_theta_o, cos_theta_o);
+        let u0 = u.x.max(1e-5);
+        let cos_theta = 1.0 + self.v[p] * (u0 + (1.0 - u0) * (-2.0 / self.v[p]).exp()).ln();
+        let sin_theta = safe_sqrt(1.0 - sqr(cos_theta));
+        let cos_phi = (TAU * u.y).cos();
+        let sin_theta_i = -cos_theta * sin_theta_op + sin_theta * cos_phi * cos_theta_op;
+        let cos_theta_i = safe_sqrt(1.0 - sqr(sin_theta_i));
+
+        // Sample the azimuthal scattering of the lobe
+        let gamma_t = safe_asin(self.sin_gamma_t(sin_theta_o, cos_theta_o));
+        let d_phi = if p < P_MAX {
+            phi(p, self.gamma_o, gamma_t) + sample_trimmed_logistic(uc, self.s, -PI, PI)
+        } else {
+            TAU * uc
+        };
+        let phi_i = phi_o + d_phi;
+        *wi = vec3(sin_theta_i, cos_theta_i * phi_i.cos(), cos_theta_i * phi_i.sin());
+        *pdf = self.pdf(wo, wi);
+        self.f(wo, wi)
+    }
+
+    fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        let (sin_theta_o, cos_theta_o, phi_o) = fiber_angles(wo);
+        let (sin_theta_i, cos_theta_i, phi_i) = fiber_angles(wi);
+
+        let gamma_t = safe_asin(self.sin_gamma_t(sin_theta_o, cos_theta_o));
+        let ap_pdf = self.ap_pdf(sin_theta_o, cos_theta_o);
+        let phi = phi_i - phi_o;
+        let mut pdf = 0.0;
+        for (p, ap_pdf) in ap_pdf.iter().enumerate().take(P_MAX) {
+            let (sin_theta_op, cos_theta_op) = self.tilt(p, sin_theta_o, cos_theta_o);
+            pdf += ap_pdf * mp(cos_theta_i, cos_theta_op, sin_theta_i, sin_theta_op, self.v[p])
+                * np(phi, p, self.s, self.gamma_o, gamma_t);
+        }
+        pdf + ap_pdf[P_MAX] * mp(cos_theta_i, cos_theta_o, sin_theta_i, sin_theta_o, self.v[P_MAX]) / TAU
+    }
+}
+
+#[inline]
+fn sqr(x: f32) -> f32 {
+    x * x
+}
+
+/// Returns the sine and cosine of the longitudinal angle of `w` to the normal plane of the fiber, and its azimuthal
+/// angle around the fiber.
+#[inline]
+fn fiber_angles(w: &Vector3f) -> (f32, f32, f32) {
+    (w.x, safe_sqrt(1.0 - sqr(w.x)), w.z.atan2(w.y))
+}
+
+/// The longitudinal scattering function for the variance `v`.
+fn mp(cos_theta_i: f32, cos_theta_o: f32, sin_theta_i: f32, sin_theta_o: f32, v: f32) -> f32 {
+    let a = cos_theta_i * cos_theta_o / v;
+    let b = sin_theta_i * sin_theta_o / v;
+    // Narrow lobes are evaluated in log space to avoid overflow
+    if v <= 0.1 {
+        (log_i0(a) - b - 1.0 / v + std::f32::consts::LN_2 + (1.0 / (2.0 * v)).ln()).exp()
+    } else {
+        (-b).exp() * i0(a) / ((1.0 / v).sinh() * 2.0 * v)
+    }
+}
+
+/// The modified Bessel function of the first kind of order zero.
+fn i0(x: f32) -> f32 {
+    let mut val = 0.0;
+    let mut x2i = 1.0;
+    let mut ifact = 1.0;
+    let mut i4 = 1.0;
+    for i in 0..10 {
+        if i > 1 {
+            ifact *= i as f32;
+        }
+        val += x2i / (i4 * sqr(ifact));
+        x2i *= x * x;
+        i4 *= 4.0;
+    }
+    val
+}
+
+/// The logarithm of [`i0`], with an asymptotic expansion for large arguments.
+fn log_i0(x: f32) -> f32 {
+    if x > 12.0 {
+        x + 0.5 * (-TAU.ln() + (1.0 / x).ln() + 1.0 / (8.0 * x))
+    } else {
+        i0(x).ln()
+    }
+}
+
+/// The azimuthal angle by which light leaves the fiber after following lobe `p`.
+#[inline]
+fn phi(p: usize, gamma_o: f32, gamma_t: f32) -> f32 {
+    let p = p as f32;
+    2.0 * p * gamma_t - 2.0 * gamma_o + p * PI
+}
+
+/// The azimuthal scattering function of lobe `p`, which is a logistic distribution around [`phi`].
+fn np(phi: f32, p: usize, s: f32, gamma_o: f32, gamma_t: f32) -> f32 {
+    let mut d_phi = phi - self::phi(p, gamma_o, gamma_t);
+    // Remap the difference to [-pi, pi]
+    while d_phi > PI {
+        d_phi -= TAU;
+    }
+    while d_phi < -PI {
+        d_phi += TAU;
+    }
+    trimmed_logistic(d_phi, s, -PI, PI)
+}
+
+#[inline]
+fn logistic(x: f32, s: f32) -> f32 {
+    let x = x.abs();
+    (-x / s).exp() / (s * sqr(1.0 + (-x / s).exp()))
+}
+
+#[inline]
+fn logistic_cdf(x: f32, s: f32) -> f32 {
+    1.0 / (1.0 + (-x / s).exp())
+}
+
+/// The logistic distribution restricted to `[a, b]`.
+#[inline]
+fn trimmed_logistic(x: f32, s: f32, a: f32, b: f32) -> f32 {
+    logistic(x, s) / (logistic_cdf(b, s) - logistic_cdf(a, s))
+}
+
+fn sample_trimmed_logistic(u: f32, s: f32, a: f32, b: f32) -> f32 {
+    let k = logistic_cdf(b, s) - logistic_cdf(a, s);
+    let x = -s * (1.0 / (u * k + logistic_cdf(a, s)) - 1.0).ln();
+    x.clamp(a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sampling::{sample_grid, uniform_sample_sphere, uniform_sphere_pdf};
+    use super::*;
+
+    #[test]
+    fn test_white_furnace() {
+        // A fiber that does not absorb scatters all light in some direction
+        let wo = vec3(0.3, 0.8, -0.4).normalize();
+        for beta_m in [0.2, 0.5, 1.0] {
+            for beta_n in [0.2, 0.5, 1.0] {
+                let bxdf = HairBxdf::new(0.3, 1.55, Spectrum::new(0.0), beta_m, beta_n, 2.0);
+                let samples: Vec<_> = sample_grid(128).collect();
+                let albedo = samples.iter().map(|u| {
+                    let wi = uniform_sample_sphere(u);
+                    bxdf.f(&wo, &wi)[0] * abs_cos_theta(&wi) / uniform_sphere_pdf()
+                }).sum::<f32>() / samples.len() as f32;
+                assert!((albedo - 1.0).abs() < 0.05, "{} {} {}", albedo, beta_m, beta_n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_f_is_consistent() {
+        let wo = vec3(-0.2, 0.5, 0.6).normalize();
+        for sigma_a in [Spectrum::new(0.0), HairBxdf::sigma_a_from_concentration(1.3, 0.2)] {
+            let bxdf = HairBxdf::new(-0.6, 1.55, sigma_a, 0.3, 0.4, 2.0);
+            for (i, u) in sample_grid(32).enumerate() {
+                let mut wi = Vector3f::default();
+                let mut pdf = 0.0;
+                let uc = (i as f32 * 0.618034).fract();
+                let f = bxdf.sample_f(&wo, &mut wi, uc, &u, &mut pdf, &mut BxdfFlags::empty());
+                if pdf == 0.0 {
+                    continue
+                }
+                assert!((wi.length() - 1.0).abs() < 1e-4);
+                assert!((pdf - bxdf.pdf(&wo, &wi)).abs() <= 1e-3 * pdf);
+                if sigma_a.is_black() {
+                    // Without absorption every lobe is sampled exactly in proportion to its contribution
+                    let weight = f[0] * abs_cos_theta(&wi) / pdf;
+                    assert!((weight - 1.0).abs() < 1e-2, "{} {:?}", weight, wi);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sigma_a_from_reflectance() {
+        // Darker colors absorb more
+        let dark = HairBxdf::sigma_a_from_reflectance(&Spectrum::new(0.1), 0.3);
+        let light = HairBxdf::sigma_a_from_reflectance(&Spectrum::new(0.6), 0.3);
+        assert!(dark[0] > light[0] && light[0] > 0.0);
+        assert!(HairBxdf::sigma_a_from_reflectance(&Spectrum::new(1.0), 0.3).is_black());
+    }
+}
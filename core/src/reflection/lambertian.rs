@@ -0,0 +1,84 @@
+use std::f32::consts::FRAC_1_PI;
+use crate::reflection::{abs_cos_theta, same_hemisphere, Bxdf, BxdfFlags};
+use crate::sampling::{cosine_hemisphere_pdf, cosine_sample_hemisphere};
+use crate::{Point2f, Spectrum, Vector3f};
+
+/// Ideal diffuse reflection that scatters light equally in all directions of the hemisphere.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LambertianReflection {
+    r: Spectrum
+}
+
+impl LambertianReflection {
+    /// Creates a diffuse reflection with the hemispherical reflectance `r`.
+    pub fn new(r: Spectrum) -> Self {
+        LambertianReflection { r }
+    }
+}
+
+impl Bxdf for LambertianReflection {
+    #[inline]
+    fn flags(&self) -> BxdfFlags {
+        BxdfFlags::REFLECTION | BxdfFlags::DIFFUSE
+    }
+
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        if !same_hemisphere(wo, wi) {
+            return Spectrum::new(0.0)
+        }
+        self.r * FRAC_1_PI
+    }
+
+    fn rho(&self, _wo: &Vector3f, _uc: &[f32], _u: &[Point2f]) -> Spectrum {
+        self.r
+    }
+}
+
+/// Ideal diffuse transmission that scatters light equally in all directions of the opposite hemisphere.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LambertianTransmission {
+    t: Spectrum
+}
+
+impl LambertianTransmission {
+    /// Creates a diffuse transmission with the hemispherical transmittance `t`.
+    pub fn new(t: Spectrum) -> Self {
+        LambertianTransmission { t }
+    }
+}
+
+impl Bxdf for LambertianTransmission {
+    #[inline]
+    fn flags(&self) -> BxdfFlags {
+        BxdfFlags::TRANSMISSION | BxdfFlags::DIFFUSE
+    }
+
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        if same_hemisphere(wo, wi) {
+            return Spectrum::new(0.0)
+        }
+        self.t * FRAC_1_PI
+    }
+
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, _uc: f32, u: &Point2f, pdf: &mut f32,
+                _sampled_type: &mut BxdfFlags) -> Spectrum {
+        *wi = cosine_sample_hemisphere(u);
+        if wo.z > 0.0 {
+            wi.z = -wi.z;
+        }
+        *pdf = self.pdf(wo, wi);
+        self.f(wo, wi)
+    }
+
+    fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        if same_hemisphere(wo, wi) {
+            0.0
+        } else {
+            cosine_hemisphere_pdf(abs_cos_theta(wi))
+        }
+    }
+
+    fn rho(&self, _wo: &Vector3f, _uc: &[f32], _u: &[Point2f]) -> Spectrum {
+        self.t
+    }
+}
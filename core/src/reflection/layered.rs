@@ -0,0 +1,463 @@
+use std::f32::consts::FRAC_1_PI;
+use crate::geom::DotProduct;
+use crate::hash::hash;
+use crate::math::Lerp;
+use crate::reflection::{abs_cos_theta, same_hemisphere, Bxdf, BxdfFlags, ConductorBxdf, DielectricBxdf,
+                        LambertianReflection, TransportMode};
+use crate::rng::Rng;
+use crate::sampling::{henyey_greenstein, power_heuristic, sample_exponential, sample_henyey_greenstein};
+use crate::{point2, Point2f, Spectrum, Vector3f};
+
+/// A dielectric coating over a base BxDF, with a homogeneous medium of the given `thickness` between the two that
+/// scatters light with the single-scattering `albedo` according to the Henyey–Greenstein phase function with
+/// asymmetry `g`. A black albedo gives a purely absorbing medium, which vanishes as the thickness goes to zero.
+///
+/// Light that enters the coating may bounce between the layers any number of times, which has no closed form.
+/// Instead, `f` and `pdf` are estimated by averaging `n_samples` random walks through the layers of at most
+/// `max_depth` scattering events each, following Guo et al., "Position-Free Monte Carlo Simulation for Arbitrary
+/// Layered BSDFs" (2018). The estimates are deterministic for a given pair of directions.
+///
+/// The coating faces both sides of the surface. The base is assumed to scatter symmetrically, which holds for
+/// diffuse and conductor bases.
+pub struct LayeredBxdf<B: Bxdf> {
+    top: DielectricBxdf,
+    bottom: B,
+    thickness: f32,
+    albedo: Spectrum,
+    g: f32,
+    max_depth: u32,
+    n_samples: u32
+}
+
+/// A diffuse base under a dielectric coating, such as lacquered wood or glazed ceramics.
+pub type CoatedDiffuseBxdf = LayeredBxdf<LambertianReflection>;
+
+/// A metal base under a dielectric coating, such as car paint.
+pub type CoatedConductorBxdf = LayeredBxdf<ConductorBxdf>;
+
+impl<B: Bxdf> LayeredBxdf<B> {
+    pub fn new(top: DielectricBxdf, bottom: B, thickness: f32, albedo: Spectrum, g: f32, max_depth: u32,
+               n_samples: u32) -> Self {
+        let thickness = f32::max(thickness, f32::MIN_POSITIVE);
+        LayeredBxdf { top, bottom, thickness, albedo, g, max_depth, n_samples }
+    }
+
+    /// Converts the value of a transmission through the top interface that was sampled from `w` to the value in
+    /// the adjoint transport mode, as needed by the walks that start from the incident direction.
+    fn adjoint_transmission(&self, f: Spectrum, w: &Vector3f) -> Spectrum {
+        let eta = if w.z > 0.0 { self.top.eta() } else { 1.0 / self.top.eta() };
+        match self.top.mode() {
+            TransportMode::Radiance => f * (eta * eta),
+            TransportMode::Importance => f / (eta * eta)
+        }
+    }
+
+    /// Samples a path from `wo`, which reflects off the top or passes through the layers, and returns the
+    /// direction in which it leaves together with the throughput of the path and its kind of scattering.
+    fn sample_path(&self, wo: &Vector3f, uc: f32, u: &Point2f) -> Option<(Vector3f, Spectrum, BxdfFlags)> {
+        let bs = sample(&self.top, wo, uc, *u)?;
+        let mut beta = bs.f * (abs_cos_theta(&bs.wi) / bs.pdf);
+        if same_hemisphere(wo, &bs.wi) {
+            return Some((bs.wi, beta, bs.flags))
+        }
+
+        let mut rng = seeded_rng(hash_direction(wo), hash(&[uc.to_bits().into(), u.x.to_bits().into(),
+                                                            u.y.to_bits().into()]));
+        let mut r = || rng.uniform_f32();
+        let mut specular_path = bs.flags.is_specular();
+        let mut z = self.thickness;
+        let mut w = bs.wi;
+        for depth in 0..self.max_depth {
+            // Possibly terminate the walk with Russian roulette
+            if depth > 3 && beta.max_component() < 0.25 {
+                let q = f32::max(0.0, 1.0 - beta.max_component());
+                if r() < q {
+                    return None
+                }
+                beta /= 1.0 - q;
+            }
+
+            if self.albedo.is_black() {
+                // Advance to the other interface
+                z = if z == self.thickness { 0.0 } else { self.thickness };
+                beta *= tr(self.thickness, &w);
+            } else {
+                // Sample the distance to the next scattering event in the medium, whose density cancels out with
+                // the transmittance
+                let dz = sample_exponential(r(), 1.0 / abs_cos_theta(&w));
+                let zp = if w.z > 0.0 { z + dz } else { z - dz };
+                if zp == z {
+                    return None
+                }
+                if 0.0 < zp && zp < self.thickness {
+                    // The value and the density of the sampled phase function cancel out as well
+                    let (wp, phase_pdf) = sample_henyey_greenstein(&-w, self.g, &point2(r(), r()));
+                    if phase_pdf == 0.0 || wp.z == 0.0 {
+                        return None
+                    }
+                    beta *= self.albedo;
+                    specular_path = false;
+                    w = wp;
+                    z = zp;
+                    continue
+                }
+                z = zp.clamp(0.0, self.thickness);
+            }
+
+            // Scatter at the interface that was reached
+            let interface = if z == 0.0 { &self.bottom as &dyn Bxdf } else { &self.top };
+            let bs = sample(interface, &-w, r(), point2(r(), r()))?;
+            beta *= bs.f * (abs_cos_theta(&bs.wi) / bs.pdf);
+            specular_path &= bs.flags.is_specular();
+            let transmitted = !same_hemisphere(&-w, &bs.wi);
+            w = bs.wi;
+
+            // The path leaves the layers once it is transmitted through either interface
+            if transmitted {
+                let mut flags = if same_hemisphere(wo, &w) { BxdfFlags::REFLECTION } else { BxdfFlags::TRANSMISSION };
+                flags |= if specular_path { BxdfFlags::SPECULAR } else { BxdfFlags::GLOSSY };
+                return Some((w, beta, flags))
+            }
+        }
+        None
+    }
+}
+
+impl<B: Bxdf> Bxdf for LayeredBxdf<B> {
+    fn flags(&self) -> BxdfFlags {
+        let (top, bottom) = (self.top.flags(), self.bottom.flags());
+        let mut flags = BxdfFlags::REFLECTION;
+        if top.is_specular() {
+            flags |= BxdfFlags::SPECULAR;
+        }
+        if top.is_diffuse() || bottom.is_diffuse() || !self.albedo.is_black() {
+            flags |= BxdfFlags::DIFFUSE;
+        } else if top.is_glossy() || bottom.is_glossy() {
+            flags |= BxdfFlags::GLOSSY;
+        }
+        if top.is_transmissive() && bottom.is_transmissive() {
+            flags |= BxdfFlags::TRANSMISSION;
+        }
+        flags
+    }
+
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        let (wo, wi) = if wo.z < 0.0 { (-*wo, -*wi) } else { (*wo, *wi) };
+        let reflected = same_hemisphere(&wo, &wi);
+        if !reflected && !self.bottom.flags().is_transmissive() {
+            return Spectrum::new(0.0)
+        }
+        // Light that is reflected leaves the layers through the top, and light that is transmitted through the base
+        let (top, bottom) = (&self.top as &dyn Bxdf, &self.bottom as &dyn Bxdf);
+        let (exit, non_exit, exit_z) = if reflected { (top, bottom, self.thickness) } else { (bottom, top, 0.0) };
+        let (exit_specular, non_exit_specular) = (exit.flags().is_specular(), non_exit.flags().is_specular());
+
+        let n = self.n_samples as f32;
+        let mut f = if reflected { self.top.f(&wo, &wi) * n } else { Spectrum::new(0.0) };
+        let mut rng = seeded_rng(hash_direction(&wo), hash_direction(&wi));
+        let mut r = || rng.uniform_f32();
+        for _ in 0..self.n_samples {
+            // Sample the transmission into the layers from wo
+            let wos = match sample(top, &wo, r(), point2(r(), r())) {
+                Some(s) if !same_hemisphere(&wo, &s.wi) => s,
+                _ => continue
+            };
+            // Sample the direction along which light that leaves through the exit interface towards wi arrives,
+            // which serves as a virtual light source for the walk
+            let wis = match sample(exit, &wi, r(), point2(r(), r())) {
+                Some(s) if !same_hemisphere(&wi, &s.wi) => s,
+                _ => continue
+            };
+            let wis_f = if reflected { self.adjoint_transmission(wis.f, &wi) } else { wis.f };
+
+            let mut beta = wos.f * (abs_cos_theta(&wos.wi) / wos.pdf);
+            let mut z = self.thickness;
+            let mut w = wos.wi;
+            for depth in 0..self.max_depth {
+                // Possibly terminate the walk with Russian roulette
+                if depth > 3 && beta.max_component() < 0.25 {
+                    let q = f32::max(0.0, 1.0 - beta.max_component());
+                    if r() < q {
+                        break
+                    }
+                    beta /= 1.0 - q;
+                }
+
+                if self.albedo.is_black() {
+                    // Advance to the other interface
+                    z = if z == self.thickness { 0.0 } else { self.thickness };
+                    beta *= tr(self.thickness, &w);
+                } else {
+                    // Sample the distance to the next scattering event in the medium
+                    let dz = sample_exponential(r(), 1.0 / abs_cos_theta(&w));
+                    let zp = if w.z > 0.0 { z + dz } else { z - dz };
+                    if 0.0 < zp && zp < self.thickness {
+                        // Connect the scattering event to the virtual light
+                        let phase = henyey_greenstein(w.dot(&wis.wi), self.g);
+                        let wt = if exit_specular { 1.0 } else { power_heuristic(1, wis.pdf, 1, phase) };
+                        f += beta * self.albedo * wis_f * (phase * wt * tr(zp - exit_z, &wis.wi) / wis.pdf);
+
+                        // Continue the walk in a direction sampled from the phase function, for which the value
+                        // and the density cancel out
+                        let (wp, phase_pdf) = sample_henyey_greenstein(&-w, self.g, &point2(r(), r()));
+                        if phase_pdf == 0.0 || wp.z == 0.0 {
+                            continue
+                        }
+                        beta *= self.albedo;
+                        w = wp;
+                        z = zp;
+
+                        // Connect the new direction to wi if it leads to the exit interface
+                        if ((z < exit_z && w.z > 0.0) || (z > exit_z && w.z < 0.0)) && !exit_specular {
+                            let f_exit = exit.f(&-w, &wi);
+                            if !f_exit.is_black() {
+                                // Weight by the density of the virtual light sampling the same direction, -w from wi
+                                let wt = power_heuristic(1, phase_pdf, 1, exit.pdf(&wi, &-w));
+                                f += beta * f_exit * (tr(zp - exit_z, &w) * wt);
+                            }
+                        }
+                        continue
+                    }
+                    z = zp.clamp(0.0, self.thickness);
+                }
+
+                if z == exit_z {
+                    // Light that is transmitted through the exit interface is accounted for by the connections, so
+                    // only reflection continues the walk
+                    match sample(exit, &-w, r(), point2(r(), r())) {
+                        Some(bs) if same_hemisphere(&-w, &bs.wi) => {
+                            beta *= bs.f * (abs_cos_theta(&bs.wi) / bs.pdf);
+                            w = bs.wi;
+                        }
+                        _ => break
+                    }
+                } else {
+                    if !non_exit_specular {
+                        // Connect the scattering at the non-exit interface to the virtual light
+                        let wt = if exit_specular {
+                            1.0
+                        } else {
+                            power_heuristic(1, wis.pdf, 1, non_exit.pdf(&-w, &-wis.wi))
+                        };
+                        f += beta * non_exit.f(&-w, &-wis.wi) * wis_f
+                            * (abs_cos_theta(&wis.wi) * wt * tr(self.thickness, &wis.wi) / wis.pdf);
+                    }
+
+                    let bs = match sample(non_exit, &-w, r(), point2(r(), r())) {
+                        Some(bs) if same_hemisphere(&-w, &bs.wi) => bs,
+                        _ => break
+                    };
+                    beta *= bs.f * (abs_cos_theta(&bs.wi) / bs.pdf);
+                    w = bs.wi;
+
+                    if !exit_specular {
+                        // Connect the sampled direction to wi through the exit interface
+                        let f_exit = exit.f(&-w, &wi);
+                        if !f_exit.is_black() {
+                            let wt = if non_exit_specular {
+                                1.0
+                            } else {
+                                power_heuristic(1, bs.pdf, 1, exit.pdf(&wi, &-w))
+                            };
+                            f += beta * f_exit * (tr(self.thickness, &w) * wt);
+                        }
+                    }
+                }
+            }
+        }
+        f / n
+    }
+
+    /// Samples a path through the layers.
+    ///
+    /// The actual density of the sampled direction has no closed form, so unless the path is specular, `pdf` is set
+    /// to the estimate of [`pdf`](Self::pdf) and the returned value is scaled to keep the ratio of the two exact.
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, uc: f32, u: &Point2f, pdf: &mut f32,
+                sampled_type: &mut BxdfFlags) -> Spectrum {
+        let flip = wo.z < 0.0;
+        let wo = if flip { -*wo } else { *wo };
+        let (w, beta, flags) = match self.sample_path(&wo, uc, u) {
+            Some(path) => path,
+            None => return Spectrum::new(0.0)
+        };
+        *wi = if flip { -w } else { w };
+        *sampled_type = flags;
+        *pdf = if flags.is_specular() { 1.0 } else { self.pdf(&wo, &w) };
+        beta * (*pdf / abs_cos_theta(&w))
+    }
+
+    /// Estimates the density of [`sample_f`](Self::sample_f) stochastically, mixed with a uniform density over the
+    /// sphere to account for the paths that the estimate misses.
+    fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        let (wo, wi) = if wo.z < 0.0 { (-*wo, -*wi) } else { (*wo, *wi) };
+        let (top, bottom) = (&self.top as &dyn Bxdf, &self.bottom as &dyn Bxdf);
+        let (top_specular, bottom_specular) = (top.flags().is_specular(), bottom.flags().is_specular());
+        let reflected = same_hemisphere(&wo, &wi);
+
+        let n = self.n_samples as f32;
+        let mut pdf_sum = if reflected { self.top.pdf(&wo, &wi) * n } else { 0.0 };
+        let mut rng = seeded_rng(hash_direction(&wi), hash_direction(&wo));
+        let mut r = || rng.uniform_f32();
+        for _ in 0..self.n_samples {
+            // Sample the transmission into the layers from both directions
+            let exit = if reflected { top } else { bottom };
+            let wos = sample(top, &wo, r(), point2(r(), r())).filter(|s| !same_hemisphere(&wo, &s.wi));
+            let wis = sample(exit, &wi, r(), point2(r(), r())).filter(|s| !same_hemisphere(&wi, &s.wi));
+            let (wos, wis) = match (wos, wis) {
+                (Some(wos), Some(wis)) => (wos, wis),
+                _ => continue
+            };
+
+            if reflected {
+                // Estimate the density of paths that are transmitted through the top, reflected at the base and
+                // transmitted back out
+                if top_specular {
+                    pdf_sum += bottom.pdf(&-wos.wi, &-wis.wi);
+                    continue
+                }
+                let rs = match sample(bottom, &-wos.wi, r(), point2(r(), r())) {
+                    Some(rs) => rs,
+                    None => continue
+                };
+                if bottom_specular {
+                    pdf_sum += top.pdf(&-rs.wi, &wi);
+                } else {
+                    // Combine the estimates that sample the reflection at the base and the exit through the top
+                    let r_pdf = bottom.pdf(&-wos.wi, &-wis.wi);
+                    pdf_sum += power_heuristic(1, wis.pdf, 1, r_pdf) * r_pdf;
+                    let t_pdf = top.pdf(&-rs.wi, &wi);
+                    pdf_sum += power_heuristic(1, rs.pdf, 1, t_pdf) * t_pdf;
+                }
+            } else if top_specular {
+                // Estimate the density of paths that are transmitted through both interfaces
+                pdf_sum += bottom.pdf(&-wos.wi, &wi);
+            } else if bottom_specular {
+                pdf_sum += top.pdf(&wo, &-wis.wi);
+            } else {
+                pdf_sum += (top.pdf(&wo, &-wis.wi) + bottom.pdf(&-wos.wi, &wi)) / 2.0;
+            }
+        }
+        f32::lerp(0.9, FRAC_1_PI / 4.0, pdf_sum / n)
+    }
+
+    #[inline]
+    fn is_stochastic(&self) -> bool {
+        true
+    }
+}
+
+/// A direction sampled from a BxDF together with the value of the BxDF and the density of the direction.
+struct BxdfSample {
+    f: Spectrum,
+    wi: Vector3f,
+    pdf: f32,
+    flags: BxdfFlags
+}
+
+/// Samples `bxdf` for `wo`, or returns `None` if the sample carries no light.
+fn sample(bxdf: &dyn Bxdf, wo: &Vector3f, uc: f32, u: Point2f) -> Option<BxdfSample> {
+    let mut wi = Vector3f::default();
+    let mut pdf = 0.0;
+    let mut flags = bxdf.flags();
+    let f = bxdf.sample_f(wo, &mut wi, uc, &u, &mut pdf, &mut flags);
+    if f.is_black() || pdf == 0.0 || wi.z == 0.0 {
+        return None
+    }
+    Some(BxdfSample { f, wi, pdf, flags })
+}
+
+/// Returns the transmittance of a medium with unit attenuation coefficient between two planes at a distance of
+/// `dz` along `w`.
+#[inline]
+fn tr(dz: f32, w: &Vector3f) -> f32 {
+    if dz.abs() <= f32::MIN_POSITIVE {
+        return 1.0
+    }
+    (-(dz / w.z).abs()).exp()
+}
+
+#[inline]
+fn hash_direction(w: &Vector3f) -> u64 {
+    hash(&[w.x.to_bits().into(), w.y.to_bits().into(), w.z.to_bits().into()])
+}
+
+#[inline]
+fn seeded_rng(sequence_index: u64, offset: u64) -> Rng {
+    let mut rng = Rng::default();
+    rng.set_sequence_with_offset(sequence_index, offset);
+    rng
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reflection::{fr_dielectric, TrowbridgeReitzDistribution};
+    use crate::sampling::{sample_grid, uniform_sample_sphere, uniform_sphere_pdf};
+    use crate::vec3;
+    use super::*;
+
+    fn coating(alpha: f32) -> DielectricBxdf {
+        DielectricBxdf::new(1.5, Box::new(TrowbridgeReitzDistribution::new(alpha, alpha)), TransportMode::Radiance)
+    }
+
+    fn albedo(bxdf: &dyn Bxdf, wo: &Vector3f) -> f32 {
+        let samples: Vec<_> = sample_grid(64).collect();
+        let uc: Vec<_> = (0..samples.len()).map(|i| (i as f32 * 0.618034).fract()).collect();
+        bxdf.rho(wo, &uc, &samples)[0]
+    }
+
+    #[test]
+    fn test_f_matches_sample_f() {
+        let wo = vec3(0.6, 0.0, 0.8);
+        let diffuse = CoatedDiffuseBxdf::new(coating(0.0), LambertianReflection::new(Spectrum::new(0.5)), 1e-4,
+                                             Spectrum::new(0.0), 0.0, 10, 1);
+        let conductor = CoatedConductorBxdf::new(
+            coating(0.3), ConductorBxdf::new(Box::new(TrowbridgeReitzDistribution::new(0.5, 0.5)),
+                                             Spectrum::new(0.2), Spectrum::new(3.0)),
+            0.1, Spectrum::new(0.8), 0.3, 10, 1);
+        // The specular reflection off a smooth coating is not part of f
+        for (bxdf, specular) in [(&diffuse as &dyn Bxdf, fr_dielectric(wo.z, 1.0, 1.5)), (&conductor, 0.0)] {
+            let samples: Vec<_> = sample_grid(128).collect();
+            let (mut uniform, mut pdf) = (specular, 0.0);
+            for u in &samples {
+                let wi = uniform_sample_sphere(u);
+                uniform += bxdf.f(&wo, &wi)[0] * abs_cos_theta(&wi) / uniform_sphere_pdf() / samples.len() as f32;
+                pdf += bxdf.pdf(&wo, &wi) / uniform_sphere_pdf() / samples.len() as f32;
+            }
+            let sampled = albedo(bxdf, &wo);
+            assert!((sampled - uniform).abs() < 1.5e-2, "{} {}", sampled, uniform);
+            // The density is only a rough estimate, but of the right magnitude
+            assert!(pdf > 0.5 && pdf < 1.5, "{}", pdf);
+        }
+    }
+
+    #[test]
+    fn test_white_furnace() {
+        // Without absorption, all light eventually leaves a smooth coating, after many bounces for a scattering
+        // medium. Rough coatings lose the energy of light that would scatter more than once between microfacets.
+        let white = |alpha, thickness, albedo| {
+            CoatedDiffuseBxdf::new(coating(alpha), LambertianReflection::new(Spectrum::new(1.0)), thickness,
+                                   Spectrum::new(albedo), 0.5, 256, 1)
+        };
+        for (bxdf, min_albedo) in [(white(0.0, 1e-4, 0.0), 0.99), (white(0.0, 0.5, 1.0), 0.99),
+                                   (white(0.2, 0.5, 1.0), 0.7)] {
+            for wo in [vec3(0.0, 0.0, 1.0), vec3(0.6, 0.0, -0.8)] {
+                let albedo = albedo(&bxdf, &wo);
+                assert!(albedo <= 1.0 + 1e-2 && albedo >= min_albedo, "{} {:?}", albedo, wo);
+            }
+        }
+    }
+
+    #[test]
+    fn test_absorbing_medium_darkens() {
+        let base = || LambertianReflection::new(Spectrum::new(0.8));
+        let thin = CoatedDiffuseBxdf::new(coating(0.0), base(), 1e-4, Spectrum::new(0.0), 0.0, 10, 1);
+        let thick = CoatedDiffuseBxdf::new(coating(0.0), base(), 0.5, Spectrum::new(0.0), 0.0, 10, 1);
+        let wo = vec3(0.0, 0.0, 1.0);
+        let wi = vec3(0.6, 0.0, 0.8);
+        assert!(thick.f(&wo, &wi)[0] < 0.5 * thin.f(&wo, &wi)[0]);
+        assert!(albedo(&thick, &wo) < albedo(&thin, &wo));
+        // An opaque base transmits nothing
+        assert!(thin.f(&wo, &-wi).is_black());
+        assert!(!thin.flags().is_transmissive());
+    }
+}
@@ -0,0 +1,123 @@
+mod merl;
+mod rgl;
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use crate::reflection::{Bxdf, BxdfFlags};
+use crate::{Point2f, Spectrum, Vector3f};
+use merl::MerlBrdf;
+use rgl::RglBrdf;
+
+/// An isotropic or anisotropic BRDF given by measured data.
+///
+/// Two formats are supported, which are told apart by the extension of the file: the binary format of the MERL
+/// database (`.binary`) and the tabulated format of the RGL material database (`.bsdf`).
+pub struct MeasuredBrdf {
+    data: MeasuredData
+}
+
+enum MeasuredData {
+    Merl(MerlBrdf),
+    Rgl(Box<RglBrdf>)
+}
+
+impl MeasuredBrdf {
+    /// Reads the measured BRDF in the file at `path`.
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let data = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("binary") => MeasuredData::Merl(MerlBrdf::read(path)?),
+            Some("bsdf") => MeasuredData::Rgl(Box::new(RglBrdf::read(path)?)),
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                           format!("unknown measured BRDF format: {}", path.display())))
+        };
+        Ok(MeasuredBrdf { data })
+    }
+
+    /// Evaluates the BRDF for `wo` in the upper hemisphere.
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        match &self.data {
+            MeasuredData::Merl(brdf) => brdf.f(wo, wi),
+            MeasuredData::Rgl(brdf) => brdf.f(wo, wi)
+        }
+    }
+
+    /// Samples an incident direction for `wo` in the upper hemisphere, returning it together with its density.
+    fn sample_wi(&self, wo: &Vector3f, uc: f32, u: &Point2f) -> Option<(Vector3f, f32)> {
+        match &self.data {
+            MeasuredData::Merl(brdf) => brdf.sample_wi(wo, uc, u),
+            MeasuredData::Rgl(brdf) => brdf.sample_wi(wo, u)
+        }
+    }
+
+    /// Returns the density of sampling `wi` for `wo` in the upper hemisphere.
+    fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        match &self.data {
+            MeasuredData::Merl(brdf) => brdf.pdf(wo, wi),
+            MeasuredData::Rgl(brdf) => brdf.pdf(wo, wi)
+        }
+    }
+}
+
+/// Returns an error for a file that does not follow its format.
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Reflection given by a [`MeasuredBrdf`], which is importance sampled with tables that are precomputed when the
+/// data is read.
+///
+/// The surface reflects the same way from both sides.
+pub struct MeasuredBxdf {
+    brdf: Arc<MeasuredBrdf>
+}
+
+impl MeasuredBxdf {
+    pub fn new(brdf: Arc<MeasuredBrdf>) -> Self {
+        MeasuredBxdf { brdf }
+    }
+}
+
+impl Bxdf for MeasuredBxdf {
+    #[inline]
+    fn flags(&self) -> BxdfFlags {
+        BxdfFlags::REFLECTION | BxdfFlags::GLOSSY
+    }
+
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        if wo.z * wi.z <= 0.0 {
+            return Spectrum::new(0.0)
+        }
+        if wo.z < 0.0 {
+            return self.brdf.f(&-*wo, &-*wi)
+        }
+        self.brdf.f(wo, wi)
+    }
+
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, uc: f32, u: &Point2f, pdf: &mut f32,
+                _sampled_type: &mut BxdfFlags) -> Spectrum {
+        if wo.z == 0.0 {
+            return Spectrum::new(0.0)
+        }
+        let flip = wo.z < 0.0;
+        let wo = if flip { -*wo } else { *wo };
+        let (w, w_pdf) = match self.brdf.sample_wi(&wo, uc, u) {
+            Some(s) => s,
+            None => return Spectrum::new(0.0)
+        };
+        *wi = if flip { -w } else { w };
+        *pdf = w_pdf;
+        self.brdf.f(&wo, &w)
+    }
+
+    fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        if wo.z * wi.z <= 0.0 {
+            return 0.0
+        }
+        if wo.z < 0.0 {
+            return self.brdf.pdf(&-*wo, &-*wi)
+        }
+        self.brdf.pdf(wo, wi)
+    }
+}
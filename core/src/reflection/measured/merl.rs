@@ -0,0 +1,240 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+use std::path::Path;
+use std::{fs, io};
+use crate::math::{safe_acos, safe_sqrt, Lerp};
+use crate::reflection::cos_theta;
+use crate::reflection::measured::invalid_data;
+use crate::sampling::{cosine_hemisphere_pdf, cosine_sample_hemisphere, spherical_direction, Distribution2D};
+use crate::spectrum::{rgb_to_xyz, SpectrumType};
+use crate::{point2, vec3, Point2f, Spectrum, Vector3f};
+
+const THETA_H_RES: usize = 90;
+const THETA_D_RES: usize = 90;
+const PHI_D_RES: usize = 180;
+/// The number of tabulated values of each color channel.
+const N_VALUES: usize = THETA_H_RES * THETA_D_RES * PHI_D_RES;
+/// Factors that scale the stored values of the red, green and blue channel to reflectances.
+const CHANNEL_SCALES: [f64; 3] = [1.0 / 1500.0, 1.15 / 1500.0, 1.66 / 1500.0];
+
+/// The number of outgoing elevations for which the BRDF is tabulated for importance sampling.
+const SAMPLING_THETA_O_RES: usize = 32;
+/// The resolution of the tables over the incident azimuth, relative to the outgoing one, and the incident cosine.
+const SAMPLING_PHI_RES: usize = 128;
+const SAMPLING_COS_THETA_RES: usize = 64;
+/// The fraction of directions that are sampled from the cosine-weighted hemisphere, which covers the reflection
+/// that the tables miss between their samples.
+const COSINE_SAMPLE_FRACTION: f32 = 0.1;
+
+/// An isotropic BRDF from the MERL database of Matusik et al., "A Data-Driven Reflectance Model" (2003), which is
+/// tabulated over the half and difference angles of Rusinkiewicz's parameterization.
+pub(super) struct MerlBrdf {
+    /// The RGB reflectances, with the difference azimuth varying fastest and the half elevation slowest.
+    values: Vec<[f32; 3]>,
+    /// The spectra of the red, green and blue primaries, which the reflectances are combined from.
+    primaries: [Spectrum; 3],
+    /// For every outgoing elevation, the distribution of the luminance of the cosine-weighted BRDF over the incident
+    /// azimuth and cosine.
+    distributions: Vec<Distribution2D>
+}
+
+impl MerlBrdf {
+    pub(super) fn read(path: &Path) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Parses a MERL file, which holds three 32 bit integers with the dimensions of the table followed by the values
+    /// of the red, green and blue channel as doubles, all in little-endian byte order.
+    fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < 12 {
+            return Err(invalid_data("MERL file is truncated"))
+        }
+        let dims: Vec<i32> = bytes[..12].chunks_exact(4)
+            .map(|b| i32::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        let n_values = dims.iter()
+            .try_fold(1usize, |n, &dim| usize::try_from(dim).ok().and_then(|dim| n.checked_mul(dim)));
+        if n_values != Some(N_VALUES) {
+            return Err(invalid_data(format!("unexpected MERL table dimensions {:?}", dims)))
+        }
+        if bytes.len() != 12 + 3 * N_VALUES * 8 {
+            return Err(invalid_data("MERL file is truncated"))
+        }
+        let doubles: Vec<f64> = bytes[12..].chunks_exact(8)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+            .collect();
+        // Directions without measurements are stored as negative values
+        let values = (0..N_VALUES)
+            .map(|i| [0, 1, 2].map(|c| f64::max(0.0, doubles[c * N_VALUES + i] * CHANNEL_SCALES[c]) as f32))
+            .collect();
+        Ok(Self::new(values))
+    }
+
+    fn new(values: Vec<[f32; 3]>) -> Self {
+        assert_eq!(values.len(), N_VALUES);
+        let primaries = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+            .map(|rgb| Spectrum::from_rgb(rgb, SpectrumType::Reflectance));
+        let mut brdf = MerlBrdf { values, primaries, distributions: Vec::with_capacity(SAMPLING_THETA_O_RES) };
+        for i in 0..SAMPLING_THETA_O_RES {
+            let theta_o = (i as f32 + 0.5) / SAMPLING_THETA_O_RES as f32 * FRAC_PI_2;
+            let wo = vec3(theta_o.sin(), 0.0, theta_o.cos());
+            let func: Vec<f32> = (0..SAMPLING_PHI_RES * SAMPLING_COS_THETA_RES)
+                .map(|j| {
+                    let (u, v) = (j % SAMPLING_PHI_RES, j / SAMPLING_PHI_RES);
+                    let phi = (u as f32 + 0.5) / SAMPLING_PHI_RES as f32 * 2.0 * PI;
+                    let cos_theta = (v as f32 + 0.5) / SAMPLING_COS_THETA_RES as f32;
+                    let wi = spherical_direction(safe_sqrt(1.0 - cos_theta * cos_theta), cos_theta, phi);
+                    rgb_to_xyz(brdf.lookup(&wo, &wi))[1] * cos_theta
+                })
+                .collect();
+            brdf.distributions.push(Distribution2D::new(&func, SAMPLING_PHI_RES, SAMPLING_COS_THETA_RES));
+        }
+        brdf
+    }
+
+    /// Looks up the RGB reflectance of the nearest tabulated pair of directions.
+    fn lookup(&self, wo: &Vector3f, wi: &Vector3f) -> [f32; 3] {
+        let wh = *wo + *wi;
+        if wh.length_squared() == 0.0 {
+            return [0.0; 3]
+        }
+        let wh = wh.normalize();
+        let theta_h = safe_acos(wh.z);
+        let phi_h = wh.y.atan2(wh.x);
+
+        // Rotate wi such that the half vector becomes the normal
+        let (sin_phi_h, cos_phi_h) = phi_h.sin_cos();
+        let w = vec3(wi.x * cos_phi_h + wi.y * sin_phi_h, wi.y * cos_phi_h - wi.x * sin_phi_h, wi.z);
+        let (sin_theta_h, cos_theta_h) = theta_h.sin_cos();
+        let wd = vec3(w.x * cos_theta_h - w.z * sin_theta_h, w.y, w.z * cos_theta_h + w.x * sin_theta_h);
+        let theta_d = safe_acos(wd.z);
+        let mut phi_d = wd.y.atan2(wd.x);
+        // The table only covers half of the azimuths, as the others follow from reciprocity
+        if phi_d < 0.0 {
+            phi_d += PI;
+        }
+
+        // The half elevation is mapped non-linearly to resolve specular highlights
+        let theta_h_index = ((theta_h / FRAC_PI_2).max(0.0).sqrt() * THETA_H_RES as f32) as usize;
+        let theta_d_index = (theta_d / FRAC_PI_2 * THETA_D_RES as f32) as usize;
+        let phi_d_index = (phi_d / PI * PHI_D_RES as f32) as usize;
+        let index = usize::min(phi_d_index, PHI_D_RES - 1)
+            + usize::min(theta_d_index, THETA_D_RES - 1) * PHI_D_RES
+            + usize::min(theta_h_index, THETA_H_RES - 1) * PHI_D_RES * THETA_D_RES;
+        self.values[index]
+    }
+
+    /// Returns the sampling distribution for the elevation of `wo`.
+    fn distribution(&self, wo: &Vector3f) -> &Distribution2D {
+        let theta_o = safe_acos(cos_theta(wo));
+        let i = (theta_o / FRAC_PI_2 * SAMPLING_THETA_O_RES as f32) as usize;
+        &self.distributions[usize::min(i, SAMPLING_THETA_O_RES - 1)]
+    }
+
+    pub(super) fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        let rgb = self.lookup(wo, wi);
+        self.primaries[0] * rgb[0] + self.primaries[1] * rgb[1] + self.primaries[2] * rgb[2]
+    }
+
+    pub(super) fn sample_wi(&self, wo: &Vector3f, uc: f32, u: &Point2f) -> Option<(Vector3f, f32)> {
+        let wi = if uc < COSINE_SAMPLE_FRACTION {
+            cosine_sample_hemisphere(u)
+        } else {
+            let (p, _) = self.distribution(wo).sample_continuous(u);
+            let phi = wo.y.atan2(wo.x) + p.x * 2.0 * PI;
+            spherical_direction(safe_sqrt(1.0 - p.y * p.y), p.y, phi)
+        };
+        if wi.z <= 0.0 {
+            return None
+        }
+        Some((wi, self.pdf(wo, &wi)))
+    }
+
+    pub(super) fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        let phi = (wi.y.atan2(wi.x) - wo.y.atan2(wo.x)).rem_euclid(2.0 * PI);
+        // The tables are uniform in the incident cosine and azimuth, which spans 2 pi
+        let table_pdf = self.distribution(wo).pdf(&point2(phi / (2.0 * PI), cos_theta(wi))) / (2.0 * PI);
+        f32::lerp(COSINE_SAMPLE_FRACTION, table_pdf, cosine_hemisphere_pdf(cos_theta(wi)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reflection::{Bxdf, BxdfFlags, MeasuredBrdf, MeasuredBxdf};
+    use crate::reflection::measured::MeasuredData;
+    use std::sync::Arc;
+    use super::*;
+
+    /// Writes a MERL file with a diffuse reflectance of 0.5 and a highlight around the mirror direction.
+    fn glossy_merl_file() -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(12 + 3 * N_VALUES * 8);
+        for dim in [THETA_H_RES, THETA_D_RES, PHI_D_RES] {
+            bytes.extend((dim as i32).to_le_bytes());
+        }
+        for scale in CHANNEL_SCALES {
+            for i in 0..N_VALUES {
+                let theta_h_index = i / (PHI_D_RES * THETA_D_RES);
+                let value = 0.5 / PI as f64 + if theta_h_index < 10 { 2.0 } else { 0.0 };
+                bytes.extend((value / scale).to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_read_merl() {
+        let bytes = glossy_merl_file();
+        let brdf = MerlBrdf::from_bytes(&bytes).unwrap();
+        let wo = vec3(0.6, 0.0, 0.8);
+        let diffuse = brdf.lookup(&wo, &vec3(0.0, 0.6, 0.8));
+        assert!(diffuse.iter().all(|&v| (v - 0.5 / PI).abs() < 1e-6), "{:?}", diffuse);
+        let specular = brdf.lookup(&wo, &vec3(-0.6, 0.0, 0.8));
+        assert!((specular[1] - (2.0 + 0.5 / PI)).abs() < 1e-5, "{:?}", specular);
+
+        assert!(MerlBrdf::from_bytes(&bytes[..1000]).is_err());
+    }
+
+    #[test]
+    fn test_reject_invalid_dimensions() {
+        let mut bytes = glossy_merl_file();
+        // Negative dimensions whose product matches the number of values
+        for (i, dim) in [-(THETA_H_RES as i32), -(THETA_D_RES as i32), PHI_D_RES as i32].into_iter().enumerate() {
+            bytes[4 * i..4 * i + 4].copy_from_slice(&dim.to_le_bytes());
+        }
+        let err = MerlBrdf::from_bytes(&bytes).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        // Dimensions whose product overflows
+        for i in 0..3 {
+            bytes[4 * i..4 * i + 4].copy_from_slice(&i32::MAX.to_le_bytes());
+        }
+        let err = MerlBrdf::from_bytes(&bytes).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_sample_f_is_consistent() {
+        let merl = MerlBrdf::from_bytes(&glossy_merl_file()).unwrap();
+        let bxdf = MeasuredBxdf::new(Arc::new(MeasuredBrdf { data: MeasuredData::Merl(merl) }));
+        let n = 64;
+        for wo in [vec3(0.0, 0.0, 1.0), vec3(0.6, 0.0, 0.8), vec3(0.3, -0.4, -0.866).normalize()] {
+            let mut pdf_integral = 0.0;
+            for i in 0..n * n {
+                let u = point2(((i % n) as f32 + 0.5) / n as f32, ((i / n) as f32 + 0.5) / n as f32);
+                let uc = (i as f32 * 0.618034).fract();
+                let mut wi = Vector3f::default();
+                let mut pdf = 0.0;
+                let f = bxdf.sample_f(&wo, &mut wi, uc, &u, &mut pdf, &mut BxdfFlags::empty());
+                if pdf == 0.0 {
+                    continue
+                }
+                assert!(wi.z * wo.z > 0.0);
+                assert!((pdf - bxdf.pdf(&wo, &wi)).abs() <= 1e-3 * pdf, "{} {}", pdf, bxdf.pdf(&wo, &wi));
+                assert_eq!(f, bxdf.f(&wo, &wi));
+
+                // Integrate the density over the hemisphere with uniform samples
+                let w = crate::sampling::uniform_sample_hemisphere(&u);
+                pdf_integral += bxdf.pdf(&wo, &if wo.z < 0.0 { -w } else { w }) * 2.0 * PI / (n * n) as f32;
+            }
+            assert!((pdf_integral - 1.0).abs() < 0.05, "{}", pdf_integral);
+        }
+    }
+}
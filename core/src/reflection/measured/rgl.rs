@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_2_PI, FRAC_PI_2, PI};
+use std::path::Path;
+use std::{fs, io};
+use crate::geom::DotProduct;
+use crate::math::safe_acos;
+use crate::reflection::{abs_cos_theta, cos_theta, reflect};
+use crate::reflection::measured::invalid_data;
+use crate::sampling::{spherical_direction, PiecewiseLinear2D};
+use crate::{point2, Point2f, Spectrum, Vector3f};
+
+/// A BRDF from the RGL material database, which is tabulated in a parameterization that follows the visible
+/// normals of the microfacet distribution fitted to the material, so that even sharp highlights need few samples.
+///
+/// See Dupuy and Jakob, "An Adaptive Parameterization for Efficient Material Acquisition and Rendering" (2018).
+pub(super) struct RglBrdf {
+    /// The microfacet distribution, over the elevation and azimuth of the microfacet normal.
+    ndf: PiecewiseLinear2D,
+    /// The projected area of the microfacets, over the elevation and azimuth of the outgoing direction.
+    sigma: PiecewiseLinear2D,
+    /// The distribution of visible normals, conditioned on the azimuth and elevation of the outgoing direction.
+    vndf: PiecewiseLinear2D,
+    /// The luminance of the reflectance in the warped domain of `vndf`, which is sampled first.
+    luminance: PiecewiseLinear2D,
+    /// The coefficients of the reflectance spectrum in the warped domain of `vndf`.
+    spectra: Vec<PiecewiseLinear2D>,
+    /// Whether the tables only depend on the difference of the outgoing and microfacet azimuths.
+    isotropic: bool
+}
+
+impl RglBrdf {
+    pub(super) fn read(path: &Path) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let fields = read_tensor_file(bytes)?;
+        let field = |name: &str| fields.get(name).ok_or_else(|| invalid_data(format!("missing field `{}`", name)));
+        let (theta_i, theta_i_shape) = field("theta_i")?.floats(1)?;
+        let (phi_i, phi_i_shape) = field("phi_i")?.floats(1)?;
+        let (ndf, ndf_shape) = field("ndf")?.floats(2)?;
+        let (sigma, sigma_shape) = field("sigma")?.floats(2)?;
+        let (vndf, vndf_shape) = field("vndf")?.floats(4)?;
+        let (luminance, luminance_shape) = field("luminance")?.floats(4)?;
+        let (spectra, spectra_shape) = field("spectra")?.floats(5)?;
+        let (wavelengths, wavelengths_shape) = field("wavelengths")?.floats(1)?;
+        let (n_phi, n_theta) = (phi_i_shape[0], theta_i_shape[0]);
+        if vndf_shape[..2] != [n_phi, n_theta]
+            || luminance_shape[..2] != [n_phi, n_theta]
+            || spectra_shape[..3] != [n_phi, n_theta, wavelengths_shape[0]]
+            || luminance_shape[2..] != spectra_shape[3..] {
+            return Err(invalid_data("inconsistent shapes of tabulated BRDF"))
+        }
+        // The tables are interpolated, so they need at least two values along each dimension of their grids
+        let grids = [ndf_shape, sigma_shape, &vndf_shape[2..], &luminance_shape[2..]];
+        if n_phi == 0 || n_theta == 0 || wavelengths.is_empty() || grids.iter().flat_map(|s| s.iter()).any(|&n| n < 2) {
+            return Err(invalid_data("tabulated BRDF is too small"))
+        }
+
+        let isotropic = n_phi <= 2;
+        if !isotropic && ((2.0 * PI) / (phi_i[n_phi - 1] - phi_i[0])).round() != 1.0 {
+            return Err(invalid_data("tabulated BRDFs with symmetric azimuths are not supported"))
+        }
+
+        // Project the spectra at the tabulated wavelengths onto the coefficients of the spectrum representation
+        let basis: Vec<Spectrum> = (0..wavelengths.len())
+            .map(|i| {
+                let mut v = vec![0.0; wavelengths.len()];
+                v[i] = 1.0;
+                Spectrum::from_sampled(&wavelengths, &v)
+            })
+            .collect();
+        let n_coefficients = Spectrum::default().coefficients().len();
+        let (ny, nx) = (spectra_shape[3], spectra_shape[4]);
+        let params = vec![phi_i, theta_i];
+        let spectra = (0..n_coefficients)
+            .map(|c| {
+                let data: Vec<f32> = spectra.chunks_exact(wavelengths.len() * nx * ny)
+                    .flat_map(|slice| (0..nx * ny).map(move |k| (slice, k)))
+                    .map(|(slice, k)| basis.iter().enumerate().map(|(i, b)| b[c] * slice[i * nx * ny + k]).sum())
+                    .collect();
+                PiecewiseLinear2D::new(&data, nx, ny, params.clone(), false)
+            })
+            .collect();
+
+        Ok(RglBrdf {
+            ndf: PiecewiseLinear2D::new(&ndf, ndf_shape[1], ndf_shape[0], vec![], false),
+            sigma: PiecewiseLinear2D::new(&sigma, sigma_shape[1], sigma_shape[0], vec![], false),
+            vndf: PiecewiseLinear2D::new(&vndf, vndf_shape[3], vndf_shape[2], params.clone(), true),
+            luminance: PiecewiseLinear2D::new(&luminance, luminance_shape[3], luminance_shape[2], params, true),
+            spectra,
+            isotropic
+        })
+    }
+
+    /// Maps the direction of a microfacet normal `wm` to the domain of the NDF and VNDF tables.
+    fn microfacet_to_unit(&self, wm: &Vector3f, phi_o: f32) -> Point2f {
+        let phi_m = wm.y.atan2(wm.x);
+        let u = phi_to_u(if self.isotropic { phi_m - phi_o } else { phi_m });
+        point2(theta_to_u(safe_acos(wm.z)), u - u.floor())
+    }
+
+    pub(super) fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        let wm = *wi + *wo;
+        if wm.length_squared() == 0.0 {
+            return Spectrum::new(0.0)
+        }
+        let wm = wm.normalize();
+        let (theta_o, phi_o) = (safe_acos(wo.z), wo.y.atan2(wo.x));
+        let u_wm = self.microfacet_to_unit(&wm, phi_o);
+        let params = [phi_o, theta_o];
+        let (sample, _) = self.vndf.invert(&u_wm, &params);
+        let mut fr = Spectrum::new(0.0);
+        for (c, spectrum) in self.spectra.iter().enumerate() {
+            fr[c] = f32::max(0.0, spectrum.evaluate(&sample, &params));
+        }
+        let u_wo = point2(theta_to_u(theta_o), phi_to_u(phi_o));
+        fr * (self.ndf.evaluate(&u_wm, &[]) / (4.0 * self.sigma.evaluate(&u_wo, &[]) * abs_cos_theta(wi)))
+    }
+
+    /// Samples the luminance in the warped domain, which is then mapped to a microfacet normal with the VNDF.
+    pub(super) fn sample_wi(&self, wo: &Vector3f, u: &Point2f) -> Option<(Vector3f, f32)> {
+        let (theta_o, phi_o) = (safe_acos(wo.z), wo.y.atan2(wo.x));
+        let params = [phi_o, theta_o];
+        let (sample, luminance_pdf) = self.luminance.sample(&point2(u.y, u.x), &params);
+        let (u_wm, vndf_pdf) = self.vndf.sample(&sample, &params);
+        let theta_m = u_to_theta(u_wm.x);
+        let phi_m = u_to_phi(u_wm.y) + if self.isotropic { phi_o } else { 0.0 };
+        let (sin_theta_m, cos_theta_m) = theta_m.sin_cos();
+        let wm = spherical_direction(sin_theta_m, cos_theta_m, phi_m);
+        let wi = reflect(wo, &wm);
+        if cos_theta(&wi) <= 0.0 {
+            return None
+        }
+        let jacobian = 4.0 * wo.dot(&wm) * f32::max(2.0 * PI * PI * u_wm.x * sin_theta_m, 1e-6);
+        Some((wi, vndf_pdf * luminance_pdf / jacobian))
+    }
+
+    pub(super) fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        let wm = *wi + *wo;
+        if wm.length_squared() == 0.0 {
+            return 0.0
+        }
+        let wm = wm.normalize();
+        let (theta_o, phi_o) = (safe_acos(wo.z), wo.y.atan2(wo.x));
+        let u_wm = self.microfacet_to_unit(&wm, phi_o);
+        let params = [phi_o, theta_o];
+        let (sample, vndf_pdf) = self.vndf.invert(&u_wm, &params);
+        let luminance_pdf = self.luminance.evaluate(&sample, &params);
+        // Account for the mapping of the unit square to microfacet normals and their reflection
+        let sin_theta_m = (wm.x * wm.x + wm.y * wm.y).sqrt();
+        let jacobian = 4.0 * wi.dot(&wm) * f32::max(2.0 * PI * PI * u_wm.x * sin_theta_m, 1e-6);
+        vndf_pdf * luminance_pdf / jacobian
+    }
+}
+
+/// The tables are denser at small elevations, where reflection varies quickly.
+#[inline]
+fn theta_to_u(theta: f32) -> f32 {
+    (theta * FRAC_2_PI).sqrt()
+}
+
+#[inline]
+fn u_to_theta(u: f32) -> f32 {
+    u * u * FRAC_PI_2
+}
+
+#[inline]
+fn phi_to_u(phi: f32) -> f32 {
+    phi * (0.5 / PI) + 0.5
+}
+
+#[inline]
+fn u_to_phi(u: f32) -> f32 {
+    (2.0 * u - 1.0) * PI
+}
+
+const DTYPE_UINT8: u8 = 1;
+const DTYPE_FLOAT32: u8 = 10;
+
+/// A named n-dimensional array of a tensor file.
+struct Field<'a> {
+    dtype: u8,
+    shape: Vec<usize>,
+    data: &'a [u8]
+}
+
+impl Field<'_> {
+    /// Returns the values and the shape of a field of 32 bit floats with `ndim` dimensions.
+    fn floats(&self, ndim: usize) -> io::Result<(Vec<f32>, &[usize])> {
+        if self.dtype != DTYPE_FLOAT32 || self.shape.len() != ndim {
+            return Err(invalid_data(format!("expected a field of floats with {} dimensions", ndim)))
+        }
+        let values = self.data.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect();
+        Ok((values, &self.shape))
+    }
+}
+
+/// Parses the fields of a tensor file, which starts with the `tensor_file` magic, the version and the number of
+/// fields, followed by the name, data type, shape and data offset of every field in little-endian byte order.
+fn read_tensor_file(bytes: &[u8]) -> io::Result<HashMap<String, Field<'_>>> {
+    let mut pos = 0;
+    let mut take = |n: usize| -> io::Result<&[u8]> {
+        let data = bytes.get(pos..pos + n).ok_or_else(|| invalid_data("tensor file is truncated"))?;
+        pos += n;
+        Ok(data)
+    };
+    if take(12)? != b"tensor_file\0" {
+        return Err(invalid_data("not a tensor file"))
+    }
+    let version = take(2)?;
+    if version != [1, 0] {
+        return Err(invalid_data(format!("unsupported tensor file version {}.{}", version[0], version[1])))
+    }
+    let n_fields = u32::from_le_bytes(take(4)?.try_into().unwrap());
+
+    let mut fields = HashMap::new();
+    for _ in 0..n_fields {
+        let name_len = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+        let name = String::from_utf8_lossy(take(name_len)?).into_owned();
+        let ndim = u16::from_le_bytes(take(2)?.try_into().unwrap()) as usize;
+        let dtype = take(1)?[0];
+        let offset = u64::from_le_bytes(take(8)?.try_into().unwrap()) as usize;
+        let shape = (0..ndim)
+            .map(|_| Ok(u64::from_le_bytes(take(8)?.try_into().unwrap()) as usize))
+            .collect::<io::Result<Vec<_>>>()?;
+        let dtype_size = match dtype {
+            DTYPE_UINT8 => 1,
+            DTYPE_FLOAT32 => 4,
+            // Fields of other types are not used, but their data must still lie within the file
+            2 => 1,
+            3 | 4 | 9 => 2,
+            5 | 6 => 4,
+            7 | 8 | 11 => 8,
+            _ => return Err(invalid_data(format!("field `{}` has an unknown data type", name)))
+        };
+        let size = shape.iter().product::<usize>() * dtype_size;
+        let data = bytes.get(offset..offset + size)
+            .ok_or_else(|| invalid_data(format!("data of field `{}` is truncated", name)))?;
+        fields.insert(name, Field { dtype, shape, data });
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::reflection::{Bxdf, BxdfFlags, MeasuredBrdf, MeasuredBxdf, MicrofacetDistribution,
+                            TrowbridgeReitzDistribution};
+    use crate::reflection::measured::MeasuredData;
+    use crate::vec3;
+    use super::*;
+
+    /// Writes a tensor file with the given fields of floats.
+    fn tensor_file(fields: &[(&str, Vec<usize>, Vec<f32>)]) -> Vec<u8> {
+        let header_size = 18 + fields.iter().map(|(name, shape, _)| 13 + name.len() + 8 * shape.len()).sum::<usize>();
+        let mut bytes = b"tensor_file\0".to_vec();
+        bytes.extend([1, 0]);
+        bytes.extend((fields.len() as u32).to_le_bytes());
+        let mut offset = header_size;
+        for (name, shape, data) in fields {
+            bytes.extend((name.len() as u16).to_le_bytes());
+            bytes.extend(name.as_bytes());
+            bytes.extend((shape.len() as u16).to_le_bytes());
+            bytes.push(DTYPE_FLOAT32);
+            bytes.extend((offset as u64).to_le_bytes());
+            for &dim in shape {
+                bytes.extend((dim as u64).to_le_bytes());
+            }
+            offset += 4 * data.len();
+        }
+        assert_eq!(bytes.len(), header_size);
+        for (_, _, data) in fields {
+            bytes.extend(data.iter().flat_map(|v| v.to_le_bytes()));
+        }
+        bytes
+    }
+
+    /// Tabulates an isotropic Trowbridge–Reitz distribution with a white reflectance.
+    fn trowbridge_reitz_file(alpha: f32) -> Vec<u8> {
+        let distrib = TrowbridgeReitzDistribution::new(alpha, alpha);
+        let n = 33;
+        let theta_i: Vec<f32> = (0..8).map(|i| i as f32 / 7.0 * 1.5).collect();
+        let grid = |f: &dyn Fn(Point2f) -> f32| -> Vec<f32> {
+            (0..n * n).map(|i| f(point2((i % n) as f32 / (n - 1) as f32, (i / n) as f32 / (n - 1) as f32))).collect()
+        };
+        let direction = |u: Point2f| {
+            let theta = u_to_theta(u.x);
+            spherical_direction(theta.sin(), theta.cos(), u_to_phi(u.y))
+        };
+        let ndf = grid(&|u| distrib.d(&direction(u)));
+        let sigma = grid(&|u| {
+            let w = direction(u);
+            w.z * (1.0 + distrib.lambda(&w))
+        });
+        let vndf: Vec<f32> = theta_i.iter()
+            .flat_map(|&theta| {
+                let wo = spherical_direction(theta.sin(), theta.cos(), 0.0);
+                grid(&|u| {
+                    let wm = direction(u);
+                    distrib.d(&wm) * f32::max(0.0, wo.dot(&wm)) * 2.0 * PI * PI * u.x * u_to_theta(u.x).sin()
+                })
+            })
+            .collect();
+        let ones = vec![1.0; theta_i.len() * n * n];
+        tensor_file(&[
+            ("theta_i", vec![theta_i.len()], theta_i.clone()),
+            ("phi_i", vec![1], vec![0.0]),
+            ("ndf", vec![n, n], ndf),
+            ("sigma", vec![n, n], sigma),
+            ("vndf", vec![1, theta_i.len(), n, n], vndf),
+            ("luminance", vec![1, theta_i.len(), n, n], ones.clone()),
+            ("spectra", vec![1, theta_i.len(), 2, n, n], ones.iter().flat_map(|&v| [v, v]).collect()),
+            ("wavelengths", vec![2], vec![360.0, 830.0])
+        ])
+    }
+
+    #[test]
+    fn test_read_tensor_file() {
+        let bytes = tensor_file(&[("a", vec![2, 1], vec![1.0, 2.0]), ("bc", vec![0], vec![])]);
+        let fields = read_tensor_file(&bytes).unwrap();
+        assert_eq!(fields["a"].floats(2).unwrap(), (vec![1.0, 2.0], &[2, 1][..]));
+        assert!(fields["bc"].floats(2).is_err());
+        assert!(read_tensor_file(&bytes[..bytes.len() - 1]).is_err());
+        assert!(RglBrdf::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_rejects_degenerate_tables() {
+        let file = |ndf_size: usize, n_theta: usize| tensor_file(&[
+            ("theta_i", vec![n_theta], vec![0.0; n_theta]),
+            ("phi_i", vec![1], vec![0.0]),
+            ("ndf", vec![ndf_size, ndf_size], vec![1.0; ndf_size * ndf_size]),
+            ("sigma", vec![2, 2], vec![1.0; 4]),
+            ("vndf", vec![1, n_theta, 2, 2], vec![1.0; 4 * n_theta]),
+            ("luminance", vec![1, n_theta, 2, 2], vec![1.0; 4 * n_theta]),
+            ("spectra", vec![1, n_theta, 1, 2, 2], vec![1.0; 4 * n_theta]),
+            ("wavelengths", vec![1], vec![550.0])
+        ]);
+        assert!(RglBrdf::from_bytes(&file(2, 1)).is_ok());
+        for bytes in [file(1, 1), file(2, 0)] {
+            assert_eq!(RglBrdf::from_bytes(&bytes).err().map(|e| e.kind()), Some(io::ErrorKind::InvalidData));
+        }
+    }
+
+    #[test]
+    fn test_matches_microfacet_model() {
+        let alpha = 0.3;
+        let brdf = RglBrdf::from_bytes(&trowbridge_reitz_file(alpha)).unwrap();
+        // With a constant unit spectrum, the table describes a microfacet BRDF with the masking of wo only
+        let distrib = TrowbridgeReitzDistribution::new(alpha, alpha);
+        let wo = vec3(0.5, 0.1, 0.8).normalize();
+        for wi in [vec3(-0.5, -0.1, 0.8), vec3(-0.3, 0.2, 0.9), vec3(0.1, -0.5, 0.8)] {
+            let wi = wi.normalize();
+            let wm = (wo + wi).normalize();
+            let expected = distrib.d(&wm) * distrib.g1(&wo) / (4.0 * wo.z * wi.z);
+            let f = brdf.f(&wo, &wi).y();
+            assert!((f - expected).abs() < 0.05 * expected, "{} {}", f, expected);
+        }
+    }
+
+    #[test]
+    fn test_sample_f_is_consistent() {
+        let rgl = RglBrdf::from_bytes(&trowbridge_reitz_file(0.3)).unwrap();
+        let bxdf = MeasuredBxdf::new(Arc::new(MeasuredBrdf { data: MeasuredData::Rgl(Box::new(rgl)) }));
+        let n = 64;
+        for wo in [vec3(0.0, 0.0, 1.0), vec3(0.6, 0.0, 0.8), vec3(0.3, -0.4, -0.866).normalize()] {
+            let mut albedo = 0.0;
+            for i in 0..n * n {
+                let u = point2(((i % n) as f32 + 0.5) / n as f32, ((i / n) as f32 + 0.5) / n as f32);
+                let mut wi = Vector3f::default();
+                let mut pdf = 0.0;
+                let f = bxdf.sample_f(&wo, &mut wi, 0.5, &u, &mut pdf, &mut BxdfFlags::empty());
+                if pdf == 0.0 {
+                    continue
+                }
+                assert!(wi.z * wo.z > 0.0);
+                assert!((pdf - bxdf.pdf(&wo, &wi)).abs() <= 1e-2 * pdf, "{} {}", pdf, bxdf.pdf(&wo, &wi));
+                albedo += f.y() * abs_cos_theta(&wi) / pdf / (n * n) as f32;
+            }
+            assert!(albedo > 0.8 && albedo < 1.02, "{} {:?}", albedo, wo);
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::f32::consts::PI;
+use crate::geom::DotProduct;
+use crate::math::{erf, erf_inv, Lerp};
+use crate::reflection::{abs_cos_theta, cos2_phi, cos2_theta, cos_phi, cos_theta, sin2_phi, sin_phi, tan2_theta};
+use crate::sampling::uniform_sample_disk;
+use crate::{vec3, Point2f, Vector3f};
+
+/// A statistical model of the orientation of the microfacets of a rough surface, given in the shading frame.
+///
+/// The distributions are normalized so that the projected area of the microfacets over any area of the surface
+/// equals that area, i.e. `∫ D(wm) cos(theta_m) dwm = 1`.
+pub trait MicrofacetDistribution: Send + Sync {
+    /// Returns the differential area of microfacets with normal `wm`.
+    fn d(&self, wm: &Vector3f) -> f32;
+
+    /// Returns the ratio of the area of microfacets that are masked from direction `w` to the visible area.
+    fn lambda(&self, w: &Vector3f) -> f32;
+
+    /// Checks if the surface is smooth enough to be treated as a perfect specular interface.
+    fn effectively_smooth(&self) -> bool;
+
+    /// Samples a microfacet normal in the upper hemisphere from the distribution of normals visible from `w`.
+    fn sample_wm(&self, w: &Vector3f, u: &Point2f) -> Vector3f;
+
+    /// Returns the fraction of microfacets that are visible from direction `w`.
+    #[inline]
+    fn g1(&self, w: &Vector3f) -> f32 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Returns the fraction of microfacets that are visible from both `wo` and `wi`, using the height-correlated
+    /// masking-shadowing function.
+    #[inline]
+    fn g(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Returns the distribution of normals visible from direction `w`, which integrates to one over the
+    /// hemisphere. Directions below the surface see the normals that are visible from their mirror image.
+    fn d_visible(&self, w: &Vector3f, wm: &Vector3f) -> f32 {
+        let cos_theta_m = if w.z < 0.0 { -w.dot(wm) } else { w.dot(wm) };
+        self.g1(w) / abs_cos_theta(w) * self.d(wm) * f32::max(0.0, cos_theta_m)
+    }
+
+    /// Returns the density with which [`sample_wm`](Self::sample_wm) samples `wm` for `w`.
+    #[inline]
+    fn pdf(&self, w: &Vector3f, wm: &Vector3f) -> f32 {
+        self.d_visible(w, wm)
+    }
+}
+
+/// The Trowbridge–Reitz or GGX distribution, whose long tails give rough surfaces a characteristic glow around
+/// highlights. See Walter et al., "Microfacet Models for Refraction through Rough Surfaces" (2007).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TrowbridgeReitzDistribution {
+    alpha_x: f32,
+    alpha_y: f32
+}
+
+impl TrowbridgeReitzDistribution {
+    /// Creates a distribution with the roughness parameters `alpha_x` and `alpha_y` along the `x` and `y` axes
+    /// of the shading frame.
+    pub fn new(alpha_x: f32, alpha_y: f32) -> Self {
+        TrowbridgeReitzDistribution { alpha_x, alpha_y }
+    }
+
+    /// Maps a perceptually linear roughness in `[0, 1]` to the `alpha` parameter of the distribution.
+    #[inline]
+    pub fn roughness_to_alpha(roughness: f32) -> f32 {
+        roughness.sqrt()
+    }
+
+    pub fn alpha_x(&self) -> f32 {
+        self.alpha_x
+    }
+
+    pub fn alpha_y(&self) -> f32 {
+        self.alpha_y
+    }
+}
+
+impl MicrofacetDistribution for TrowbridgeReitzDistribution {
+    fn d(&self, wm: &Vector3f) -> f32 {
+        let tan2_theta = tan2_theta(wm);
+        if tan2_theta.is_infinite() {
+            return 0.0
+        }
+        let cos4_theta = cos2_theta(wm) * cos2_theta(wm);
+        if cos4_theta < 1e-16 {
+            return 0.0
+        }
+        let e = tan2_theta * (cos2_phi(wm) / (self.alpha_x * self.alpha_x)
+            + sin2_phi(wm) / (self.alpha_y * self.alpha_y));
+        1.0 / (PI * self.alpha_x * self.alpha_y * cos4_theta * (1.0 + e) * (1.0 + e))
+    }
+
+    fn lambda(&self, w: &Vector3f) -> f32 {
+        let tan2_theta = tan2_theta(w);
+        if tan2_theta.is_infinite() {
+            return 0.0
+        }
+        let alpha2 = cos2_phi(w) * self.alpha_x * self.alpha_x + sin2_phi(w) * self.alpha_y * self.alpha_y;
+        ((1.0 + alpha2 * tan2_theta).sqrt() - 1.0) / 2.0
+    }
+
+    #[inline]
+    fn effectively_smooth(&self) -> bool {
+        f32::max(self.alpha_x, self.alpha_y) < 1e-3
+    }
+
+    /// Samples visible normals by sampling the projection of a hemisphere in the stretched configuration with
+    /// unit roughness, after Heitz, "Sampling the GGX Distribution of Visible Normals" (2018).
+    fn sample_wm(&self, w: &Vector3f, u: &Point2f) -> Vector3f {
+        // Transform w to the hemisphere configuration
+        let mut wh = vec3(self.alpha_x * w.x, self.alpha_y * w.y, w.z).normalize();
+        if wh.z < 0.0 {
+            wh = -wh;
+        }
+
+        // Find an orthonormal basis for the visible normal sampling
+        let t1 = if wh.z < 0.99999 { vec3(0.0, 0.0, 1.0).cross(&wh).normalize() } else { vec3(1.0, 0.0, 0.0) };
+        let t2 = wh.cross(&t1);
+
+        // Sample the projected area of the hemisphere, which is a half disk joined with half an ellipse
+        let mut p = uniform_sample_disk(u);
+        let h = (1.0 - p.x * p.x).sqrt();
+        p.y = f32::lerp((1.0 + wh.z) / 2.0, h, p.y);
+
+        // Project the point back up to the hemisphere and transform the normal to the ellipsoid configuration
+        let pz = f32::max(0.0, 1.0 - p.x * p.x - p.y * p.y).sqrt();
+        let nh = t1 * p.x + t2 * p.y + wh * pz;
+        vec3(self.alpha_x * nh.x, self.alpha_y * nh.y, f32::max(1e-6, nh.z)).normalize()
+    }
+}
+
+/// The Beckmann–Spizzichino distribution, which models surfaces with Gaussian distributed slopes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BeckmannDistribution {
+    alpha_x: f32,
+    alpha_y: f32
+}
+
+impl BeckmannDistribution {
+    /// Creates a distribution with the RMS slopes `alpha_x` and `alpha_y` along the `x` and `y` axes of the
+    /// shading frame.
+    pub fn new(alpha_x: f32, alpha_y: f32) -> Self {
+        BeckmannDistribution { alpha_x, alpha_y }
+    }
+
+    /// Maps a perceptually linear roughness in `[0, 1]` to the `alpha` parameter of the distribution.
+    #[inline]
+    pub fn roughness_to_alpha(roughness: f32) -> f32 {
+        roughness.sqrt()
+    }
+
+    pub fn alpha_x(&self) -> f32 {
+        self.alpha_x
+    }
+
+    pub fn alpha_y(&self) -> f32 {
+        self.alpha_y
+    }
+}
+
+impl MicrofacetDistribution for BeckmannDistribution {
+    fn d(&self, wm: &Vector3f) -> f32 {
+        let tan2_theta = tan2_theta(wm);
+        if tan2_theta.is_infinite() {
+            return 0.0
+        }
+        let cos4_theta = cos2_theta(wm) * cos2_theta(wm);
+        if cos4_theta < 1e-16 {
+            return 0.0
+        }
+        let e = tan2_theta * (cos2_phi(wm) / (self.alpha_x * self.alpha_x)
+            + sin2_phi(wm) / (self.alpha_y * self.alpha_y));
+        (-e).exp() / (PI * self.alpha_x * self.alpha_y * cos4_theta)
+    }
+
+    /// Uses the rational approximation of Walter et al. (2007) for the masking function.
+    fn lambda(&self, w: &Vector3f) -> f32 {
+        let tan2_theta = tan2_theta(w);
+        if tan2_theta.is_infinite() {
+            return 0.0
+        }
+        let alpha = (cos2_phi(w) * self.alpha_x * self.alpha_x + sin2_phi(w) * self.alpha_y * self.alpha_y).sqrt();
+        let a = 1.0 / (alpha * tan2_theta.sqrt());
+        if a >= 1.6 {
+            return 0.0
+        }
+        (1.0 - 1.259 * a + 0.396 * a * a) / (3.535 * a + 2.181 * a * a)
+    }
+
+    #[inline]
+    fn effectively_smooth(&self) -> bool {
+        f32::max(self.alpha_x, self.alpha_y) < 1e-3
+    }
+
+    /// Samples visible normals by sampling the distribution of visible slopes in the stretched configuration with
+    /// unit roughness, after Heitz and d'Eon, "Importance Sampling Microfacet-Based BSDFs using the Distribution of
+    /// Visible Normals" (2014).
+    fn sample_wm(&self, w: &Vector3f, u: &Point2f) -> Vector3f {
+        // Transform w to the configuration with unit roughness
+        let mut ws = vec3(self.alpha_x * w.x, self.alpha_y * w.y, w.z).normalize();
+        if ws.z < 0.0 {
+            ws = -ws;
+        }
+
+        // Sample the slopes for unit roughness and rotate them to the azimuth of ws
+        let (sx, sy) = beckmann_sample_11(cos_theta(&ws), u);
+        let (sin_phi, cos_phi) = (sin_phi(&ws), cos_phi(&ws));
+        let (sx, sy) = (cos_phi * sx - sin_phi * sy, sin_phi * sx + cos_phi * sy);
+
+        // Unstretch the slopes and compute the normal
+        vec3(-self.alpha_x * sx, -self.alpha_y * sy, 1.0).normalize()
+    }
+}
+
+/// Samples the slopes of the visible normals of the isotropic Beckmann distribution with unit roughness for a
+/// direction with the given `cos_theta` to the normal.
+fn beckmann_sample_11(cos_theta: f32, u: &Point2f) -> (f32, f32) {
+    // Special case for normal incidence, where the visible slopes follow the full distribution
+    if cos_theta > 0.9999 {
+        let r = (-(1.0 - u.x).ln()).sqrt();
+        let (sin_phi, cos_phi) = (2.0 * PI * u.y).sin_cos();
+        return (r * cos_phi, r * sin_phi)
+    }
+
+    // Invert the CDF of the slope in x with Newton-Bisection, starting from a fitted approximation
+    let sin_theta = f32::max(0.0, 1.0 - cos_theta * cos_theta).sqrt();
+    let tan_theta = sin_theta / cos_theta;
+    let cot_theta = 1.0 / tan_theta;
+    let inv_sqrt_pi = 1.0 / PI.sqrt();
+
+    let mut a = -1.0;
+    let mut c = erf(cot_theta);
+    let sample_x = f32::max(u.x, 1e-6);
+    let theta = cos_theta.acos();
+    let fit = 1.0 + theta * (-0.876 + theta * (0.4265 - 0.0594 * theta));
+    let mut b = c - (1.0 + c) * (1.0 - sample_x).powf(fit);
+    let normalization = 1.0 / (1.0 + c + inv_sqrt_pi * tan_theta * (-cot_theta * cot_theta).exp());
+    for _ in 0..9 {
+        // Fall back to bisection if the Newton step left the bracket
+        if !(b >= a && b <= c) {
+            b = 0.5 * (a + c);
+        }
+        let inv_erf = erf_inv(b);
+        let value = normalization * (1.0 + b + inv_sqrt_pi * tan_theta * (-inv_erf * inv_erf).exp()) - sample_x;
+        let derivative = normalization * (1.0 - inv_erf * tan_theta);
+        if value.abs() < 1e-5 {
+            break
+        }
+        if value > 0.0 {
+            c = b;
+        } else {
+            a = b;
+        }
+        b -= value / derivative;
+    }
+    (erf_inv(b), erf_inv(2.0 * f32::max(u.y, 1e-6) - 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::point2;
+    use crate::sampling::spherical_direction;
+    use super::*;
+
+    /// Integrates `f` over the hemisphere (or the sphere if `sphere` is set) with the midpoint rule in
+    /// `(cos(theta), phi)`.
+    fn integrate(sphere: bool, f: impl Fn(&Vector3f) -> f32) -> f32 {
+        let (nz, nphi) = (512, 128);
+        let z_min = if sphere { -1.0 } else { 0.0 };
+        let dz = (1.0 - z_min) / nz as f32;
+        let dphi = 2.0 * PI / nphi as f32;
+        let mut sum = 0.0f64;
+        for i in 0..nz {
+            let z = z_min + (i as f32 + 0.5) * dz;
+            let sin_theta = (1.0 - z * z).sqrt();
+            for j in 0..nphi {
+                let w = spherical_direction(sin_theta, z, (j as f32 + 0.5) * dphi);
+                sum += f(&w) as f64;
+            }
+        }
+        (sum * (dz * dphi) as f64) as f32
+    }
+
+    fn distributions() -> Vec<Box<dyn MicrofacetDistribution>> {
+        vec![
+            Box::new(TrowbridgeReitzDistribution::new(0.5, 0.5)),
+            Box::new(TrowbridgeReitzDistribution::new(0.3, 0.8)),
+            Box::new(BeckmannDistribution::new(0.5, 0.5)),
+            Box::new(BeckmannDistribution::new(0.8, 0.3))
+        ]
+    }
+
+    fn directions() -> [Vector3f; 3] {
+        [vec3(0.0, 0.0, 1.0), vec3(0.6, 0.0, 0.8), vec3(-0.5, 0.7, 0.1).normalize()]
+    }
+
+    #[test]
+    fn test_projected_area_is_normalized() {
+        for distrib in distributions() {
+            let area = integrate(false, |wm| distrib.d(wm) * cos_theta(wm));
+            assert!((area - 1.0).abs() < 1e-2, "{}", area);
+        }
+    }
+
+    #[test]
+    fn test_visible_normals_are_normalized() {
+        for distrib in distributions() {
+            for wo in directions() {
+                let area = integrate(false, |wm| distrib.d_visible(&wo, wm));
+                // The Beckmann masking function is a rational approximation
+                assert!((area - 1.0).abs() < 2e-2, "{} {:?}", area, wo);
+            }
+        }
+    }
+
+    #[test]
+    fn test_weak_white_furnace() {
+        // With a Fresnel term of one and masking only, the reflected directions cover the full distribution of
+        // visible normals, see Heitz, "Understanding the Masking-Shadowing Function" (2014)
+        for distrib in distributions() {
+            for wo in directions() {
+                let energy = integrate(true, |wi| {
+                    let wm = (*wi + wo).normalize();
+                    if wm.z <= 0.0 {
+                        return 0.0
+                    }
+                    distrib.d(&wm) * distrib.g1(&wo) / (4.0 * cos_theta(&wo))
+                });
+                assert!((energy - 1.0).abs() < 2e-2, "{} {:?}", energy, wo);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_wm_follows_visible_normals() {
+        let n = 128;
+        for distrib in distributions() {
+            for wo in directions() {
+                // Compare the mean of the sampled normals with the mean of the distribution
+                let mut mean = vec3(0.0, 0.0, 0.0);
+                for i in 0..n * n {
+                    let u = point2(((i % n) as f32 + 0.5) / n as f32, ((i / n) as f32 + 0.5) / n as f32);
+                    let wm = distrib.sample_wm(&wo, &u);
+                    assert!(wm.z > 0.0 && (wm.length() - 1.0).abs() < 1e-4);
+                    mean += wm / (n * n) as f32;
+                }
+                for axis in 0..3 {
+                    let expected = integrate(false, |wm| wm[axis] * distrib.d_visible(&wo, wm));
+                    assert!((mean[axis] - expected).abs() < 2e-2, "{:?} {} {:?}", mean, expected, wo);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::f32::consts::FRAC_1_PI;
+use crate::reflection::{abs_cos_theta, cos_phi, same_hemisphere, sin_phi, sin_theta, Bxdf, BxdfFlags};
+use crate::{Spectrum, Vector3f};
+
+/// Diffuse reflection from a rough surface made of Lambertian microfacets, after Oren and Nayar, "Generalization
+/// of Lambert's Reflectance Model" (1994).
+///
+/// Unlike a Lambertian surface, rough surfaces appear brighter when viewed from the direction of the light.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OrenNayar {
+    r: Spectrum,
+    a: f32,
+    b: f32
+}
+
+impl OrenNayar {
+    /// Creates a rough diffuse reflection with reflectance `r`, where `sigma` is the standard deviation of the
+    /// microfacet orientation angle in degrees.
+    pub fn new(r: Spectrum, sigma: f32) -> Self {
+        let sigma = sigma.to_radians();
+        let sigma2 = sigma * sigma;
+        let a = 1.0 - sigma2 / (2.0 * (sigma2 + 0.33));
+        let b = 0.45 * sigma2 / (sigma2 + 0.09);
+        OrenNayar { r, a, b }
+    }
+}
+
+impl Bxdf for OrenNayar {
+    #[inline]
+    fn flags(&self) -> BxdfFlags {
+        BxdfFlags::REFLECTION | BxdfFlags::DIFFUSE
+    }
+
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        if !same_hemisphere(wo, wi) {
+            return Spectrum::new(0.0)
+        }
+        let sin_theta_i = sin_theta(wi);
+        let sin_theta_o = sin_theta(wo);
+
+        // Cosine of the azimuthal angle between the directions, clamped to zero
+        let max_cos = if sin_theta_i > 1e-4 && sin_theta_o > 1e-4 {
+            f32::max(0.0, cos_phi(wi) * cos_phi(wo) + sin_phi(wi) * sin_phi(wo))
+        } else {
+            0.0
+        };
+
+        // sin(alpha) and tan(beta) for alpha = max(theta_i, theta_o) and beta = min(theta_i, theta_o)
+        let (sin_alpha, tan_beta) = if abs_cos_theta(wi) > abs_cos_theta(wo) {
+            (sin_theta_o, sin_theta_i / abs_cos_theta(wi))
+        } else {
+            (sin_theta_i, sin_theta_o / abs_cos_theta(wo))
+        };
+        self.r * (FRAC_1_PI * (self.a + self.b * max_cos * sin_alpha * tan_beta))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck_macros::quickcheck;
+    use crate::point2;
+    use crate::reflection::LambertianReflection;
+    use crate::sampling::uniform_sample_hemisphere;
+    use super::*;
+
+    #[quickcheck]
+    fn smooth_surface_is_lambertian(u: (u16, u16), v: (u16, u16)) -> bool {
+        let wo = uniform_sample_hemisphere(&point2(u.0 as f32 / 65536.0, u.1 as f32 / 65536.0));
+        let wi = uniform_sample_hemisphere(&point2(v.0 as f32 / 65536.0, v.1 as f32 / 65536.0));
+        let r = Spectrum::new(0.5);
+        let f = OrenNayar::new(r, 0.0).f(&wo, &wi);
+        (f[0] - LambertianReflection::new(r).f(&wo, &wi)[0]).abs() < 1e-6
+    }
+
+    #[quickcheck]
+    fn reciprocity(u: (u16, u16), v: (u16, u16)) -> bool {
+        let wo = uniform_sample_hemisphere(&point2(u.0 as f32 / 65536.0, u.1 as f32 / 65536.0));
+        let wi = uniform_sample_hemisphere(&point2(v.0 as f32 / 65536.0, v.1 as f32 / 65536.0));
+        let bxdf = OrenNayar::new(Spectrum::new(0.8), 20.0);
+        (bxdf.f(&wo, &wi)[0] - bxdf.f(&wi, &wo)[0]).abs() < 1e-5
+    }
+
+    #[test]
+    fn test_retroreflection() {
+        let bxdf = OrenNayar::new(Spectrum::new(1.0), 30.0);
+        let w = Vector3f::new(0.6, 0.0, 0.8);
+        let mirror = Vector3f::new(-0.6, 0.0, 0.8);
+        assert!(bxdf.f(&w, &w)[0] > bxdf.f(&w, &mirror)[0]);
+        // Rough surfaces do not create energy
+        let u: Vec<_> = (0..64 * 64).map(|i| point2((i % 64) as f32 / 64.0 + 1.0 / 128.0,
+                                                    (i / 64) as f32 / 64.0 + 1.0 / 128.0)).collect();
+        assert!(bxdf.rho(&w, &vec![0.5; u.len()], &u)[0] < 1.0);
+    }
+}
@@ -0,0 +1,59 @@
+use crate::reflection::{estimate_rho, same_hemisphere, Bxdf, BxdfFlags};
+use crate::{Point2f, Spectrum, Vector3f};
+
+/// Scales the scattering of another BxDF, which lets materials blend the BxDFs of others or weight their
+/// reflection and transmission separately.
+pub struct ScaledBxdf {
+    bxdf: Box<dyn Bxdf>,
+    reflection: Spectrum,
+    transmission: Spectrum
+}
+
+impl ScaledBxdf {
+    pub fn new(bxdf: Box<dyn Bxdf>, scale: Spectrum) -> Self {
+        ScaledBxdf { bxdf, reflection: scale, transmission: scale }
+    }
+
+    /// Scales the reflection of `bxdf` by `reflection` and its transmission by `transmission`.
+    pub fn split(bxdf: Box<dyn Bxdf>, reflection: Spectrum, transmission: Spectrum) -> Self {
+        ScaledBxdf { bxdf, reflection, transmission }
+    }
+
+    #[inline]
+    fn scale(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        if same_hemisphere(wo, wi) { self.reflection } else { self.transmission }
+    }
+}
+
+impl Bxdf for ScaledBxdf {
+    #[inline]
+    fn flags(&self) -> BxdfFlags {
+        self.bxdf.flags()
+    }
+
+    fn f(&self, wo: &Vector3f, wi: &Vector3f) -> Spectrum {
+        self.scale(wo, wi) * self.bxdf.f(wo, wi)
+    }
+
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, uc: f32, u: &Point2f, pdf: &mut f32,
+                sampled_type: &mut BxdfFlags) -> Spectrum {
+        let f = self.bxdf.sample_f(wo, wi, uc, u, pdf, sampled_type);
+        self.scale(wo, wi) * f
+    }
+
+    fn pdf(&self, wo: &Vector3f, wi: &Vector3f) -> f32 {
+        self.bxdf.pdf(wo, wi)
+    }
+
+    fn rho(&self, wo: &Vector3f, uc: &[f32], u: &[Point2f]) -> Spectrum {
+        if self.reflection == self.transmission {
+            return self.reflection * self.bxdf.rho(wo, uc, u)
+        }
+        estimate_rho(self, wo, uc, u)
+    }
+
+    #[inline]
+    fn is_stochastic(&self) -> bool {
+        self.bxdf.is_stochastic()
+    }
+}
@@ -0,0 +1,197 @@
+use crate::reflection::{abs_cos_theta, cos_theta, fr_dielectric, refract, Bxdf, BxdfFlags, Fresnel,
+                        FresnelDielectric, TransportMode};
+use crate::{vec3, Normal3f, Point2f, Spectrum, Vector3f};
+
+/// Perfect specular reflection about the normal, scaled by a Fresnel term.
+pub struct SpecularReflection {
+    r: Spectrum,
+    fresnel: Box<dyn Fresnel>
+}
+
+impl SpecularReflection {
+    pub fn new(r: Spectrum, fresnel: Box<dyn Fresnel>) -> Self {
+        SpecularReflection { r, fresnel }
+    }
+}
+
+impl Bxdf for SpecularReflection {
+    #[inline]
+    fn flags(&self) -> BxdfFlags {
+        BxdfFlags::REFLECTION | BxdfFlags::SPECULAR
+    }
+
+    fn f(&self, _wo: &Vector3f, _wi: &Vector3f) -> Spectrum {
+        Spectrum::new(0.0)
+    }
+
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, _uc: f32, _u: &Point2f, pdf: &mut f32,
+                _sampled_type: &mut BxdfFlags) -> Spectrum {
+        *wi = vec3(-wo.x, -wo.y, wo.z);
+        *pdf = 1.0;
+        self.fresnel.evaluate(cos_theta(wi)) * self.r / abs_cos_theta(wi)
+    }
+
+    fn pdf(&self, _wo: &Vector3f, _wi: &Vector3f) -> f32 {
+        0.0
+    }
+}
+
+/// Perfect specular transmission through a dielectric interface, scaled by the fraction of light that is not
+/// reflected.
+///
+/// `eta_a` is the index of refraction above the surface, on the side of the normal, and `eta_b` the one below.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpecularTransmission {
+    t: Spectrum,
+    eta_a: f32,
+    eta_b: f32,
+    fresnel: FresnelDielectric,
+    mode: TransportMode
+}
+
+impl SpecularTransmission {
+    pub fn new(t: Spectrum, eta_a: f32, eta_b: f32, mode: TransportMode) -> Self {
+        SpecularTransmission { t, eta_a, eta_b, fresnel: FresnelDielectric::new(eta_a, eta_b), mode }
+    }
+}
+
+impl Bxdf for SpecularTransmission {
+    #[inline]
+    fn flags(&self) -> BxdfFlags {
+        BxdfFlags::TRANSMISSION | BxdfFlags::SPECULAR
+    }
+
+    fn f(&self, _wo: &Vector3f, _wi: &Vector3f) -> Spectrum {
+        Spectrum::new(0.0)
+    }
+
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, _uc: f32, _u: &Point2f, pdf: &mut f32,
+                _sampled_type: &mut BxdfFlags) -> Spectrum {
+        let (eta_i, eta_t) = if cos_theta(wo) > 0.0 { (self.eta_a, self.eta_b) } else { (self.eta_b, self.eta_a) };
+        *wi = match refract(wo, &Normal3f::new(0.0, 0.0, 1.0).face_forward(wo), eta_i / eta_t) {
+            Some(wt) => wt,
+            None => return Spectrum::new(0.0)
+        };
+        *pdf = 1.0;
+        let ft = self.t * (Spectrum::new(1.0) - self.fresnel.evaluate(cos_theta(wi)));
+        transmission_scale(ft, eta_i, eta_t, self.mode) / abs_cos_theta(wi)
+    }
+
+    fn pdf(&self, _wo: &Vector3f, _wi: &Vector3f) -> f32 {
+        0.0
+    }
+}
+
+/// Specular reflection and transmission at a dielectric interface, with one of the two chosen according to the
+/// Fresnel reflectance with the sample `uc`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FresnelSpecular {
+    r: Spectrum,
+    t: Spectrum,
+    eta_a: f32,
+    eta_b: f32,
+    mode: TransportMode
+}
+
+impl FresnelSpecular {
+    pub fn new(r: Spectrum, t: Spectrum, eta_a: f32, eta_b: f32, mode: TransportMode) -> Self {
+        FresnelSpecular { r, t, eta_a, eta_b, mode }
+    }
+}
+
+impl Bxdf for FresnelSpecular {
+    #[inline]
+    fn flags(&self) -> BxdfFlags {
+        BxdfFlags::REFLECTION | BxdfFlags::TRANSMISSION | BxdfFlags::SPECULAR
+    }
+
+    fn f(&self, _wo: &Vector3f, _wi: &Vector3f) -> Spectrum {
+        Spectrum::new(0.0)
+    }
+
+    fn sample_f(&self, wo: &Vector3f, wi: &mut Vector3f, uc: f32, _u: &Point2f, pdf: &mut f32,
+                sampled_type: &mut BxdfFlags) -> Spectrum {
+        let f = fr_dielectric(cos_theta(wo), self.eta_a, self.eta_b);
+        if uc < f {
+            *wi = vec3(-wo.x, -wo.y, wo.z);
+            *sampled_type = BxdfFlags::REFLECTION | BxdfFlags::SPECULAR;
+            *pdf = f;
+            return self.r * (f / abs_cos_theta(wi))
+        }
+
+        let (eta_i, eta_t) = if cos_theta(wo) > 0.0 { (self.eta_a, self.eta_b) } else { (self.eta_b, self.eta_a) };
+        *wi = match refract(wo, &Normal3f::new(0.0, 0.0, 1.0).face_forward(wo), eta_i / eta_t) {
+            Some(wt) => wt,
+            None => return Spectrum::new(0.0)
+        };
+        *sampled_type = BxdfFlags::TRANSMISSION | BxdfFlags::SPECULAR;
+        *pdf = 1.0 - f;
+        transmission_scale(self.t * (1.0 - f), eta_i, eta_t, self.mode) / abs_cos_theta(wi)
+    }
+
+    fn pdf(&self, _wo: &Vector3f, _wi: &Vector3f) -> f32 {
+        0.0
+    }
+}
+
+/// Accounts for the compression of radiance when light is refracted into a medium with a higher index of
+/// refraction. Importance is not affected, which makes transmission non-symmetric.
+#[inline]
+pub(crate) fn transmission_scale(ft: Spectrum, eta_i: f32, eta_t: f32, mode: TransportMode) -> Spectrum {
+    match mode {
+        TransportMode::Radiance => ft * ((eta_i * eta_i) / (eta_t * eta_t)),
+        TransportMode::Importance => ft
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck_macros::quickcheck;
+    use crate::point2;
+    use crate::reflection::FresnelNoOp;
+    use crate::sampling::uniform_sample_sphere;
+    use super::*;
+
+    fn sample(bxdf: &dyn Bxdf, wo: &Vector3f, uc: f32, u: &Point2f) -> (Spectrum, Vector3f, f32, BxdfFlags) {
+        let mut wi = Vector3f::default();
+        let mut pdf = 0.0;
+        let mut sampled_type = bxdf.flags();
+        let f = bxdf.sample_f(wo, &mut wi, uc, u, &mut pdf, &mut sampled_type);
+        (f, wi, pdf, sampled_type)
+    }
+
+    #[test]
+    fn test_mirror() {
+        let bxdf = SpecularReflection::new(Spectrum::new(1.0), Box::new(FresnelNoOp));
+        let wo = vec3(0.48, 0.6, 0.64);
+        let (f, wi, pdf, _) = sample(&bxdf, &wo, 0.5, &point2(0.5, 0.5));
+        assert_eq!(wi, vec3(-0.48, -0.6, 0.64));
+        assert!((f[0] * abs_cos_theta(&wi) / pdf - 1.0).abs() < 1e-6);
+        assert!(bxdf.f(&wo, &wi).is_black());
+    }
+
+    #[test]
+    fn test_transmission_scales_radiance() {
+        let wo = vec3(0.0, 0.0, 1.0);
+        let radiance = SpecularTransmission::new(Spectrum::new(1.0), 1.0, 1.5, TransportMode::Radiance);
+        let importance = SpecularTransmission::new(Spectrum::new(1.0), 1.0, 1.5, TransportMode::Importance);
+        let (f, wi, _, _) = sample(&importance, &wo, 0.5, &point2(0.5, 0.5));
+        assert!((wi - vec3(0.0, 0.0, -1.0)).length() < 1e-6);
+        assert!((f[0] - 0.96).abs() < 1e-5);
+        let (f, _, _, _) = sample(&radiance, &wo, 0.5, &point2(0.5, 0.5));
+        assert!((f[0] - 0.96 / 2.25).abs() < 1e-5);
+    }
+
+    #[quickcheck]
+    fn fresnel_specular_conserves_energy(u0: u16, u1: u16, uc: u16) -> bool {
+        let wo = uniform_sample_sphere(&point2(u0 as f32 / 65536.0, u1 as f32 / 65536.0));
+        let bxdf = FresnelSpecular::new(Spectrum::new(1.0), Spectrum::new(1.0), 1.0, 1.5, TransportMode::Importance);
+        let (f, wi, pdf, sampled_type) = sample(&bxdf, &wo, uc as f32 / 65536.0, &point2(0.5, 0.5));
+        if pdf == 0.0 || wo.z == 0.0 {
+            return true
+        }
+        // Each sample carries the full energy since the choice between the lobes follows the Fresnel term
+        let reflected = sampled_type.is_reflective();
+        (f[0] * abs_cos_theta(&wi) / pdf - 1.0).abs() < 1e-3 && reflected == (wi.z * wo.z > 0.0)
+    }
+}
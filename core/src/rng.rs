@@ -0,0 +1,111 @@
+//! A small, fast pseudo-random number generator with independent streams.
+
+use crate::math::ONE_MINUS_EPSILON;
+use crate::hash::mix_bits;
+
+const PCG32_DEFAULT_STATE: u64 = 0x853c49e6748fea9b;
+const PCG32_DEFAULT_STREAM: u64 = 0xda3e39cb94b95bdb;
+const PCG32_MULT: u64 = 0x5851f42d4c957f2d;
+
+/// The PCG32 generator of O'Neill, "PCG: A Family of Simple Fast Space-Efficient Statistically Good Algorithms
+/// for Random Number Generation" (2014).
+///
+/// Each sequence index selects one of 2^63 independent streams, and the generator can be advanced by an arbitrary
+/// number of steps in logarithmic time, which lets samplers jump directly to the values of a given sample.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+    inc: u64
+}
+
+impl Rng {
+    /// Creates a generator for stream `sequence_index`, with the initial state derived from the index.
+    pub fn new(sequence_index: u64) -> Self {
+        let mut rng = Rng::default();
+        rng.set_sequence(sequence_index);
+        rng
+    }
+
+    /// Restarts the generator on stream `sequence_index`.
+    #[inline]
+    pub fn set_sequence(&mut self, sequence_index: u64) {
+        self.set_sequence_with_offset(sequence_index, mix_bits(sequence_index));
+    }
+
+    pub fn set_sequence_with_offset(&mut self, sequence_index: u64, offset: u64) {
+        self.state = 0;
+        self.inc = (sequence_index << 1) | 1;
+        self.uniform_u32();
+        self.state = self.state.wrapping_add(offset);
+        self.uniform_u32();
+    }
+
+    #[inline]
+    pub fn uniform_u32(&mut self) -> u32 {
+        let old_state = self.state;
+        self.state = old_state.wrapping_mul(PCG32_MULT).wrapping_add(self.inc);
+        let xor_shifted = (((old_state >> 18) ^ old_state) >> 27) as u32;
+        let rot = (old_state >> 59) as u32;
+        xor_shifted.rotate_right(rot)
+    }
+
+    #[inline]
+    pub fn uniform_u64(&mut self) -> u64 {
+        let high = self.uniform_u32() as u64;
+        let low = self.uniform_u32() as u64;
+        (high << 32) | low
+    }
+
+    /// Returns a uniformly distributed value in `[0, 1)`.
+    #[inline]
+    pub fn uniform_f32(&mut self) -> f32 {
+        f32::min(ONE_MINUS_EPSILON, self.uniform_u32() as f32 * (1.0 / 4294967296.0))
+    }
+
+    /// Skips the next `delta` values, or goes back if `delta` is negative.
+    pub fn advance(&mut self, delta: i64) {
+        let (mut cur_mult, mut cur_plus) = (PCG32_MULT, self.inc);
+        let (mut acc_mult, mut acc_plus) = (1u64, 0u64);
+        let mut delta = delta as u64;
+        while delta > 0 {
+            if delta & 1 != 0 {
+                acc_mult = acc_mult.wrapping_mul(cur_mult);
+                acc_plus = acc_plus.wrapping_mul(cur_mult).wrapping_add(cur_plus);
+            }
+            cur_plus = cur_mult.wrapping_add(1).wrapping_mul(cur_plus);
+            cur_mult = cur_mult.wrapping_mul(cur_mult);
+            delta /= 2;
+        }
+        self.state = acc_mult.wrapping_mul(self.state).wrapping_add(acc_plus);
+    }
+}
+
+impl Default for Rng {
+    #[inline]
+    fn default() -> Self {
+        Rng { state: PCG32_DEFAULT_STATE, inc: PCG32_DEFAULT_STREAM }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advance() {
+        let mut rng = Rng::new(1234);
+        let values: Vec<u32> = (0..100).map(|_| rng.uniform_u32()).collect();
+        let mut other = Rng::new(1234);
+        other.advance(37);
+        assert_eq!(other.uniform_u32(), values[37]);
+        rng.advance(-63);
+        assert_eq!(rng.uniform_u32(), values[37]);
+    }
+
+    #[test]
+    fn test_streams_differ() {
+        let mut a = Rng::new(1);
+        let mut b = Rng::new(2);
+        assert!((0..16).any(|_| a.uniform_u32() != b.uniform_u32()));
+    }
+}
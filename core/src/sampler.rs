@@ -0,0 +1,135 @@
+//! Samplers that generate the sample values used to render each pixel.
+
+mod dithered;
+mod halton;
+mod independent;
+mod sobol;
+mod stratified;
+mod zsobol;
+
+pub use dithered::*;
+pub use halton::*;
+pub use independent::*;
+pub use sobol::*;
+pub use stratified::*;
+pub use zsobol::*;
+
+use crate::camera::CameraSample;
+use crate::{point2, Point2f, Point2i};
+
+/// A source of sample vectors in `[0, 1)^n` for the pixels of an image.
+///
+/// The values of a sample only depend on the pixel, the index of the sample within the pixel, the dimension and
+/// the seed of the sampler, so that images are reproducible regardless of how pixels are assigned to threads.
+pub trait Sampler: Send + Sync {
+    fn samples_per_pixel(&self) -> usize;
+
+    /// Restarts the sampler at sample `sample_index` of pixel `p`, beginning with its first dimension.
+    fn start_pixel_sample(&mut self, p: &Point2i, sample_index: usize);
+
+    /// Returns the pixel that is currently being sampled.
+    fn current_pixel(&self) -> Point2i;
+
+    /// Returns the index of the current sample within its pixel.
+    fn current_sample_index(&self) -> usize;
+
+    /// Returns the next dimension of the current sample.
+    fn get_1d(&mut self) -> f32;
+
+    /// Returns the next two dimensions of the current sample.
+    fn get_2d(&mut self) -> Point2f;
+
+    /// Returns the two dimensions of the current sample used for the position within the pixel.
+    ///
+    /// Samplers that distribute a single sequence over the whole image override this to stratify the film
+    /// positions across neighboring pixels.
+    fn get_pixel_2d(&mut self) -> Point2f {
+        self.get_2d()
+    }
+
+    /// Creates an independent copy of the sampler whose values are decorrelated by `seed`.
+    fn clone_with_seed(&self, seed: u64) -> Box<dyn Sampler>;
+
+    /// Starts generating samples for pixel `p`.
+    fn start_pixel(&mut self, p: &Point2i) {
+        self.start_pixel_sample(p, 0);
+    }
+
+    /// Advances to the next sample of the current pixel and returns whether there are samples left.
+    fn start_next_sample(&mut self) -> bool {
+        self.set_sample_number(self.current_sample_index() + 1)
+    }
+
+    /// Jumps to sample `sample_index` of the current pixel and returns whether it is within the sample count.
+    fn set_sample_number(&mut self, sample_index: usize) -> bool {
+        let p = self.current_pixel();
+        self.start_pixel_sample(&p, sample_index);
+        sample_index < self.samples_per_pixel()
+    }
+
+    /// Returns the film position, time and lens position for a camera ray through the pixel `p_raster`.
+    fn get_camera_sample(&mut self, p_raster: &Point2i) -> CameraSample {
+        let u = self.get_pixel_2d();
+        let p_film = point2(p_raster.x as f32 + u.x, p_raster.y as f32 + u.y);
+        let time = self.get_1d();
+        let p_lens = self.get_2d();
+        CameraSample { p_film, p_lens, time }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point2;
+
+    fn samplers() -> Vec<Box<dyn Sampler>> {
+        vec![
+            Box::new(IndependentSampler::new(16, 0)),
+            Box::new(StratifiedSampler::new(4, 4, true, 0)),
+            Box::new(HaltonSampler::new(16, Point2::new(64, 64), 0)),
+            Box::new(SobolSampler::new(16, 0)),
+            Box::new(ZSobolSampler::new(16, Point2::new(64, 64), 0)),
+            Box::new(DitheredSampler::new(Box::new(ZSobolSampler::new(16, Point2::new(64, 64), 0)), 0))
+        ]
+    }
+
+    #[test]
+    fn test_samples_are_reproducible() {
+        for sampler in samplers() {
+            let mut a = sampler.clone_with_seed(7);
+            let mut b = a.clone_with_seed(7);
+            // Visit the pixels in different orders
+            a.start_pixel(&Point2::new(3, 5));
+            a.set_sample_number(9);
+            let expected = (a.get_camera_sample(&Point2::new(3, 5)), a.get_1d(), a.get_2d());
+            b.start_pixel(&Point2::new(1, 1));
+            b.get_2d();
+            b.start_pixel_sample(&Point2::new(3, 5), 9);
+            assert_eq!((b.get_camera_sample(&Point2::new(3, 5)), b.get_1d(), b.get_2d()), expected);
+
+            let mut c = sampler.clone_with_seed(8);
+            c.start_pixel_sample(&Point2::new(3, 5), 9);
+            assert_ne!(c.get_camera_sample(&Point2::new(3, 5)), expected.0);
+        }
+    }
+
+    #[test]
+    fn test_sample_values_in_range() {
+        for mut sampler in samplers() {
+            sampler.start_pixel(&Point2::new(2, 7));
+            let mut n = 0;
+            loop {
+                let sample = sampler.get_camera_sample(&Point2::new(2, 7));
+                assert!((2.0..3.0).contains(&sample.p_film.x) && (7.0..8.0).contains(&sample.p_film.y));
+                for _ in 0..50 {
+                    assert!((0.0..1.0).contains(&sampler.get_1d()));
+                }
+                n += 1;
+                if !sampler.start_next_sample() {
+                    break
+                }
+            }
+            assert_eq!(n, sampler.samples_per_pixel());
+        }
+    }
+}
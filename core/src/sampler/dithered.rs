@@ -0,0 +1,214 @@
+use std::lazy::SyncLazy;
+use crate::hash::hash;
+use crate::rng::Rng;
+use crate::sampler::Sampler;
+use crate::{point2, Point2f, Point2i};
+
+/// Width and height of the tileable blue-noise mask.
+const BLUE_NOISE_RESOLUTION: usize = 64;
+/// Standard deviation of the Gaussian filter that measures the density of points in the void-and-cluster method.
+const BLUE_NOISE_SIGMA: f32 = 1.5;
+/// Radius beyond which the Gaussian filter is negligible.
+const BLUE_NOISE_FILTER_RADIUS: i32 = 6;
+
+/// A threshold mask whose values are uniformly distributed over `[0, 1)` with neighboring pixels receiving very
+/// different values, generated with Ulichney's void-and-cluster method (1993).
+static BLUE_NOISE_MASK: SyncLazy<Vec<f32>> = SyncLazy::new(|| {
+    let n = BLUE_NOISE_RESOLUTION;
+    let mut field = EnergyField::new();
+    let mut rng = Rng::new(0);
+
+    // Start with a random pattern and move points from the tightest clusters to the largest voids until stable
+    let initial_points = n * n / 10;
+    while field.count < initial_points {
+        let i = rng.uniform_u32() as usize % (n * n);
+        if !field.occupied[i] {
+            field.insert(i);
+        }
+    }
+    loop {
+        let cluster = field.tightest_cluster();
+        field.remove(cluster);
+        let void = field.largest_void();
+        field.insert(void);
+        if void == cluster {
+            break
+        }
+    }
+
+    // Rank the initial points by removing the tightest clusters, and all others by filling the largest voids
+    let mut rank = vec![0; n * n];
+    let mut removal = field.clone();
+    while removal.count > 0 {
+        let cluster = removal.tightest_cluster();
+        removal.remove(cluster);
+        rank[cluster] = removal.count;
+    }
+    while field.count < n * n {
+        let void = field.largest_void();
+        rank[void] = field.count;
+        field.insert(void);
+    }
+    rank.into_iter().map(|r| (r as f32 + 0.5) / (n * n) as f32).collect()
+});
+
+/// A set of pixels of the mask together with the filtered density of the set at every pixel.
+#[derive(Clone)]
+struct EnergyField {
+    occupied: Vec<bool>,
+    energy: Vec<f32>,
+    count: usize
+}
+
+impl EnergyField {
+    fn new() -> Self {
+        let n = BLUE_NOISE_RESOLUTION * BLUE_NOISE_RESOLUTION;
+        EnergyField { occupied: vec![false; n], energy: vec![0.0; n], count: 0 }
+    }
+
+    fn splat(&mut self, i: usize, sign: f32) {
+        let n = BLUE_NOISE_RESOLUTION as i32;
+        let (x, y) = ((i % BLUE_NOISE_RESOLUTION) as i32, (i / BLUE_NOISE_RESOLUTION) as i32);
+        for dy in -BLUE_NOISE_FILTER_RADIUS..=BLUE_NOISE_FILTER_RADIUS {
+            for dx in -BLUE_NOISE_FILTER_RADIUS..=BLUE_NOISE_FILTER_RADIUS {
+                let j = ((y + dy).rem_euclid(n) * n + (x + dx).rem_euclid(n)) as usize;
+                let d2 = (dx * dx + dy * dy) as f32;
+                self.energy[j] += sign * f32::exp(-d2 / (2.0 * BLUE_NOISE_SIGMA * BLUE_NOISE_SIGMA));
+            }
+        }
+    }
+
+    fn insert(&mut self, i: usize) {
+        self.occupied[i] = true;
+        self.count += 1;
+        self.splat(i, 1.0);
+    }
+
+    fn remove(&mut self, i: usize) {
+        self.occupied[i] = false;
+        self.count -= 1;
+        self.splat(i, -1.0);
+    }
+
+    fn tightest_cluster(&self) -> usize {
+        (0..self.energy.len())
+            .filter(|&i| self.occupied[i])
+            .max_by(|&a, &b| self.energy[a].partial_cmp(&self.energy[b]).unwrap())
+            .unwrap()
+    }
+
+    fn largest_void(&self) -> usize {
+        (0..self.energy.len())
+            .filter(|&i| !self.occupied[i])
+            .min_by(|&a, &b| self.energy[a].partial_cmp(&self.energy[b]).unwrap())
+            .unwrap()
+    }
+}
+
+/// Returns the value of the tileable blue-noise mask at pixel `p`.
+#[inline]
+pub fn blue_noise(p: &Point2i) -> f32 {
+    let n = BLUE_NOISE_RESOLUTION as i32;
+    BLUE_NOISE_MASK[(p.y.rem_euclid(n) * n + p.x.rem_euclid(n)) as usize]
+}
+
+/// Wraps another sampler and toroidally shifts every dimension of its samples by the value of a blue-noise mask at
+/// the pixel, so that the error of neighboring pixels is negatively correlated at low sample counts.
+///
+/// Each dimension uses the mask at a different random offset. Since the shift is the same for all samples of a
+/// pixel, the stratification of the wrapped sampler within the pixel is preserved.
+pub struct DitheredSampler {
+    sampler: Box<dyn Sampler>,
+    seed: u64,
+    dimension: u64
+}
+
+impl DitheredSampler {
+    pub fn new(sampler: Box<dyn Sampler>, seed: u64) -> Self {
+        DitheredSampler { sampler, seed, dimension: 0 }
+    }
+
+    fn dither(&mut self, u: f32) -> f32 {
+        let offset = hash(&[self.dimension, self.seed]);
+        self.dimension += 1;
+        let p = self.sampler.current_pixel();
+        let shift = blue_noise(&point2(p.x + (offset & 0xffff) as i32, p.y + (offset >> 16 & 0xffff) as i32));
+        let v = u + shift;
+        if v >= 1.0 {
+            v - 1.0
+        } else {
+            v
+        }
+    }
+}
+
+impl Sampler for DitheredSampler {
+    #[inline]
+    fn samples_per_pixel(&self) -> usize {
+        self.sampler.samples_per_pixel()
+    }
+
+    fn start_pixel_sample(&mut self, p: &Point2i, sample_index: usize) {
+        self.sampler.start_pixel_sample(p, sample_index);
+        self.dimension = 0;
+    }
+
+    #[inline]
+    fn current_pixel(&self) -> Point2i {
+        self.sampler.current_pixel()
+    }
+
+    #[inline]
+    fn current_sample_index(&self) -> usize {
+        self.sampler.current_sample_index()
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let u = self.sampler.get_1d();
+        self.dither(u)
+    }
+
+    fn get_2d(&mut self) -> Point2f {
+        let u = self.sampler.get_2d();
+        point2(self.dither(u.x), self.dither(u.y))
+    }
+
+    fn get_pixel_2d(&mut self) -> Point2f {
+        let u = self.sampler.get_pixel_2d();
+        point2(self.dither(u.x), self.dither(u.y))
+    }
+
+    fn clone_with_seed(&self, seed: u64) -> Box<dyn Sampler> {
+        Box::new(DitheredSampler::new(self.sampler.clone_with_seed(seed), seed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point2;
+
+    #[test]
+    fn test_mask_is_uniform() {
+        let mut values = BLUE_NOISE_MASK.clone();
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let n = values.len();
+        for (i, v) in values.into_iter().enumerate() {
+            assert_eq!(v, (i as f32 + 0.5) / n as f32);
+        }
+    }
+
+    #[test]
+    fn test_mask_neighbors_differ() {
+        // Blue noise has little low-frequency energy, so neighboring values are anticorrelated
+        let (mut covariance, mut variance) = (0.0, 0.0);
+        for y in 0..BLUE_NOISE_RESOLUTION as i32 {
+            for x in 0..BLUE_NOISE_RESOLUTION as i32 {
+                let v = blue_noise(&Point2::new(x, y)) - 0.5;
+                covariance += v * (blue_noise(&Point2::new(x + 1, y)) - 0.5);
+                variance += v * v;
+            }
+        }
+        assert!(covariance / variance < -0.1, "{}", covariance / variance);
+    }
+}
@@ -0,0 +1,160 @@
+use std::sync::Arc;
+use crate::lowdiscrepancy::{
+    compute_radical_inverse_permutations, inverse_radical_inverse, multiplicative_inverse, radical_inverse,
+    scrambled_radical_inverse, DigitPermutation, PRIME_TABLE_SIZE
+};
+use crate::sampler::Sampler;
+use crate::{point2, Point2f, Point2i};
+
+/// Resolution up to which the first two dimensions of the Halton sequence are mapped to distinct pixels before
+/// the mapping repeats.
+const MAX_HALTON_RESOLUTION: i32 = 128;
+
+/// Generates samples from the Halton sequence, with each dimension using the radical inverse in a different
+/// prime base and randomized by random digit permutations.
+///
+/// A single sequence covers the whole image: the first two dimensions are scaled so that the samples of each pixel
+/// are found at a regular stride of sample indices, which stratifies the film positions across pixels.
+#[derive(Debug, Clone)]
+pub struct HaltonSampler {
+    samples_per_pixel: usize,
+    seed: u64,
+    digit_permutations: Arc<Vec<DigitPermutation>>,
+    base_scales: [u64; 2],
+    base_exponents: [u32; 2],
+    mult_inverse: [u64; 2],
+    pixel: Point2i,
+    sample_index: usize,
+    halton_index: u64,
+    dimension: usize
+}
+
+impl HaltonSampler {
+    pub fn new(samples_per_pixel: usize, full_resolution: Point2i, seed: u64) -> Self {
+        let mut base_scales = [1; 2];
+        let mut base_exponents = [0; 2];
+        for (i, resolution) in [full_resolution.x, full_resolution.y].into_iter().enumerate() {
+            let base = if i == 0 { 2 } else { 3 };
+            while base_scales[i] < i32::min(resolution, MAX_HALTON_RESOLUTION) as u64 {
+                base_scales[i] *= base;
+                base_exponents[i] += 1;
+            }
+        }
+        let mult_inverse = [
+            multiplicative_inverse(base_scales[1] as i64, base_scales[0] as i64),
+            multiplicative_inverse(base_scales[0] as i64, base_scales[1] as i64)
+        ];
+        HaltonSampler {
+            samples_per_pixel,
+            seed,
+            digit_permutations: Arc::new(compute_radical_inverse_permutations(seed)),
+            base_scales,
+            base_exponents,
+            mult_inverse,
+            pixel: Point2i::default(),
+            sample_index: 0,
+            halton_index: 0,
+            dimension: 2
+        }
+    }
+
+    #[inline]
+    fn sample_dimension(&self, dimension: usize) -> f32 {
+        scrambled_radical_inverse(dimension, self.halton_index, &self.digit_permutations[dimension])
+    }
+}
+
+impl Sampler for HaltonSampler {
+    #[inline]
+    fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, p: &Point2i, sample_index: usize) {
+        self.pixel = *p;
+        self.sample_index = sample_index;
+        // Find the first index of the sequence whose first two dimensions fall into the pixel, which follows from
+        // the Chinese remainder theorem
+        self.halton_index = 0;
+        let sample_stride = self.base_scales[0] * self.base_scales[1];
+        if sample_stride > 1 {
+            for (i, pm) in [p.x, p.y].into_iter().enumerate() {
+                let base = if i == 0 { 2 } else { 3 };
+                let pm = pm.rem_euclid(MAX_HALTON_RESOLUTION) as u64;
+                let dim_offset = inverse_radical_inverse(pm, base, self.base_exponents[i]);
+                self.halton_index += dim_offset * (sample_stride / self.base_scales[i]) * self.mult_inverse[i];
+            }
+            self.halton_index %= sample_stride;
+        }
+        self.halton_index += sample_index as u64 * sample_stride;
+        self.dimension = 2;
+    }
+
+    #[inline]
+    fn current_pixel(&self) -> Point2i {
+        self.pixel
+    }
+
+    #[inline]
+    fn current_sample_index(&self) -> usize {
+        self.sample_index
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        if self.dimension >= PRIME_TABLE_SIZE {
+            self.dimension = 2;
+        }
+        let dimension = self.dimension;
+        self.dimension += 1;
+        self.sample_dimension(dimension)
+    }
+
+    fn get_2d(&mut self) -> Point2f {
+        if self.dimension + 1 >= PRIME_TABLE_SIZE {
+            self.dimension = 2;
+        }
+        let dimension = self.dimension;
+        self.dimension += 2;
+        point2(self.sample_dimension(dimension), self.sample_dimension(dimension + 1))
+    }
+
+    fn get_pixel_2d(&mut self) -> Point2f {
+        // The digits that select the pixel are shifted out, leaving the position within it
+        point2(
+            radical_inverse(0, self.halton_index >> self.base_exponents[0]),
+            radical_inverse(1, self.halton_index / self.base_scales[1])
+        )
+    }
+
+    fn clone_with_seed(&self, seed: u64) -> Box<dyn Sampler> {
+        let digit_permutations = if seed == self.seed {
+            self.digit_permutations.clone()
+        } else {
+            Arc::new(compute_radical_inverse_permutations(seed))
+        };
+        Box::new(HaltonSampler { seed, digit_permutations, ..self.clone() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point2;
+
+    #[test]
+    fn test_pixel_samples_stratified_across_pixels() {
+        let mut sampler = HaltonSampler::new(1, Point2::new(4, 3), 0);
+        let mut offsets = Vec::new();
+        for y in 0..3 {
+            for x in 0..4 {
+                sampler.start_pixel(&Point2::new(x, y));
+                let u = sampler.get_pixel_2d();
+                // The halton index must map back to the pixel
+                assert_eq!((radical_inverse(0, sampler.halton_index) * 4.0) as i32, x);
+                assert_eq!((radical_inverse(1, sampler.halton_index) * 3.0) as i32, y);
+                offsets.push(u);
+            }
+        }
+        assert!(offsets.windows(2).any(|w| w[0] != w[1]));
+    }
+}
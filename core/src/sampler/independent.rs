@@ -0,0 +1,59 @@
+use crate::hash::hash;
+use crate::rng::Rng;
+use crate::sampler::Sampler;
+use crate::{point2, Point2f, Point2i};
+
+/// Generates uniform random sample values without any stratification.
+#[derive(Debug, Clone)]
+pub struct IndependentSampler {
+    samples_per_pixel: usize,
+    seed: u64,
+    rng: Rng,
+    pixel: Point2i,
+    sample_index: usize
+}
+
+impl IndependentSampler {
+    pub fn new(samples_per_pixel: usize, seed: u64) -> Self {
+        IndependentSampler { samples_per_pixel, seed, rng: Rng::default(), pixel: Point2i::default(), sample_index: 0 }
+    }
+}
+
+impl Sampler for IndependentSampler {
+    #[inline]
+    fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, p: &Point2i, sample_index: usize) {
+        self.pixel = *p;
+        self.sample_index = sample_index;
+        self.rng.set_sequence(hash(&[p.x as u64, p.y as u64, self.seed]));
+        // Each sample has its own range of values within the stream of the pixel
+        self.rng.advance(sample_index as i64 * 65536);
+    }
+
+    #[inline]
+    fn current_pixel(&self) -> Point2i {
+        self.pixel
+    }
+
+    #[inline]
+    fn current_sample_index(&self) -> usize {
+        self.sample_index
+    }
+
+    #[inline]
+    fn get_1d(&mut self) -> f32 {
+        self.rng.uniform_f32()
+    }
+
+    #[inline]
+    fn get_2d(&mut self) -> Point2f {
+        point2(self.rng.uniform_f32(), self.rng.uniform_f32())
+    }
+
+    fn clone_with_seed(&self, seed: u64) -> Box<dyn Sampler> {
+        Box::new(IndependentSampler { seed, ..self.clone() })
+    }
+}
@@ -0,0 +1,139 @@
+use crate::hash::hash;
+use crate::lowdiscrepancy::{owen_scramble, sobol_sample, N_SOBOL_DIMENSIONS};
+use crate::sampler::Sampler;
+use crate::{point2, Point2f, Point2i};
+
+/// Generates samples from the Sobol sequence, randomized for each pixel by Owen scrambling.
+///
+/// The sample count is rounded up to a power of two, so that the samples of a pixel form a complete net with
+/// good stratification in every pair of dimensions.
+///
+/// Dimensions beyond [`N_SOBOL_DIMENSIONS`] are padded with the first two dimensions of the sequence, which form a
+/// (0,2)-sequence. Each padded sample scrambles them with its own seeds and shuffles the sample indices with a
+/// nested permutation that maps aligned blocks of indices to aligned blocks. This keeps every padded 2D sample
+/// stratified, while the padded dimensions are only decorrelated from each other by the randomization.
+#[derive(Debug, Clone)]
+pub struct SobolSampler {
+    samples_per_pixel: usize,
+    seed: u64,
+    pixel: Point2i,
+    sample_index: usize,
+    pixel_seed: u64,
+    dimension: usize
+}
+
+impl SobolSampler {
+    pub fn new(samples_per_pixel: usize, seed: u64) -> Self {
+        SobolSampler {
+            samples_per_pixel: samples_per_pixel.next_power_of_two(),
+            seed,
+            pixel: Point2i::default(),
+            sample_index: 0,
+            pixel_seed: 0,
+            dimension: 0
+        }
+    }
+
+    #[inline]
+    fn sample_dimension(&self, dimension: usize) -> f32 {
+        let scramble_seed = hash(&[dimension as u64, self.pixel_seed]) as u32;
+        sobol_sample(self.sample_index as u64, dimension, |v| owen_scramble(v, scramble_seed))
+    }
+
+    /// Samples dimension `dimension` beyond the tabulated ones with dimension `sobol_dimension` of the sequence, at
+    /// the sample index shuffled by the sample's `index_seed`.
+    #[inline]
+    fn padded_dimension(&self, dimension: usize, sobol_dimension: usize, index_seed: u32) -> f32 {
+        let index = owen_scramble(self.sample_index as u32, index_seed);
+        let scramble_seed = hash(&[dimension as u64, self.pixel_seed]) as u32;
+        sobol_sample(index as u64, sobol_dimension, |v| owen_scramble(v, scramble_seed))
+    }
+
+    #[inline]
+    fn index_seed(&self, dimension: usize) -> u32 {
+        (hash(&[dimension as u64, self.pixel_seed]) >> 32) as u32
+    }
+}
+
+impl Sampler for SobolSampler {
+    #[inline]
+    fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, p: &Point2i, sample_index: usize) {
+        self.pixel = *p;
+        self.sample_index = sample_index;
+        self.pixel_seed = hash(&[p.x as u64, p.y as u64, self.seed]);
+        self.dimension = 0;
+    }
+
+    #[inline]
+    fn current_pixel(&self) -> Point2i {
+        self.pixel
+    }
+
+    #[inline]
+    fn current_sample_index(&self) -> usize {
+        self.sample_index
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        if dimension < N_SOBOL_DIMENSIONS {
+            return self.sample_dimension(dimension)
+        }
+        self.padded_dimension(dimension, 0, self.index_seed(dimension))
+    }
+
+    fn get_2d(&mut self) -> Point2f {
+        let dimension = self.dimension;
+        self.dimension += 2;
+        if dimension + 1 < N_SOBOL_DIMENSIONS {
+            return point2(self.sample_dimension(dimension), self.sample_dimension(dimension + 1))
+        }
+        // Both dimensions share the shuffled index, so that they keep the stratification of the first two
+        let index_seed = self.index_seed(dimension);
+        point2(self.padded_dimension(dimension, 0, index_seed), self.padded_dimension(dimension + 1, 1, index_seed))
+    }
+
+    fn clone_with_seed(&self, seed: u64) -> Box<dyn Sampler> {
+        Box::new(SobolSampler { seed, ..self.clone() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point2;
+
+    /// Checks that every aligned block of 2^b points forms a (0, b, 2)-net.
+    fn is_02_sequence(points: &[Point2f]) -> bool {
+        let m = points.len().trailing_zeros() as usize;
+        (0..=m).all(|b| points.chunks(1 << b).all(|block| (0..=b).all(|k| {
+            let (cells_x, cells_y) = (1usize << k, 1usize << (b - k));
+            let mut seen = vec![false; 1 << b];
+            block.iter().all(|u| {
+                let cell = (u.y * cells_y as f32) as usize * cells_x + (u.x * cells_x as f32) as usize;
+                !std::mem::replace(&mut seen[cell], true)
+            })
+        })))
+    }
+
+    #[test]
+    fn test_padded_dimensions_form_02_sequences() {
+        let mut sampler = SobolSampler::new(64, 3);
+        let (first, second): (Vec<Point2f>, Vec<Point2f>) = (0..64).map(|i| {
+            sampler.start_pixel_sample(&Point2::new(5, 7), i);
+            for _ in 0..N_SOBOL_DIMENSIONS / 2 + 20 {
+                sampler.get_2d();
+            }
+            (sampler.get_2d(), sampler.get_2d())
+        }).unzip();
+        assert!(is_02_sequence(&first));
+        assert!(is_02_sequence(&second));
+        // The padded samples are shuffled differently
+        assert!(first.iter().zip(&second).any(|(u, v)| (u.x < 0.5) != (v.x < 0.5)));
+    }
+}
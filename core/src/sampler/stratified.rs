@@ -0,0 +1,119 @@
+use crate::hash::{hash, permutation_element};
+use crate::rng::Rng;
+use crate::sampler::Sampler;
+use crate::{point2, Point2f, Point2i};
+
+/// Divides each dimension of the sample domain of a pixel into strata that each receive one sample, optionally
+/// jittered within the stratum.
+///
+/// 2D samples use an `x_samples` by `y_samples` grid and 1D samples as many strata as there are samples per pixel.
+/// The strata are assigned to the samples of a pixel by a different random permutation for each dimension, which
+/// decorrelates the dimensions.
+#[derive(Debug, Clone)]
+pub struct StratifiedSampler {
+    x_samples: usize,
+    y_samples: usize,
+    jitter: bool,
+    seed: u64,
+    rng: Rng,
+    pixel: Point2i,
+    sample_index: usize,
+    dimension: usize
+}
+
+impl StratifiedSampler {
+    pub fn new(x_samples: usize, y_samples: usize, jitter: bool, seed: u64) -> Self {
+        StratifiedSampler {
+            x_samples,
+            y_samples,
+            jitter,
+            seed,
+            rng: Rng::default(),
+            pixel: Point2i::default(),
+            sample_index: 0,
+            dimension: 0
+        }
+    }
+
+    /// Returns the stratum of the current sample in the current dimension.
+    fn stratum(&self) -> usize {
+        let seed = hash(&[self.pixel.x as u64, self.pixel.y as u64, self.dimension as u64, self.seed]);
+        permutation_element(self.sample_index as u32, self.samples_per_pixel() as u32, seed as u32) as usize
+    }
+
+    #[inline]
+    fn offset(&mut self) -> f32 {
+        if self.jitter {
+            self.rng.uniform_f32()
+        } else {
+            0.5
+        }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    #[inline]
+    fn samples_per_pixel(&self) -> usize {
+        self.x_samples * self.y_samples
+    }
+
+    fn start_pixel_sample(&mut self, p: &Point2i, sample_index: usize) {
+        self.pixel = *p;
+        self.sample_index = sample_index;
+        self.dimension = 0;
+        self.rng.set_sequence(hash(&[p.x as u64, p.y as u64, self.seed]));
+        self.rng.advance(sample_index as i64 * 65536);
+    }
+
+    #[inline]
+    fn current_pixel(&self) -> Point2i {
+        self.pixel
+    }
+
+    #[inline]
+    fn current_sample_index(&self) -> usize {
+        self.sample_index
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let stratum = self.stratum();
+        self.dimension += 1;
+        (stratum as f32 + self.offset()) / self.samples_per_pixel() as f32
+    }
+
+    fn get_2d(&mut self) -> Point2f {
+        let stratum = self.stratum();
+        self.dimension += 2;
+        let (x, y) = (stratum % self.x_samples, stratum / self.x_samples);
+        let (dx, dy) = (self.offset(), self.offset());
+        point2((x as f32 + dx) / self.x_samples as f32, (y as f32 + dy) / self.y_samples as f32)
+    }
+
+    fn clone_with_seed(&self, seed: u64) -> Box<dyn Sampler> {
+        Box::new(StratifiedSampler { seed, ..self.clone() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point2;
+
+    #[test]
+    fn test_each_stratum_gets_one_sample() {
+        let mut sampler = StratifiedSampler::new(4, 2, true, 3);
+        let mut strata_1d = [false; 8];
+        let mut strata_2d = [false; 8];
+        sampler.start_pixel(&Point2::new(1, 2));
+        loop {
+            let u = sampler.get_1d();
+            strata_1d[(u * 8.0) as usize] = true;
+            let u = sampler.get_2d();
+            strata_2d[(u.y * 2.0) as usize * 4 + (u.x * 4.0) as usize] = true;
+            if !sampler.start_next_sample() {
+                break
+            }
+        }
+        assert!(strata_1d.iter().chain(strata_2d.iter()).all(|&s| s));
+    }
+}
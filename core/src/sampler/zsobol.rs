@@ -0,0 +1,169 @@
+use crate::hash::{hash, mix_bits};
+use crate::lowdiscrepancy::{owen_scramble, sobol_sample};
+use crate::sampler::Sampler;
+use crate::{point2, Point2f, Point2i};
+
+/// All permutations of four elements, used to shuffle the base 4 digits of sample indices.
+#[rustfmt::skip]
+const PERMUTATIONS: [[u8; 4]; 24] = [
+    [0, 1, 2, 3], [0, 1, 3, 2], [0, 2, 1, 3], [0, 2, 3, 1], [0, 3, 2, 1], [0, 3, 1, 2],
+    [1, 0, 2, 3], [1, 0, 3, 2], [1, 2, 0, 3], [1, 2, 3, 0], [1, 3, 2, 0], [1, 3, 0, 2],
+    [2, 1, 0, 3], [2, 1, 3, 0], [2, 0, 1, 3], [2, 0, 3, 1], [2, 3, 0, 1], [2, 3, 1, 0],
+    [3, 1, 2, 0], [3, 1, 0, 2], [3, 2, 1, 0], [3, 2, 0, 1], [3, 0, 2, 1], [3, 0, 1, 2]
+];
+
+/// Distributes the points of a single Owen-scrambled Sobol sequence over the image by ordering the pixels along a
+/// Morton curve, so that neighboring pixels receive well-stratified samples and the error has a blue-noise
+/// distribution, which is most effective at low sample counts.
+///
+/// The base 4 digits of the Morton index are randomly permuted per dimension, which decorrelates the dimensions
+/// and the pixels while preserving the stratification.
+///
+/// See Ahmed and Wonka, "Screen-Space Blue-Noise Diffusion of Monte Carlo Sampling Error via Hierarchical Ordering
+/// of Pixels" (2020).
+#[derive(Debug, Clone)]
+pub struct ZSobolSampler {
+    log2_samples_per_pixel: u32,
+    n_base4_digits: u32,
+    seed: u64,
+    pixel: Point2i,
+    sample_index: usize,
+    morton_index: u64,
+    dimension: u64
+}
+
+impl ZSobolSampler {
+    /// Creates a sampler for an image of `full_resolution` pixels, rounding the sample count up to a power of two.
+    pub fn new(samples_per_pixel: usize, full_resolution: Point2i, seed: u64) -> Self {
+        let log2_samples_per_pixel = samples_per_pixel.next_power_of_two().trailing_zeros();
+        let resolution = i32::max(full_resolution.x, full_resolution.y).max(1) as u32;
+        let log4_samples_per_pixel = (log2_samples_per_pixel + 1) / 2;
+        let n_base4_digits = resolution.next_power_of_two().trailing_zeros() + log4_samples_per_pixel;
+        ZSobolSampler {
+            log2_samples_per_pixel,
+            n_base4_digits,
+            seed,
+            pixel: Point2i::default(),
+            sample_index: 0,
+            morton_index: 0,
+            dimension: 0
+        }
+    }
+
+    /// Returns the index of the Sobol point used for the current sample and dimension.
+    fn sobol_index(&self) -> u64 {
+        let mut sample_index = 0;
+        // With an odd power of two samples per pixel, the lowest digit is in base 2
+        let pow2_samples = self.log2_samples_per_pixel & 1 != 0;
+        let last_digit = if pow2_samples { 1 } else { 0 };
+        for i in (last_digit..self.n_base4_digits).rev() {
+            let digit_shift = 2 * i - last_digit;
+            let digit = (self.morton_index >> digit_shift) & 3;
+            let higher_digits = self.morton_index >> (digit_shift + 2);
+            let p = (mix_bits(higher_digits ^ (0x55555555 * self.dimension)) >> 24) % 24;
+            sample_index |= (PERMUTATIONS[p as usize][digit as usize] as u64) << digit_shift;
+        }
+        if pow2_samples {
+            let digit = self.morton_index & 1;
+            sample_index |= digit ^ (mix_bits((self.morton_index >> 1) ^ (0x55555555 * self.dimension)) & 1);
+        }
+        sample_index
+    }
+}
+
+impl Sampler for ZSobolSampler {
+    #[inline]
+    fn samples_per_pixel(&self) -> usize {
+        1 << self.log2_samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, p: &Point2i, sample_index: usize) {
+        self.pixel = *p;
+        self.sample_index = sample_index;
+        self.dimension = 0;
+        let morton_pixel = encode_morton2(p.x as u32, p.y as u32);
+        self.morton_index = (morton_pixel << self.log2_samples_per_pixel) | sample_index as u64;
+    }
+
+    #[inline]
+    fn current_pixel(&self) -> Point2i {
+        self.pixel
+    }
+
+    #[inline]
+    fn current_sample_index(&self) -> usize {
+        self.sample_index
+    }
+
+    fn get_1d(&mut self) -> f32 {
+        let sample_index = self.sobol_index();
+        self.dimension += 1;
+        let sample_hash = hash(&[self.dimension, self.seed]) as u32;
+        sobol_sample(sample_index, 0, |v| owen_scramble(v, sample_hash))
+    }
+
+    fn get_2d(&mut self) -> Point2f {
+        let sample_index = self.sobol_index();
+        self.dimension += 2;
+        let bits = hash(&[self.dimension, self.seed]);
+        point2(
+            sobol_sample(sample_index, 0, |v| owen_scramble(v, bits as u32)),
+            sobol_sample(sample_index, 1, |v| owen_scramble(v, (bits >> 32) as u32))
+        )
+    }
+
+    fn clone_with_seed(&self, seed: u64) -> Box<dyn Sampler> {
+        Box::new(ZSobolSampler { seed, ..self.clone() })
+    }
+}
+
+/// Interleaves the bits of `x` and `y`, with the bits of `x` in the even positions.
+#[inline]
+fn encode_morton2(x: u32, y: u32) -> u64 {
+    fn left_shift2(x: u32) -> u64 {
+        let mut x = x as u64;
+        x = (x | (x << 16)) & 0x0000ffff0000ffff;
+        x = (x | (x << 8)) & 0x00ff00ff00ff00ff;
+        x = (x | (x << 4)) & 0x0f0f0f0f0f0f0f0f;
+        x = (x | (x << 2)) & 0x3333333333333333;
+        x = (x | (x << 1)) & 0x5555555555555555;
+        x
+    }
+    (left_shift2(y) << 1) | left_shift2(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point2;
+
+    #[test]
+    fn test_morton_order() {
+        assert_eq!(encode_morton2(0b11, 0b00), 0b0101);
+        assert_eq!(encode_morton2(0b10, 0b01), 0b0110);
+    }
+
+    #[test]
+    fn test_pixel_blocks_are_stratified() {
+        // With one sample per pixel, each 2x2 block of pixels covers the four quadrants of the first dimensions
+        for spp in [1, 2] {
+            let mut sampler = ZSobolSampler::new(spp, Point2::new(8, 8), 5);
+            for by in 0..4 {
+                for bx in 0..4 {
+                    let mut quadrants = [0; 4];
+                    for (x, y) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                        sampler.start_pixel(&Point2::new(2 * bx + x, 2 * by + y));
+                        loop {
+                            let u = sampler.get_2d();
+                            quadrants[(u.y * 2.0) as usize * 2 + (u.x * 2.0) as usize] += 1;
+                            if !sampler.start_next_sample() {
+                                break
+                            }
+                        }
+                    }
+                    assert_eq!(quadrants, [spp; 4]);
+                }
+            }
+        }
+    }
+}
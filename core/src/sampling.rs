@@ -0,0 +1,521 @@
+//! Routines for warping uniform samples to other distributions.
+//!
+//! Most warps come with the density of the resulting distribution and an inverse that maps a sampled value back
+//! to the uniform sample that generates it.
+
+mod alias;
+mod distribution;
+mod piecewise_linear;
+
+pub use alias::*;
+pub use distribution::*;
+pub use piecewise_linear::*;
+
+use std::f32::consts::{FRAC_1_PI, FRAC_PI_2, FRAC_PI_4, PI};
+use crate::geom::DotProduct;
+use crate::math::{safe_asin, safe_sqrt, Lerp};
+use crate::{point2, vec3, Point2, Point2f, Point3f, Vector3f};
+
+/// Maps a uniform sample in `[0, 1)^2` to a point on the unit disk using Shirley's concentric mapping, which
+/// preserves relative areas and keeps distortion low.
+pub fn concentric_sample_disk(u: &Point2f) -> Point2f {
+    // Map the sample to [-1, 1]^2
+    let (ox, oy) = (2.0 * u.x - 1.0, 2.0 * u.y - 1.0);
+    if ox == 0.0 && oy == 0.0 {
+        return Point2::new(0.0, 0.0)
+    }
+    let (r, theta) = if ox.abs() > oy.abs() {
+        (ox, FRAC_PI_4 * (oy / ox))
+    } else {
+        (oy, 2.0 * FRAC_PI_4 - FRAC_PI_4 * (ox / oy))
+    };
+    Point2::new(r * theta.cos(), r * theta.sin())
+}
+
+/// Maps a point on the unit disk back to the sample that [`concentric_sample_disk`] maps to it.
+pub fn invert_concentric_sample_disk(p: &Point2f) -> Point2f {
+    let theta = f32::atan2(p.y, p.x);
+    let r = f32::sqrt(p.x * p.x + p.y * p.y);
+    let (ox, oy);
+    if theta.abs() < FRAC_PI_4 || theta.abs() > 3.0 * FRAC_PI_4 {
+        let r = r.copysign(p.x);
+        ox = r;
+        oy = if p.x < 0.0 {
+            if p.y < 0.0 {
+                (PI + theta) * r / FRAC_PI_4
+            } else {
+                (theta - PI) * r / FRAC_PI_4
+            }
+        } else {
+            theta * r / FRAC_PI_4
+        };
+    } else {
+        let r = r.copysign(p.y);
+        oy = r;
+        ox = if p.y < 0.0 {
+            -(FRAC_PI_2 + theta) * r / FRAC_PI_4
+        } else {
+            (FRAC_PI_2 - theta) * r / FRAC_PI_4
+        };
+    }
+    point2((ox + 1.0) / 2.0, (oy + 1.0) / 2.0)
+}
+
+/// Maps a uniform sample to a point on the unit disk using polar coordinates.
+#[inline]
+pub fn uniform_sample_disk(u: &Point2f) -> Point2f {
+    let r = u.x.sqrt();
+    let theta = 2.0 * PI * u.y;
+    point2(r * theta.cos(), r * theta.sin())
+}
+
+pub fn invert_uniform_sample_disk(p: &Point2f) -> Point2f {
+    point2(p.x * p.x + p.y * p.y, azimuth(p.y, p.x) / (2.0 * PI))
+}
+
+/// Returns the angle of `(x, y)` around the origin in `[0, 2pi)`.
+#[inline]
+fn azimuth(y: f32, x: f32) -> f32 {
+    let phi = f32::atan2(y, x);
+    if phi < 0.0 {
+        phi + 2.0 * PI
+    } else {
+        phi
+    }
+}
+
+/// Returns the direction with the given polar angle, given by its sine and cosine, and azimuth `phi`.
+#[inline]
+pub fn spherical_direction(sin_theta: f32, cos_theta: f32, phi: f32) -> Vector3f {
+    let sin_theta = sin_theta.clamp(-1.0, 1.0);
+    vec3(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta.clamp(-1.0, 1.0))
+}
+
+/// Samples a direction on the hemisphere around the `z` axis with uniform density.
+#[inline]
+pub fn uniform_sample_hemisphere(u: &Point2f) -> Vector3f {
+    let z = u.x;
+    spherical_direction(safe_sqrt(1.0 - z * z), z, 2.0 * PI * u.y)
+}
+
+#[inline]
+pub const fn uniform_hemisphere_pdf() -> f32 {
+    FRAC_1_PI / 2.0
+}
+
+pub fn invert_uniform_sample_hemisphere(w: &Vector3f) -> Point2f {
+    point2(w.z, azimuth(w.y, w.x) / (2.0 * PI))
+}
+
+/// Samples a direction on the unit sphere with uniform density.
+#[inline]
+pub fn uniform_sample_sphere(u: &Point2f) -> Vector3f {
+    let z = 1.0 - 2.0 * u.x;
+    spherical_direction(safe_sqrt(1.0 - z * z), z, 2.0 * PI * u.y)
+}
+
+#[inline]
+pub const fn uniform_sphere_pdf() -> f32 {
+    FRAC_1_PI / 4.0
+}
+
+pub fn invert_uniform_sample_sphere(w: &Vector3f) -> Point2f {
+    point2((1.0 - w.z) / 2.0, azimuth(w.y, w.x) / (2.0 * PI))
+}
+
+/// Samples a direction on the hemisphere around the `z` axis with a density proportional to the cosine of the
+/// angle to the axis, by projecting a uniformly sampled point on the disk up to the hemisphere (Malley's method).
+#[inline]
+pub fn cosine_sample_hemisphere(u: &Point2f) -> Vector3f {
+    let d = concentric_sample_disk(u);
+    let z = safe_sqrt(1.0 - d.x * d.x - d.y * d.y);
+    vec3(d.x, d.y, z)
+}
+
+#[inline]
+pub fn cosine_hemisphere_pdf(cos_theta: f32) -> f32 {
+    cos_theta * FRAC_1_PI
+}
+
+pub fn invert_cosine_sample_hemisphere(w: &Vector3f) -> Point2f {
+    invert_concentric_sample_disk(&point2(w.x, w.y))
+}
+
+/// Samples a direction within the cone of directions around the `z` axis whose angle to the axis has a cosine of
+/// at least `cos_theta_max`, with uniform density.
+#[inline]
+pub fn uniform_sample_cone(u: &Point2f, cos_theta_max: f32) -> Vector3f {
+    let cos_theta = (1.0 - u.x) + u.x * cos_theta_max;
+    spherical_direction(safe_sqrt(1.0 - cos_theta * cos_theta), cos_theta, 2.0 * PI * u.y)
+}
+
+#[inline]
+pub fn uniform_cone_pdf(cos_theta_max: f32) -> f32 {
+    1.0 / (2.0 * PI * (1.0 - cos_theta_max))
+}
+
+pub fn invert_uniform_sample_cone(w: &Vector3f, cos_theta_max: f32) -> Point2f {
+    point2((1.0 - w.z) / (1.0 - cos_theta_max), azimuth(w.y, w.x) / (2.0 * PI))
+}
+
+/// Samples barycentric coordinates of a point in a triangle with uniform density over its area.
+///
+/// This mapping by Heitz (2019) has less distortion than the classic square root warp, which helps preserve the
+/// stratification of the samples.
+#[inline]
+pub fn uniform_sample_triangle(u: &Point2f) -> [f32; 3] {
+    let (b0, b1);
+    if u.x < u.y {
+        b0 = u.x / 2.0;
+        b1 = u.y - b0;
+    } else {
+        b1 = u.y / 2.0;
+        b0 = u.x - b1;
+    }
+    [b0, b1, 1.0 - b0 - b1]
+}
+
+pub fn invert_uniform_sample_triangle(b: &[f32; 3]) -> Point2f {
+    if b[0] > b[1] {
+        point2(b[0] + b[1], 2.0 * b[1])
+    } else {
+        point2(2.0 * b[0], b[1] + b[0])
+    }
+}
+
+/// Returns the angle between the normalized vectors `v1` and `v2`, computed in a numerically robust way.
+#[inline]
+fn angle_between(v1: &Vector3f, v2: &Vector3f) -> f32 {
+    if v1.dot(v2) < 0.0 {
+        PI - 2.0 * safe_asin((*v1 + *v2).length() / 2.0)
+    } else {
+        2.0 * safe_asin((*v2 - *v1).length() / 2.0)
+    }
+}
+
+/// Returns the component of `v` that is orthogonal to the normalized vector `w`.
+#[inline]
+fn gram_schmidt(v: &Vector3f, w: &Vector3f) -> Vector3f {
+    *v - *w * v.dot(w)
+}
+
+/// The normalized directions from a reference point to the vertices of a triangle, the normals of the great
+/// circles through each pair of them and the angles of the spherical triangle at the vertices.
+struct SphericalTriangle {
+    a: Vector3f,
+    b: Vector3f,
+    c: Vector3f,
+    n_ab: Vector3f,
+    alpha: f32,
+    beta: f32,
+    gamma: f32
+}
+
+impl SphericalTriangle {
+    fn new(v: &[Point3f; 3], p: &Point3f) -> Option<Self> {
+        let a = (v[0] - *p).normalize();
+        let b = (v[1] - *p).normalize();
+        let c = (v[2] - *p).normalize();
+        let (n_ab, n_bc, n_ca) = (a.cross(&b), b.cross(&c), c.cross(&a));
+        if n_ab.length_squared() == 0.0 || n_bc.length_squared() == 0.0 || n_ca.length_squared() == 0.0 {
+            return None
+        }
+        let (n_ab, n_bc, n_ca) = (n_ab.normalize(), n_bc.normalize(), n_ca.normalize());
+        let alpha = angle_between(&n_ab, &-n_ca);
+        let beta = angle_between(&n_bc, &-n_ab);
+        let gamma = angle_between(&n_ca, &-n_bc);
+        Some(SphericalTriangle { a, b, c, n_ab, alpha, beta, gamma })
+    }
+
+    /// Returns the solid angle of the triangle.
+    #[inline]
+    fn area(&self) -> f32 {
+        self.alpha + self.beta + self.gamma - PI
+    }
+}
+
+/// Samples a point on the triangle `v` with uniform density over the solid angle it subtends from `p`, returning
+/// its barycentric coordinates and the density with respect to solid angle.
+///
+/// Returns `None` if the triangle is degenerate as seen from `p`. See Arvo, "Stratified Sampling of Spherical
+/// Triangles" (1995).
+pub fn sample_spherical_triangle(v: &[Point3f; 3], p: &Point3f, u: &Point2f) -> Option<([f32; 3], f32)> {
+    let t = SphericalTriangle::new(v, p)?;
+    let SphericalTriangle { a, b, c, alpha, .. } = t;
+    let area = t.area();
+    let pdf = if area <= 0.0 { 0.0 } else { 1.0 / area };
+
+    // Find the point b' on the arc between a and c that bounds the subtriangle with the sampled area
+    let area_pi = f32::lerp(u.x, PI, area + PI);
+    let (cos_alpha, sin_alpha) = (alpha.cos(), alpha.sin());
+    let sin_phi = area_pi.sin() * cos_alpha - area_pi.cos() * sin_alpha;
+    let cos_phi = area_pi.cos() * cos_alpha + area_pi.sin() * sin_alpha;
+    let k1 = cos_phi + cos_alpha;
+    let k2 = sin_phi - sin_alpha * a.dot(&b);
+    let cos_bp = ((k2 + (k2 * cos_phi - k1 * sin_phi) * cos_alpha) / ((k2 * sin_phi + k1 * cos_phi) * sin_alpha))
+        .clamp(-1.0, 1.0);
+    let sin_bp = safe_sqrt(1.0 - cos_bp * cos_bp);
+    let cp = a * cos_bp + gram_schmidt(&c, &a).normalize() * sin_bp;
+
+    // Sample the direction along the arc between b and c'
+    let cos_theta = 1.0 - u.y * (1.0 - cp.dot(&b));
+    let sin_theta = safe_sqrt(1.0 - cos_theta * cos_theta);
+    let w = b * cos_theta + gram_schmidt(&cp, &b).normalize() * sin_theta;
+
+    // Intersect the direction with the triangle to find the barycentric coordinates
+    let e1 = v[1] - v[0];
+    let e2 = v[2] - v[0];
+    let s1 = w.cross(&e2);
+    let divisor = s1.dot(&e1);
+    if divisor == 0.0 {
+        return Some(([1.0 / 3.0; 3], pdf))
+    }
+    let s = *p - v[0];
+    let mut b1 = (s.dot(&s1) / divisor).clamp(0.0, 1.0);
+    let mut b2 = (w.dot(&s.cross(&e1)) / divisor).clamp(0.0, 1.0);
+    if b1 + b2 > 1.0 {
+        let sum = b1 + b2;
+        b1 /= sum;
+        b2 /= sum;
+    }
+    Some(([1.0 - b1 - b2, b1, b2], pdf))
+}
+
+/// Maps the normalized direction `w` from `p` towards the triangle `v` back to the sample that
+/// [`sample_spherical_triangle`] maps to it.
+pub fn invert_spherical_triangle_sample(v: &[Point3f; 3], p: &Point3f, w: &Vector3f) -> Point2f {
+    let t = match SphericalTriangle::new(v, p) {
+        Some(t) => t,
+        None => return point2(0.5, 0.5)
+    };
+    let SphericalTriangle { a, b, c, n_ab, alpha, .. } = t;
+
+    // Find the vertex c' on the arc between a and c that lies on the great circle through b and w
+    let mut cp = b.cross(w).cross(&c.cross(&a)).normalize();
+    if cp.dot(&(a + c)) < 0.0 {
+        cp = -cp;
+    }
+
+    // Invert the sampling of the subtriangle area
+    // Directions within 0.1 degrees of a are treated as a, which avoids an unstable area computation
+    let u0 = if a.dot(&cp) > 0.9999985 {
+        0.0
+    } else {
+        let (n_cpb, n_acp) = (cp.cross(&b), a.cross(&cp));
+        if n_cpb.length_squared() == 0.0 || n_acp.length_squared() == 0.0 {
+            return point2(0.5, 0.5)
+        }
+        let (n_cpb, n_acp) = (n_cpb.normalize(), n_acp.normalize());
+        let area_p = alpha + angle_between(&n_ab, &n_cpb) + angle_between(&n_acp, &-n_cpb) - PI;
+        area_p / t.area()
+    };
+
+    // Invert the sampling along the arc between b and c'
+    let u1 = (1.0 - w.dot(&b)) / (1.0 - cp.dot(&b));
+    point2(u0.clamp(0.0, 1.0), u1.clamp(0.0, 1.0))
+}
+
+/// Samples a distance with the density `a * exp(-a * x)`, such as the distance to the next collision in a medium
+/// with attenuation coefficient `a`.
+#[inline]
+pub fn sample_exponential(u: f32, a: f32) -> f32 {
+    -(1.0 - u).ln() / a
+}
+
+/// The Henyey–Greenstein phase function, where `cos_theta` is the cosine of the angle between the directions
+/// pointing away from the scattering point and `g` is the asymmetry parameter, positive for forward scattering.
+#[inline]
+pub fn henyey_greenstein(cos_theta: f32, g: f32) -> f32 {
+    let g = g.clamp(-0.99, 0.99);
+    let denom = 1.0 + g * g + 2.0 * g * cos_theta;
+    FRAC_1_PI / 4.0 * (1.0 - g * g) / (denom * safe_sqrt(denom))
+}
+
+/// Samples a direction `wi` from the Henyey–Greenstein phase function for `wo` and returns it together with its
+/// density, which equals the value of the phase function.
+pub fn sample_henyey_greenstein(wo: &Vector3f, g: f32, u: &Point2f) -> (Vector3f, f32) {
+    let g = g.clamp(-0.99, 0.99);
+    let cos_theta = if g.abs() < 1e-3 {
+        1.0 - 2.0 * u.x
+    } else {
+        -1.0 / (2.0 * g) * (1.0 + g * g - ((1.0 - g * g) / (1.0 + g - 2.0 * g * u.x)).powi(2))
+    };
+    let w = spherical_direction(safe_sqrt(1.0 - cos_theta * cos_theta), cos_theta, 2.0 * PI * u.y);
+    let (v1, v2) = wo.coordinate_system();
+    let wi = v1 * w.x + v2 * w.y + *wo * w.z;
+    (wi, henyey_greenstein(cos_theta, g))
+}
+
+/// Weights a sample of one of two sampling strategies for multiple importance sampling, given the number of
+/// samples and the densities of both strategies.
+///
+/// See Veach and Guibas, "Optimally Combining Sampling Techniques for Monte Carlo Rendering" (1995).
+#[inline]
+pub fn power_heuristic(nf: u32, f_pdf: f32, ng: u32, g_pdf: f32) -> f32 {
+    let f = nf as f32 * f_pdf;
+    let g = ng as f32 * g_pdf;
+    if f.is_infinite() {
+        return 1.0
+    }
+    (f * f) / (f * f + g * g)
+}
+
+/// Samples a wavelength in `[360, 830]` nanometers proportionally to an approximation of the luminous efficiency
+/// of the human eye, which focuses samples on the wavelengths that contribute most to the image.
+///
+/// See Radziszewski et al., "An Improved Technique for Full Spectral Rendering" (2009).
+#[inline]
+#[allow(clippy::excessive_precision)]
+pub fn sample_visible_wavelengths(u: f32) -> f32 {
+    538.0 - 138.888889 * f32::atanh(0.85691062 - 1.82750197 * u)
+}
+
+/// Returns the density of [`sample_visible_wavelengths`] at wavelength `lambda`.
+#[inline]
+#[allow(clippy::excessive_precision)]
+pub fn visible_wavelengths_pdf(lambda: f32) -> f32 {
+    if !(360.0..=830.0).contains(&lambda) {
+        return 0.0
+    }
+    0.0039398042 / f32::cosh(0.0072 * (lambda - 538.0)).powi(2)
+}
+
+/// Returns the centers of the cells of an `n` by `n` grid over `[0, 1)^2`, which tests use as stratified samples
+/// for deterministic estimates.
+#[cfg(test)]
+pub(crate) fn sample_grid(n: usize) -> impl Iterator<Item = Point2f> {
+    (0..n * n).map(move |i| point2(((i % n) as f32 + 0.5) / n as f32, ((i / n) as f32 + 0.5) / n as f32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck::TestResult;
+    use quickcheck_macros::quickcheck;
+    use crate::Point3;
+
+    fn unit_sample(u0: u16, u1: u16) -> Point2f {
+        point2(u0 as f32 / 65536.0, u1 as f32 / 65536.0)
+    }
+
+    fn close(a: &Point2f, b: &Point2f) -> bool {
+        (a.x - b.x).abs() < 1e-3 && (a.y - b.y).abs() < 1e-3
+    }
+
+    #[quickcheck]
+    fn disk_warps_invert(u0: u16, u1: u16) -> bool {
+        let u = unit_sample(u0, u1);
+        let p = concentric_sample_disk(&u);
+        let q = uniform_sample_disk(&u);
+        p.x * p.x + p.y * p.y <= 1.0 + 1e-6 &&
+            close(&invert_concentric_sample_disk(&p), &u) &&
+            (u.x < 1e-3 || close(&invert_uniform_sample_disk(&q), &u))
+    }
+
+    #[quickcheck]
+    fn sphere_warps_invert(u0: u16, u1: u16) -> TestResult {
+        let u = unit_sample(u0, u1);
+        // The azimuth is undefined at the poles
+        if u.x < 1e-3 || u.x > 1.0 - 1e-3 {
+            return TestResult::discard()
+        }
+        let hemisphere = uniform_sample_hemisphere(&u);
+        let sphere = uniform_sample_sphere(&u);
+        let cosine = cosine_sample_hemisphere(&u);
+        let cone = uniform_sample_cone(&u, 0.5);
+        TestResult::from_bool(
+            hemisphere.z >= 0.0 &&
+                cosine.z >= 0.0 &&
+                cone.z >= 0.5 - 1e-6 &&
+                [hemisphere, sphere, cosine, cone].iter().all(|w| (w.length() - 1.0).abs() < 1e-4) &&
+                close(&invert_uniform_sample_hemisphere(&hemisphere), &u) &&
+                close(&invert_uniform_sample_sphere(&sphere), &u) &&
+                close(&invert_cosine_sample_hemisphere(&cosine), &u) &&
+                close(&invert_uniform_sample_cone(&cone, 0.5), &u)
+        )
+    }
+
+    #[quickcheck]
+    fn triangle_warp_inverts(u0: u16, u1: u16) -> bool {
+        let u = unit_sample(u0, u1);
+        let b = uniform_sample_triangle(&u);
+        b.iter().all(|&b| (0.0..=1.0).contains(&b)) && close(&invert_uniform_sample_triangle(&b), &u)
+    }
+
+    #[quickcheck]
+    fn spherical_triangle_warp_inverts(u0: u16, u1: u16) -> TestResult {
+        let u = unit_sample(u0, u1);
+        if u.x < 1e-2 || u.y < 1e-2 || u.x > 0.99 || u.y > 0.99 {
+            return TestResult::discard()
+        }
+        let v = [Point3::new(0.0, 0.0, 1.0), Point3::new(1.0, 0.0, 1.0), Point3::new(0.0, 2.0, 1.5)];
+        let p = Point3::new(0.2, 0.3, 0.0);
+        let (b, pdf) = sample_spherical_triangle(&v, &p, &u).unwrap();
+        let point = Point3::new(
+            b[0] * v[0].x + b[1] * v[1].x + b[2] * v[2].x,
+            b[0] * v[0].y + b[1] * v[1].y + b[2] * v[2].y,
+            b[0] * v[0].z + b[1] * v[1].z + b[2] * v[2].z
+        );
+        let w = (point - p).normalize();
+        let inverse = invert_spherical_triangle_sample(&v, &p, &w);
+        TestResult::from_bool(pdf > 0.0 && (inverse.x - u.x).abs() < 1e-2 && (inverse.y - u.y).abs() < 1e-2)
+    }
+
+    #[test]
+    fn test_pdfs_integrate_to_one() {
+        // Integrate the densities over the sphere with the midpoint rule in (cos theta, phi), for which the solid
+        // angle of each cell is the same
+        let integrate = |pdf: &dyn Fn(Vector3f) -> f32| {
+            let n = 256;
+            let mut sum = 0.0;
+            for i in 0..n {
+                for j in 0..n {
+                    let cos_theta = -1.0 + 2.0 * (i as f32 + 0.5) / n as f32;
+                    let phi = 2.0 * PI * (j as f32 + 0.5) / n as f32;
+                    let sin_theta = safe_sqrt(1.0 - cos_theta * cos_theta);
+                    sum += pdf(vec3(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta));
+                }
+            }
+            sum * 4.0 * PI / (n * n) as f32
+        };
+        let upper = |w: Vector3f, pdf: f32| if w.z > 0.0 { pdf } else { 0.0 };
+        assert!((integrate(&|_| uniform_sphere_pdf()) - 1.0).abs() < 1e-3);
+        assert!((integrate(&|w| upper(w, uniform_hemisphere_pdf())) - 1.0).abs() < 1e-3);
+        assert!((integrate(&|w| upper(w, cosine_hemisphere_pdf(w.z))) - 1.0).abs() < 1e-3);
+        // The boundary of the cone lies on the boundary of a cell
+        let cone = integrate(&|w| if w.z > 0.75 { uniform_cone_pdf(0.75) } else { 0.0 });
+        assert!((cone - 1.0).abs() < 1e-3, "{}", cone);
+        for g in [-0.7, 0.0, 0.4] {
+            let hg = integrate(&|w| henyey_greenstein(w.z, g));
+            assert!((hg - 1.0).abs() < 1e-3, "{} {}", g, hg);
+        }
+    }
+
+    #[test]
+    fn test_spherical_triangle_solid_angle() {
+        // An octant of the unit sphere subtends a solid angle of pi / 2
+        let v = [Point3::new(1.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0), Point3::new(0.0, 0.0, 1.0)];
+        let (_, pdf) = sample_spherical_triangle(&v, &Point3::new(0.0, 0.0, 0.0), &point2(0.3, 0.6)).unwrap();
+        assert!((pdf - 2.0 / PI).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_henyey_greenstein_sampling() {
+        let wo = vec3(0.3, -0.5, 0.6).normalize();
+        let n = 64;
+        for g in [-0.7, 0.0, 0.4] {
+            // The mean cosine of the scattering angle is the asymmetry parameter
+            let mut mean_cos = 0.0;
+            for i in 0..n {
+                for j in 0..n {
+                    let u = point2((i as f32 + 0.5) / n as f32, (j as f32 + 0.5) / n as f32);
+                    let (wi, pdf) = sample_henyey_greenstein(&wo, g, &u);
+                    assert!((wi.length() - 1.0).abs() < 1e-4);
+                    assert!((pdf - henyey_greenstein(wo.dot(&wi), g)).abs() < 1e-3 * pdf);
+                    mean_cos -= wo.dot(&wi) / (n * n) as f32;
+                }
+            }
+            assert!((mean_cos - g).abs() < 1e-2, "{} {}", mean_cos, g);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use crate::math::ONE_MINUS_EPSILON;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Bin {
+    /// Probability of choosing the bin itself rather than its alias.
+    q: f32,
+    /// Probability of the outcome of the bin.
+    p: f32,
+    alias: Option<usize>
+}
+
+/// Samples discrete outcomes with given weights in constant time using Walker's alias method.
+///
+/// Each outcome has a bin, and every bin is either chosen directly or redirects to a single alias, so that a
+/// sample only needs one uniform value and two lookups.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AliasTable {
+    bins: Vec<Bin>
+}
+
+impl AliasTable {
+    /// Creates a table for outcomes with probabilities proportional to `weights`, which must not all be zero.
+    pub fn new(weights: &[f32]) -> Self {
+        let sum: f64 = weights.iter().map(|&w| w as f64).sum();
+        assert!(sum > 0.0);
+        let n = weights.len();
+        let mut bins: Vec<Bin> =
+            weights.iter().map(|&w| Bin { q: 0.0, p: (w as f64 / sum) as f32, alias: None }).collect();
+
+        // Pair bins with less than the average probability with bins that have an excess
+        let mut under: Vec<(f64, usize)> = Vec::new();
+        let mut over: Vec<(f64, usize)> = Vec::new();
+        for (i, bin) in bins.iter().enumerate() {
+            let p_hat = bin.p as f64 * n as f64;
+            if p_hat < 1.0 {
+                under.push((p_hat, i));
+            } else {
+                over.push((p_hat, i));
+            }
+        }
+        while let (Some(&(p_under, i_under)), Some(&(p_over, i_over))) = (under.last(), over.last()) {
+            under.pop();
+            over.pop();
+            bins[i_under].q = p_under as f32;
+            bins[i_under].alias = Some(i_over);
+            let p_excess = p_under + p_over - 1.0;
+            if p_excess < 1.0 {
+                under.push((p_excess, i_over));
+            } else {
+                over.push((p_excess, i_over));
+            }
+        }
+        // Due to round-off, the remaining bins may be slightly off from the average
+        for (_, i) in over.into_iter().chain(under) {
+            bins[i].q = 1.0;
+            bins[i].alias = None;
+        }
+        AliasTable { bins }
+    }
+
+    /// Returns the number of outcomes.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.bins.len()
+    }
+
+    /// Samples an outcome, returning it together with its probability and `u` remapped to `[0, 1)`.
+    pub fn sample(&self, u: f32) -> (usize, f32, f32) {
+        let n = self.bins.len();
+        let offset = usize::min((u * n as f32) as usize, n - 1);
+        let up = f32::min(u * n as f32 - offset as f32, ONE_MINUS_EPSILON);
+        let bin = &self.bins[offset];
+        match bin.alias {
+            Some(alias) if up >= bin.q => {
+                let u_remapped = f32::min((up - bin.q) / (1.0 - bin.q), ONE_MINUS_EPSILON);
+                (alias, self.bins[alias].p, u_remapped)
+            }
+            _ => (offset, bin.p, f32::min(up / bin.q, ONE_MINUS_EPSILON))
+        }
+    }
+
+    /// Returns the probability of sampling outcome `index`.
+    #[inline]
+    pub fn pmf(&self, index: usize) -> f32 {
+        self.bins[index].p
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck::TestResult;
+    use quickcheck_macros::quickcheck;
+
+    #[quickcheck]
+    fn alias_table_pmf_matches_weights(weights: Vec<u8>, u: u16) -> TestResult {
+        let sum: f32 = weights.iter().map(|&w| w as f32).sum();
+        if sum == 0.0 {
+            return TestResult::discard()
+        }
+        let weights: Vec<f32> = weights.into_iter().map(|w| w as f32).collect();
+        let table = AliasTable::new(&weights);
+        let (index, pmf, u_remapped) = table.sample(u as f32 / 65536.0);
+        TestResult::from_bool(
+            weights[index] > 0.0 &&
+                (pmf - weights[index] / sum).abs() < 1e-5 &&
+                (0.0..1.0).contains(&u_remapped) &&
+                weights.iter().enumerate().all(|(i, w)| (table.pmf(i) - w / sum).abs() < 1e-5)
+        )
+    }
+
+    #[test]
+    fn test_sample_frequencies() {
+        let weights = [1.0, 0.0, 3.0, 4.0];
+        let table = AliasTable::new(&weights);
+        let n = 8000;
+        let mut counts = [0; 4];
+        for i in 0..n {
+            counts[table.sample((i as f32 + 0.5) / n as f32).0] += 1;
+        }
+        // Stratified samples hit every outcome exactly in proportion to its probability
+        assert_eq!(counts, [1000, 0, 3000, 4000]);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::math::{Lerp, ONE_MINUS_EPSILON};
+use crate::{point2, Point2f};
+
+/// A piecewise-constant distribution over `[0, 1]` with one segment per function value, sampled by inverting its
+/// cumulative distribution.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution1D {
+    func: Vec<f32>,
+    cdf: Vec<f32>,
+    func_int: f32
+}
+
+impl Distribution1D {
+    /// Creates the distribution proportional to the absolute values of `f`.
+    ///
+    /// If all values are zero, the distribution is uniform.
+    pub fn new(f: &[f32]) -> Self {
+        assert!(!f.is_empty());
+        let n = f.len();
+        let func: Vec<f32> = f.iter().map(|v| v.abs()).collect();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 1..=n {
+            cdf[i] = cdf[i - 1] + func[i - 1] / n as f32;
+        }
+        let func_int = cdf[n];
+        if func_int == 0.0 {
+            for (i, c) in cdf.iter_mut().enumerate().skip(1) {
+                *c = i as f32 / n as f32;
+            }
+        } else {
+            for c in cdf.iter_mut().skip(1) {
+                *c /= func_int;
+            }
+        }
+        Distribution1D { func, cdf, func_int }
+    }
+
+    /// Returns the number of segments.
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.func.len()
+    }
+
+    /// Returns the integral of the function over `[0, 1]`.
+    #[inline]
+    pub fn func_int(&self) -> f32 {
+        self.func_int
+    }
+
+    /// Returns the segment containing the value of the cumulative distribution `u`.
+    #[inline]
+    fn find_segment(&self, u: f32) -> usize {
+        self.cdf.partition_point(|&c| c <= u).clamp(1, self.count()) - 1
+    }
+
+    /// Samples a value in `[0, 1)`, returning it together with its density and the index of its segment.
+    pub fn sample_continuous(&self, u: f32) -> (f32, f32, usize) {
+        let offset = self.find_segment(u);
+        let mut du = u - self.cdf[offset];
+        if self.cdf[offset + 1] - self.cdf[offset] > 0.0 {
+            du /= self.cdf[offset + 1] - self.cdf[offset];
+        }
+        let pdf = self.pdf_of_segment(offset);
+        let x = f32::min((offset as f32 + du) / self.count() as f32, ONE_MINUS_EPSILON);
+        (x, pdf, offset)
+    }
+
+    /// Samples a segment, returning it together with its probability and `u` remapped to `[0, 1)` within the
+    /// segment, so that it can be reused as a new uniform sample.
+    pub fn sample_discrete(&self, u: f32) -> (usize, f32, f32) {
+        let offset = self.find_segment(u);
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let u_remapped = if width > 0.0 { f32::min((u - self.cdf[offset]) / width, ONE_MINUS_EPSILON) } else { 0.0 };
+        (offset, self.discrete_pdf(offset), u_remapped)
+    }
+
+    /// Returns the probability of sampling segment `index` with [`sample_discrete`](Self::sample_discrete).
+    #[inline]
+    pub fn discrete_pdf(&self, index: usize) -> f32 {
+        self.cdf[index + 1] - self.cdf[index]
+    }
+
+    #[inline]
+    fn pdf_of_segment(&self, index: usize) -> f32 {
+        if self.func_int > 0.0 {
+            self.func[index] / self.func_int
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns the density of sampling `x` with [`sample_continuous`](Self::sample_continuous).
+    pub fn pdf(&self, x: f32) -> f32 {
+        if !(0.0..=1.0).contains(&x) {
+            return 0.0
+        }
+        let offset = usize::min((x * self.count() as f32) as usize, self.count() - 1);
+        self.pdf_of_segment(offset)
+    }
+
+    /// Returns the sample that [`sample_continuous`](Self::sample_continuous) maps to `x`, or `None` if `x` has
+    /// zero density.
+    pub fn invert(&self, x: f32) -> Option<f32> {
+        if !(0.0..=1.0).contains(&x) {
+            return None
+        }
+        let offset = usize::min((x * self.count() as f32) as usize, self.count() - 1);
+        if self.pdf_of_segment(offset) == 0.0 {
+            return None
+        }
+        let dx = x * self.count() as f32 - offset as f32;
+        Some(f32::lerp(dx, self.cdf[offset], self.cdf[offset + 1]))
+    }
+}
+
+/// A piecewise-constant distribution over `[0, 1]^2`, sampled by first choosing `v` from the marginal distribution
+/// and then `u` from the conditional distribution of that row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution2D {
+    conditional_v: Vec<Distribution1D>,
+    marginal: Distribution1D
+}
+
+impl Distribution2D {
+    /// Creates the distribution proportional to the `nu` by `nv` function values `func`, stored in rows of
+    /// constant `v`.
+    pub fn new(func: &[f32], nu: usize, nv: usize) -> Self {
+        assert_eq!(func.len(), nu * nv);
+        let conditional_v: Vec<Distribution1D> = func.chunks_exact(nu).map(Distribution1D::new).collect();
+        let marginal_func: Vec<f32> = conditional_v.iter().map(|d| d.func_int()).collect();
+        Distribution2D { conditional_v, marginal: Distribution1D::new(&marginal_func) }
+    }
+
+    /// Returns the integral of the function over `[0, 1]^2`.
+    #[inline]
+    pub fn func_int(&self) -> f32 {
+        self.marginal.func_int()
+    }
+
+    /// Samples a point in `[0, 1)^2`, returning it together with its density.
+    pub fn sample_continuous(&self, u: &Point2f) -> (Point2f, f32) {
+        let (d1, pdf1, v) = self.marginal.sample_continuous(u.y);
+        let (d0, pdf0, _) = self.conditional_v[v].sample_continuous(u.x);
+        (point2(d0, d1), pdf0 * pdf1)
+    }
+
+    /// Returns the density of sampling `p` with [`sample_continuous`](Self::sample_continuous).
+    pub fn pdf(&self, p: &Point2f) -> f32 {
+        if !(0.0..=1.0).contains(&p.y) {
+            return 0.0
+        }
+        let nv = self.conditional_v.len();
+        let v = usize::min((p.y * nv as f32) as usize, nv - 1);
+        self.marginal.pdf(p.y) * self.conditional_v[v].pdf(p.x)
+    }
+
+    /// Returns the sample that [`sample_continuous`](Self::sample_continuous) maps to `p`, or `None` if `p` has
+    /// zero density.
+    pub fn invert(&self, p: &Point2f) -> Option<Point2f> {
+        let uv = self.marginal.invert(p.y)?;
+        let nv = self.conditional_v.len();
+        let v = usize::min((p.y * nv as f32) as usize, nv - 1);
+        let uu = self.conditional_v[v].invert(p.x)?;
+        Some(point2(uu, uv))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck::TestResult;
+    use quickcheck_macros::quickcheck;
+
+    #[quickcheck]
+    fn distribution1d_sample_matches_pdf_and_inverts(f: Vec<u8>, u: u16) -> TestResult {
+        if f.is_empty() {
+            return TestResult::discard()
+        }
+        let f: Vec<f32> = f.into_iter().map(|v| v as f32).collect();
+        let distribution = Distribution1D::new(&f);
+        let u = u as f32 / 65536.0;
+        let (x, pdf, offset) = distribution.sample_continuous(u);
+        let inverse = distribution.invert(x);
+        TestResult::from_bool(
+            (0.0..1.0).contains(&x) &&
+                pdf > 0.0 &&
+                pdf == distribution.pdf(x) &&
+                offset == usize::min((x * f.len() as f32) as usize, f.len() - 1) &&
+                inverse.map_or(false, |v| (v - u).abs() < 1e-3)
+        )
+    }
+
+    #[test]
+    fn test_distribution1d() {
+        let distribution = Distribution1D::new(&[1.0, 3.0]);
+        assert_eq!(distribution.func_int(), 2.0);
+        assert_eq!(distribution.sample_continuous(0.125), (0.25, 0.5, 0));
+        assert_eq!(distribution.sample_continuous(0.625), (0.75, 1.5, 1));
+        assert_eq!(distribution.sample_discrete(0.625), (1, 0.75, 0.5));
+        assert_eq!(distribution.invert(0.75), Some(0.625));
+        // All zero functions are sampled uniformly
+        assert_eq!(Distribution1D::new(&[0.0, 0.0]).sample_continuous(0.25), (0.25, 1.0, 0));
+    }
+
+    #[test]
+    fn test_distribution2d() {
+        let distribution = Distribution2D::new(&[1.0, 0.0, 2.0, 5.0], 2, 2);
+        assert_eq!(distribution.func_int(), 2.0);
+        let (p, pdf) = distribution.sample_continuous(&point2(0.5, 0.5));
+        assert_eq!(pdf, distribution.pdf(&p));
+        assert_eq!(distribution.pdf(&point2(0.75, 0.25)), 0.0);
+        assert_eq!(distribution.pdf(&point2(0.75, 0.75)), 2.5);
+        let u = distribution.invert(&p).unwrap();
+        assert!((u.x - 0.5).abs() < 1e-5 && (u.y - 0.5).abs() < 1e-5);
+    }
+}
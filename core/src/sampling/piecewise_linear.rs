@@ -0,0 +1,297 @@
+use crate::math::{safe_sqrt, ONE_MINUS_EPSILON};
+use crate::{point2, Point2f};
+
+/// The maximum number of parameters a [`PiecewiseLinear2D`] can be conditioned on.
+const MAX_PARAMS: usize = 3;
+
+/// A piecewise-bilinear distribution over `[0, 1]^2`, given by its values at the vertices of a regular grid and
+/// sampled by inverting its marginal and conditional cumulative distributions.
+///
+/// The distribution can be conditioned on additional parameters, in which case a grid is stored for every
+/// combination of tabulated parameter values and lookups interpolate multilinearly between the neighbouring grids.
+///
+/// See Dupuy and Jakob, "An Adaptive Parameterization for Efficient Material Acquisition and Rendering" (2018).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiecewiseLinear2D {
+    x_size: usize,
+    y_size: usize,
+    param_values: Vec<Vec<f32>>,
+    param_strides: Vec<usize>,
+    data: Vec<f32>,
+    marginal_cdf: Vec<f32>,
+    conditional_cdf: Vec<f32>
+}
+
+/// The grids that a lookup interpolates between, with their weights.
+struct Slices {
+    offsets: [usize; 1 << MAX_PARAMS],
+    weights: [f32; 1 << MAX_PARAMS],
+    count: usize
+}
+
+impl Slices {
+    /// Interpolates the value at `index` of the grids of `size` values each in `data`.
+    #[inline]
+    fn lookup(&self, data: &[f32], index: usize, size: usize) -> f32 {
+        (0..self.count).map(|i| self.weights[i] * data[self.offsets[i] * size + index]).sum()
+    }
+}
+
+impl PiecewiseLinear2D {
+    /// Creates the distribution from the `x_size` by `y_size` grids of values in `data`, which are stored in rows of
+    /// constant `y`. For every parameter, `param_values` holds the sorted values the grids are tabulated for, where
+    /// the grids of the last parameter are adjacent in `data`.
+    ///
+    /// If `normalize` is not set, the values are only interpolated by [`evaluate`](Self::evaluate) and the
+    /// distribution can not be sampled.
+    pub fn new(data: &[f32], x_size: usize, y_size: usize, param_values: Vec<Vec<f32>>, normalize: bool) -> Self {
+        assert!(x_size >= 2 && y_size >= 2);
+        assert!(param_values.len() <= MAX_PARAMS && param_values.iter().all(|v| !v.is_empty()));
+        let mut param_strides = vec![0; param_values.len()];
+        let mut slices = 1;
+        for (stride, values) in param_strides.iter_mut().zip(&param_values).rev() {
+            *stride = if values.len() > 1 { slices } else { 0 };
+            slices *= values.len();
+        }
+        let n_values = x_size * y_size;
+        assert_eq!(data.len(), slices * n_values);
+
+        let mut pwl = PiecewiseLinear2D {
+            x_size,
+            y_size,
+            param_values,
+            param_strides,
+            data: Vec::with_capacity(data.len()),
+            marginal_cdf: Vec::new(),
+            conditional_cdf: Vec::new()
+        };
+        if !normalize {
+            // Store the values as densities over [0, 1]^2, which evaluate() scales back
+            let scale = 1.0 / pwl.inv_patch_area();
+            pwl.data.extend(data.iter().map(|v| v * scale));
+            return pwl
+        }
+
+        pwl.marginal_cdf = vec![0.0; slices * y_size];
+        pwl.conditional_cdf = vec![0.0; slices * n_values];
+        for ((slice, conditional_cdf), marginal_cdf) in data.chunks_exact(n_values)
+            .zip(pwl.conditional_cdf.chunks_exact_mut(n_values))
+            .zip(pwl.marginal_cdf.chunks_exact_mut(y_size)) {
+            // Integrate the rows with the trapezoidal rule
+            for (row, cdf) in slice.chunks_exact(x_size).zip(conditional_cdf.chunks_exact_mut(x_size)) {
+                let mut sum = 0.0f64;
+                for x in 0..x_size - 1 {
+                    sum += 0.5 * (row[x] as f64 + row[x + 1] as f64);
+                    cdf[x + 1] = sum as f32;
+                }
+            }
+            let mut sum = 0.0f64;
+            for y in 0..y_size - 1 {
+                let (r0, r1) = (conditional_cdf[(y + 1) * x_size - 1], conditional_cdf[(y + 2) * x_size - 1]);
+                sum += 0.5 * (r0 as f64 + r1 as f64);
+                marginal_cdf[y + 1] = sum as f32;
+            }
+
+            let normalization = 1.0 / marginal_cdf[y_size - 1];
+            conditional_cdf.iter_mut().for_each(|c| *c *= normalization);
+            marginal_cdf.iter_mut().for_each(|c| *c *= normalization);
+            pwl.data.extend(slice.iter().map(|v| v * normalization));
+        }
+        pwl
+    }
+
+    /// Returns the ratio of the area of `[0, 1]^2` to the area of a grid cell.
+    #[inline]
+    fn inv_patch_area(&self) -> f32 {
+        ((self.x_size - 1) * (self.y_size - 1)) as f32
+    }
+
+    /// Finds the grids to interpolate between for the parameter values `params`.
+    fn slices(&self, params: &[f32]) -> Slices {
+        debug_assert_eq!(params.len(), self.param_values.len());
+        let mut slices = Slices { offsets: [0; 1 << MAX_PARAMS], weights: [0.0; 1 << MAX_PARAMS], count: 1 };
+        slices.weights[0] = 1.0;
+        for (dim, values) in self.param_values.iter().enumerate() {
+            if values.len() == 1 {
+                continue
+            }
+            let index = find_interval(values.len(), |i| values[i] <= params[dim]);
+            let (p0, p1) = (values[index], values[index + 1]);
+            let w1 = ((params[dim] - p0) / (p1 - p0)).clamp(0.0, 1.0);
+            let stride = self.param_strides[dim];
+            for i in 0..slices.count {
+                slices.offsets[i] += stride * index;
+                slices.offsets[i + slices.count] = slices.offsets[i] + stride;
+                slices.weights[i + slices.count] = slices.weights[i] * w1;
+                slices.weights[i] *= 1.0 - w1;
+            }
+            slices.count *= 2;
+        }
+        slices
+    }
+
+    /// Samples a point in `[0, 1]^2` for the parameter values `params`, returning it together with its density.
+    pub fn sample(&self, u: &Point2f, params: &[f32]) -> (Point2f, f32) {
+        debug_assert!(!self.marginal_cdf.is_empty(), "the distribution is not normalized");
+        let (nx, ny) = (self.x_size, self.y_size);
+        let slice_size = nx * ny;
+        let slices = self.slices(params);
+        // Avoid degeneracies at the extrema
+        let mut sx = u.x.clamp(1.0 - ONE_MINUS_EPSILON, ONE_MINUS_EPSILON);
+        let mut sy = u.y.clamp(1.0 - ONE_MINUS_EPSILON, ONE_MINUS_EPSILON);
+
+        // Sample the row first, inverting the integral of the linear density between the row sums r0 and r1
+        let row = find_interval(ny, |i| slices.lookup(&self.marginal_cdf, i, ny) < sy);
+        sy -= slices.lookup(&self.marginal_cdf, row, ny);
+        let offset = row * nx;
+        let r0 = slices.lookup(&self.conditional_cdf, offset + nx - 1, slice_size);
+        let r1 = slices.lookup(&self.conditional_cdf, offset + 2 * nx - 1, slice_size);
+        sy = if (r0 - r1).abs() < 1e-4 * (r0 + r1) {
+            2.0 * sy / (r0 + r1)
+        } else {
+            (r0 - safe_sqrt(r0 * r0 - 2.0 * sy * (r0 - r1))) / (r0 - r1)
+        };
+
+        // Sample the column within the interpolated row
+        sx *= (1.0 - sy) * r0 + sy * r1;
+        let conditional = |i| {
+            let v0 = slices.lookup(&self.conditional_cdf, offset + i, slice_size);
+            let v1 = slices.lookup(&self.conditional_cdf, offset + nx + i, slice_size);
+            (1.0 - sy) * v0 + sy * v1
+        };
+        let col = find_interval(nx, |i| conditional(i) < sx);
+        sx -= conditional(col);
+        let offset = offset + col;
+        let v00 = slices.lookup(&self.data, offset, slice_size);
+        let v10 = slices.lookup(&self.data, offset + 1, slice_size);
+        let v01 = slices.lookup(&self.data, offset + nx, slice_size);
+        let v11 = slices.lookup(&self.data, offset + nx + 1, slice_size);
+        let c0 = (1.0 - sy) * v00 + sy * v01;
+        let c1 = (1.0 - sy) * v10 + sy * v11;
+        sx = if (c0 - c1).abs() < 1e-4 * (c0 + c1) {
+            2.0 * sx / (c0 + c1)
+        } else {
+            (c0 - safe_sqrt(c0 * c0 - 2.0 * sx * (c0 - c1))) / (c0 - c1)
+        };
+
+        let p = point2((col as f32 + sx) / (nx - 1) as f32, (row as f32 + sy) / (ny - 1) as f32);
+        (p, ((1.0 - sx) * c0 + sx * c1) * self.inv_patch_area())
+    }
+
+    /// Returns the sample that [`sample`](Self::sample) maps to `p` for the parameter values `params`, together with
+    /// the density of `p`.
+    pub fn invert(&self, p: &Point2f, params: &[f32]) -> (Point2f, f32) {
+        debug_assert!(!self.marginal_cdf.is_empty(), "the distribution is not normalized");
+        let (nx, ny) = (self.x_size, self.y_size);
+        let slice_size = nx * ny;
+        let slices = self.slices(params);
+        let (x, y) = (p.x * (nx - 1) as f32, p.y * (ny - 1) as f32);
+        let (col, row) = (usize::min(x as usize, nx - 2), usize::min(y as usize, ny - 2));
+        let (mut sx, mut sy) = (x - col as f32, y - row as f32);
+
+        // Invert the sampling of the column
+        let offset = row * nx + col;
+        let v00 = slices.lookup(&self.data, offset, slice_size);
+        let v10 = slices.lookup(&self.data, offset + 1, slice_size);
+        let v01 = slices.lookup(&self.data, offset + nx, slice_size);
+        let v11 = slices.lookup(&self.data, offset + nx + 1, slice_size);
+        let c0 = (1.0 - sy) * v00 + sy * v01;
+        let c1 = (1.0 - sy) * v10 + sy * v11;
+        let pdf = (1.0 - sx) * c0 + sx * c1;
+        sx *= c0 + 0.5 * sx * (c1 - c0);
+        let v0 = slices.lookup(&self.conditional_cdf, offset, slice_size);
+        let v1 = slices.lookup(&self.conditional_cdf, offset + nx, slice_size);
+        sx += (1.0 - sy) * v0 + sy * v1;
+        let offset = row * nx;
+        let r0 = slices.lookup(&self.conditional_cdf, offset + nx - 1, slice_size);
+        let r1 = slices.lookup(&self.conditional_cdf, offset + 2 * nx - 1, slice_size);
+        sx /= (1.0 - sy) * r0 + sy * r1;
+
+        // Invert the sampling of the row
+        sy *= r0 + 0.5 * sy * (r1 - r0);
+        sy += slices.lookup(&self.marginal_cdf, row, ny);
+        (point2(sx, sy), pdf * self.inv_patch_area())
+    }
+
+    /// Evaluates the bilinear interpolant at `p` for the parameter values `params`, which is the density of `p` if
+    /// the distribution is normalized.
+    pub fn evaluate(&self, p: &Point2f, params: &[f32]) -> f32 {
+        let (nx, ny) = (self.x_size, self.y_size);
+        let slices = self.slices(params);
+        let (x, y) = (p.x * (nx - 1) as f32, p.y * (ny - 1) as f32);
+        let (col, row) = (usize::min(x as usize, nx - 2), usize::min(y as usize, ny - 2));
+        let (wx, wy) = (x - col as f32, y - row as f32);
+
+        let index = row * nx + col;
+        let v00 = slices.lookup(&self.data, index, nx * ny);
+        let v10 = slices.lookup(&self.data, index + 1, nx * ny);
+        let v01 = slices.lookup(&self.data, index + nx, nx * ny);
+        let v11 = slices.lookup(&self.data, index + nx + 1, nx * ny);
+        let v = (1.0 - wy) * ((1.0 - wx) * v00 + wx * v10) + wy * ((1.0 - wx) * v01 + wx * v11);
+        v * self.inv_patch_area()
+    }
+}
+
+/// Returns the index `i` in `[0, size - 2]` such that `pred` holds for `i` but not for `i + 1`, for a predicate that
+/// holds for a prefix of the indices, clamping the result if it holds for none or all of them.
+fn find_interval(size: usize, pred: impl Fn(usize) -> bool) -> usize {
+    let mut first = 1;
+    let mut len = size as isize - 2;
+    while len > 0 {
+        let half = len as usize >> 1;
+        let middle = first + half;
+        if pred(middle) {
+            first = middle + 1;
+            len -= half as isize + 1;
+        } else {
+            len = half as isize;
+        }
+    }
+    (first as isize - 1).clamp(0, size as isize - 2) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A distribution over `[0, 1]^2` that changes with one parameter in `[0, 1]`.
+    fn distribution() -> PiecewiseLinear2D {
+        let (nx, ny) = (5, 4);
+        let data: Vec<f32> = (0..2 * nx * ny)
+            .map(|i| {
+                let (slice, x, y) = (i / (nx * ny), i % nx, i / nx % ny);
+                1.0 + (x * y) as f32 + if slice == 1 { 5.0 * (x == 1) as u8 as f32 } else { 0.0 }
+            })
+            .collect();
+        PiecewiseLinear2D::new(&data, nx, ny, vec![vec![0.0, 1.0]], true)
+    }
+
+    #[test]
+    fn test_sample_inverts_and_matches_density() {
+        let distrib = distribution();
+        for param in [0.0, 0.3, 1.0] {
+            for i in 0..100 {
+                let u = point2((i % 10) as f32 / 10.0 + 0.05, (i / 10) as f32 / 10.0 + 0.03);
+                let (p, pdf) = distrib.sample(&u, &[param]);
+                assert!((0.0..=1.0).contains(&p.x) && (0.0..=1.0).contains(&p.y));
+                assert!((pdf - distrib.evaluate(&p, &[param])).abs() < 1e-3 * pdf);
+                let (v, inv_pdf) = distrib.invert(&p, &[param]);
+                assert!((v.x - u.x).abs() < 1e-3 && (v.y - u.y).abs() < 1e-3, "{:?} {:?}", u, v);
+                assert!((inv_pdf - pdf).abs() < 1e-3 * pdf);
+            }
+        }
+    }
+
+    #[test]
+    fn test_density_is_normalized() {
+        let distrib = distribution();
+        let n = 200;
+        for param in [0.0, 0.6] {
+            let integral: f32 = (0..n * n)
+                .map(|i| distrib.evaluate(&point2(((i % n) as f32 + 0.5) / n as f32, ((i / n) as f32 + 0.5) / n as f32),
+                                          &[param]))
+                .sum::<f32>() / (n * n) as f32;
+            assert!((integral - 1.0).abs() < 1e-3, "{}", integral);
+        }
+    }
+}
@@ -1,9 +1,36 @@
 use crate::bounds::Bounds3;
+use crate::interaction::SurfaceInteraction;
 use crate::Ray;
 
-pub trait Shape {
+mod sphere;
+mod triangle;
+
+pub use sphere::*;
+pub use triangle::*;
+
+pub trait Shape: Send + Sync {
     fn object_bound(&self) -> Bounds3<f32>;
     fn world_bound(&self) -> Bounds3<f32>;
-    fn intersect(&self, ray: &Ray, hit: *mut f32, /* surface_interaction: SurfaceInteraction, test_alpha_texture: bool */) -> bool;
+
+    /// Intersects the shape with `ray`.
+    ///
+    /// On a hit closer than `ray.tmax`, the parametric distance is written to `t_hit`, the local differential
+    /// geometry of the hit point is written to `interaction` and `true` is returned.
+    fn intersect(&self, ray: &Ray, t_hit: &mut f32, interaction: &mut SurfaceInteraction, test_alpha_texture: bool) -> bool;
+
+    /// Tests whether `ray` intersects the shape without computing the surface interaction.
+    fn intersect_p(&self, ray: &Ray, test_alpha_texture: bool) -> bool {
+        let mut t_hit = ray.tmax.get();
+        let mut interaction = SurfaceInteraction::default();
+        self.intersect(ray, &mut t_hit, &mut interaction, test_alpha_texture)
+    }
+
     fn area(&self) -> f32;
-}
\ No newline at end of file
+
+    /// Returns whether the normals of the shape point to the inside instead of the outside.
+    fn reverse_orientation(&self) -> bool;
+
+    /// Returns whether the object to world transformation of the shape changes the handedness of the coordinate
+    /// system.
+    fn transform_swaps_handedness(&self) -> bool;
+}
@@ -0,0 +1,241 @@
+use std::f32::consts::PI;
+use crate::bounds::Bounds3;
+use crate::geom::{DotProduct, Normal3};
+use crate::interaction::SurfaceInteraction;
+use crate::math::{gamma, quadratic, safe_acos, safe_sqrt};
+use crate::shape::Shape;
+use crate::{Bounds3f, Point2, Point3, Point3f, Ray, Transform, Vector3};
+
+/// A sphere centered at the origin of its object space.
+///
+/// The sphere can be clipped to a partial sweep by limiting its z-range and the maximum azimuthal angle `phi_max`.
+#[derive(Debug, Clone)]
+pub struct Sphere {
+    object_to_world: Transform,
+    world_to_object: Transform,
+    reverse_orientation: bool,
+    transform_swaps_handedness: bool,
+    radius: f32,
+    z_min: f32,
+    z_max: f32,
+    theta_z_min: f32,
+    theta_z_max: f32,
+    phi_max: f32
+}
+
+impl Sphere {
+    /// Creates a new sphere.
+    ///
+    /// `z_min` and `z_max` are clamped to `[-radius, radius]` and `phi_max` is given in degrees and clamped to `[0, 360]`.
+    pub fn new(object_to_world: Transform, reverse_orientation: bool, radius: f32, z_min: f32, z_max: f32, phi_max: f32) -> Self {
+        let (z_min, z_max) = (
+            f32::min(z_min, z_max).clamp(-radius, radius),
+            f32::max(z_min, z_max).clamp(-radius, radius)
+        );
+        Sphere {
+            world_to_object: object_to_world.inverse(),
+            transform_swaps_handedness: object_to_world.swaps_handedness(),
+            object_to_world,
+            reverse_orientation,
+            radius,
+            z_min,
+            z_max,
+            theta_z_min: (z_min / radius).clamp(-1.0, 1.0).acos(),
+            theta_z_max: (z_max / radius).clamp(-1.0, 1.0).acos(),
+            phi_max: phi_max.clamp(0.0, 360.0).to_radians()
+        }
+    }
+
+    /// Creates a full sphere with the given radius.
+    pub fn full(object_to_world: Transform, reverse_orientation: bool, radius: f32) -> Self {
+        Self::new(object_to_world, reverse_orientation, radius, -radius, radius, 360.0)
+    }
+
+    #[inline]
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Finds the closest hit of an object space ray against the clipped sphere and returns its parametric distance,
+    /// the hit point and its azimuthal angle.
+    fn hit(&self, ray: &Ray) -> Option<(f32, Point3f, f32)> {
+        let (ox, oy, oz) = (ray.o.x as f64, ray.o.y as f64, ray.o.z as f64);
+        let (dx, dy, dz) = (ray.d.x as f64, ray.d.y as f64, ray.d.z as f64);
+        let radius = self.radius as f64;
+        let a = dx * dx + dy * dy + dz * dz;
+        let b = 2.0 * (dx * ox + dy * oy + dz * oz);
+        let c = ox * ox + oy * oy + oz * oz - radius * radius;
+
+        let (t0, t1) = quadratic(a, b, c)?;
+        let tmax = ray.tmax.get() as f64;
+        if t0 > tmax || t1 <= 0.0 {
+            return None
+        }
+
+        for t in [t0, t1] {
+            if t <= 0.0 {
+                continue
+            }
+            if t > tmax {
+                return None
+            }
+            let t = t as f32;
+            let (p_hit, phi) = self.hit_point(ray, t);
+            let clipped = (self.z_min > -self.radius && p_hit.z < self.z_min) ||
+                (self.z_max < self.radius && p_hit.z > self.z_max) ||
+                phi > self.phi_max;
+            if !clipped {
+                return Some((t, p_hit, phi))
+            }
+        }
+        None
+    }
+
+    /// Computes the hit point at `t` reprojected onto the sphere surface together with its azimuthal angle.
+    fn hit_point(&self, ray: &Ray, t: f32) -> (Point3f, f32) {
+        let mut p_hit = ray.at(t);
+        // Refine the hit point by reprojecting it onto the surface
+        let scale = self.radius / Point3::distance(&p_hit, &Point3::new(0.0, 0.0, 0.0));
+        p_hit = Point3::new(p_hit.x * scale, p_hit.y * scale, p_hit.z * scale);
+        if p_hit.x == 0.0 && p_hit.y == 0.0 {
+            p_hit.x = 1e-5 * self.radius;
+        }
+        let mut phi = p_hit.y.atan2(p_hit.x);
+        if phi < 0.0 {
+            phi += 2.0 * PI;
+        }
+        (p_hit, phi)
+    }
+}
+
+impl Shape for Sphere {
+    fn object_bound(&self) -> Bounds3<f32> {
+        Bounds3::from((
+            Point3::new(-self.radius, -self.radius, self.z_min),
+            Point3::new(self.radius, self.radius, self.z_max)
+        ))
+    }
+
+    fn world_bound(&self) -> Bounds3f {
+        self.object_to_world.transform_bounds(&self.object_bound())
+    }
+
+    fn intersect(&self, r: &Ray, t_hit: &mut f32, interaction: &mut SurfaceInteraction, _test_alpha_texture: bool) -> bool {
+        let ray = self.world_to_object.transform_ray(r);
+        let (t, p_hit, phi) = match self.hit(&ray) {
+            Some(hit) => hit,
+            None => return false
+        };
+
+        // Parametric representation of the sphere hit
+        let u = phi / self.phi_max;
+        let cos_theta = p_hit.z / self.radius;
+        let theta = safe_acos(cos_theta);
+        let v = (theta - self.theta_z_min) / (self.theta_z_max - self.theta_z_min);
+
+        // Partial derivatives of the surface position
+        let z_radius = (p_hit.x * p_hit.x + p_hit.y * p_hit.y).sqrt();
+        let cos_phi = p_hit.x / z_radius;
+        let sin_phi = p_hit.y / z_radius;
+        let theta_range = self.theta_z_max - self.theta_z_min;
+        let dpdu = Vector3::new(-self.phi_max * p_hit.y, self.phi_max * p_hit.x, 0.0);
+        let sin_theta = safe_sqrt(1.0 - cos_theta * cos_theta);
+        let dpdv = Vector3::new(p_hit.z * cos_phi, p_hit.z * sin_phi, -self.radius * sin_theta) * theta_range;
+
+        // Partial derivatives of the normal from the Weingarten equations
+        let d2pduu = Vector3::new(p_hit.x, p_hit.y, 0.0) * (-self.phi_max * self.phi_max);
+        let d2pduv = Vector3::new(-sin_phi, cos_phi, 0.0) * (theta_range * p_hit.z * self.phi_max);
+        let d2pdvv = Vector3::new(p_hit.x, p_hit.y, p_hit.z) * (-theta_range * theta_range);
+        let e1 = dpdu.dot(&dpdu);
+        let f1 = dpdu.dot(&dpdv);
+        let g1 = dpdv.dot(&dpdv);
+        let n = dpdu.cross(&dpdv).normalize();
+        let e2 = n.dot(&d2pduu);
+        let f2 = n.dot(&d2pduv);
+        let g2 = n.dot(&d2pdvv);
+        let inv_egf2 = 1.0 / (e1 * g1 - f1 * f1);
+        let dndu = Normal3::from(
+            dpdu * ((f2 * f1 - e2 * g1) * inv_egf2) + dpdv * ((e2 * f1 - f2 * e1) * inv_egf2)
+        );
+        let dndv = Normal3::from(
+            dpdu * ((g2 * f1 - f2 * g1) * inv_egf2) + dpdv * ((f2 * f1 - g2 * e1) * inv_egf2)
+        );
+
+        // The reprojected hit point is within gamma(5) of the true surface
+        let p_error = Vector3::from(p_hit.abs()) * gamma(5);
+
+        let local = SurfaceInteraction::new(
+            p_hit,
+            p_error,
+            Point2::new(u, v),
+            -ray.d,
+            dpdu,
+            dpdv,
+            dndu,
+            dndv,
+            ray.time,
+            self.reverse_orientation ^ self.transform_swaps_handedness
+        );
+        *interaction = self.object_to_world.transform_surface_interaction(&local);
+        *t_hit = t;
+        true
+    }
+
+    fn intersect_p(&self, r: &Ray, _test_alpha_texture: bool) -> bool {
+        let ray = self.world_to_object.transform_ray(r);
+        self.hit(&ray).is_some()
+    }
+
+    fn area(&self) -> f32 {
+        self.phi_max * self.radius * (self.z_max - self.z_min)
+    }
+
+    fn reverse_orientation(&self) -> bool {
+        self.reverse_orientation
+    }
+
+    fn transform_swaps_handedness(&self) -> bool {
+        self.transform_swaps_handedness
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vec3;
+    use crate::interaction::Interaction;
+
+    #[test]
+    fn test_ray_hits_sphere() {
+        let sphere = Sphere::full(Transform::translate(vec3(0.0, 0.0, 5.0)), false, 1.0);
+        let ray = Ray::new(Point3::new(0.0, 0.0, 0.0), vec3(0.0, 0.0, 1.0));
+        let mut t_hit = 0.0;
+        let mut interaction = SurfaceInteraction::default();
+        assert!(sphere.intersect(&ray, &mut t_hit, &mut interaction, false));
+        assert!((t_hit - 4.0).abs() < 1e-4);
+        assert!((interaction.p.z - 4.0).abs() < 1e-4);
+        assert!(interaction.n.z.abs() > 0.999);
+        // The spawned ray must not hit the sphere it leaves from
+        let r = interaction.spawn_ray(&vec3(0.0, 0.0, -1.0));
+        assert!(!sphere.intersect_p(&r, false));
+    }
+
+    #[test]
+    fn test_partial_sphere_is_clipped() {
+        let sphere = Sphere::new(Transform::translate(vec3(0.0, 0.0, 0.0)), false, 1.0, -1.0, 0.5, 360.0);
+        let ray = Ray::new(Point3::new(0.0, 0.0, 5.0), vec3(0.0, 0.0, -1.0));
+        let mut t_hit = 0.0;
+        let mut interaction = SurfaceInteraction::default();
+        // The cap above z = 0.5 is clipped, so the ray passes through and hits the inside of the bottom
+        assert!(sphere.intersect(&ray, &mut t_hit, &mut interaction, false));
+        assert!((t_hit - 6.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_sphere_area() {
+        let sphere = Sphere::full(Transform::scale(1.0, 1.0, 1.0), false, 2.0);
+        assert!((sphere.area() - 4.0 * PI * 4.0).abs() < 1e-3);
+        let hemisphere = Sphere::new(Transform::scale(1.0, 1.0, 1.0), false, 2.0, 0.0, 2.0, 180.0);
+        assert!((hemisphere.area() - PI * 4.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,414 @@
+use std::sync::Arc;
+use crate::bounds::Bounds3;
+use crate::geom::Normal3;
+use crate::interaction::SurfaceInteraction;
+use crate::math::gamma;
+use crate::shape::Shape;
+use crate::{Bounds3f, Normal3f, Point2, Point2f, Point3f, Ray, Transform, Vector3, Vector3f};
+
+/// Vertex data shared by all triangles of a mesh.
+///
+/// Vertex positions, normals and tangents are transformed to world space once when the mesh is created.
+#[derive(Debug)]
+pub struct TriangleMesh {
+    world_to_object: Transform,
+    reverse_orientation: bool,
+    transform_swaps_handedness: bool,
+    vertex_indices: Vec<u32>,
+    p: Vec<Point3f>,
+    n: Option<Vec<Normal3f>>,
+    s: Option<Vec<Vector3f>>,
+    uv: Option<Vec<Point2f>>
+}
+
+impl TriangleMesh {
+    /// Creates a new mesh from object space vertex data.
+    ///
+    /// Every three consecutive entries of `vertex_indices` describe one triangle. The optional per-vertex normals,
+    /// tangents and texture coordinates must have the same length as `p`.
+    pub fn new(
+        object_to_world: &Transform,
+        reverse_orientation: bool,
+        vertex_indices: Vec<u32>,
+        p: Vec<Point3f>,
+        n: Option<Vec<Normal3f>>,
+        s: Option<Vec<Vector3f>>,
+        uv: Option<Vec<Point2f>>
+    ) -> Arc<TriangleMesh> {
+        assert_eq!(vertex_indices.len() % 3, 0, "vertex index count must be a multiple of 3");
+        assert!(vertex_indices.iter().all(|&i| (i as usize) < p.len()), "vertex index out of bounds");
+        assert!(n.as_ref().map_or(true, |n| n.len() == p.len()));
+        assert!(s.as_ref().map_or(true, |s| s.len() == p.len()));
+        assert!(uv.as_ref().map_or(true, |uv| uv.len() == p.len()));
+
+        let p = p.iter().map(|p| object_to_world.transform_point(p)).collect();
+        let n = n.map(|n| n.iter().map(|n| object_to_world.transform_normal(n)).collect());
+        let s = s.map(|s| s.iter().map(|s| object_to_world.transform_vector(s)).collect());
+        Arc::new(TriangleMesh {
+            world_to_object: object_to_world.inverse(),
+            reverse_orientation,
+            transform_swaps_handedness: object_to_world.swaps_handedness(),
+            vertex_indices,
+            p,
+            n,
+            s,
+            uv
+        })
+    }
+
+    #[inline]
+    pub fn num_triangles(&self) -> usize {
+        self.vertex_indices.len() / 3
+    }
+
+    #[inline]
+    pub fn num_vertices(&self) -> usize {
+        self.p.len()
+    }
+
+    /// Creates the triangle shapes that reference this mesh.
+    pub fn triangles(self: &Arc<Self>) -> Vec<Triangle> {
+        (0..self.num_triangles())
+            .map(|index| Triangle { mesh: self.clone(), index })
+            .collect()
+    }
+}
+
+/// A single triangle of a [`TriangleMesh`].
+#[derive(Debug, Clone)]
+pub struct Triangle {
+    mesh: Arc<TriangleMesh>,
+    index: usize
+}
+
+impl Triangle {
+    #[inline]
+    pub fn mesh(&self) -> &Arc<TriangleMesh> {
+        &self.mesh
+    }
+
+    #[inline]
+    fn vertices(&self) -> [usize; 3] {
+        let v = &self.mesh.vertex_indices[3 * self.index..3 * self.index + 3];
+        [v[0] as usize, v[1] as usize, v[2] as usize]
+    }
+
+    #[inline]
+    fn positions(&self) -> [Point3f; 3] {
+        let [v0, v1, v2] = self.vertices();
+        [self.mesh.p[v0], self.mesh.p[v1], self.mesh.p[v2]]
+    }
+
+    /// Returns the texture coordinates of the vertices, or a default parameterization if the mesh has none.
+    #[inline]
+    fn uvs(&self) -> [Point2f; 3] {
+        match &self.mesh.uv {
+            Some(uv) => {
+                let [v0, v1, v2] = self.vertices();
+                [uv[v0], uv[v1], uv[v2]]
+            },
+            None => [Point2::new(0.0, 0.0), Point2::new(1.0, 0.0), Point2::new(1.0, 1.0)]
+        }
+    }
+
+    /// Watertight ray-triangle intersection test.
+    ///
+    /// Returns the parametric distance of the hit and the barycentric coordinates of the hit point.
+    fn hit(&self, ray: &Ray) -> Option<(f32, [f32; 3])> {
+        let [p0, p1, p2] = self.positions();
+
+        // Transform the triangle vertices to a coordinate system where the ray starts at the origin
+        // and points along +z
+        let o = Vector3f::from(ray.o);
+        let mut p0t = Vector3f::from(p0) - o;
+        let mut p1t = Vector3f::from(p1) - o;
+        let mut p2t = Vector3f::from(p2) - o;
+        let kz = ray.d.abs().max_dimension();
+        let kx = if kz + 1 == 3 { 0 } else { kz + 1 };
+        let ky = if kx + 1 == 3 { 0 } else { kx + 1 };
+        let d = ray.d.permute(kx, ky, kz);
+        p0t = p0t.permute(kx, ky, kz);
+        p1t = p1t.permute(kx, ky, kz);
+        p2t = p2t.permute(kx, ky, kz);
+
+        // Apply the shear transformation to the x and y coordinates, the z coordinates are sheared lazily
+        let sx = -d.x / d.z;
+        let sy = -d.y / d.z;
+        let sz = 1.0 / d.z;
+        p0t.x += sx * p0t.z;
+        p0t.y += sy * p0t.z;
+        p1t.x += sx * p1t.z;
+        p1t.y += sy * p1t.z;
+        p2t.x += sx * p2t.z;
+        p2t.y += sy * p2t.z;
+
+        // Edge functions
+        let mut e0 = p1t.x * p2t.y - p1t.y * p2t.x;
+        let mut e1 = p2t.x * p0t.y - p2t.y * p0t.x;
+        let mut e2 = p0t.x * p1t.y - p0t.y * p1t.x;
+
+        // Fall back to double precision if the ray passes exactly through an edge
+        if e0 == 0.0 || e1 == 0.0 || e2 == 0.0 {
+            e0 = (p1t.x as f64 * p2t.y as f64 - p1t.y as f64 * p2t.x as f64) as f32;
+            e1 = (p2t.x as f64 * p0t.y as f64 - p2t.y as f64 * p0t.x as f64) as f32;
+            e2 = (p0t.x as f64 * p1t.y as f64 - p0t.y as f64 * p1t.x as f64) as f32;
+        }
+
+        if (e0 < 0.0 || e1 < 0.0 || e2 < 0.0) && (e0 > 0.0 || e1 > 0.0 || e2 > 0.0) {
+            return None
+        }
+        let det = e0 + e1 + e2;
+        if det == 0.0 {
+            return None
+        }
+
+        // Compute the scaled hit distance and test it against the ray's parametric range
+        p0t.z *= sz;
+        p1t.z *= sz;
+        p2t.z *= sz;
+        let t_scaled = e0 * p0t.z + e1 * p1t.z + e2 * p2t.z;
+        let tmax = ray.tmax.get();
+        let out_of_range = if det < 0.0 {
+            t_scaled >= 0.0 || t_scaled < tmax * det
+        } else {
+            t_scaled <= 0.0 || t_scaled > tmax * det
+        };
+        if out_of_range {
+            return None
+        }
+
+        let inv_det = 1.0 / det;
+        let b = [e0 * inv_det, e1 * inv_det, e2 * inv_det];
+        let t = t_scaled * inv_det;
+
+        // Ensure that the hit distance is conservatively greater than zero
+        let max_zt = Vector3::new(p0t.z, p1t.z, p2t.z).abs().max_component();
+        let delta_z = gamma(3) * max_zt;
+        let max_xt = Vector3::new(p0t.x, p1t.x, p2t.x).abs().max_component();
+        let max_yt = Vector3::new(p0t.y, p1t.y, p2t.y).abs().max_component();
+        let delta_x = gamma(5) * (max_xt + max_zt);
+        let delta_y = gamma(5) * (max_yt + max_zt);
+        let delta_e = 2.0 * (gamma(2) * max_xt * max_yt + delta_y * max_xt + delta_x * max_yt);
+        let max_e = Vector3::new(e0, e1, e2).abs().max_component();
+        let delta_t = 3.0 * (gamma(3) * max_e * max_zt + delta_e * max_zt + delta_z * max_e) * inv_det.abs();
+        if t <= delta_t {
+            return None
+        }
+
+        Some((t, b))
+    }
+}
+
+impl Shape for Triangle {
+    fn object_bound(&self) -> Bounds3<f32> {
+        let [p0, p1, p2] = self.positions();
+        let w2o = &self.mesh.world_to_object;
+        Bounds3::new(w2o.transform_point(&p0), w2o.transform_point(&p1))
+            .union_point(&w2o.transform_point(&p2))
+    }
+
+    fn world_bound(&self) -> Bounds3f {
+        let [p0, p1, p2] = self.positions();
+        Bounds3::new(p0, p1).union_point(&p2)
+    }
+
+    fn intersect(&self, ray: &Ray, t_hit: &mut f32, interaction: &mut SurfaceInteraction, _test_alpha_texture: bool) -> bool {
+        let (t, [b0, b1, b2]) = match self.hit(ray) {
+            Some(hit) => hit,
+            None => return false
+        };
+        let [p0, p1, p2] = self.positions();
+        let [uv0, uv1, uv2] = self.uvs();
+
+        // Partial derivatives of the surface position
+        let duv02 = uv0 - uv2;
+        let duv12 = uv1 - uv2;
+        let dp02 = p0 - p2;
+        let dp12 = p1 - p2;
+        let determinant = duv02.x * duv12.y - duv02.y * duv12.x;
+        let degenerate_uv = determinant.abs() < 1e-8;
+        let (mut dpdu, mut dpdv) = (Vector3f::default(), Vector3f::default());
+        if !degenerate_uv {
+            let inv_det = 1.0 / determinant;
+            dpdu = (dp02 * duv12.y - dp12 * duv02.y) * inv_det;
+            dpdv = (dp12 * duv02.x - dp02 * duv12.x) * inv_det;
+        }
+        if degenerate_uv || dpdu.cross(&dpdv).length_squared() == 0.0 {
+            // Pick an arbitrary coordinate system about the triangle normal
+            let ng = (p2 - p0).cross(&(p1 - p0));
+            if ng.length_squared() == 0.0 {
+                return false
+            }
+            (dpdu, dpdv) = ng.normalize().coordinate_system();
+        }
+
+        // Hit point and conservative bounds on its error
+        let x_abs_sum = (b0 * p0.x).abs() + (b1 * p1.x).abs() + (b2 * p2.x).abs();
+        let y_abs_sum = (b0 * p0.y).abs() + (b1 * p1.y).abs() + (b2 * p2.y).abs();
+        let z_abs_sum = (b0 * p0.z).abs() + (b1 * p1.z).abs() + (b2 * p2.z).abs();
+        let p_error = Vector3::new(x_abs_sum, y_abs_sum, z_abs_sum) * gamma(7);
+        let p_hit = Point3f::from(Vector3f::from(p0) * b0 + Vector3f::from(p1) * b1 + Vector3f::from(p2) * b2);
+        let uv_hit = Point2::new(
+            b0 * uv0.x + b1 * uv1.x + b2 * uv2.x,
+            b0 * uv0.y + b1 * uv1.y + b2 * uv2.y
+        );
+
+        let mut si = SurfaceInteraction::new(
+            p_hit,
+            p_error,
+            uv_hit,
+            -ray.d,
+            dpdu,
+            dpdv,
+            Normal3::default(),
+            Normal3::default(),
+            ray.time,
+            false
+        );
+        // Override the surface normal with the true geometric normal of the triangle
+        let flip = self.mesh.reverse_orientation ^ self.mesh.transform_swaps_handedness;
+        si.n = Normal3::from(dp02.cross(&dp12).normalize());
+        if flip {
+            si.n = -si.n;
+        }
+        si.shading.n = si.n;
+
+        if self.mesh.n.is_some() || self.mesh.s.is_some() {
+            let [v0, v1, v2] = self.vertices();
+
+            // Interpolated shading normal
+            let ns = match &self.mesh.n {
+                Some(n) => {
+                    let ns = n[v0] * b0 + n[v1] * b1 + n[v2] * b2;
+                    if ns.dot(&ns) > 0.0 { ns.normalize() } else { si.n }
+                },
+                None => si.n
+            };
+
+            // Shading tangent and bitangent
+            let mut ss = match &self.mesh.s {
+                Some(s) => {
+                    let ss = s[v0] * b0 + s[v1] * b1 + s[v2] * b2;
+                    if ss.length_squared() > 0.0 { ss } else { si.dpdu }
+                },
+                None => si.dpdu
+            }.normalize();
+            let mut ts = ss.cross(&Vector3f::from(ns));
+            if ts.length_squared() > 0.0 {
+                ts = ts.normalize();
+                ss = ts.cross(&Vector3f::from(ns));
+            } else {
+                (ss, ts) = Vector3f::from(ns).coordinate_system();
+            }
+
+            // Partial derivatives of the interpolated normal
+            let (dndu, dndv) = match &self.mesh.n {
+                Some(n) => {
+                    let dn1 = Vector3f::from(n[v0] - n[v2]);
+                    let dn2 = Vector3f::from(n[v1] - n[v2]);
+                    if degenerate_uv {
+                        let dn = Vector3f::from(n[v2] - n[v0]).cross(&Vector3f::from(n[v1] - n[v0]));
+                        if dn.length_squared() == 0.0 {
+                            (Normal3::default(), Normal3::default())
+                        } else {
+                            let (dnu, dnv) = dn.coordinate_system();
+                            (Normal3::from(dnu), Normal3::from(dnv))
+                        }
+                    } else {
+                        let inv_det = 1.0 / determinant;
+                        (
+                            Normal3::from((dn1 * duv12.y - dn2 * duv02.y) * inv_det),
+                            Normal3::from((dn2 * duv02.x - dn1 * duv12.x) * inv_det)
+                        )
+                    }
+                },
+                None => (Normal3::default(), Normal3::default())
+            };
+            if self.mesh.reverse_orientation {
+                ts = -ts;
+            }
+
+            // The interpolated normals define the orientation of the surface
+            si.shading.n = Normal3::from(ss.cross(&ts).normalize());
+            si.n = si.n.face_forward(&si.shading.n);
+            si.shading.dpdu = ss;
+            si.shading.dpdv = ts;
+            si.shading.dndu = dndu;
+            si.shading.dndv = dndv;
+        }
+
+        *interaction = si;
+        *t_hit = t;
+        true
+    }
+
+    fn intersect_p(&self, ray: &Ray, _test_alpha_texture: bool) -> bool {
+        self.hit(ray).is_some()
+    }
+
+    fn area(&self) -> f32 {
+        let [p0, p1, p2] = self.positions();
+        0.5 * (p1 - p0).cross(&(p2 - p0)).length()
+    }
+
+    fn reverse_orientation(&self) -> bool {
+        self.mesh.reverse_orientation
+    }
+
+    fn transform_swaps_handedness(&self) -> bool {
+        self.mesh.transform_swaps_handedness
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Point3, vec3};
+
+    fn quad() -> Arc<TriangleMesh> {
+        TriangleMesh::new(
+            &Transform::translate(vec3(0.0, 0.0, 2.0)),
+            false,
+            vec![0, 1, 2, 0, 2, 3],
+            vec![
+                Point3::new(-1.0, -1.0, 0.0),
+                Point3::new(1.0, -1.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(-1.0, 1.0, 0.0)
+            ],
+            None,
+            None,
+            None
+        )
+    }
+
+    #[test]
+    fn test_ray_hits_triangle() {
+        let triangles = quad().triangles();
+        let ray = Ray::new(Point3::new(0.5, -0.5, 0.0), vec3(0.0, 0.0, 1.0));
+        let mut t_hit = 0.0;
+        let mut interaction = SurfaceInteraction::default();
+        assert!(triangles[0].intersect(&ray, &mut t_hit, &mut interaction, false));
+        assert!((t_hit - 2.0).abs() < 1e-5);
+        assert!((interaction.p.z - 2.0).abs() < 1e-5);
+        assert!(!triangles[1].intersect_p(&ray, false));
+    }
+
+    #[test]
+    fn test_shared_edge_is_watertight() {
+        let triangles = quad().triangles();
+        // The ray passes exactly through the diagonal shared by both triangles
+        for i in 0..16 {
+            let x = -0.9 + i as f32 * 0.12;
+            let ray = Ray::new(Point3::new(x, x, 0.0), vec3(0.0, 0.0, 1.0));
+            assert!(triangles.iter().any(|t| t.intersect_p(&ray, false)), "ray at {x} leaked through the mesh");
+        }
+    }
+
+    #[test]
+    fn test_triangle_area() {
+        let triangles = quad().triangles();
+        assert!((triangles[0].area() - 2.0).abs() < 1e-6);
+        assert!((triangles.iter().map(Shape::area).sum::<f32>() - 4.0).abs() < 1e-6);
+    }
+}
@@ -1,6 +1,133 @@
-use std::ops::*;
+//! Representations of spectral power distributions.
 
-#[derive(Debug, PartialEq, Copy, Clone)]
-pub struct SampledSpectrum {
-    c: [f32; 60]
+mod blackbody;
+mod cie;
+pub(crate) mod macros;
+mod metal;
+mod named;
+mod rgb;
+mod sampled;
+mod std_illuminant;
+mod upsample;
+mod wavelengths;
+
+pub use blackbody::*;
+pub use cie::*;
+pub use metal::*;
+pub use named::*;
+pub use rgb::*;
+pub use sampled::*;
+pub use std_illuminant::*;
+pub use upsample::*;
+pub use wavelengths::*;
+
+/// The spectrum representation used by the renderer, selected at build time with the `spectral` feature.
+#[cfg(not(feature = "spectral"))]
+pub type Spectrum = RgbSpectrum;
+/// The spectrum representation used by the renderer, selected at build time with the `spectral` feature.
+#[cfg(feature = "spectral")]
+pub type Spectrum = SampledSpectrum;
+
+/// Determines how an RGB color is converted to a spectrum.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpectrumType {
+    /// A reflectance with values in `[0, 1]`.
+    Reflectance,
+    /// A non-negative quantity without an upper bound, such as an absorption coefficient.
+    Unbounded,
+    /// The emission of a light source, relative to illuminant D65.
+    Illuminant
+}
+
+/// Computes the average of the piecewise-linear distribution given by the sorted samples `(lambda[i], v[i])`
+/// over the wavelength range `[lambda_start, lambda_end]`.
+///
+/// The distribution is extended as a constant beyond the first and last sample.
+pub fn average_spectrum_samples(lambda: &[f32], v: &[f32], lambda_start: f32, lambda_end: f32) -> f32 {
+    let n = lambda.len();
+    debug_assert!(n > 0 && n == v.len());
+    if lambda_end <= lambda[0] {
+        return v[0]
+    }
+    if lambda_start >= lambda[n - 1] {
+        return v[n - 1]
+    }
+    if n == 1 {
+        return v[0]
+    }
+
+    // Constant segments before the first and after the last sample
+    let mut sum = 0.0;
+    if lambda_start < lambda[0] {
+        sum += v[0] * (lambda[0] - lambda_start);
+    }
+    if lambda_end > lambda[n - 1] {
+        sum += v[n - 1] * (lambda_end - lambda[n - 1]);
+    }
+
+    // Piecewise-linear segments overlapping the range
+    let mut i = lambda.partition_point(|&l| l <= lambda_start).saturating_sub(1);
+    let interpolate = |w: f32, i: usize| {
+        let t = (w - lambda[i]) / (lambda[i + 1] - lambda[i]);
+        (1.0 - t) * v[i] + t * v[i + 1]
+    };
+    while i + 1 < n && lambda_end >= lambda[i] {
+        let segment_start = f32::max(lambda_start, lambda[i]);
+        let segment_end = f32::min(lambda_end, lambda[i + 1]);
+        if segment_end > segment_start {
+            sum += 0.5 * (interpolate(segment_start, i) + interpolate(segment_end, i)) * (segment_end - segment_start);
+        }
+        i += 1;
+    }
+    sum / (lambda_end - lambda_start)
+}
+
+/// Evaluates the piecewise-linear distribution given by the sorted samples `(lambda[i], v[i])` at wavelength `l`.
+///
+/// The distribution is extended as a constant beyond the first and last sample.
+pub fn interpolate_spectrum_samples(lambda: &[f32], v: &[f32], l: f32) -> f32 {
+    let n = lambda.len();
+    debug_assert!(n > 0 && n == v.len());
+    if l <= lambda[0] {
+        return v[0]
+    }
+    if l >= lambda[n - 1] {
+        return v[n - 1]
+    }
+    let i = lambda.partition_point(|&x| x <= l) - 1;
+    let t = (l - lambda[i]) / (lambda[i + 1] - lambda[i]);
+    (1.0 - t) * v[i] + t * v[i + 1]
+}
+
+/// Like [`interpolate_spectrum_samples`], but for samples stored as interleaved `(lambda, value)` pairs.
+pub(crate) fn interpolate_interleaved(samples: &[f32], l: f32) -> f32 {
+    let (lambda, v) = deinterleave(samples);
+    interpolate_spectrum_samples(&lambda, &v, l)
+}
+
+/// Splits samples stored as interleaved `(lambda, value)` pairs into wavelengths and values.
+pub(crate) fn deinterleave(samples: &[f32]) -> (Vec<f32>, Vec<f32>) {
+    samples.chunks_exact(2).map(|s| (s[0], s[1])).unzip()
+}
+
+pub(crate) const XYZ_TO_RGB: [[f64; 3]; 3] = [
+    [3.240479, -1.537150, -0.498535],
+    [-0.969256, 1.875991, 0.041556],
+    [0.055648, -0.204043, 1.057311]
+];
+
+/// Converts CIE XYZ to linear sRGB with a D65 white point.
+#[inline]
+pub fn xyz_to_rgb(xyz: [f32; 3]) -> [f32; 3] {
+    XYZ_TO_RGB.map(|m| m[0] as f32 * xyz[0] + m[1] as f32 * xyz[1] + m[2] as f32 * xyz[2])
+}
+
+/// Converts linear sRGB with a D65 white point to CIE XYZ.
+#[inline]
+pub fn rgb_to_xyz(rgb: [f32; 3]) -> [f32; 3] {
+    [
+        0.412453 * rgb[0] + 0.357580 * rgb[1] + 0.180423 * rgb[2],
+        0.212671 * rgb[0] + 0.715160 * rgb[1] + 0.072169 * rgb[2],
+        0.019334 * rgb[0] + 0.119193 * rgb[1] + 0.950227 * rgb[2]
+    ]
 }
@@ -0,0 +1,91 @@
+use crate::spectrum::cie::{cie_lambda, CIE_SAMPLES};
+use crate::spectrum::wavelengths::{SampledWavelengths, SpectrumSample, N_WAVELENGTH_SAMPLES};
+use crate::spectrum::Spectrum;
+
+/// Returns the spectral radiance emitted by a blackbody at `temperature` in Kelvin at wavelength `lambda` in
+/// nanometers, given by Planck's law in W/(m^2 sr m).
+pub fn blackbody(lambda: f32, temperature: f32) -> f32 {
+    if temperature <= 0.0 {
+        return 0.0
+    }
+    const C: f64 = 299792458.0;
+    const H: f64 = 6.62606957e-34;
+    const KB: f64 = 1.3806488e-23;
+    let l = lambda as f64 * 1e-9;
+    let le = (2.0 * H * C * C) / (l.powi(5) * (((H * C) / (l * KB * temperature as f64)).exp() - 1.0));
+    le as f32
+}
+
+/// Wien's displacement constant in m K, which relates the temperature of a blackbody to the wavelength of its peak.
+const WIEN_DISPLACEMENT: f64 = 2.8977721e-3;
+
+/// The emission spectrum of a blackbody, optionally normalized so that its maximum is one.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BlackbodySpectrum {
+    temperature: f32,
+    normalization: f32
+}
+
+impl BlackbodySpectrum {
+    /// Creates the spectrum of a blackbody at `temperature` in Kelvin scaled to a maximum value of one, which
+    /// separates the color of an emitter from its brightness.
+    pub fn new(temperature: f32) -> Self {
+        let lambda_max = (WIEN_DISPLACEMENT / temperature as f64 * 1e9) as f32;
+        BlackbodySpectrum { temperature, normalization: 1.0 / blackbody(lambda_max, temperature) }
+    }
+
+    /// Creates the spectrum of a blackbody at `temperature` in Kelvin with its absolute spectral radiance.
+    pub const fn unnormalized(temperature: f32) -> Self {
+        BlackbodySpectrum { temperature, normalization: 1.0 }
+    }
+
+    #[inline]
+    pub const fn temperature(&self) -> f32 {
+        self.temperature
+    }
+
+    /// Evaluates the spectrum at wavelength `lambda` in nanometers.
+    #[inline]
+    pub fn evaluate(&self, lambda: f32) -> f32 {
+        blackbody(lambda, self.temperature) * self.normalization
+    }
+
+    /// Evaluates the spectrum at the wavelengths `lambda`.
+    pub fn sample(&self, lambda: &SampledWavelengths) -> SpectrumSample {
+        let mut c = [0.0; N_WAVELENGTH_SAMPLES];
+        for (i, c) in c.iter_mut().enumerate() {
+            *c = self.evaluate(lambda[i]);
+        }
+        SpectrumSample::from_coefficients(c)
+    }
+
+    /// Converts the spectrum to the representation used by the renderer.
+    pub fn to_spectrum(&self) -> Spectrum {
+        let lambda: Vec<f32> = (0..CIE_SAMPLES).map(cie_lambda).collect();
+        let v: Vec<f32> = lambda.iter().map(|&l| self.evaluate(l)).collect();
+        Spectrum::from_sampled(&lambda, &v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalized_peak() {
+        let s = BlackbodySpectrum::new(5000.0);
+        let peak = (WIEN_DISPLACEMENT / 5000.0 * 1e9) as f32;
+        assert!((s.evaluate(peak) - 1.0).abs() < 1e-5);
+        assert!(s.evaluate(peak - 10.0) < 1.0 && s.evaluate(peak + 10.0) < 1.0);
+        assert_eq!(BlackbodySpectrum::unnormalized(5000.0).evaluate(500.0), blackbody(500.0, 5000.0));
+    }
+
+    #[test]
+    fn test_color_temperature() {
+        // Low temperatures are reddish and high temperatures bluish
+        let [r, _, b] = BlackbodySpectrum::new(2700.0).to_spectrum().to_rgb();
+        assert!(r > b);
+        let [r, _, b] = BlackbodySpectrum::new(10000.0).to_spectrum().to_rgb();
+        assert!(b > r);
+    }
+}
@@ -0,0 +1,162 @@
+/// Implements the arithmetic shared by spectra that are represented by a fixed number of coefficients stored in
+/// a field `c: [f32; N]`.
+macro_rules! impl_coefficient_spectrum {
+    ($name:ident, $n:expr) => {
+        impl $name {
+            /// Creates a spectrum with all coefficients set to `v`.
+            #[inline]
+            pub const fn new(v: f32) -> Self {
+                $name { c: [v; $n] }
+            }
+
+            #[inline]
+            pub const fn from_coefficients(c: [f32; $n]) -> Self {
+                $name { c }
+            }
+
+            #[inline]
+            pub const fn coefficients(&self) -> &[f32; $n] {
+                &self.c
+            }
+
+            #[inline]
+            fn map(&self, f: impl Fn(f32) -> f32) -> Self {
+                let mut c = self.c;
+                for v in c.iter_mut() {
+                    *v = f(*v);
+                }
+                $name { c }
+            }
+
+            #[inline]
+            fn zip_with(&self, rhs: &Self, f: impl Fn(f32, f32) -> f32) -> Self {
+                let mut c = self.c;
+                for (v, r) in c.iter_mut().zip(rhs.c) {
+                    *v = f(*v, r);
+                }
+                $name { c }
+            }
+
+            /// Checks if all coefficients are zero.
+            pub fn is_black(&self) -> bool {
+                self.c.iter().all(|&v| v == 0.0)
+            }
+
+            pub fn has_nans(&self) -> bool {
+                self.c.iter().any(|v| v.is_nan())
+            }
+
+            pub fn sqrt(&self) -> Self {
+                self.map(f32::sqrt)
+            }
+
+            pub fn exp(&self) -> Self {
+                self.map(f32::exp)
+            }
+
+            pub fn pow(&self, e: f32) -> Self {
+                self.map(|v| v.powf(e))
+            }
+
+            /// Clamps all coefficients to `[low, high]`.
+            pub fn clamp(&self, low: f32, high: f32) -> Self {
+                self.map(|v| v.clamp(low, high))
+            }
+
+            /// Linearly interpolates between `s1` and `s2`.
+            pub fn lerp(t: f32, s1: &Self, s2: &Self) -> Self {
+                *s1 * (1.0 - t) + *s2 * t
+            }
+
+            pub fn max_component(&self) -> f32 {
+                self.c.iter().copied().fold(f32::NEG_INFINITY, f32::max)
+            }
+        }
+
+        impl Default for $name {
+            #[inline]
+            fn default() -> Self {
+                $name::new(0.0)
+            }
+        }
+
+        impl Index<usize> for $name {
+            type Output = f32;
+
+            #[inline]
+            fn index(&self, i: usize) -> &f32 {
+                &self.c[i]
+            }
+        }
+
+        impl IndexMut<usize> for $name {
+            #[inline]
+            fn index_mut(&mut self, i: usize) -> &mut f32 {
+                &mut self.c[i]
+            }
+        }
+
+        impl Neg for $name {
+            type Output = $name;
+
+            #[inline]
+            fn neg(self) -> $name {
+                self.map(|v| -v)
+            }
+        }
+
+        $crate::spectrum::macros::impl_spectrum_binop!($name, Add::add, AddAssign::add_assign, +);
+        $crate::spectrum::macros::impl_spectrum_binop!($name, Sub::sub, SubAssign::sub_assign, -);
+        $crate::spectrum::macros::impl_spectrum_binop!($name, Mul::mul, MulAssign::mul_assign, *);
+        $crate::spectrum::macros::impl_spectrum_binop!($name, Div::div, DivAssign::div_assign, /);
+
+        impl Mul<$name> for f32 {
+            type Output = $name;
+
+            #[inline]
+            fn mul(self, rhs: $name) -> $name {
+                rhs * self
+            }
+        }
+    }
+}
+
+/// Implements a coefficient-wise binary operator between spectra and between a spectrum and a scalar.
+macro_rules! impl_spectrum_binop {
+    ($name:ident, $trait:ident :: $fn:ident, $assign_trait:ident :: $assign_fn:ident, $op:tt) => {
+        impl $trait for $name {
+            type Output = $name;
+
+            #[inline]
+            fn $fn(self, rhs: $name) -> $name {
+                self.zip_with(&rhs, |a, b| a $op b)
+            }
+        }
+
+        impl $trait<f32> for $name {
+            type Output = $name;
+
+            #[inline]
+            fn $fn(self, rhs: f32) -> $name {
+                self.map(|a| a $op rhs)
+            }
+        }
+
+        impl $assign_trait for $name {
+            #[inline]
+            fn $assign_fn(&mut self, rhs: $name) {
+                *self = $trait::$fn(*self, rhs);
+            }
+        }
+
+        impl $assign_trait<f32> for $name {
+            #[inline]
+            fn $assign_fn(&mut self, rhs: f32) {
+                *self = $trait::$fn(*self, rhs);
+            }
+        }
+    }
+}
+
+pub(crate) use impl_coefficient_spectrum;
+pub(crate) use impl_spectrum_binop;
@@ -0,0 +1,206 @@
+//! Complex indices of refraction `eta + ik` of conductors, stored as interleaved `(lambda, value)` pairs.
+//!
+//! The measurements of the pure metals are sampled uniformly in photon energy, from 4.15 eV down to 1.4 eV in steps
+//! of 0.05 eV.
+
+#![allow(clippy::approx_constant)]
+
+/// Index of refraction of silver, measured by Johnson and Christy, "Optical Constants of the Noble Metals" (1972).
+#[rustfmt::skip]
+pub const METAL_AG_ETA: [f32; 112] = [
+    298.757, 1.519, 302.4, 1.496, 306.134, 1.4325, 309.96, 1.323,
+    313.884, 1.142062, 317.908, 0.932, 322.037, 0.719062, 326.274, 0.526,
+    330.625, 0.388125, 335.092, 0.294, 339.683, 0.253313, 344.401, 0.238,
+    349.251, 0.221438, 354.241, 0.209, 359.374, 0.194813, 364.659, 0.186,
+    370.102, 0.192063, 375.71, 0.2, 381.49, 0.198938, 387.451, 0.192,
+    393.601, 0.182, 399.949, 0.173, 406.506, 0.172937, 413.281, 0.173,
+    420.285, 0.166063, 427.532, 0.16, 435.032, 0.158063, 442.801, 0.157,
+    450.852, 0.151063, 459.201, 0.144, 467.865, 0.137313, 476.862, 0.132,
+    486.213, 0.13025, 495.937, 0.13, 506.058, 0.129938, 516.601, 0.13,
+    527.592, 0.130063, 539.062, 0.129, 551.041, 0.124688, 563.565, 0.12,
+    576.671, 0.119187, 590.401, 0.121, 604.801, 0.1255, 619.921, 0.131,
+    635.816, 0.136125, 652.548, 0.14, 670.185, 0.140063, 688.801, 0.14,
+    708.481, 0.144313, 729.319, 0.148, 751.419, 0.145875, 774.901, 0.143,
+    799.898, 0.142563, 826.561, 0.145, 855.063, 0.151938, 885.601, 0.163
+];
+
+/// Extinction coefficient of silver, see [`METAL_AG_ETA`].
+#[rustfmt::skip]
+pub const METAL_AG_K: [f32; 112] = [
+    298.757, 1.08, 302.4, 0.882, 306.134, 0.761063, 309.96, 0.647,
+    313.884, 0.550875, 317.908, 0.504, 322.037, 0.554375, 326.274, 0.663,
+    330.625, 0.818563, 335.092, 0.986, 339.683, 1.120687, 344.401, 1.24,
+    349.251, 1.34525, 354.241, 1.44, 359.374, 1.53375, 364.659, 1.61,
+    370.102, 1.641875, 375.71, 1.67, 381.49, 1.735, 387.451, 1.81,
+    393.601, 1.87875, 399.949, 1.95, 406.506, 2.029375, 413.281, 2.11,
+    420.285, 2.18625, 427.532, 2.26, 435.032, 2.329375, 442.801, 2.4,
+    450.852, 2.47875, 459.201, 2.56, 467.865, 2.64, 476.862, 2.72,
+    486.213, 2.798125, 495.937, 2.88, 506.058, 2.97375, 516.601, 3.07,
+    527.592, 3.159375, 539.062, 3.25, 551.041, 3.348125, 563.565, 3.45,
+    576.671, 3.55375, 590.401, 3.66, 604.801, 3.76625, 619.921, 3.88,
+    635.816, 4.010625, 652.548, 4.15, 670.185, 4.293125, 688.801, 4.44,
+    708.481, 4.58625, 729.319, 4.74, 751.419, 4.908125, 774.901, 5.09,
+    799.898, 5.28875, 826.561, 5.5, 855.063, 5.720624, 885.601, 5.95
+];
+
+/// Index of refraction of aluminium, after Rakić, "Algorithm for the determination of intrinsic optical constants
+/// of metal films: application to aluminum" (1995).
+#[rustfmt::skip]
+pub const METAL_AL_ETA: [f32; 112] = [
+    298.757, 0.273375, 302.4, 0.28, 306.134, 0.286813, 309.96, 0.294,
+    313.884, 0.301875, 317.908, 0.31, 322.037, 0.317875, 326.274, 0.326,
+    330.625, 0.33475, 335.092, 0.344, 339.683, 0.353813, 344.401, 0.364,
+    349.251, 0.374375, 354.241, 0.385, 359.374, 0.39575, 364.659, 0.407,
+    370.102, 0.419125, 375.71, 0.432, 381.49, 0.445688, 387.451, 0.46,
+    393.601, 0.474688, 399.949, 0.49, 406.506, 0.506188, 413.281, 0.523,
+    420.285, 0.540063, 427.532, 0.558, 435.032, 0.577313, 442.801, 0.598,
+    450.852, 0.620313, 459.201, 0.644, 467.865, 0.668625, 476.862, 0.695,
+    486.213, 0.72375, 495.937, 0.755, 506.058, 0.789, 516.601, 0.826,
+    527.592, 0.867, 539.062, 0.912, 551.041, 0.963, 563.565, 1.02,
+    576.671, 1.08, 590.401, 1.15, 604.801, 1.22, 619.921, 1.3,
+    635.816, 1.39, 652.548, 1.49, 670.185, 1.6, 688.801, 1.74,
+    708.481, 1.91, 729.319, 2.14, 751.419, 2.41, 774.901, 2.63,
+    799.898, 2.8, 826.561, 2.74, 855.063, 2.58, 885.601, 2.24
+];
+
+/// Extinction coefficient of aluminium, see [`METAL_AL_ETA`].
+#[rustfmt::skip]
+pub const METAL_AL_K: [f32; 112] = [
+    298.757, 3.59375, 302.4, 3.64, 306.134, 3.689375, 309.96, 3.74,
+    313.884, 3.789375, 317.908, 3.84, 322.037, 3.894375, 326.274, 3.95,
+    330.625, 4.005, 335.092, 4.06, 339.683, 4.11375, 344.401, 4.17,
+    349.251, 4.23375, 354.241, 4.3, 359.374, 4.365, 364.659, 4.43,
+    370.102, 4.49375, 375.71, 4.56, 381.49, 4.63375, 387.451, 4.71,
+    393.601, 4.784375, 399.949, 4.86, 406.506, 4.938125, 413.281, 5.02,
+    420.285, 5.10875, 427.532, 5.2, 435.032, 5.29, 442.801, 5.38,
+    450.852, 5.48, 459.201, 5.58, 467.865, 5.69, 476.862, 5.8,
+    486.213, 5.915, 495.937, 6.03, 506.058, 6.15, 516.601, 6.28,
+    527.592, 6.42, 539.062, 6.55, 551.041, 6.7, 563.565, 6.85,
+    576.671, 7.0, 590.401, 7.15, 604.801, 7.31, 619.921, 7.48,
+    635.816, 7.65, 652.548, 7.82, 670.185, 8.01, 688.801, 8.21,
+    708.481, 8.39, 729.319, 8.57, 751.419, 8.62, 774.901, 8.6,
+    799.898, 8.45, 826.561, 8.31, 855.063, 8.21, 885.601, 8.21
+];
+
+/// Index of refraction of gold, measured by Johnson and Christy, "Optical Constants of the Noble Metals" (1972).
+#[rustfmt::skip]
+pub const METAL_AU_ETA: [f32; 112] = [
+    298.757, 1.795, 302.4, 1.812, 306.134, 1.822625, 309.96, 1.83,
+    313.884, 1.837125, 317.908, 1.84, 322.037, 1.83425, 326.274, 1.824,
+    330.625, 1.812, 335.092, 1.798, 339.683, 1.782, 344.401, 1.766,
+    349.251, 1.7525, 354.241, 1.74, 359.374, 1.727625, 364.659, 1.716,
+    370.102, 1.705875, 375.71, 1.696, 381.49, 1.68475, 387.451, 1.674,
+    393.601, 1.666, 399.949, 1.658, 406.506, 1.64725, 413.281, 1.636,
+    420.285, 1.628, 427.532, 1.616, 435.032, 1.59625, 442.801, 1.562,
+    450.852, 1.502125, 459.201, 1.426, 467.865, 1.345875, 476.862, 1.242,
+    486.213, 1.08675, 495.937, 0.916, 506.058, 0.7545, 516.601, 0.608,
+    527.592, 0.49175, 539.062, 0.402, 551.041, 0.3455, 563.565, 0.306,
+    576.671, 0.267625, 590.401, 0.236, 604.801, 0.212375, 619.921, 0.194,
+    635.816, 0.17775, 652.548, 0.166, 670.185, 0.161, 688.801, 0.16,
+    708.481, 0.160875, 729.319, 0.164, 751.419, 0.1695, 774.901, 0.176,
+    799.898, 0.181375, 826.561, 0.188, 855.063, 0.198125, 885.601, 0.21
+];
+
+/// Extinction coefficient of gold, see [`METAL_AU_ETA`].
+#[rustfmt::skip]
+pub const METAL_AU_K: [f32; 112] = [
+    298.757, 1.920375, 302.4, 1.92, 306.134, 1.918875, 309.96, 1.916,
+    313.884, 1.911375, 317.908, 1.904, 322.037, 1.891375, 326.274, 1.878,
+    330.625, 1.86825, 335.092, 1.86, 339.683, 1.85175, 344.401, 1.846,
+    349.251, 1.84525, 354.241, 1.848, 359.374, 1.852375, 364.659, 1.862,
+    370.102, 1.883, 375.71, 1.906, 381.49, 1.9225, 387.451, 1.936,
+    393.601, 1.94775, 399.949, 1.956, 406.506, 1.959375, 413.281, 1.958,
+    420.285, 1.951375, 427.532, 1.94, 435.032, 1.9245, 442.801, 1.904,
+    450.852, 1.875875, 459.201, 1.846, 467.865, 1.814625, 476.862, 1.796,
+    486.213, 1.797375, 495.937, 1.84, 506.058, 1.9565, 516.601, 2.12,
+    527.592, 2.32625, 539.062, 2.54, 551.041, 2.730625, 563.565, 2.88,
+    576.671, 2.940625, 590.401, 2.97, 604.801, 3.015, 619.921, 3.06,
+    635.816, 3.07, 652.548, 3.15, 670.185, 3.445812, 688.801, 3.8,
+    708.481, 4.087687, 729.319, 4.357, 751.419, 4.610188, 774.901, 4.86,
+    799.898, 5.125813, 826.561, 5.39, 855.063, 5.63125, 885.601, 5.88
+];
+
+/// Index of refraction of copper, measured by Johnson and Christy, "Optical Constants of the Noble Metals" (1972).
+#[rustfmt::skip]
+pub const METAL_CU_ETA: [f32; 112] = [
+    298.757, 1.400313, 302.4, 1.38, 306.134, 1.358438, 309.96, 1.34,
+    313.884, 1.329063, 317.908, 1.325, 322.037, 1.3325, 326.274, 1.34,
+    330.625, 1.334375, 335.092, 1.325, 339.683, 1.317812, 344.401, 1.31,
+    349.251, 1.300313, 354.241, 1.29, 359.374, 1.281563, 364.659, 1.27,
+    370.102, 1.249062, 375.71, 1.225, 381.49, 1.2, 387.451, 1.18,
+    393.601, 1.174375, 399.949, 1.175, 406.506, 1.1775, 413.281, 1.18,
+    420.285, 1.178125, 427.532, 1.175, 435.032, 1.172812, 442.801, 1.17,
+    450.852, 1.165312, 459.201, 1.16, 467.865, 1.155312, 476.862, 1.15,
+    486.213, 1.142812, 495.937, 1.135, 506.058, 1.131562, 516.601, 1.12,
+    527.592, 1.092437, 539.062, 1.04, 551.041, 0.950375, 563.565, 0.826,
+    576.671, 0.645875, 590.401, 0.468, 604.801, 0.35125, 619.921, 0.272,
+    635.816, 0.230813, 652.548, 0.214, 670.185, 0.20925, 688.801, 0.213,
+    708.481, 0.21625, 729.319, 0.223, 751.419, 0.2365, 774.901, 0.25,
+    799.898, 0.254188, 826.561, 0.26, 855.063, 0.28, 885.601, 0.3
+];
+
+/// Extinction coefficient of copper, see [`METAL_CU_ETA`].
+#[rustfmt::skip]
+pub const METAL_CU_K: [f32; 112] = [
+    298.757, 1.662125, 302.4, 1.687, 306.134, 1.703313, 309.96, 1.72,
+    313.884, 1.744563, 317.908, 1.77, 322.037, 1.791625, 326.274, 1.81,
+    330.625, 1.822125, 335.092, 1.834, 339.683, 1.85175, 344.401, 1.872,
+    349.251, 1.89425, 354.241, 1.916, 359.374, 1.931688, 364.659, 1.95,
+    370.102, 1.972438, 375.71, 2.015, 381.49, 2.121562, 387.451, 2.21,
+    393.601, 2.177188, 399.949, 2.13, 406.506, 2.160063, 413.281, 2.21,
+    420.285, 2.249938, 427.532, 2.289, 435.032, 2.326, 442.801, 2.362,
+    450.852, 2.397625, 459.201, 2.433, 467.865, 2.469187, 476.862, 2.504,
+    486.213, 2.535875, 495.937, 2.564, 506.058, 2.589625, 516.601, 2.605,
+    527.592, 2.595562, 539.062, 2.583, 551.041, 2.5765, 563.565, 2.599,
+    576.671, 2.678062, 590.401, 2.809, 604.801, 3.01075, 619.921, 3.24,
+    635.816, 3.458187, 652.548, 3.67, 670.185, 3.863125, 688.801, 4.05,
+    708.481, 4.239563, 729.319, 4.43, 751.419, 4.619563, 774.901, 4.817,
+    799.898, 5.034125, 826.561, 5.26, 855.063, 5.485625, 885.601, 5.717
+];
+
+/// Index of refraction of brass with 30% zinc, after Querry, "Optical constants of minerals and other materials
+/// from the millimeter to the ultraviolet" (1987).
+#[rustfmt::skip]
+pub const METAL_CUZN_ETA: [f32; 42] = [
+    380.0, 1.26, 400.0, 1.24, 420.0, 1.22, 440.0, 1.2,
+    460.0, 1.16, 480.0, 1.1, 500.0, 1.0, 520.0, 0.86,
+    540.0, 0.68, 560.0, 0.52, 580.0, 0.44, 600.0, 0.4,
+    620.0, 0.38, 640.0, 0.37, 660.0, 0.37, 680.0, 0.38,
+    700.0, 0.39, 720.0, 0.4, 740.0, 0.42, 760.0, 0.44,
+    780.0, 0.46
+];
+
+/// Extinction coefficient of brass, see [`METAL_CUZN_ETA`].
+#[rustfmt::skip]
+pub const METAL_CUZN_K: [f32; 42] = [
+    380.0, 1.85, 400.0, 1.88, 420.0, 1.89, 440.0, 1.88,
+    460.0, 1.87, 480.0, 1.88, 500.0, 1.93, 520.0, 2.04,
+    540.0, 2.24, 560.0, 2.52, 580.0, 2.78, 600.0, 3.01,
+    620.0, 3.22, 640.0, 3.42, 660.0, 3.61, 680.0, 3.8,
+    700.0, 3.98, 720.0, 4.16, 740.0, 4.34, 760.0, 4.51,
+    780.0, 4.68
+];
+
+/// Index of refraction of magnesium oxide from the dispersion formula of Stephens and Malitson, "Index of
+/// refraction of magnesium oxide" (1952). It does not absorb visible light.
+#[rustfmt::skip]
+pub const METAL_MGO_ETA: [f32; 42] = [
+    380.0, 1.7672, 400.0, 1.7621, 420.0, 1.7576, 440.0, 1.7538,
+    460.0, 1.7506, 480.0, 1.7477, 500.0, 1.7452, 520.0, 1.7429,
+    540.0, 1.7409, 560.0, 1.7391, 580.0, 1.7374, 600.0, 1.7359,
+    620.0, 1.7345, 640.0, 1.7332, 660.0, 1.732, 680.0, 1.7309,
+    700.0, 1.7299, 720.0, 1.7288, 740.0, 1.7279, 760.0, 1.727,
+    780.0, 1.7261
+];
+
+/// Ordinary index of refraction of rutile titanium dioxide from the dispersion formula of DeVore, "Refractive
+/// Indices of Rutile and Sphalerite" (1951), which holds above 430 nm. It does not absorb visible light at these
+/// wavelengths.
+#[rustfmt::skip]
+pub const METAL_TIO2_ETA: [f32; 36] = [
+    440.0, 2.8403, 460.0, 2.7878, 480.0, 2.7458, 500.0, 2.7114,
+    520.0, 2.6827, 540.0, 2.6586, 560.0, 2.638, 580.0, 2.6203,
+    600.0, 2.6049, 620.0, 2.5915, 640.0, 2.5796, 660.0, 2.5691,
+    680.0, 2.5597, 700.0, 2.5512, 720.0, 2.5437, 740.0, 2.5368,
+    760.0, 2.5306, 780.0, 2.5249
+];
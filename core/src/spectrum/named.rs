@@ -0,0 +1,197 @@
+//! A library of standard and measured spectra that scene descriptions can refer to by name.
+
+use std::collections::HashMap;
+use std::lazy::SyncLazy;
+use crate::spectrum::cie::{cie_lambda, CIE_SAMPLES};
+use crate::spectrum::metal::*;
+use crate::spectrum::std_illuminant::*;
+use crate::spectrum::{deinterleave, Spectrum};
+
+/// A glass whose index of refraction is given by the Sellmeier equation
+/// `n^2(lambda) = 1 + sum_i b_i lambda^2 / (lambda^2 - c_i)` with `lambda` in micrometers.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SellmeierGlass {
+    pub b: [f32; 3],
+    pub c: [f32; 3]
+}
+
+impl SellmeierGlass {
+    /// Returns the index of refraction at wavelength `lambda` in nanometers.
+    pub fn ior(&self, lambda: f32) -> f32 {
+        let l2 = (lambda as f64 * 1e-3).powi(2);
+        let n2: f64 = 1.0 + self.b.iter().zip(self.c).map(|(&b, c)| b as f64 * l2 / (l2 - c as f64)).sum::<f64>();
+        n2.sqrt() as f32
+    }
+}
+
+/// Schott N-BK7 borosilicate crown glass.
+#[allow(clippy::excessive_precision)]
+pub const GLASS_BK7: SellmeierGlass = SellmeierGlass {
+    b: [1.03961212, 0.231792344, 1.01046945],
+    c: [0.00600069867, 0.0200179144, 103.560653]
+};
+/// Schott N-BAF10 barium flint glass.
+#[allow(clippy::excessive_precision)]
+pub const GLASS_BAF10: SellmeierGlass = SellmeierGlass {
+    b: [1.5851495, 0.143559385, 1.08521269],
+    c: [0.00926681282, 0.0424489805, 105.613573]
+};
+/// Schott N-BAK1 barium crown glass.
+#[allow(clippy::excessive_precision)]
+pub const GLASS_BAK1: SellmeierGlass = SellmeierGlass {
+    b: [1.12365662, 0.309276848, 0.881511957],
+    c: [0.00644742752, 0.0222284402, 107.297751]
+};
+/// Schott F2 flint glass.
+#[allow(clippy::excessive_precision)]
+pub const GLASS_F2: SellmeierGlass = SellmeierGlass {
+    b: [1.34533359, 0.209073176, 0.937357162],
+    c: [0.00997743871, 0.0470450767, 111.886764]
+};
+/// Schott N-SF11 dense flint glass.
+#[allow(clippy::excessive_precision)]
+pub const GLASS_SF11: SellmeierGlass = SellmeierGlass {
+    b: [1.73759695, 0.313747346, 1.89878101],
+    c: [0.013188707, 0.0623068142, 155.23629]
+};
+/// Fused silica, after Malitson (1965).
+#[allow(clippy::excessive_precision)]
+pub const GLASS_FUSED_SILICA: SellmeierGlass = SellmeierGlass {
+    b: [0.6961663, 0.4079426, 0.8974794],
+    c: [0.00467914826, 0.0135120631, 97.9340025]
+};
+
+static NAMED_SPECTRA: SyncLazy<HashMap<&'static str, Spectrum>> = SyncLazy::new(|| {
+    let mut spectra = HashMap::new();
+    let mut insert_interleaved = |name, samples: &[f32]| {
+        let (lambda, v) = deinterleave(samples);
+        spectra.insert(name, Spectrum::from_sampled(&lambda, &v));
+    };
+    insert_interleaved("stdillum-A", &cie_illuminant_a());
+    insert_interleaved("stdillum-D50", &cie_illuminant_d(5003.0));
+    insert_interleaved("stdillum-D55", &cie_illuminant_d(5503.0));
+    insert_interleaved("stdillum-D65", &CIE_ILLUM_D65);
+    insert_interleaved("stdillum-D75", &cie_illuminant_d(7504.0));
+
+    insert_interleaved("stdillum-F1", &CIE_ILLUM_F1);
+    insert_interleaved("stdillum-F2", &CIE_ILLUM_F2);
+    insert_interleaved("stdillum-F3", &CIE_ILLUM_F3);
+    insert_interleaved("stdillum-F4", &CIE_ILLUM_F4);
+    insert_interleaved("stdillum-F5", &CIE_ILLUM_F5);
+    insert_interleaved("stdillum-F6", &CIE_ILLUM_F6);
+    insert_interleaved("stdillum-F7", &CIE_ILLUM_F7);
+    insert_interleaved("stdillum-F8", &CIE_ILLUM_F8);
+    insert_interleaved("stdillum-F9", &CIE_ILLUM_F9);
+    insert_interleaved("stdillum-F10", &CIE_ILLUM_F10);
+    insert_interleaved("stdillum-F11", &CIE_ILLUM_F11);
+    insert_interleaved("stdillum-F12", &CIE_ILLUM_F12);
+
+    insert_interleaved("metal-Ag-eta", &METAL_AG_ETA);
+    insert_interleaved("metal-Ag-k", &METAL_AG_K);
+    insert_interleaved("metal-Al-eta", &METAL_AL_ETA);
+    insert_interleaved("metal-Al-k", &METAL_AL_K);
+    insert_interleaved("metal-Au-eta", &METAL_AU_ETA);
+    insert_interleaved("metal-Au-k", &METAL_AU_K);
+    insert_interleaved("metal-Cu-eta", &METAL_CU_ETA);
+    insert_interleaved("metal-Cu-k", &METAL_CU_K);
+    insert_interleaved("metal-CuZn-eta", &METAL_CUZN_ETA);
+    insert_interleaved("metal-CuZn-k", &METAL_CUZN_K);
+    insert_interleaved("metal-MgO-eta", &METAL_MGO_ETA);
+    insert_interleaved("metal-TiO2-eta", &METAL_TIO2_ETA);
+    spectra.insert("metal-MgO-k", Spectrum::new(0.0));
+    spectra.insert("metal-TiO2-k", Spectrum::new(0.0));
+
+    let lambda: Vec<f32> = (0..CIE_SAMPLES).map(cie_lambda).collect();
+    for (name, glass) in [
+        ("glass-BK7", GLASS_BK7),
+        ("glass-BAF10", GLASS_BAF10),
+        ("glass-BAK1", GLASS_BAK1),
+        ("glass-F2", GLASS_F2),
+        ("glass-SF11", GLASS_SF11),
+        ("glass-fused-silica", GLASS_FUSED_SILICA)
+    ] {
+        let v: Vec<f32> = lambda.iter().map(|&l| glass.ior(l)).collect();
+        spectra.insert(name, Spectrum::from_sampled(&lambda, &v));
+    }
+    spectra
+});
+
+/// Looks up a spectrum by the name used in scene descriptions, e.g. `stdillum-D65`, `metal-Au-eta` or
+/// `glass-BK7`.
+///
+/// The A and D series illuminants are normalized to 100 at 560 nm, as defined by the CIE, while the F series keeps the
+/// relative scale of its CIE tables.
+pub fn named_spectrum(name: &str) -> Option<&'static Spectrum> {
+    NAMED_SPECTRA.get(name)
+}
+
+/// Returns the names of all spectra known to [`named_spectrum`].
+pub fn named_spectrum_names() -> impl Iterator<Item = &'static str> {
+    NAMED_SPECTRA.keys().copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chromaticity(s: &Spectrum) -> (f32, f32) {
+        let [x, y, z] = s.to_xyz();
+        (x / (x + y + z), y / (x + y + z))
+    }
+
+    #[test]
+    fn test_illuminant_chromaticities() {
+        for (name, expected) in [
+            ("stdillum-A", (0.4476, 0.4074)),
+            ("stdillum-D50", (0.3457, 0.3585)),
+            ("stdillum-D65", (0.3127, 0.3290)),
+            ("stdillum-F1", (0.3131, 0.3373)),
+            ("stdillum-F2", (0.3721, 0.3751)),
+            ("stdillum-F3", (0.4091, 0.3941)),
+            ("stdillum-F4", (0.4402, 0.4031)),
+            ("stdillum-F5", (0.3138, 0.3452)),
+            ("stdillum-F6", (0.3779, 0.3882)),
+            ("stdillum-F7", (0.3129, 0.3292)),
+            ("stdillum-F8", (0.3458, 0.3586)),
+            ("stdillum-F9", (0.3741, 0.3727)),
+            ("stdillum-F10", (0.3458, 0.3588)),
+            ("stdillum-F11", (0.3805, 0.3769)),
+            ("stdillum-F12", (0.4370, 0.4042))
+        ] {
+            let (x, y) = chromaticity(named_spectrum(name).unwrap());
+            // The sampled representation only covers part of the visible range
+            assert!((x - expected.0).abs() < 5e-3 && (y - expected.1).abs() < 5e-3, "{}: {:?}", name, (x, y));
+        }
+    }
+
+    #[test]
+    fn test_daylight_matches_d65() {
+        let d65 = cie_illuminant_d(6504.0);
+        for (a, b) in d65.iter().zip(CIE_ILLUM_D65) {
+            assert!((a - b).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_metals() {
+        for metal in ["Ag", "Al", "Au", "Cu", "CuZn", "MgO", "TiO2"] {
+            assert!(named_spectrum(&format!("metal-{}-eta", metal)).is_some(), "{}", metal);
+            assert!(named_spectrum(&format!("metal-{}-k", metal)).is_some(), "{}", metal);
+        }
+        // Gold and copper reflect less at short wavelengths
+        for metal in ["Au", "Cu"] {
+            let [r, _, b] = named_spectrum(&format!("metal-{}-eta", metal)).unwrap().to_rgb();
+            assert!(r < b, "{}", metal);
+        }
+    }
+
+    #[test]
+    fn test_glass_ior() {
+        // Refractive indices at the helium d line
+        assert!((GLASS_BK7.ior(587.56) - 1.5168).abs() < 1e-4);
+        assert!((GLASS_FUSED_SILICA.ior(587.56) - 1.4585).abs() < 1e-4);
+        assert!(GLASS_SF11.ior(450.0) > GLASS_SF11.ior(650.0));
+        assert!(named_spectrum("glass-BK7").is_some());
+        assert!(named_spectrum("unobtainium").is_none());
+    }
+}
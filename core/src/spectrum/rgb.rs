@@ -0,0 +1,81 @@
+use std::ops::*;
+use crate::spectrum::cie::{cie_lambda, CIE_SAMPLES, CIE_X, CIE_Y, CIE_Y_INTEGRAL, CIE_Z};
+use crate::spectrum::macros::impl_coefficient_spectrum;
+use crate::spectrum::{interpolate_spectrum_samples, rgb_to_xyz, xyz_to_rgb, SpectrumType};
+
+/// A spectral distribution represented by its linear sRGB color.
+///
+/// Products of RGB colors only approximate the products of the spectra they stand for, but are much cheaper to
+/// compute than a [`SampledSpectrum`](crate::spectrum::SampledSpectrum).
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct RgbSpectrum {
+    c: [f32; 3]
+}
+
+impl_coefficient_spectrum!(RgbSpectrum, 3);
+
+impl RgbSpectrum {
+    #[inline]
+    pub const fn from_rgb(rgb: [f32; 3], _spectrum_type: SpectrumType) -> Self {
+        RgbSpectrum { c: rgb }
+    }
+
+    #[inline]
+    pub fn from_xyz(xyz: [f32; 3], spectrum_type: SpectrumType) -> Self {
+        Self::from_rgb(xyz_to_rgb(xyz), spectrum_type)
+    }
+
+    /// Creates a spectrum from `(lambda[i], v[i])` samples of a piecewise-linear distribution by projecting it
+    /// onto the CIE matching functions.
+    ///
+    /// The samples do not need to be sorted by wavelength.
+    pub fn from_sampled(lambda: &[f32], v: &[f32]) -> Self {
+        assert_eq!(lambda.len(), v.len());
+        if !lambda.windows(2).all(|w| w[0] <= w[1]) {
+            let mut samples: Vec<(f32, f32)> = lambda.iter().copied().zip(v.iter().copied()).collect();
+            samples.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            let (lambda, v): (Vec<f32>, Vec<f32>) = samples.into_iter().unzip();
+            return Self::from_sampled(&lambda, &v)
+        }
+        let mut xyz = [0.0; 3];
+        for i in 0..CIE_SAMPLES {
+            let value = interpolate_spectrum_samples(lambda, v, cie_lambda(i));
+            xyz[0] += value * CIE_X[i];
+            xyz[1] += value * CIE_Y[i];
+            xyz[2] += value * CIE_Z[i];
+        }
+        // The tables are spaced 5 nm apart
+        let scale = 5.0 / CIE_Y_INTEGRAL;
+        Self::from_xyz(xyz.map(|v| v * scale), SpectrumType::Reflectance)
+    }
+
+    #[inline]
+    pub fn to_rgb(&self) -> [f32; 3] {
+        self.c
+    }
+
+    #[inline]
+    pub fn to_xyz(&self) -> [f32; 3] {
+        rgb_to_xyz(self.c)
+    }
+
+    /// Returns the luminance, i.e. the `Y` coordinate of the spectrum in CIE XYZ.
+    #[inline]
+    pub fn y(&self) -> f32 {
+        0.212671 * self.c[0] + 0.715160 * self.c[1] + 0.072169 * self.c[2]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_sampled_constant_is_equal_energy() {
+        let [x, y, z] = RgbSpectrum::from_sampled(&[400.0, 700.0], &[0.5, 0.5]).to_xyz();
+        assert!((y - 0.5).abs() < 1e-3);
+        assert!((x / (x + y + z) - 1.0 / 3.0).abs() < 1e-3);
+        assert!((y / (x + y + z) - 1.0 / 3.0).abs() < 1e-3);
+        assert!((RgbSpectrum::new(1.0).y() - 1.0).abs() < 1e-5);
+    }
+}
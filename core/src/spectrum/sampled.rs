@@ -0,0 +1,199 @@
+use std::lazy::SyncLazy;
+use std::ops::*;
+use crate::spectrum::cie::{cie_lambda, CIE_SAMPLES, CIE_X, CIE_Y, CIE_Y_INTEGRAL, CIE_Z};
+use crate::spectrum::macros::impl_coefficient_spectrum;
+use crate::spectrum::std_illuminant::CIE_ILLUM_D65;
+use crate::spectrum::upsample::RgbSigmoidPolynomial;
+use crate::spectrum::wavelengths::{SampledWavelengths, SpectrumSample, N_WAVELENGTH_SAMPLES};
+use crate::spectrum::{average_spectrum_samples, deinterleave, xyz_to_rgb, SpectrumType};
+
+/// Lower bound of the wavelength range covered by [`SampledSpectrum`] in nanometers.
+pub const SAMPLED_LAMBDA_START: f32 = 400.0;
+/// Upper bound of the wavelength range covered by [`SampledSpectrum`] in nanometers.
+pub const SAMPLED_LAMBDA_END: f32 = 700.0;
+pub const N_SPECTRAL_SAMPLES: usize = 60;
+
+/// A spectral distribution represented by its average value over each of `N_SPECTRAL_SAMPLES` equally sized
+/// wavelength ranges between `SAMPLED_LAMBDA_START` and `SAMPLED_LAMBDA_END`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct SampledSpectrum {
+    c: [f32; N_SPECTRAL_SAMPLES]
+}
+
+impl_coefficient_spectrum!(SampledSpectrum, N_SPECTRAL_SAMPLES);
+
+/// The CIE matching functions resampled to the wavelength ranges of [`SampledSpectrum`].
+static MATCHING_FUNCTIONS: SyncLazy<[SampledSpectrum; 3]> = SyncLazy::new(|| {
+    let lambda: Vec<f32> = (0..CIE_SAMPLES).map(cie_lambda).collect();
+    [
+        SampledSpectrum::from_sampled(&lambda, &CIE_X),
+        SampledSpectrum::from_sampled(&lambda, &CIE_Y),
+        SampledSpectrum::from_sampled(&lambda, &CIE_Z)
+    ]
+});
+
+/// Illuminant D65 normalized to a luminance of one, which illuminant spectra upsampled from RGB are relative to.
+static ILLUMINANT_D65: SyncLazy<SampledSpectrum> = SyncLazy::new(|| {
+    let (lambda, v) = deinterleave(&CIE_ILLUM_D65);
+    let d65 = SampledSpectrum::from_sampled(&lambda, &v);
+    d65 / d65.y()
+});
+
+impl SampledSpectrum {
+    /// Returns the start and end wavelength of the range covered by coefficient `i`.
+    #[inline]
+    pub fn wavelength_range(i: usize) -> (f32, f32) {
+        let width = (SAMPLED_LAMBDA_END - SAMPLED_LAMBDA_START) / N_SPECTRAL_SAMPLES as f32;
+        (SAMPLED_LAMBDA_START + width * i as f32, SAMPLED_LAMBDA_START + width * (i + 1) as f32)
+    }
+
+    /// Creates a spectrum from `(lambda[i], v[i])` samples of a piecewise-linear distribution.
+    ///
+    /// The samples do not need to be sorted by wavelength.
+    pub fn from_sampled(lambda: &[f32], v: &[f32]) -> Self {
+        assert_eq!(lambda.len(), v.len());
+        if !lambda.windows(2).all(|w| w[0] <= w[1]) {
+            let mut samples: Vec<(f32, f32)> = lambda.iter().copied().zip(v.iter().copied()).collect();
+            samples.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            let (lambda, v): (Vec<f32>, Vec<f32>) = samples.into_iter().unzip();
+            return Self::from_sampled(&lambda, &v)
+        }
+        let mut c = [0.0; N_SPECTRAL_SAMPLES];
+        for (i, c) in c.iter_mut().enumerate() {
+            let (lambda0, lambda1) = Self::wavelength_range(i);
+            *c = average_spectrum_samples(lambda, v, lambda0, lambda1);
+        }
+        SampledSpectrum { c }
+    }
+
+    /// Creates a smooth spectrum whose color is the linear sRGB color `rgb`.
+    ///
+    /// Reflectances are clamped to `[0, 1]`, while unbounded spectra and illuminants may be arbitrarily large.
+    /// Illuminants have the spectral shape of D65 if `rgb` is white.
+    pub fn from_rgb(rgb: [f32; 3], spectrum_type: SpectrumType) -> Self {
+        if spectrum_type == SpectrumType::Reflectance {
+            return Self::from_sigmoid(&RgbSigmoidPolynomial::from_rgb(rgb))
+        }
+        let max = rgb.iter().copied().fold(0.0, f32::max);
+        if max <= 0.0 {
+            return SampledSpectrum::default()
+        }
+        let scale = 2.0 * max;
+        let polynomial = RgbSigmoidPolynomial::from_rgb(rgb.map(|v| v / scale));
+        let s = Self::from_sigmoid(&polynomial) * scale;
+        if spectrum_type == SpectrumType::Illuminant {
+            s * *ILLUMINANT_D65
+        } else {
+            s
+        }
+    }
+
+    /// Creates a spectrum whose color is the CIE XYZ color `xyz`.
+    #[inline]
+    pub fn from_xyz(xyz: [f32; 3], spectrum_type: SpectrumType) -> Self {
+        Self::from_rgb(xyz_to_rgb(xyz), spectrum_type)
+    }
+
+    fn from_sigmoid(polynomial: &RgbSigmoidPolynomial) -> Self {
+        let mut c = [0.0; N_SPECTRAL_SAMPLES];
+        for (i, c) in c.iter_mut().enumerate() {
+            let (lambda0, lambda1) = Self::wavelength_range(i);
+            *c = polynomial.evaluate(0.5 * (lambda0 + lambda1));
+        }
+        SampledSpectrum { c }
+    }
+
+    /// Returns the values of the spectrum at the wavelengths `lambda`, which are zero outside of the covered range.
+    pub fn sample(&self, lambda: &SampledWavelengths) -> SpectrumSample {
+        let mut c = [0.0; N_WAVELENGTH_SAMPLES];
+        for (i, c) in c.iter_mut().enumerate() {
+            let x = (lambda[i] - SAMPLED_LAMBDA_START) / (SAMPLED_LAMBDA_END - SAMPLED_LAMBDA_START);
+            if (0.0..=1.0).contains(&x) {
+                *c = self.c[usize::min((x * N_SPECTRAL_SAMPLES as f32) as usize, N_SPECTRAL_SAMPLES - 1)];
+            }
+        }
+        SpectrumSample::from_coefficients(c)
+    }
+
+    /// Converts the spectrum to CIE XYZ, normalized so that a constant spectrum of one has a luminance of one.
+    pub fn to_xyz(&self) -> [f32; 3] {
+        let scale = (SAMPLED_LAMBDA_END - SAMPLED_LAMBDA_START) / (CIE_Y_INTEGRAL * N_SPECTRAL_SAMPLES as f32);
+        let mut xyz = [0.0; 3];
+        for (xyz, matching) in xyz.iter_mut().zip(MATCHING_FUNCTIONS.iter()) {
+            *xyz = self.c.iter().zip(matching.c).map(|(a, b)| a * b).sum::<f32>() * scale;
+        }
+        xyz
+    }
+
+    /// Returns the luminance, i.e. the `Y` coordinate of the spectrum in CIE XYZ.
+    pub fn y(&self) -> f32 {
+        self.to_xyz()[1]
+    }
+
+    /// Converts the spectrum to linear sRGB.
+    pub fn to_rgb(&self) -> [f32; 3] {
+        xyz_to_rgb(self.to_xyz())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_spectrum_is_white() {
+        let s = SampledSpectrum::new(1.0);
+        let [x, y, z] = s.to_xyz();
+        // The matching functions are only integrated over the visible range covered by the samples
+        assert!((y - 1.0).abs() < 1e-2);
+        assert!((x / (x + y + z) - 1.0 / 3.0).abs() < 1e-2);
+        assert!((y / (x + y + z) - 1.0 / 3.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_from_sampled_averages_ranges() {
+        let s = SampledSpectrum::from_sampled(&[700.0, 400.0], &[2.0, 0.0]);
+        let (lambda0, lambda1) = SampledSpectrum::wavelength_range(10);
+        let expected = (lambda0 + lambda1 - 800.0) / 300.0;
+        assert!((s[10] - expected).abs() < 1e-5);
+        // Values beyond the samples are extended as constants
+        assert_eq!(SampledSpectrum::from_sampled(&[550.0], &[0.5]), SampledSpectrum::new(0.5));
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let a = SampledSpectrum::new(4.0);
+        let b = SampledSpectrum::new(2.0);
+        assert_eq!(a + b, SampledSpectrum::new(6.0));
+        assert_eq!((a - b) * 2.0, SampledSpectrum::new(4.0));
+        assert_eq!(a / b, 0.5 * a);
+        assert_eq!(a.sqrt(), b);
+        assert_eq!(SampledSpectrum::lerp(0.25, &a, &b), SampledSpectrum::new(3.5));
+        assert_eq!((-a).clamp(0.0, 1.0), SampledSpectrum::default());
+        assert!(SampledSpectrum::default().is_black());
+        let mut c = a;
+        c[3] = 10.0;
+        assert_eq!(c.max_component(), 10.0);
+    }
+
+    #[test]
+    fn test_from_rgb_round_trip() {
+        for rgb in [[0.8, 0.2, 0.1], [0.1, 0.5, 0.9], [0.3, 0.3, 0.3], [0.05, 0.9, 0.4]] {
+            let s = SampledSpectrum::from_rgb(rgb, SpectrumType::Reflectance);
+            assert!(s.c.iter().all(|&v| (0.0..=1.0).contains(&v)));
+            let lit = (s * *ILLUMINANT_D65).to_rgb();
+            for (a, b) in lit.iter().zip(rgb) {
+                assert!((a - b).abs() < 2e-2, "{:?} != {:?}", lit, rgb);
+            }
+        }
+        assert_eq!(SampledSpectrum::from_rgb([1.0; 3], SpectrumType::Reflectance), SampledSpectrum::new(1.0));
+    }
+
+    #[test]
+    fn test_from_rgb_illuminant() {
+        let rgb = SampledSpectrum::from_rgb([2.0, 2.0, 2.0], SpectrumType::Illuminant).to_rgb();
+        for v in rgb {
+            assert!((v - 2.0).abs() < 2e-2, "{:?}", rgb);
+        }
+    }
+}
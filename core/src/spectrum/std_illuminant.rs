@@ -0,0 +1,442 @@
+//! Spectral power distributions of standard illuminants, stored as interleaved `(lambda, value)` pairs.
+
+#![allow(clippy::approx_constant, clippy::excessive_precision)]
+
+/// CIE standard illuminant D65, representing average daylight with a correlated color temperature of about
+/// 6504 K, normalized to 100 at 560 nm.
+#[rustfmt::skip]
+pub const CIE_ILLUM_D65: [f32; 108] = [
+    300.0, 0.0341, 310.0, 3.2945, 320.0, 20.236, 330.0, 37.0535,
+    340.0, 39.9488, 350.0, 44.9117, 360.0, 46.6383, 370.0, 52.0891,
+    380.0, 49.9755, 390.0, 54.6482, 400.0, 82.7549, 410.0, 91.486,
+    420.0, 93.4318, 430.0, 86.6823, 440.0, 104.865, 450.0, 117.008,
+    460.0, 117.812, 470.0, 114.861, 480.0, 115.923, 490.0, 108.811,
+    500.0, 109.354, 510.0, 107.802, 520.0, 104.79, 530.0, 107.689,
+    540.0, 104.405, 550.0, 104.046, 560.0, 100.0, 570.0, 96.3342,
+    580.0, 95.788, 590.0, 88.6856, 600.0, 90.0062, 610.0, 89.5991,
+    620.0, 87.6987, 630.0, 83.2886, 640.0, 83.6992, 650.0, 80.0268,
+    660.0, 80.2146, 670.0, 82.2778, 680.0, 78.2842, 690.0, 69.7213,
+    700.0, 71.6091, 710.0, 74.349, 720.0, 61.604, 730.0, 69.8856,
+    740.0, 75.087, 750.0, 63.5927, 760.0, 46.4182, 770.0, 66.8054,
+    780.0, 63.3828, 790.0, 64.304, 800.0, 59.4519, 810.0, 51.959,
+    820.0, 57.4406, 830.0, 60.3125
+];
+
+/// Computes the CIE daylight illuminant with correlated color temperature `cct` in Kelvin from the mean and
+/// characteristic vectors, normalized to 100 at 560 nm.
+///
+/// The temperatures of the standard illuminants follow from a revision of the radiation constant, e.g. D50 and D65
+/// are obtained with 5003 K and 6504 K.
+pub fn cie_illuminant_d(cct: f32) -> [f32; 108] {
+    let cct = cct.clamp(4000.0, 25000.0) as f64;
+    let x = if cct <= 7000.0 {
+        -4.6070e9 / cct.powi(3) + 2.9678e6 / cct.powi(2) + 0.09911e3 / cct + 0.244063
+    } else {
+        -2.0064e9 / cct.powi(3) + 1.9018e6 / cct.powi(2) + 0.24748e3 / cct + 0.237040
+    };
+    let y = -3.0 * x * x + 2.87 * x - 0.275;
+    // The CIE rounds the weights of the characteristic vectors to three decimals
+    let m = 0.0241 + 0.2562 * x - 0.7341 * y;
+    let m1 = ((-1.3515 - 1.7703 * x + 5.9114 * y) / m * 1000.0).round() / 1000.0;
+    let m2 = ((0.0300 - 31.4424 * x + 30.0717 * y) / m * 1000.0).round() / 1000.0;
+
+    let mut samples = CIE_ILLUM_S0;
+    for i in (1..samples.len()).step_by(2) {
+        samples[i] = (CIE_ILLUM_S0[i] as f64 + m1 * CIE_ILLUM_S1[i] as f64 + m2 * CIE_ILLUM_S2[i] as f64) as f32;
+    }
+    samples
+}
+
+/// Computes CIE standard illuminant A, representing incandescent lighting, from 300 nm to 830 nm at 5 nm
+/// intervals, normalized to 100 at 560 nm.
+pub fn cie_illuminant_a() -> Vec<f32> {
+    let c2: f64 = 1.435e7 / 2848.0;
+    (0..107)
+        .flat_map(|i| {
+            let lambda = 300.0 + 5.0 * i as f64;
+            let v = 100.0 * (560.0 / lambda).powi(5) * ((c2 / 560.0).exp() - 1.0) / ((c2 / lambda).exp() - 1.0);
+            [lambda as f32, v as f32]
+        })
+        .collect()
+}
+
+/// Mean spectral distribution of CIE daylight, see [`cie_illuminant_d`].
+#[rustfmt::skip]
+pub const CIE_ILLUM_S0: [f32; 108] = [
+    300.0, 0.04, 310.0, 6.0, 320.0, 29.6, 330.0, 55.3,
+    340.0, 57.3, 350.0, 61.8, 360.0, 61.5, 370.0, 68.8,
+    380.0, 63.4, 390.0, 65.8, 400.0, 94.8, 410.0, 104.8,
+    420.0, 105.9, 430.0, 96.8, 440.0, 113.9, 450.0, 125.6,
+    460.0, 125.5, 470.0, 121.3, 480.0, 121.3, 490.0, 113.5,
+    500.0, 113.1, 510.0, 110.8, 520.0, 106.5, 530.0, 108.8,
+    540.0, 105.3, 550.0, 104.4, 560.0, 100.0, 570.0, 96.0,
+    580.0, 95.1, 590.0, 89.1, 600.0, 90.5, 610.0, 90.3,
+    620.0, 88.4, 630.0, 84.0, 640.0, 85.1, 650.0, 81.9,
+    660.0, 82.6, 670.0, 84.9, 680.0, 81.3, 690.0, 71.9,
+    700.0, 74.3, 710.0, 76.4, 720.0, 63.3, 730.0, 71.7,
+    740.0, 77.0, 750.0, 65.2, 760.0, 47.7, 770.0, 68.6,
+    780.0, 65.0, 790.0, 66.0, 800.0, 61.0, 810.0, 53.3,
+    820.0, 58.9, 830.0, 61.9
+];
+
+/// First characteristic vector of CIE daylight, see [`cie_illuminant_d`].
+#[rustfmt::skip]
+pub const CIE_ILLUM_S1: [f32; 108] = [
+    300.0, 0.02, 310.0, 4.5, 320.0, 22.4, 330.0, 42.0,
+    340.0, 40.6, 350.0, 41.6, 360.0, 38.0, 370.0, 42.4,
+    380.0, 38.5, 390.0, 35.0, 400.0, 43.4, 410.0, 46.3,
+    420.0, 43.9, 430.0, 37.1, 440.0, 36.7, 450.0, 35.9,
+    460.0, 32.6, 470.0, 27.9, 480.0, 24.3, 490.0, 20.1,
+    500.0, 16.2, 510.0, 13.2, 520.0, 8.6, 530.0, 6.1,
+    540.0, 4.2, 550.0, 1.9, 560.0, 0.0, 570.0, -1.6,
+    580.0, -3.5, 590.0, -3.5, 600.0, -5.8, 610.0, -7.2,
+    620.0, -8.6, 630.0, -9.5, 640.0, -10.9, 650.0, -10.7,
+    660.0, -12.0, 670.0, -14.0, 680.0, -13.6, 690.0, -12.0,
+    700.0, -13.3, 710.0, -12.9, 720.0, -10.6, 730.0, -11.6,
+    740.0, -12.2, 750.0, -10.2, 760.0, -7.8, 770.0, -11.2,
+    780.0, -10.4, 790.0, -10.6, 800.0, -9.7, 810.0, -8.3,
+    820.0, -9.3, 830.0, -9.8
+];
+
+/// Second characteristic vector of CIE daylight, see [`cie_illuminant_d`].
+#[rustfmt::skip]
+pub const CIE_ILLUM_S2: [f32; 108] = [
+    300.0, 0.0, 310.0, 2.0, 320.0, 4.0, 330.0, 8.5,
+    340.0, 7.8, 350.0, 6.7, 360.0, 5.3, 370.0, 6.1,
+    380.0, 3.0, 390.0, 1.2, 400.0, -1.1, 410.0, -0.5,
+    420.0, -0.7, 430.0, -1.2, 440.0, -2.6, 450.0, -2.9,
+    460.0, -2.8, 470.0, -2.6, 480.0, -2.6, 490.0, -1.8,
+    500.0, -1.5, 510.0, -1.3, 520.0, -1.2, 530.0, -1.0,
+    540.0, -0.5, 550.0, -0.3, 560.0, 0.0, 570.0, 0.2,
+    580.0, 0.5, 590.0, 2.1, 600.0, 3.2, 610.0, 4.1,
+    620.0, 4.7, 630.0, 5.1, 640.0, 6.7, 650.0, 7.3,
+    660.0, 8.6, 670.0, 9.8, 680.0, 10.2, 690.0, 8.3,
+    700.0, 9.6, 710.0, 8.5, 720.0, 7.0, 730.0, 7.6,
+    740.0, 8.0, 750.0, 6.7, 760.0, 5.2, 770.0, 7.4,
+    780.0, 6.8, 790.0, 7.0, 800.0, 6.4, 810.0, 5.5,
+    820.0, 6.1, 830.0, 6.5
+];
+
+/// CIE standard illuminant F1, representing a standard halophosphate daylight fluorescent lamp with a correlated
+/// color temperature of 6430 K.
+#[rustfmt::skip]
+pub const CIE_ILLUM_F1: [f32; 162] = [
+    380.0, 1.87, 385.0, 2.36, 390.0, 2.94, 395.0, 3.47,
+    400.0, 5.17, 405.0, 19.49, 410.0, 6.13, 415.0, 6.24,
+    420.0, 7.01, 425.0, 7.79, 430.0, 8.56, 435.0, 43.67,
+    440.0, 16.94, 445.0, 10.72, 450.0, 11.35, 455.0, 11.89,
+    460.0, 12.37, 465.0, 12.75, 470.0, 13.0, 475.0, 13.15,
+    480.0, 13.23, 485.0, 13.17, 490.0, 13.13, 495.0, 12.85,
+    500.0, 12.52, 505.0, 12.2, 510.0, 11.83, 515.0, 11.5,
+    520.0, 11.22, 525.0, 11.05, 530.0, 11.03, 535.0, 11.18,
+    540.0, 11.53, 545.0, 27.74, 550.0, 17.05, 555.0, 13.55,
+    560.0, 14.33, 565.0, 15.01, 570.0, 15.52, 575.0, 18.29,
+    580.0, 19.55, 585.0, 15.48, 590.0, 14.91, 595.0, 14.15,
+    600.0, 13.22, 605.0, 12.19, 610.0, 11.12, 615.0, 10.03,
+    620.0, 8.95, 625.0, 7.96, 630.0, 7.02, 635.0, 6.2,
+    640.0, 5.42, 645.0, 4.73, 650.0, 4.15, 655.0, 3.64,
+    660.0, 3.2, 665.0, 2.81, 670.0, 2.47, 675.0, 2.18,
+    680.0, 1.93, 685.0, 1.72, 690.0, 1.67, 695.0, 1.43,
+    700.0, 1.29, 705.0, 1.19, 710.0, 1.08, 715.0, 0.96,
+    720.0, 0.88, 725.0, 0.81, 730.0, 0.77, 735.0, 0.75,
+    740.0, 0.73, 745.0, 0.68, 750.0, 0.69, 755.0, 0.64,
+    760.0, 0.68, 765.0, 0.69, 770.0, 0.61, 775.0, 0.52,
+    780.0, 0.43
+];
+
+/// CIE standard illuminant F2, representing a standard cool white fluorescent lamp with a correlated color
+/// temperature of 4230 K.
+#[rustfmt::skip]
+pub const CIE_ILLUM_F2: [f32; 162] = [
+    380.0, 1.18, 385.0, 1.48, 390.0, 1.84, 395.0, 2.15,
+    400.0, 3.44, 405.0, 15.69, 410.0, 3.85, 415.0, 3.74,
+    420.0, 4.19, 425.0, 4.62, 430.0, 5.06, 435.0, 34.98,
+    440.0, 11.81, 445.0, 6.27, 450.0, 6.63, 455.0, 6.93,
+    460.0, 7.19, 465.0, 7.4, 470.0, 7.54, 475.0, 7.62,
+    480.0, 7.65, 485.0, 7.62, 490.0, 7.62, 495.0, 7.45,
+    500.0, 7.28, 505.0, 7.15, 510.0, 7.05, 515.0, 7.04,
+    520.0, 7.16, 525.0, 7.47, 530.0, 8.04, 535.0, 8.88,
+    540.0, 10.01, 545.0, 24.88, 550.0, 16.64, 555.0, 14.59,
+    560.0, 16.16, 565.0, 17.56, 570.0, 18.62, 575.0, 21.47,
+    580.0, 22.79, 585.0, 19.29, 590.0, 18.66, 595.0, 17.73,
+    600.0, 16.54, 605.0, 15.21, 610.0, 13.8, 615.0, 12.36,
+    620.0, 10.95, 625.0, 9.65, 630.0, 8.4, 635.0, 7.32,
+    640.0, 6.31, 645.0, 5.43, 650.0, 4.68, 655.0, 4.02,
+    660.0, 3.45, 665.0, 2.96, 670.0, 2.55, 675.0, 2.19,
+    680.0, 1.89, 685.0, 1.64, 690.0, 1.53, 695.0, 1.27,
+    700.0, 1.1, 705.0, 0.99, 710.0, 0.88, 715.0, 0.76,
+    720.0, 0.68, 725.0, 0.61, 730.0, 0.56, 735.0, 0.54,
+    740.0, 0.51, 745.0, 0.47, 750.0, 0.47, 755.0, 0.43,
+    760.0, 0.46, 765.0, 0.47, 770.0, 0.4, 775.0, 0.33,
+    780.0, 0.27
+];
+
+/// CIE standard illuminant F3, representing a standard white fluorescent lamp with a correlated color temperature
+/// of 3450 K.
+#[rustfmt::skip]
+pub const CIE_ILLUM_F3: [f32; 162] = [
+    380.0, 0.82, 385.0, 1.02, 390.0, 1.26, 395.0, 1.44,
+    400.0, 2.57, 405.0, 14.36, 410.0, 2.7, 415.0, 2.45,
+    420.0, 2.73, 425.0, 3.0, 430.0, 3.28, 435.0, 31.85,
+    440.0, 9.47, 445.0, 4.02, 450.0, 4.25, 455.0, 4.44,
+    460.0, 4.59, 465.0, 4.72, 470.0, 4.8, 475.0, 4.86,
+    480.0, 4.87, 485.0, 4.85, 490.0, 4.88, 495.0, 4.77,
+    500.0, 4.67, 505.0, 4.62, 510.0, 4.62, 515.0, 4.73,
+    520.0, 4.99, 525.0, 5.48, 530.0, 6.25, 535.0, 7.34,
+    540.0, 8.78, 545.0, 23.82, 550.0, 16.14, 555.0, 14.59,
+    560.0, 16.63, 565.0, 18.49, 570.0, 19.95, 575.0, 23.11,
+    580.0, 24.69, 585.0, 21.41, 590.0, 20.85, 595.0, 19.93,
+    600.0, 18.67, 605.0, 17.22, 610.0, 15.65, 615.0, 14.04,
+    620.0, 12.45, 625.0, 10.95, 630.0, 9.51, 635.0, 8.27,
+    640.0, 7.11, 645.0, 6.09, 650.0, 5.22, 655.0, 4.45,
+    660.0, 3.8, 665.0, 3.23, 670.0, 2.75, 675.0, 2.33,
+    680.0, 1.99, 685.0, 1.7, 690.0, 1.55, 695.0, 1.27,
+    700.0, 1.09, 705.0, 0.96, 710.0, 0.83, 715.0, 0.71,
+    720.0, 0.62, 725.0, 0.54, 730.0, 0.49, 735.0, 0.46,
+    740.0, 0.43, 745.0, 0.39, 750.0, 0.39, 755.0, 0.35,
+    760.0, 0.38, 765.0, 0.39, 770.0, 0.33, 775.0, 0.28,
+    780.0, 0.21
+];
+
+/// CIE standard illuminant F4, representing a standard warm white fluorescent lamp with a correlated color
+/// temperature of 2940 K.
+#[rustfmt::skip]
+pub const CIE_ILLUM_F4: [f32; 162] = [
+    380.0, 0.57, 385.0, 0.7, 390.0, 0.87, 395.0, 0.98,
+    400.0, 2.01, 405.0, 13.75, 410.0, 1.95, 415.0, 1.59,
+    420.0, 1.76, 425.0, 1.93, 430.0, 2.1, 435.0, 30.28,
+    440.0, 8.03, 445.0, 2.55, 450.0, 2.7, 455.0, 2.82,
+    460.0, 2.91, 465.0, 2.99, 470.0, 3.04, 475.0, 3.08,
+    480.0, 3.09, 485.0, 3.09, 490.0, 3.14, 495.0, 3.06,
+    500.0, 3.0, 505.0, 2.98, 510.0, 3.01, 515.0, 3.14,
+    520.0, 3.41, 525.0, 3.9, 530.0, 4.69, 535.0, 5.81,
+    540.0, 7.32, 545.0, 22.59, 550.0, 15.11, 555.0, 13.88,
+    560.0, 16.33, 565.0, 18.68, 570.0, 20.64, 575.0, 24.28,
+    580.0, 26.26, 585.0, 23.28, 590.0, 22.94, 595.0, 22.14,
+    600.0, 20.91, 605.0, 19.43, 610.0, 17.74, 615.0, 16.0,
+    620.0, 14.42, 625.0, 12.56, 630.0, 10.93, 635.0, 9.52,
+    640.0, 8.18, 645.0, 7.01, 650.0, 6.0, 655.0, 5.11,
+    660.0, 4.36, 665.0, 3.69, 670.0, 3.13, 675.0, 2.64,
+    680.0, 2.24, 685.0, 1.91, 690.0, 1.7, 695.0, 1.39,
+    700.0, 1.18, 705.0, 1.03, 710.0, 0.88, 715.0, 0.74,
+    720.0, 0.64, 725.0, 0.54, 730.0, 0.49, 735.0, 0.46,
+    740.0, 0.42, 745.0, 0.37, 750.0, 0.37, 755.0, 0.33,
+    760.0, 0.35, 765.0, 0.36, 770.0, 0.31, 775.0, 0.26,
+    780.0, 0.19
+];
+
+/// CIE standard illuminant F5, representing a standard daylight fluorescent lamp with a correlated color
+/// temperature of 6350 K.
+#[rustfmt::skip]
+pub const CIE_ILLUM_F5: [f32; 162] = [
+    380.0, 1.87, 385.0, 2.35, 390.0, 2.92, 395.0, 3.45,
+    400.0, 5.1, 405.0, 18.91, 410.0, 6.0, 415.0, 6.11,
+    420.0, 6.85, 425.0, 7.58, 430.0, 8.31, 435.0, 40.76,
+    440.0, 16.06, 445.0, 10.32, 450.0, 10.91, 455.0, 11.4,
+    460.0, 11.83, 465.0, 12.17, 470.0, 12.4, 475.0, 12.54,
+    480.0, 12.58, 485.0, 12.52, 490.0, 12.47, 495.0, 12.2,
+    500.0, 11.89, 505.0, 11.61, 510.0, 11.33, 515.0, 11.1,
+    520.0, 10.96, 525.0, 10.97, 530.0, 11.16, 535.0, 11.54,
+    540.0, 12.12, 545.0, 27.78, 550.0, 17.73, 555.0, 14.47,
+    560.0, 15.2, 565.0, 15.77, 570.0, 16.1, 575.0, 18.54,
+    580.0, 19.5, 585.0, 15.39, 590.0, 14.64, 595.0, 13.72,
+    600.0, 12.69, 605.0, 11.57, 610.0, 10.45, 615.0, 9.35,
+    620.0, 8.29, 625.0, 7.32, 630.0, 6.41, 635.0, 5.63,
+    640.0, 4.9, 645.0, 4.26, 650.0, 3.72, 655.0, 3.25,
+    660.0, 2.83, 665.0, 2.49, 670.0, 2.19, 675.0, 1.93,
+    680.0, 1.71, 685.0, 1.52, 690.0, 1.48, 695.0, 1.26,
+    700.0, 1.13, 705.0, 1.05, 710.0, 0.96, 715.0, 0.85,
+    720.0, 0.78, 725.0, 0.72, 730.0, 0.68, 735.0, 0.67,
+    740.0, 0.65, 745.0, 0.61, 750.0, 0.62, 755.0, 0.59,
+    760.0, 0.62, 765.0, 0.64, 770.0, 0.55, 775.0, 0.47,
+    780.0, 0.4
+];
+
+/// CIE standard illuminant F6, representing a standard lite white fluorescent lamp with a correlated color
+/// temperature of 4150 K.
+#[rustfmt::skip]
+pub const CIE_ILLUM_F6: [f32; 162] = [
+    380.0, 1.05, 385.0, 1.31, 390.0, 1.63, 395.0, 1.9,
+    400.0, 3.11, 405.0, 14.8, 410.0, 3.43, 415.0, 3.3,
+    420.0, 3.68, 425.0, 4.07, 430.0, 4.45, 435.0, 32.61,
+    440.0, 10.74, 445.0, 5.48, 450.0, 5.78, 455.0, 6.03,
+    460.0, 6.25, 465.0, 6.41, 470.0, 6.52, 475.0, 6.58,
+    480.0, 6.59, 485.0, 6.56, 490.0, 6.56, 495.0, 6.42,
+    500.0, 6.28, 505.0, 6.2, 510.0, 6.19, 515.0, 6.3,
+    520.0, 6.6, 525.0, 7.12, 530.0, 7.94, 535.0, 9.07,
+    540.0, 10.49, 545.0, 25.22, 550.0, 17.46, 555.0, 15.63,
+    560.0, 17.22, 565.0, 18.53, 570.0, 19.43, 575.0, 21.97,
+    580.0, 23.01, 585.0, 19.41, 590.0, 18.56, 595.0, 17.42,
+    600.0, 16.09, 605.0, 14.64, 610.0, 13.15, 615.0, 11.68,
+    620.0, 10.25, 625.0, 8.95, 630.0, 7.74, 635.0, 6.69,
+    640.0, 5.71, 645.0, 4.87, 650.0, 4.16, 655.0, 3.55,
+    660.0, 3.02, 665.0, 2.57, 670.0, 2.2, 675.0, 1.87,
+    680.0, 1.6, 685.0, 1.37, 690.0, 1.29, 695.0, 1.05,
+    700.0, 0.91, 705.0, 0.81, 710.0, 0.71, 715.0, 0.61,
+    720.0, 0.54, 725.0, 0.48, 730.0, 0.44, 735.0, 0.43,
+    740.0, 0.4, 745.0, 0.37, 750.0, 0.38, 755.0, 0.35,
+    760.0, 0.39, 765.0, 0.41, 770.0, 0.33, 775.0, 0.26,
+    780.0, 0.21
+];
+
+/// CIE standard illuminant F7, representing a broadband D65 simulator with a correlated color temperature of 6500
+/// K.
+#[rustfmt::skip]
+pub const CIE_ILLUM_F7: [f32; 162] = [
+    380.0, 2.56, 385.0, 3.18, 390.0, 3.84, 395.0, 4.53,
+    400.0, 6.15, 405.0, 19.37, 410.0, 7.37, 415.0, 7.05,
+    420.0, 7.71, 425.0, 8.41, 430.0, 9.15, 435.0, 44.14,
+    440.0, 17.52, 445.0, 11.35, 450.0, 12.0, 455.0, 12.58,
+    460.0, 13.08, 465.0, 13.45, 470.0, 13.71, 475.0, 13.88,
+    480.0, 13.95, 485.0, 13.93, 490.0, 13.82, 495.0, 13.64,
+    500.0, 13.43, 505.0, 13.25, 510.0, 13.08, 515.0, 12.93,
+    520.0, 12.78, 525.0, 12.6, 530.0, 12.44, 535.0, 12.33,
+    540.0, 12.26, 545.0, 29.52, 550.0, 17.05, 555.0, 12.44,
+    560.0, 12.58, 565.0, 12.72, 570.0, 12.83, 575.0, 15.46,
+    580.0, 16.75, 585.0, 12.83, 590.0, 12.67, 595.0, 12.45,
+    600.0, 12.19, 605.0, 11.89, 610.0, 11.6, 615.0, 11.35,
+    620.0, 11.12, 625.0, 10.95, 630.0, 10.76, 635.0, 10.42,
+    640.0, 10.11, 645.0, 10.04, 650.0, 10.02, 655.0, 10.11,
+    660.0, 9.87, 665.0, 8.65, 670.0, 7.27, 675.0, 6.44,
+    680.0, 5.83, 685.0, 5.41, 690.0, 5.04, 695.0, 4.57,
+    700.0, 4.12, 705.0, 3.77, 710.0, 3.46, 715.0, 3.08,
+    720.0, 2.73, 725.0, 2.47, 730.0, 2.25, 735.0, 2.06,
+    740.0, 1.9, 745.0, 1.75, 750.0, 1.62, 755.0, 1.54,
+    760.0, 1.45, 765.0, 1.32, 770.0, 1.17, 775.0, 0.99,
+    780.0, 0.81
+];
+
+/// CIE standard illuminant F8, representing a broadband D50 simulator with a correlated color temperature of 5000
+/// K.
+#[rustfmt::skip]
+pub const CIE_ILLUM_F8: [f32; 162] = [
+    380.0, 1.21, 385.0, 1.5, 390.0, 1.81, 395.0, 2.13,
+    400.0, 3.17, 405.0, 13.08, 410.0, 3.83, 415.0, 3.45,
+    420.0, 3.86, 425.0, 4.42, 430.0, 5.09, 435.0, 34.1,
+    440.0, 12.42, 445.0, 7.68, 450.0, 8.6, 455.0, 9.46,
+    460.0, 10.24, 465.0, 10.84, 470.0, 11.33, 475.0, 11.71,
+    480.0, 11.98, 485.0, 12.17, 490.0, 12.28, 495.0, 12.32,
+    500.0, 12.35, 505.0, 12.44, 510.0, 12.55, 515.0, 12.68,
+    520.0, 12.77, 525.0, 12.72, 530.0, 12.6, 535.0, 12.43,
+    540.0, 12.22, 545.0, 28.96, 550.0, 16.51, 555.0, 11.79,
+    560.0, 11.76, 565.0, 11.77, 570.0, 11.84, 575.0, 14.61,
+    580.0, 16.11, 585.0, 12.34, 590.0, 12.53, 595.0, 12.72,
+    600.0, 12.92, 605.0, 13.12, 610.0, 13.34, 615.0, 13.61,
+    620.0, 13.87, 625.0, 14.07, 630.0, 14.2, 635.0, 14.16,
+    640.0, 14.13, 645.0, 14.34, 650.0, 14.5, 655.0, 14.46,
+    660.0, 14.0, 665.0, 12.58, 670.0, 10.99, 675.0, 9.98,
+    680.0, 9.22, 685.0, 8.62, 690.0, 8.07, 695.0, 7.39,
+    700.0, 6.71, 705.0, 6.16, 710.0, 5.63, 715.0, 5.03,
+    720.0, 4.46, 725.0, 4.02, 730.0, 3.66, 735.0, 3.36,
+    740.0, 3.09, 745.0, 2.85, 750.0, 2.65, 755.0, 2.51,
+    760.0, 2.37, 765.0, 2.15, 770.0, 1.89, 775.0, 1.61,
+    780.0, 1.32
+];
+
+/// CIE standard illuminant F9, representing a broadband cool white deluxe fluorescent lamp with a correlated color
+/// temperature of 4150 K.
+#[rustfmt::skip]
+pub const CIE_ILLUM_F9: [f32; 162] = [
+    380.0, 0.9, 385.0, 1.12, 390.0, 1.36, 395.0, 1.6,
+    400.0, 2.59, 405.0, 12.8, 410.0, 3.05, 415.0, 2.56,
+    420.0, 2.86, 425.0, 3.3, 430.0, 3.82, 435.0, 32.62,
+    440.0, 10.77, 445.0, 5.84, 450.0, 6.57, 455.0, 7.25,
+    460.0, 7.86, 465.0, 8.35, 470.0, 8.75, 475.0, 9.06,
+    480.0, 9.31, 485.0, 9.48, 490.0, 9.61, 495.0, 9.68,
+    500.0, 9.74, 505.0, 9.88, 510.0, 10.04, 515.0, 10.26,
+    520.0, 10.48, 525.0, 10.63, 530.0, 10.76, 535.0, 10.96,
+    540.0, 11.18, 545.0, 27.71, 550.0, 16.29, 555.0, 12.28,
+    560.0, 12.74, 565.0, 13.21, 570.0, 13.65, 575.0, 16.57,
+    580.0, 18.14, 585.0, 14.55, 590.0, 14.65, 595.0, 14.66,
+    600.0, 14.61, 605.0, 14.5, 610.0, 14.39, 615.0, 14.4,
+    620.0, 14.47, 625.0, 14.62, 630.0, 14.72, 635.0, 14.55,
+    640.0, 14.4, 645.0, 14.58, 650.0, 14.88, 655.0, 15.51,
+    660.0, 15.47, 665.0, 13.2, 670.0, 10.57, 675.0, 9.18,
+    680.0, 8.25, 685.0, 7.57, 690.0, 7.03, 695.0, 6.35,
+    700.0, 5.72, 705.0, 5.2, 710.0, 4.7, 715.0, 4.15,
+    720.0, 3.65, 725.0, 3.27, 730.0, 2.93, 735.0, 2.67,
+    740.0, 2.45, 745.0, 2.19, 750.0, 2.0, 755.0, 1.8,
+    760.0, 1.76, 765.0, 1.61, 770.0, 1.39, 775.0, 1.12,
+    780.0, 0.89
+];
+
+/// CIE standard illuminant F10, representing a narrow tri-band fluorescent lamp with a correlated color temperature
+/// of 5000 K.
+#[rustfmt::skip]
+pub const CIE_ILLUM_F10: [f32; 162] = [
+    380.0, 1.11, 385.0, 0.63, 390.0, 0.62, 395.0, 0.57,
+    400.0, 1.48, 405.0, 12.16, 410.0, 2.12, 415.0, 2.7,
+    420.0, 3.74, 425.0, 5.14, 430.0, 6.75, 435.0, 34.39,
+    440.0, 14.86, 445.0, 10.4, 450.0, 10.76, 455.0, 10.67,
+    460.0, 10.11, 465.0, 9.27, 470.0, 8.29, 475.0, 7.29,
+    480.0, 7.91, 485.0, 16.64, 490.0, 16.73, 495.0, 10.44,
+    500.0, 5.94, 505.0, 3.34, 510.0, 2.35, 515.0, 1.88,
+    520.0, 1.59, 525.0, 1.47, 530.0, 1.8, 535.0, 5.71,
+    540.0, 40.98, 545.0, 73.69, 550.0, 33.61, 555.0, 8.24,
+    560.0, 3.38, 565.0, 2.47, 570.0, 2.14, 575.0, 4.86,
+    580.0, 11.45, 585.0, 14.79, 590.0, 12.16, 595.0, 8.97,
+    600.0, 6.52, 605.0, 8.31, 610.0, 44.12, 615.0, 34.55,
+    620.0, 12.09, 625.0, 12.15, 630.0, 10.52, 635.0, 4.43,
+    640.0, 1.95, 645.0, 2.19, 650.0, 3.19, 655.0, 2.77,
+    660.0, 2.29, 665.0, 2.0, 670.0, 1.52, 675.0, 1.35,
+    680.0, 1.47, 685.0, 1.79, 690.0, 1.74, 695.0, 1.02,
+    700.0, 1.14, 705.0, 3.32, 710.0, 4.49, 715.0, 2.05,
+    720.0, 0.49, 725.0, 0.24, 730.0, 0.21, 735.0, 0.21,
+    740.0, 0.24, 745.0, 0.24, 750.0, 0.21, 755.0, 0.17,
+    760.0, 0.21, 765.0, 0.22, 770.0, 0.17, 775.0, 0.12,
+    780.0, 0.09
+];
+
+/// CIE standard illuminant F11, representing a narrow tri-band fluorescent lamp with a correlated color temperature
+/// of 4000 K.
+#[rustfmt::skip]
+pub const CIE_ILLUM_F11: [f32; 162] = [
+    380.0, 0.91, 385.0, 0.63, 390.0, 0.46, 395.0, 0.37,
+    400.0, 1.29, 405.0, 12.68, 410.0, 1.59, 415.0, 1.79,
+    420.0, 2.46, 425.0, 3.33, 430.0, 4.49, 435.0, 33.94,
+    440.0, 12.13, 445.0, 6.95, 450.0, 7.19, 455.0, 7.12,
+    460.0, 6.72, 465.0, 6.13, 470.0, 5.46, 475.0, 4.79,
+    480.0, 5.66, 485.0, 14.29, 490.0, 14.96, 495.0, 8.97,
+    500.0, 4.72, 505.0, 2.33, 510.0, 1.47, 515.0, 1.1,
+    520.0, 0.89, 525.0, 0.83, 530.0, 1.18, 535.0, 4.9,
+    540.0, 39.59, 545.0, 72.84, 550.0, 32.61, 555.0, 7.52,
+    560.0, 2.83, 565.0, 1.96, 570.0, 1.67, 575.0, 4.43,
+    580.0, 11.28, 585.0, 14.76, 590.0, 12.73, 595.0, 9.74,
+    600.0, 7.33, 605.0, 9.72, 610.0, 55.27, 615.0, 42.58,
+    620.0, 13.18, 625.0, 13.16, 630.0, 12.26, 635.0, 5.11,
+    640.0, 2.07, 645.0, 2.34, 650.0, 3.58, 655.0, 3.01,
+    660.0, 2.48, 665.0, 2.14, 670.0, 1.54, 675.0, 1.33,
+    680.0, 1.46, 685.0, 1.94, 690.0, 2.0, 695.0, 1.2,
+    700.0, 1.35, 705.0, 4.1, 710.0, 5.58, 715.0, 2.51,
+    720.0, 0.57, 725.0, 0.27, 730.0, 0.23, 735.0, 0.21,
+    740.0, 0.24, 745.0, 0.24, 750.0, 0.2, 755.0, 0.24,
+    760.0, 0.32, 765.0, 0.26, 770.0, 0.16, 775.0, 0.12,
+    780.0, 0.09
+];
+
+/// CIE standard illuminant F12, representing a narrow tri-band fluorescent lamp with a correlated color temperature
+/// of 3000 K.
+#[rustfmt::skip]
+pub const CIE_ILLUM_F12: [f32; 162] = [
+    380.0, 0.96, 385.0, 0.64, 390.0, 0.4, 395.0, 0.33,
+    400.0, 1.19, 405.0, 12.48, 410.0, 1.12, 415.0, 0.94,
+    420.0, 1.08, 425.0, 1.37, 430.0, 1.78, 435.0, 29.05,
+    440.0, 7.9, 445.0, 2.65, 450.0, 2.71, 455.0, 2.65,
+    460.0, 2.49, 465.0, 2.33, 470.0, 2.1, 475.0, 1.91,
+    480.0, 3.01, 485.0, 10.83, 490.0, 11.88, 495.0, 6.88,
+    500.0, 3.43, 505.0, 1.49, 510.0, 0.92, 515.0, 0.71,
+    520.0, 0.6, 525.0, 0.63, 530.0, 1.1, 535.0, 4.56,
+    540.0, 34.4, 545.0, 65.4, 550.0, 29.48, 555.0, 7.16,
+    560.0, 3.08, 565.0, 2.47, 570.0, 2.27, 575.0, 5.09,
+    580.0, 11.96, 585.0, 15.32, 590.0, 14.27, 595.0, 11.86,
+    600.0, 9.28, 605.0, 12.31, 610.0, 68.53, 615.0, 53.02,
+    620.0, 14.67, 625.0, 14.38, 630.0, 14.71, 635.0, 6.46,
+    640.0, 2.57, 645.0, 2.75, 650.0, 4.18, 655.0, 3.44,
+    660.0, 2.81, 665.0, 2.42, 670.0, 1.64, 675.0, 1.36,
+    680.0, 1.49, 685.0, 2.14, 690.0, 2.34, 695.0, 1.42,
+    700.0, 1.61, 705.0, 5.04, 710.0, 6.98, 715.0, 3.19,
+    720.0, 0.71, 725.0, 0.3, 730.0, 0.26, 735.0, 0.23,
+    740.0, 0.28, 745.0, 0.28, 750.0, 0.21, 755.0, 0.17,
+    760.0, 0.21, 765.0, 0.19, 770.0, 0.15, 775.0, 0.1,
+    780.0, 0.05
+];
@@ -0,0 +1,351 @@
+use std::lazy::SyncLazy;
+use rayon::prelude::*;
+use crate::spectrum::cie::{cie_lambda, CIE_LAMBDA_END, CIE_LAMBDA_START, CIE_SAMPLES, CIE_X, CIE_Y, CIE_Z};
+use crate::spectrum::std_illuminant::CIE_ILLUM_D65;
+use crate::spectrum::wavelengths::{SampledWavelengths, SpectrumSample, N_WAVELENGTH_SAMPLES};
+use crate::spectrum::{interpolate_interleaved, XYZ_TO_RGB};
+
+/// A smooth spectrum bounded to `[0, 1]` given by a quadratic polynomial in wavelength mapped through a sigmoid,
+/// which is used to upsample RGB reflectances to spectra.
+///
+/// See Jakob and Hanika, "A Low-Dimensional Function Space for Efficient Spectral Upsampling" (2019).
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct RgbSigmoidPolynomial {
+    c0: f32,
+    c1: f32,
+    c2: f32
+}
+
+/// The normalized wavelengths of the CIE tables together with weights that integrate a spectrum sampled at them
+/// under illuminant D65 to linear sRGB, normalized so that a constant spectrum of one is white.
+static D65_RGB_WEIGHTS: SyncLazy<Vec<(f64, [f64; 3])>> = SyncLazy::new(|| {
+    let weights: Vec<[f64; 3]> = (0..CIE_SAMPLES)
+        .map(|i| {
+            let d = interpolate_interleaved(&CIE_ILLUM_D65, cie_lambda(i)) as f64;
+            [d * CIE_X[i] as f64, d * CIE_Y[i] as f64, d * CIE_Z[i] as f64]
+        })
+        .collect();
+    let y_sum: f64 = weights.iter().map(|w| w[1]).sum();
+    weights
+        .into_iter()
+        .enumerate()
+        .map(|(i, xyz)| {
+            let xyz = xyz.map(|v| v / y_sum);
+            (normalize_lambda(cie_lambda(i) as f64), XYZ_TO_RGB.map(|m| dot3(&m, &xyz)))
+        })
+        .collect()
+});
+
+const MAX_FIT_ITERATIONS: usize = 64;
+
+impl RgbSigmoidPolynomial {
+    /// Creates a polynomial with coefficients for the wavelength normalized to `[0, 1]` over the range of the CIE
+    /// tables.
+    pub const fn new(c0: f32, c1: f32, c2: f32) -> Self {
+        RgbSigmoidPolynomial { c0, c1, c2 }
+    }
+
+    /// Finds the polynomial whose spectrum, lit by illuminant D65, has the linear sRGB color `rgb`.
+    ///
+    /// The components of `rgb` are clamped to `[0, 1]`. Gray values map to constant spectra and fully saturated
+    /// colors are matched as closely as the representation allows.
+    pub fn fit(rgb: [f32; 3]) -> Self {
+        let rgb = rgb.map(|v| v.clamp(0.0, 1.0) as f64);
+        if rgb[0] == rgb[1] && rgb[1] == rgb[2] {
+            return RgbSigmoidPolynomial::new(0.0, 0.0, inverse_sigmoid(rgb[0]) as f32)
+        }
+        let c = fit_coefficients(&rgb, [0.0; 3]);
+        RgbSigmoidPolynomial::new(c[0] as f32, c[1] as f32, c[2] as f32)
+    }
+
+    /// Returns the polynomial for the linear sRGB color `rgb`, which is interpolated from polynomials fitted to a
+    /// grid of colors ahead of time.
+    ///
+    /// This is much faster than [`fit`](Self::fit) and differs from it by less than the precision of 8-bit colors.
+    pub fn from_rgb(rgb: [f32; 3]) -> Self {
+        let rgb = rgb.map(|v| v.clamp(0.0, 1.0));
+        if rgb[0] == rgb[1] && rgb[1] == rgb[2] {
+            return RgbSigmoidPolynomial::new(0.0, 0.0, inverse_sigmoid(rgb[0] as f64) as f32)
+        }
+        RGB_TO_SPECTRUM_TABLE.lookup(rgb)
+    }
+
+    /// Evaluates the spectrum at wavelength `lambda` in nanometers.
+    #[inline]
+    pub fn evaluate(&self, lambda: f32) -> f32 {
+        let t = normalize_lambda(lambda as f64);
+        sigmoid(self.c0 as f64 * t * t + self.c1 as f64 * t + self.c2 as f64) as f32
+    }
+
+    /// Evaluates the spectrum at the wavelengths `lambda`.
+    pub fn sample(&self, lambda: &SampledWavelengths) -> SpectrumSample {
+        let mut c = [0.0; N_WAVELENGTH_SAMPLES];
+        for (i, c) in c.iter_mut().enumerate() {
+            *c = self.evaluate(lambda[i]);
+        }
+        SpectrumSample::from_coefficients(c)
+    }
+
+    /// Returns the maximum value of the spectrum over the range of the CIE tables.
+    pub fn max_value(&self) -> f32 {
+        let mut result = f32::max(self.evaluate(CIE_LAMBDA_START), self.evaluate(CIE_LAMBDA_END));
+        if self.c0 != 0.0 {
+            let t = -self.c1 / (2.0 * self.c0);
+            if (0.0..=1.0).contains(&t) {
+                result = f32::max(result, self.evaluate(CIE_LAMBDA_START + t * (CIE_LAMBDA_END - CIE_LAMBDA_START)));
+            }
+        }
+        result
+    }
+}
+
+/// Number of nodes of [`RgbToSpectrumTable`] along each of its axes.
+const TABLE_RESOLUTION: usize = 32;
+
+/// The coefficients of sigmoid polynomials fitted to a grid of linear sRGB colors, see pbrt-v4's
+/// `RGBToSpectrumTable`.
+///
+/// Colors are located by their largest component `z` and the ratios of the other two components to it. There is a
+/// grid for each choice of the largest component, whose nodes along `z` are packed more densely towards black and
+/// white, where the polynomials change fastest.
+struct RgbToSpectrumTable {
+    z_nodes: Vec<f32>,
+    /// Coefficients indexed by the largest component, `z`, the ratio of the second and that of the third component.
+    coefficients: Vec<[f32; 3]>
+}
+
+static RGB_TO_SPECTRUM_TABLE: SyncLazy<RgbToSpectrumTable> = SyncLazy::new(RgbToSpectrumTable::new);
+
+impl RgbToSpectrumTable {
+    fn new() -> Self {
+        const RES: usize = TABLE_RESOLUTION;
+        let smoothstep = |x: f32| x * x * (3.0 - 2.0 * x);
+        let z_nodes: Vec<f32> = (0..RES).map(|i| smoothstep(smoothstep(i as f32 / (RES - 1) as f32))).collect();
+
+        // Fit the polynomials along z for every ratio, starting at a medium brightness and using each fit as the
+        // initial guess for the next, which keeps them on the same branch of solutions and speeds up the fits
+        let columns: Vec<[[f32; 3]; RES]> = (0..3 * RES * RES)
+            .into_par_iter()
+            .map(|i| {
+                let (max_component, y, x) = (i / (RES * RES), (i / RES) % RES, i % RES);
+                let fit_at = |k: usize, guess| {
+                    let z = z_nodes[k] as f64;
+                    let mut rgb = [0.0; 3];
+                    rgb[max_component] = z;
+                    rgb[(max_component + 1) % 3] = x as f64 / (RES - 1) as f64 * z;
+                    rgb[(max_component + 2) % 3] = y as f64 / (RES - 1) as f64 * z;
+                    fit_coefficients(&rgb, guess)
+                };
+                let mut column = [[0.0; 3]; RES];
+                let start = RES / 5;
+                let mut c = [0.0; 3];
+                for (k, node) in column.iter_mut().enumerate().skip(start) {
+                    c = fit_at(k, c);
+                    *node = c.map(|c| c as f32);
+                }
+                c = column[start].map(|c| c as f64);
+                for (k, node) in column.iter_mut().enumerate().take(start).rev() {
+                    c = fit_at(k, c);
+                    *node = c.map(|c| c as f32);
+                }
+                column
+            })
+            .collect();
+
+        let mut coefficients = vec![[0.0; 3]; 3 * RES * RES * RES];
+        for (i, column) in columns.iter().enumerate() {
+            let (max_component, yx) = (i / (RES * RES), i % (RES * RES));
+            for (k, c) in column.iter().enumerate() {
+                coefficients[(max_component * RES + k) * RES * RES + yx] = *c;
+            }
+        }
+        RgbToSpectrumTable { z_nodes, coefficients }
+    }
+
+    /// Trilinearly interpolates the coefficients for the color `rgb` with components in `[0, 1]`.
+    fn lookup(&self, rgb: [f32; 3]) -> RgbSigmoidPolynomial {
+        const RES: usize = TABLE_RESOLUTION;
+        let max_component = if rgb[0] > rgb[1] {
+            if rgb[0] > rgb[2] { 0 } else { 2 }
+        } else if rgb[1] > rgb[2] {
+            1
+        } else {
+            2
+        };
+        let z = rgb[max_component];
+        let scale = (RES - 1) as f32 / z;
+        let x = rgb[(max_component + 1) % 3] * scale;
+        let y = rgb[(max_component + 2) % 3] * scale;
+
+        let xi = (x as usize).min(RES - 2);
+        let yi = (y as usize).min(RES - 2);
+        let zi = self.z_nodes.partition_point(|&node| node <= z).clamp(1, RES - 1) - 1;
+        let (dx, dy) = (x - xi as f32, y - yi as f32);
+        let dz = (z - self.z_nodes[zi]) / (self.z_nodes[zi + 1] - self.z_nodes[zi]);
+
+        let mut c = [0.0; 3];
+        for (k, wz) in [(zi, 1.0 - dz), (zi + 1, dz)] {
+            for (j, wy) in [(yi, 1.0 - dy), (yi + 1, dy)] {
+                for (i, wx) in [(xi, 1.0 - dx), (xi + 1, dx)] {
+                    let node = &self.coefficients[((max_component * RES + k) * RES + j) * RES + i];
+                    for (c, node) in c.iter_mut().zip(node) {
+                        *c += wx * wy * wz * node;
+                    }
+                }
+            }
+        }
+        RgbSigmoidPolynomial::new(c[0], c[1], c[2])
+    }
+}
+
+/// Fits the coefficients of a sigmoid polynomial to `rgb` with the Gauss-Newton method, starting at `c`.
+fn fit_coefficients(rgb: &[f64; 3], mut c: [f64; 3]) -> [f64; 3] {
+    let (mut residual, mut jacobian) = fit_residual(&c, rgb);
+    let mut error = dot3(&residual, &residual);
+    for _ in 0..MAX_FIT_ITERATIONS {
+        if error < 1e-14 {
+            break
+        }
+        // Gauss-Newton step with backtracking
+        let step = match solve3(jacobian, residual.map(|r| -r)) {
+            Some(step) => step,
+            None => break
+        };
+        let mut alpha = 1.0;
+        let mut improved = false;
+        while alpha > 1e-4 {
+            let candidate = [c[0] + alpha * step[0], c[1] + alpha * step[1], c[2] + alpha * step[2]];
+            let (r, j) = fit_residual(&candidate, rgb);
+            let e = dot3(&r, &r);
+            if e < error {
+                c = candidate;
+                residual = r;
+                jacobian = j;
+                error = e;
+                improved = true;
+                break
+            }
+            alpha *= 0.5;
+        }
+        if !improved {
+            break
+        }
+    }
+    c
+}
+
+#[inline]
+fn normalize_lambda(lambda: f64) -> f64 {
+    (lambda - CIE_LAMBDA_START as f64) / (CIE_LAMBDA_END - CIE_LAMBDA_START) as f64
+}
+
+#[inline]
+fn sigmoid(x: f64) -> f64 {
+    if x.is_infinite() {
+        return if x > 0.0 { 1.0 } else { 0.0 }
+    }
+    0.5 + x / (2.0 * (1.0 + x * x).sqrt())
+}
+
+#[inline]
+fn inverse_sigmoid(y: f64) -> f64 {
+    if y <= 0.0 {
+        return f64::NEG_INFINITY
+    }
+    if y >= 1.0 {
+        return f64::INFINITY
+    }
+    (y - 0.5) / (y * (1.0 - y)).sqrt()
+}
+
+/// Returns the difference between the color of the polynomial `c` and `rgb` together with its Jacobian with
+/// respect to the coefficients.
+fn fit_residual(c: &[f64; 3], rgb: &[f64; 3]) -> ([f64; 3], [[f64; 3]; 3]) {
+    let mut residual = rgb.map(|v| -v);
+    let mut jacobian = [[0.0; 3]; 3];
+    for &(t, w) in D65_RGB_WEIGHTS.iter() {
+        let x = c[0] * t * t + c[1] * t + c[2];
+        let s = sigmoid(x);
+        let q = 1.0 + x * x;
+        let ds = 0.5 / (q * q.sqrt());
+        for r in 0..3 {
+            residual[r] += s * w[r];
+            jacobian[r][0] += ds * t * t * w[r];
+            jacobian[r][1] += ds * t * w[r];
+            jacobian[r][2] += ds * w[r];
+        }
+    }
+    (residual, jacobian)
+}
+
+#[inline]
+fn dot3(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// Solves the linear system `a * x = b` with Cramer's rule.
+fn solve3(a: [[f64; 3]; 3], b: [f64; 3]) -> Option<[f64; 3]> {
+    let det = |m: &[[f64; 3]; 3]| {
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1]) - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0]) +
+            m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    };
+    let d = det(&a);
+    if d.abs() < 1e-30 {
+        return None
+    }
+    let mut x = [0.0; 3];
+    for (j, x) in x.iter_mut().enumerate() {
+        let mut m = a;
+        for r in 0..3 {
+            m[r][j] = b[r];
+        }
+        *x = det(&m) / d;
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rng::Rng;
+    use super::*;
+
+    fn fitted_rgb(polynomial: &RgbSigmoidPolynomial) -> [f64; 3] {
+        let c = [polynomial.c0 as f64, polynomial.c1 as f64, polynomial.c2 as f64];
+        fit_residual(&c, &[0.0; 3]).0
+    }
+
+    #[test]
+    fn test_fit_matches_color() {
+        for rgb in [[0.8, 0.2, 0.1], [0.1, 0.5, 0.9], [0.2, 0.2, 0.21], [0.9, 0.9, 0.1]] {
+            let polynomial = RgbSigmoidPolynomial::fit(rgb);
+            let fitted = fitted_rgb(&polynomial);
+            for (a, b) in fitted.iter().zip(rgb) {
+                assert!((a - b as f64).abs() < 1e-3, "{:?} != {:?}", fitted, rgb);
+            }
+            assert!(polynomial.max_value() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn test_table_matches_fit() {
+        let mut rng = Rng::default();
+        for _ in 0..200 {
+            let rgb = [rng.uniform_f32(), rng.uniform_f32(), rng.uniform_f32()];
+            let fitted = fitted_rgb(&RgbSigmoidPolynomial::fit(rgb));
+            let looked_up = fitted_rgb(&RgbSigmoidPolynomial::from_rgb(rgb));
+            for (a, b) in looked_up.iter().zip(fitted) {
+                assert!((a - b).abs() < 1.0 / 255.0, "{:?} != {:?} for {:?}", looked_up, fitted, rgb);
+            }
+        }
+        assert_eq!(RgbSigmoidPolynomial::from_rgb([0.5; 3]), RgbSigmoidPolynomial::fit([0.5; 3]));
+    }
+
+    #[test]
+    fn test_fit_gray() {
+        assert_eq!(RgbSigmoidPolynomial::fit([0.0; 3]).evaluate(550.0), 0.0);
+        assert_eq!(RgbSigmoidPolynomial::fit([1.0; 3]).evaluate(550.0), 1.0);
+        let half = RgbSigmoidPolynomial::fit([0.5; 3]);
+        assert_eq!(half.evaluate(400.0), 0.5);
+        assert_eq!(half.max_value(), 0.5);
+    }
+}
@@ -0,0 +1,179 @@
+use std::ops::*;
+use crate::math::Lerp;
+use crate::sampling::{sample_visible_wavelengths, visible_wavelengths_pdf};
+use crate::spectrum::cie::{cie_matching, CIE_Y_INTEGRAL};
+use crate::spectrum::macros::impl_coefficient_spectrum;
+use crate::spectrum::xyz_to_rgb;
+
+/// Number of wavelengths carried along each path.
+pub const N_WAVELENGTH_SAMPLES: usize = 4;
+
+/// Values of a spectral distribution at the wavelengths of a [`SampledWavelengths`].
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct SpectrumSample {
+    c: [f32; N_WAVELENGTH_SAMPLES]
+}
+
+impl_coefficient_spectrum!(SpectrumSample, N_WAVELENGTH_SAMPLES);
+
+/// A set of wavelengths sampled for a single path, with the first one being the hero wavelength.
+///
+/// The remaining wavelengths are placed at equal offsets from the hero wavelength so that the set stratifies the
+/// sampled range. See Wilkie et al., "Hero Wavelength Spectral Sampling" (2014).
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct SampledWavelengths {
+    lambda: [f32; N_WAVELENGTH_SAMPLES],
+    pdf: [f32; N_WAVELENGTH_SAMPLES]
+}
+
+impl SampledWavelengths {
+    /// Samples wavelengths uniformly over `[lambda_min, lambda_max]`.
+    pub fn sample_uniform(u: f32, lambda_min: f32, lambda_max: f32) -> Self {
+        let mut lambda = [0.0; N_WAVELENGTH_SAMPLES];
+        lambda[0] = f32::lerp(u, lambda_min, lambda_max);
+        let delta = (lambda_max - lambda_min) / N_WAVELENGTH_SAMPLES as f32;
+        for i in 1..N_WAVELENGTH_SAMPLES {
+            lambda[i] = lambda[i - 1] + delta;
+            if lambda[i] > lambda_max {
+                lambda[i] = lambda_min + (lambda[i] - lambda_max);
+            }
+        }
+        SampledWavelengths { lambda, pdf: [1.0 / (lambda_max - lambda_min); N_WAVELENGTH_SAMPLES] }
+    }
+
+    /// Samples wavelengths in the visible range proportionally to the sensitivity of the human eye.
+    pub fn sample_visible(u: f32) -> Self {
+        let mut lambda = [0.0; N_WAVELENGTH_SAMPLES];
+        let mut pdf = [0.0; N_WAVELENGTH_SAMPLES];
+        for (i, (lambda, pdf)) in lambda.iter_mut().zip(pdf.iter_mut()).enumerate() {
+            let mut up = u + i as f32 / N_WAVELENGTH_SAMPLES as f32;
+            if up > 1.0 {
+                up -= 1.0;
+            }
+            *lambda = sample_visible_wavelengths(up);
+            *pdf = visible_wavelengths_pdf(*lambda);
+        }
+        SampledWavelengths { lambda, pdf }
+    }
+
+    /// Returns wavelength `i` in nanometers.
+    #[inline]
+    pub fn lambda(&self, i: usize) -> f32 {
+        self.lambda[i]
+    }
+
+    /// Returns the densities with which the wavelengths were sampled, which are zero for terminated wavelengths.
+    #[inline]
+    pub fn pdf(&self) -> SpectrumSample {
+        SpectrumSample::from_coefficients(self.pdf)
+    }
+
+    /// Drops all wavelengths but the hero wavelength.
+    ///
+    /// This must be called before scattering at a surface whose behavior depends on wavelength, e.g. a dispersive
+    /// dielectric, since the secondary wavelengths would follow different paths.
+    pub fn terminate_secondary(&mut self) {
+        if self.secondary_terminated() {
+            return
+        }
+        for pdf in self.pdf[1..].iter_mut() {
+            *pdf = 0.0;
+        }
+        self.pdf[0] /= N_WAVELENGTH_SAMPLES as f32;
+    }
+
+    pub fn secondary_terminated(&self) -> bool {
+        self.pdf[1..].iter().all(|&pdf| pdf == 0.0)
+    }
+}
+
+impl Index<usize> for SampledWavelengths {
+    type Output = f32;
+
+    #[inline]
+    fn index(&self, index: usize) -> &f32 {
+        &self.lambda[index]
+    }
+}
+
+impl SpectrumSample {
+    /// Returns the average of the coefficients.
+    #[inline]
+    pub fn average(&self) -> f32 {
+        self.c.iter().sum::<f32>() / N_WAVELENGTH_SAMPLES as f32
+    }
+
+    /// Computes the Monte Carlo estimate of CIE XYZ for the distribution given by this sample at the wavelengths
+    /// `lambda`, normalized so that a constant spectrum of one has a luminance of one.
+    pub fn to_xyz(&self, lambda: &SampledWavelengths) -> [f32; 3] {
+        let mut xyz = [0.0; 3];
+        for i in 0..N_WAVELENGTH_SAMPLES {
+            if lambda.pdf[i] == 0.0 {
+                continue
+            }
+            let matching = cie_matching(lambda.lambda[i]);
+            for (xyz, m) in xyz.iter_mut().zip(matching) {
+                *xyz += m * self.c[i] / lambda.pdf[i];
+            }
+        }
+        xyz.map(|v| v / (N_WAVELENGTH_SAMPLES as f32 * CIE_Y_INTEGRAL))
+    }
+
+    /// Returns the luminance estimate, i.e. the `Y` coordinate of [`to_xyz`](Self::to_xyz).
+    #[inline]
+    pub fn y(&self, lambda: &SampledWavelengths) -> f32 {
+        self.to_xyz(lambda)[1]
+    }
+
+    /// Converts the estimate of [`to_xyz`](Self::to_xyz) to linear sRGB.
+    #[inline]
+    pub fn to_rgb(&self, lambda: &SampledWavelengths) -> [f32; 3] {
+        xyz_to_rgb(self.to_xyz(lambda))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_uniform_stratifies() {
+        let lambda = SampledWavelengths::sample_uniform(0.9, 400.0, 800.0);
+        let mut sorted = lambda.lambda;
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!((sorted[0] - 460.0).abs() < 1e-3);
+        for w in sorted.windows(2) {
+            assert!((w[1] - w[0] - 100.0).abs() < 1e-3);
+        }
+        assert_eq!(lambda.pdf(), SpectrumSample::new(1.0 / 400.0));
+    }
+
+    #[test]
+    fn test_terminate_secondary() {
+        let mut lambda = SampledWavelengths::sample_visible(0.3);
+        let pdf = lambda.pdf()[0];
+        assert!(!lambda.secondary_terminated());
+        lambda.terminate_secondary();
+        lambda.terminate_secondary();
+        assert!(lambda.secondary_terminated());
+        assert_eq!(lambda.pdf()[0], pdf / N_WAVELENGTH_SAMPLES as f32);
+        // A single wavelength still gives an unbiased estimate
+        let xyz = SpectrumSample::new(1.0).to_xyz(&lambda);
+        assert!(xyz[1] > 0.0 && !xyz[1].is_nan());
+    }
+
+    #[test]
+    fn test_constant_spectrum_converges_to_white() {
+        let n = 1024;
+        let mut xyz = [0.0; 3];
+        for i in 0..n {
+            let lambda = SampledWavelengths::sample_visible((i as f32 + 0.5) / n as f32);
+            for (sum, v) in xyz.iter_mut().zip(SpectrumSample::new(1.0).to_xyz(&lambda)) {
+                *sum += v / n as f32;
+            }
+        }
+        let [x, y, z] = xyz;
+        assert!((y - 1.0).abs() < 1e-2, "{:?}", xyz);
+        assert!((x / (x + y + z) - 1.0 / 3.0).abs() < 1e-2);
+    }
+}
@@ -0,0 +1,728 @@
+//! A cache of the tiles of MIP mapped images, for scenes whose textures do not fit in memory.
+//!
+//! The tiles of the levels of an image are read from its [`TileSource`] when a lookup first touches them, and kept
+//! in tile buffers whose total size in bytes is bounded by the memory budget of the [`TextureCache`]. Buffers are
+//! pooled by the size of their tiles, so images with different tile sizes share the budget. When the budget is
+//! exhausted, a tile of the same size is evicted with the CLOCK algorithm, which approximates evicting the least
+//! recently used tile without ordering the lookups, see Corbató, "A Paging Experiment with the Multics System"
+//! (1968).
+//!
+//! Lookups of resident tiles do not take locks. Each buffer is guarded by a sequence lock, so that readers copy
+//! texels optimistically and retry if the buffer was replaced meanwhile, see Boehm, "Can Seqlocks Get Along With
+//! Programming Language Memory Models?" (2012). Only misses are serialized, and the tile is read before the lock is
+//! taken.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::hint;
+use std::io;
+use std::lazy::SyncOnceCell;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{fence, AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use openexr::core::attr::{LevelMode, Storage};
+use openexr::core::context::{Part, ReadContext};
+use crate::image::{exr_error, exr_slices, select_exr_channels};
+use crate::Point2i;
+
+/// The largest width and height of the tiles of the images in a [`TextureCache`].
+pub const MAX_TILE_SIZE: i32 = 256;
+
+/// Size in bytes of the smallest tile that is counted against the memory budget, which bounds the number of tiles
+/// a [`TextureCache`] holds.
+const MIN_TILE_BYTES: usize = 64;
+
+/// Number of tile buffers that are allocated together, so that buffers can be added without moving the others.
+const BUFFER_CHUNK_SIZE: usize = 1024;
+
+/// A tiled, MIP mapped image that a [`TextureCache`] pages in tile by tile.
+///
+/// Level 0 is the image at full resolution, and each further level has about half the resolution of the one before.
+pub trait TileSource: Send + Sync {
+    /// Number of channels, which is 1 for monochrome or 3 for RGB images.
+    fn n_channels(&self) -> usize;
+
+    /// Size of the tiles in pixels, which is the same for all levels and at most [`MAX_TILE_SIZE`] in each
+    /// dimension.
+    fn tile_size(&self) -> Point2i;
+
+    /// Resolution of each level.
+    fn level_resolutions(&self) -> &[Point2i];
+
+    /// Reads the tile at tile coordinates `tile` of `level`, returning the channels of its pixels interleaved in
+    /// scanline order. The result always covers the whole tile size, with zeros for pixels outside of the level.
+    fn read_tile(&self, level: usize, tile: Point2i) -> io::Result<Vec<f32>>;
+}
+
+/// The tiles of the first part of a tiled EXR file with a single level or MIP map levels.
+///
+/// The red, green and blue channels are read if the part has them, otherwise its luminance channel `Y` or its only
+/// channel.
+pub struct ExrTileSource {
+    ctxt: ReadContext,
+    channels: Vec<String>,
+    tile_size: Point2i,
+    levels: Vec<Point2i>
+}
+
+impl ExrTileSource {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let ctxt = ReadContext::open(path).map_err(exr_error)?;
+        let part = Part::new(0);
+        if ctxt.storage(part).map_err(exr_error)? != Storage::Tiled {
+            return Err(invalid_data("image is not tiled"))
+        }
+        let tiles = ctxt.tile_description(part).map_err(exr_error)?;
+        if tiles.level_mode == LevelMode::Ripmap {
+            return Err(invalid_data("RIP maps are not supported"))
+        }
+
+        let channels = select_exr_channels(ctxt.channels(part).map_err(exr_error)?)?;
+
+        let (n_levels, _) = ctxt.tile_levels(part).map_err(exr_error)?;
+        let levels = (0..n_levels)
+            .map(|level| ctxt.level_size(part, level, level).map(|(w, h)| Point2i::new(w, h)))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(exr_error)?;
+        let tile_size = Point2i::new(tiles.x_size as i32, tiles.y_size as i32);
+        Ok(ExrTileSource { ctxt, channels, tile_size, levels })
+    }
+}
+
+impl TileSource for ExrTileSource {
+    fn n_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    fn tile_size(&self) -> Point2i {
+        self.tile_size
+    }
+
+    fn level_resolutions(&self) -> &[Point2i] {
+        &self.levels
+    }
+
+    fn read_tile(&self, level: usize, tile: Point2i) -> io::Result<Vec<f32>> {
+        let width = self.tile_size.x as usize;
+        let n = width * self.tile_size.y as usize;
+        let mut planes = vec![vec![0.0; n]; self.channels.len()];
+        let mut slices = exr_slices(&self.channels, &mut planes, 0, width);
+        self.ctxt.read_tile(Part::new(0), tile.x, tile.y, level as i32, level as i32, &mut slices)
+            .map_err(exr_error)?;
+        Ok((0..n).flat_map(|i| planes.iter().map(move |plane| plane[i])).collect())
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// An image whose tiles are cached by a [`TextureCache`].
+pub struct TiledImage {
+    /// Index of the image in the cache
+    id: u32,
+    source: Box<dyn TileSource>,
+    n_channels: usize,
+    tile_size: Point2i,
+    levels: Vec<Point2i>,
+    /// Number of tiles in x of each level
+    tiles_x: Vec<i32>,
+    /// Index of the first tile of each level in `tiles`
+    level_offsets: Vec<usize>,
+    /// The tile buffer that holds each tile plus one, or zero if the tile is not resident
+    tiles: Box<[AtomicU32]>
+}
+
+impl TiledImage {
+    #[inline]
+    pub fn n_channels(&self) -> usize {
+        self.n_channels
+    }
+
+    #[inline]
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    #[inline]
+    pub fn resolution(&self, level: usize) -> Point2i {
+        self.levels[level]
+    }
+
+    /// Number of samples of each tile.
+    #[inline]
+    fn tile_samples(&self) -> usize {
+        (self.tile_size.x * self.tile_size.y) as usize * self.n_channels
+    }
+
+    /// Identifies tile `index` among the tiles of all images of the cache.
+    #[inline]
+    fn tile_key(&self, index: usize) -> u64 {
+        (self.id as u64) << 32 | index as u64
+    }
+}
+
+/// A tile buffer of a [`TextureCache`].
+struct TileBuffer {
+    /// Sequence number, which is odd while the buffer is written
+    seq: AtomicU64,
+    /// Key of the tile in the buffer
+    tile: AtomicU64,
+    /// Whether the tile was looked up since the clock hand of its pool last passed it
+    referenced: AtomicBool,
+    /// Samples of the tile as bits, allocated for the tile size of its pool when the buffer is first used
+    data: SyncOnceCell<Box<[AtomicU32]>>
+}
+
+impl TileBuffer {
+    fn new() -> Self {
+        TileBuffer {
+            seq: AtomicU64::new(0),
+            tile: AtomicU64::new(u64::MAX),
+            referenced: AtomicBool::new(false),
+            data: SyncOnceCell::new()
+        }
+    }
+}
+
+/// The tile buffers of a [`TextureCache`] for tiles with the same number of samples.
+#[derive(Default)]
+struct BufferPool {
+    buffers: Vec<u32>,
+    /// Position of the clock hand in `buffers`
+    hand: usize
+}
+
+/// The images and tile buffers of a [`TextureCache`], which are only changed while holding its lock.
+#[derive(Default)]
+struct CacheState {
+    images: Vec<Arc<TiledImage>>,
+    paths: HashMap<PathBuf, Arc<TiledImage>>,
+    /// Pools of the tile buffers by the number of samples of their tiles
+    pools: HashMap<usize, BufferPool>,
+    /// Number of tile buffers that have been used
+    used_buffers: usize,
+    /// Size of the samples of the tile buffers that have been used in bytes
+    used_bytes: usize
+}
+
+/// Statistics of the lookups of a [`TextureCache`].
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct TextureCacheStats {
+    /// Lookups of texels whose tile was resident
+    pub hits: u64,
+    /// Lookups of texels whose tile had to be read
+    pub misses: u64,
+    /// Tiles that were evicted to make room for others
+    pub evictions: u64,
+    /// Tiles that could not be read
+    pub read_errors: u64,
+    /// Number of tiles in memory
+    pub resident_tiles: usize,
+    /// Size of the tiles in memory in bytes
+    pub resident_bytes: usize,
+    /// Budget for the tile buffers in bytes
+    pub memory_budget: usize
+}
+
+impl TextureCacheStats {
+    /// Returns the fraction of lookups that were hits.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            return 0.0
+        }
+        self.hits as f64 / lookups as f64
+    }
+}
+
+impl Display for TextureCacheStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Texture cache: {} lookups, {:.2}% hits, {} misses, {} evictions, {} read errors, {} tiles resident, {} MiB budget",
+            self.hits + self.misses,
+            100.0 * self.hit_rate(),
+            self.misses,
+            self.evictions,
+            self.read_errors,
+            self.resident_tiles,
+            self.memory_budget >> 20
+        )
+    }
+}
+
+/// A cache of the tiles of [`TiledImage`]s, which holds as many tiles as fit in its memory budget.
+///
+/// The statistics of the lookups are collected over the lifetime of the cache, so that they can be reported when
+/// rendering has finished.
+pub struct TextureCache {
+    /// Tile buffers in chunks of [`BUFFER_CHUNK_SIZE`], which are allocated as they are needed
+    buffers: Box<[SyncOnceCell<Box<[TileBuffer]>>]>,
+    /// Maximum number of tile buffers
+    max_buffers: usize,
+    memory_budget: usize,
+    state: Mutex<CacheState>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    read_errors: AtomicU64
+}
+
+impl TextureCache {
+    /// Creates a cache whose tiles take up at most `memory_budget` bytes, except that it holds at least one tile of
+    /// each tile size.
+    ///
+    /// The buffers are allocated as they are needed.
+    pub fn new(memory_budget: usize) -> Self {
+        let max_buffers = (memory_budget / MIN_TILE_BYTES).max(1);
+        let n_chunks = (max_buffers + BUFFER_CHUNK_SIZE - 1) / BUFFER_CHUNK_SIZE;
+        let buffers = (0..n_chunks).map(|_| SyncOnceCell::new()).collect();
+        TextureCache {
+            buffers,
+            max_buffers,
+            memory_budget,
+            state: Mutex::new(CacheState::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            read_errors: AtomicU64::new(0)
+        }
+    }
+
+    /// Opens the tiled EXR file at `path`, which is only opened once for all callers.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> io::Result<Arc<TiledImage>> {
+        let path = path.as_ref();
+        if let Some(image) = self.state.lock().unwrap().paths.get(path) {
+            return Ok(image.clone())
+        }
+        let source = ExrTileSource::open(path)?;
+        let mut state = self.state.lock().unwrap();
+        // Another thread may have opened the file meanwhile
+        if let Some(image) = state.paths.get(path) {
+            return Ok(image.clone())
+        }
+        let image = Self::insert(&mut state, Box::new(source))?;
+        state.paths.insert(path.to_path_buf(), image.clone());
+        Ok(image)
+    }
+
+    /// Adds an image whose tiles are read from `source`.
+    pub fn add(&self, source: Box<dyn TileSource>) -> io::Result<Arc<TiledImage>> {
+        Self::insert(&mut self.state.lock().unwrap(), source)
+    }
+
+    fn insert(state: &mut CacheState, source: Box<dyn TileSource>) -> io::Result<Arc<TiledImage>> {
+        let n_channels = source.n_channels();
+        let tile_size = source.tile_size();
+        let levels = source.level_resolutions().to_vec();
+        if n_channels != 1 && n_channels != 3 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "tiled images must have 1 or 3 channels"))
+        }
+        if tile_size.x <= 0 || tile_size.y <= 0 || tile_size.x > MAX_TILE_SIZE || tile_size.y > MAX_TILE_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "tiles must have 1 to 256 pixels in each dimension"))
+        }
+        if levels.is_empty() || levels.iter().any(|res| res.x <= 0 || res.y <= 0) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "tiled images must have non-empty levels"))
+        }
+
+        let tiles_x: Vec<i32> = levels.iter().map(|res| (res.x + tile_size.x - 1) / tile_size.x).collect();
+        let mut level_offsets = Vec::with_capacity(levels.len());
+        let mut n_tiles = 0;
+        for (res, &nx) in levels.iter().zip(&tiles_x) {
+            level_offsets.push(n_tiles);
+            n_tiles += (nx * ((res.y + tile_size.y - 1) / tile_size.y)) as usize;
+        }
+        let image = Arc::new(TiledImage {
+            id: state.images.len() as u32,
+            source,
+            n_channels,
+            tile_size,
+            levels,
+            tiles_x,
+            level_offsets,
+            tiles: (0..n_tiles).map(|_| AtomicU32::new(0)).collect()
+        });
+        state.images.push(image.clone());
+        Ok(image)
+    }
+
+    /// Returns the texel at `p` of `level` of `image`, which must lie within the level, with the channel of
+    /// monochrome images repeated.
+    ///
+    /// Tiles that can not be read are black.
+    pub fn texel(&self, image: &TiledImage, level: usize, p: Point2i) -> [f32; 3] {
+        debug_assert!(p.x >= 0 && p.y >= 0 && p.x < image.levels[level].x && p.y < image.levels[level].y);
+        let size = image.tile_size;
+        let tile = Point2i::new(p.x / size.x, p.y / size.y);
+        let index = image.level_offsets[level] + (tile.y * image.tiles_x[level] + tile.x) as usize;
+        let offset = ((p.y % size.y) * size.x + p.x % size.x) as usize * image.n_channels;
+
+        let mut texel = [0.0; 3];
+        if self.read_resident(image, index, offset, &mut texel) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            match self.load(image, level, tile, index) {
+                Ok(data) => texel[..image.n_channels].copy_from_slice(&data[offset..offset + image.n_channels]),
+                Err(_) => {
+                    self.read_errors.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        if image.n_channels == 1 {
+            texel = [texel[0]; 3];
+        }
+        texel
+    }
+
+    /// Copies the texel at `offset` of tile `index` of `image` to `texel` if the tile is resident.
+    fn read_resident(&self, image: &TiledImage, index: usize, offset: usize, texel: &mut [f32; 3]) -> bool {
+        let key = image.tile_key(index);
+        loop {
+            let slot = image.tiles[index].load(Ordering::Acquire);
+            if slot == 0 {
+                return false
+            }
+            let buffer = self.buffer(slot as usize - 1);
+            let seq = buffer.seq.load(Ordering::Acquire);
+            if seq & 1 == 1 {
+                // The buffer is being replaced, after which the slot is cleared
+                hint::spin_loop();
+                continue
+            }
+            let data = match buffer.data.get() {
+                Some(data) => data,
+                None => return false
+            };
+            let tile = buffer.tile.load(Ordering::Relaxed);
+            for (c, value) in texel.iter_mut().take(image.n_channels).enumerate() {
+                *value = f32::from_bits(data[offset + c].load(Ordering::Relaxed));
+            }
+            fence(Ordering::Acquire);
+            if buffer.seq.load(Ordering::Relaxed) != seq || tile != key {
+                // The texel may be torn, or the slot was stale
+                continue
+            }
+            // Only the first use of a tile since the clock hand passed it writes the flag, which keeps hits of tiles in
+            // use from contending on it
+            if !buffer.referenced.load(Ordering::Relaxed) {
+                buffer.referenced.store(true, Ordering::Relaxed);
+            }
+            return true
+        }
+    }
+
+    /// Reads a tile that is not resident and stores it in a tile buffer, returning its samples.
+    fn load(&self, image: &TiledImage, level: usize, tile: Point2i, index: usize) -> io::Result<Vec<f32>> {
+        let data = image.source.read_tile(level, tile)?;
+        if data.len() != image.tile_samples() {
+            return Err(invalid_data("tile has the wrong number of samples"))
+        }
+
+        let mut state = self.state.lock().unwrap();
+        // Another thread may have read the tile meanwhile
+        if image.tiles[index].load(Ordering::Relaxed) != 0 {
+            return Ok(data)
+        }
+        let CacheState { images, pools, used_buffers, used_bytes, .. } = &mut *state;
+        let pool = pools.entry(data.len()).or_default();
+        let bytes = data.len() * 4;
+        // Each tile size gets a buffer even if the budget is exhausted, so that its tiles are not read per lookup
+        let has_room = *used_bytes + bytes <= self.memory_budget || pool.buffers.is_empty();
+        let b = if *used_buffers < self.max_buffers && has_room {
+            let b = *used_buffers;
+            *used_buffers += 1;
+            *used_bytes += bytes;
+            let _ = self.buffer(b).data.set((0..data.len()).map(|_| AtomicU32::new(0)).collect());
+            pool.buffers.push(b as u32);
+            b
+        } else if pool.buffers.is_empty() {
+            // There are no buffers left for tiles of this size
+            return Ok(data)
+        } else {
+            // Give the tiles that were used since the hand last passed them a second chance
+            let b = loop {
+                let b = pool.buffers[pool.hand] as usize;
+                pool.hand = (pool.hand + 1) % pool.buffers.len();
+                if !self.buffer(b).referenced.swap(false, Ordering::Relaxed) {
+                    break b
+                }
+            };
+            let key = self.buffer(b).tile.load(Ordering::Relaxed);
+            images[(key >> 32) as usize].tiles[(key & 0xffff_ffff) as usize].store(0, Ordering::Release);
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+            b
+        };
+
+        let buffer = self.buffer(b);
+        let seq = buffer.seq.load(Ordering::Relaxed);
+        buffer.seq.store(seq + 1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        buffer.tile.store(image.tile_key(index), Ordering::Relaxed);
+        for (sample, value) in buffer.data.get().unwrap().iter().zip(&data) {
+            sample.store(value.to_bits(), Ordering::Relaxed);
+        }
+        buffer.seq.store(seq + 2, Ordering::Release);
+        buffer.referenced.store(false, Ordering::Relaxed);
+        image.tiles[index].store(b as u32 + 1, Ordering::Release);
+        Ok(data)
+    }
+
+    /// Returns tile buffer `b`, allocating its chunk if it is the first buffer of the chunk that is used.
+    #[inline]
+    fn buffer(&self, b: usize) -> &TileBuffer {
+        let chunk = self.buffers[b / BUFFER_CHUNK_SIZE]
+            .get_or_init(|| (0..BUFFER_CHUNK_SIZE).map(|_| TileBuffer::new()).collect());
+        &chunk[b % BUFFER_CHUNK_SIZE]
+    }
+
+    pub fn stats(&self) -> TextureCacheStats {
+        let state = self.state.lock().unwrap();
+        TextureCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            read_errors: self.read_errors.load(Ordering::Relaxed),
+            resident_tiles: state.used_buffers,
+            resident_bytes: state.used_bytes,
+            memory_budget: self.memory_budget
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::thread;
+    use super::*;
+
+    /// Size in bytes of the tiles of [`GradientSource::new`].
+    const TILE_BYTES: usize = 4 * 4 * 3 * 4;
+
+    /// An RGB image with square tiles whose texels encode their coordinates and level.
+    struct GradientSource {
+        levels: Vec<Point2i>,
+        tile_size: i32,
+        reads: AtomicUsize
+    }
+
+    impl GradientSource {
+        /// Creates an image with 4x4 tiles.
+        fn new() -> Self {
+            Self::with_tile_size(4)
+        }
+
+        fn with_tile_size(tile_size: i32) -> Self {
+            let levels = vec![Point2i::new(8, 6), Point2i::new(4, 3), Point2i::new(2, 1), Point2i::new(1, 1)];
+            GradientSource { levels, tile_size, reads: AtomicUsize::new(0) }
+        }
+
+        fn value(level: usize, p: Point2i) -> [f32; 3] {
+            let v = (1000 * level as i32 + 100 * p.y + p.x) as f32;
+            [v, v + 0.25, v + 0.5]
+        }
+    }
+
+    impl TileSource for GradientSource {
+        fn n_channels(&self) -> usize {
+            3
+        }
+
+        fn tile_size(&self) -> Point2i {
+            Point2i::new(self.tile_size, self.tile_size)
+        }
+
+        fn level_resolutions(&self) -> &[Point2i] {
+            &self.levels
+        }
+
+        fn read_tile(&self, level: usize, tile: Point2i) -> io::Result<Vec<f32>> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            let res = self.levels[level];
+            let size = self.tile_size;
+            let mut data = vec![0.0; (size * size) as usize * 3];
+            for y in 0..size {
+                for x in 0..size {
+                    let p = Point2i::new(size * tile.x + x, size * tile.y + y);
+                    if p.x < res.x && p.y < res.y {
+                        let i = (y * size + x) as usize * 3;
+                        data[i..i + 3].copy_from_slice(&Self::value(level, p));
+                    }
+                }
+            }
+            Ok(data)
+        }
+    }
+
+    /// A source whose tiles are wrapped in an `Arc`, so that tests can count its reads.
+    struct Shared(Arc<GradientSource>);
+
+    impl TileSource for Shared {
+        fn n_channels(&self) -> usize {
+            self.0.n_channels()
+        }
+
+        fn tile_size(&self) -> Point2i {
+            self.0.tile_size()
+        }
+
+        fn level_resolutions(&self) -> &[Point2i] {
+            self.0.level_resolutions()
+        }
+
+        fn read_tile(&self, level: usize, tile: Point2i) -> io::Result<Vec<f32>> {
+            self.0.read_tile(level, tile)
+        }
+    }
+
+    fn texels(image: &TiledImage, level: usize) -> impl Iterator<Item = Point2i> {
+        let res = image.resolution(level);
+        (0..res.y).flat_map(move |y| (0..res.x).map(move |x| Point2i::new(x, y)))
+    }
+
+    #[test]
+    fn test_texture_cache() {
+        let source = Arc::new(GradientSource::new());
+        let cache = TextureCache::new(1 << 20);
+        let image = cache.add(Box::new(Shared(source.clone()))).unwrap();
+        assert_eq!(image.levels(), 4);
+        for _ in 0..2 {
+            for level in 0..image.levels() {
+                for p in texels(&image, level) {
+                    assert_eq!(cache.texel(&image, level, p), GradientSource::value(level, p));
+                }
+            }
+        }
+        // Level 0 has 2x2 tiles and the others one each
+        assert_eq!(source.reads.load(Ordering::Relaxed), 7);
+        let stats = cache.stats();
+        assert_eq!(stats.misses, 7);
+        assert_eq!(stats.hits, 2 * (48 + 12 + 2 + 1) - 7);
+        assert_eq!(stats.evictions, 0);
+        assert_eq!(stats.resident_tiles, 7);
+        assert_eq!(stats.resident_bytes, 7 * TILE_BYTES);
+    }
+
+    #[test]
+    fn test_eviction() {
+        let source = Arc::new(GradientSource::new());
+        let cache = TextureCache::new(2 * TILE_BYTES);
+        let image = cache.add(Box::new(Shared(source.clone()))).unwrap();
+        let lookup = |x, y| {
+            let p = Point2i::new(x, y);
+            assert_eq!(cache.texel(&image, 0, p), GradientSource::value(0, p));
+        };
+        // The first tile gets a second chance after it is used again, so the second tile is evicted
+        lookup(0, 0);
+        lookup(4, 0);
+        lookup(1, 1);
+        lookup(0, 4);
+        assert_eq!(source.reads.load(Ordering::Relaxed), 3);
+        lookup(2, 3);
+        assert_eq!(source.reads.load(Ordering::Relaxed), 3);
+        lookup(7, 0);
+        assert_eq!(source.reads.load(Ordering::Relaxed), 4);
+
+        for level in 0..image.levels() {
+            for p in texels(&image, level) {
+                assert_eq!(cache.texel(&image, level, p), GradientSource::value(level, p));
+            }
+        }
+        let stats = cache.stats();
+        assert_eq!(stats.resident_tiles, 2);
+        assert_eq!(stats.evictions as usize, source.reads.load(Ordering::Relaxed) - 2);
+    }
+
+    #[test]
+    fn test_concurrent_lookups() {
+        let cache = Arc::new(TextureCache::new(3 * TILE_BYTES));
+        let image = cache.add(Box::new(GradientSource::new())).unwrap();
+        let points: Arc<Vec<(usize, Point2i)>> = Arc::new(
+            (0..image.levels()).flat_map(|level| texels(&image, level).map(move |p| (level, p))).collect()
+        );
+        let threads: Vec<_> = (0..4).map(|t| {
+            let (cache, image, points) = (cache.clone(), image.clone(), points.clone());
+            thread::spawn(move || {
+                for i in 0..20000 {
+                    let (level, p) = points[(i * 7 + t * 13) % points.len()];
+                    assert_eq!(cache.texel(&image, level, p), GradientSource::value(level, p));
+                }
+            })
+        }).collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let stats = cache.stats();
+        assert_eq!(stats.hits + stats.misses, 4 * 20000);
+        assert!(stats.evictions > 0);
+        assert_eq!(stats.resident_tiles, 3);
+    }
+
+    struct FailingSource(Vec<Point2i>, Point2i);
+
+    impl TileSource for FailingSource {
+        fn n_channels(&self) -> usize {
+            1
+        }
+
+        fn tile_size(&self) -> Point2i {
+            self.1
+        }
+
+        fn level_resolutions(&self) -> &[Point2i] {
+            &self.0
+        }
+
+        fn read_tile(&self, _level: usize, _tile: Point2i) -> io::Result<Vec<f32>> {
+            Err(invalid_data("corrupt tile"))
+        }
+    }
+
+    #[test]
+    fn test_invalid_sources() {
+        let cache = TextureCache::new(0);
+        let image = cache.add(Box::new(FailingSource(vec![Point2i::new(4, 4)], Point2i::new(4, 4)))).unwrap();
+        assert_eq!(cache.texel(&image, 0, Point2i::new(1, 2)), [0.0; 3]);
+        let stats = cache.stats();
+        assert_eq!((stats.misses, stats.read_errors, stats.resident_tiles), (1, 1, 0));
+
+        let large = FailingSource(vec![Point2i::new(1024, 1024)], Point2i::new(512, 512));
+        assert_eq!(cache.add(Box::new(large)).err().unwrap().kind(), io::ErrorKind::InvalidInput);
+        let empty = FailingSource(vec![], Point2i::new(4, 4));
+        assert_eq!(cache.add(Box::new(empty)).err().unwrap().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_tile_sizes() {
+        // The budget holds one tile of 256x256 texels and the tiles of an image with 4x4 tiles, which are kept in
+        // separate pools
+        let large_tile = 256 * 256 * 3 * 4;
+        let cache = TextureCache::new(large_tile + 7 * TILE_BYTES);
+        let large = Arc::new(GradientSource::with_tile_size(256));
+        let large_image = cache.add(Box::new(Shared(large.clone()))).unwrap();
+        let small_image = cache.add(Box::new(GradientSource::new())).unwrap();
+        for _ in 0..2 {
+            for image in [&large_image, &small_image] {
+                for level in 0..image.levels() {
+                    for p in texels(image, level) {
+                        assert_eq!(cache.texel(image, level, p), GradientSource::value(level, p));
+                    }
+                }
+            }
+        }
+        // Each level of the large image is a single tile, which replaces the one before
+        assert_eq!(large.reads.load(Ordering::Relaxed), 8);
+        let stats = cache.stats();
+        assert_eq!(stats.evictions, 7);
+        assert_eq!(stats.resident_tiles, 8);
+        assert_eq!(stats.resident_bytes, large_tile + 7 * TILE_BYTES);
+    }
+
+    #[test]
+    fn test_stats_display() {
+        let stats = TextureCacheStats { hits: 3, misses: 1, memory_budget: 64 << 20, ..Default::default() };
+        assert_eq!(stats.to_string(), "Texture cache: 4 lookups, 75.00% hits, 1 misses, 0 evictions, 0 read errors, \
+                                       0 tiles resident, 64 MiB budget");
+    }
+}
@@ -0,0 +1,33 @@
+//! Textures, which vary the parameters of materials over surfaces.
+//!
+//! Most textures look up their value by coordinates that a [`TextureMapping2D`] or [`TextureMapping3D`] computes
+//! for the surface interaction, along with the change of the coordinates between pixels that determines how much
+//! of the texture has to be filtered to avoid aliasing.
+
+mod checkerboard;
+mod constant;
+mod dots;
+mod image;
+mod mapping;
+mod mix;
+mod scale;
+mod tiled;
+mod uv;
+
+pub use checkerboard::*;
+pub use constant::*;
+pub use dots::*;
+pub use image::*;
+pub use mapping::*;
+pub use mix::*;
+pub use scale::*;
+pub use tiled::*;
+pub use uv::*;
+
+use crate::interaction::SurfaceInteraction;
+
+/// A function of type `T` over the surfaces it is applied to.
+pub trait Texture<T>: Send + Sync {
+    /// Evaluates the texture at the surface interaction `si`.
+    fn evaluate(&self, si: &SurfaceInteraction) -> T;
+}
@@ -0,0 +1,155 @@
+use std::ops::{Add, Mul};
+use std::sync::Arc;
+use crate::interaction::SurfaceInteraction;
+use crate::texture::{Texture, TextureMapping2D, TextureMapping3D};
+
+/// How a checkerboard is filtered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AaMethod {
+    /// The checkerboard is point sampled, which aliases where the checks are smaller than a pixel.
+    None,
+    /// The checkerboard is averaged over a box around the lookup point that covers the change of the texture
+    /// coordinates between pixels, which can be integrated in closed form.
+    ClosedForm
+}
+
+/// Alternates between `tex1` and `tex2` in the unit squares of the texture coordinates, with `tex1` in the square at
+/// the origin.
+pub struct Checkerboard2DTexture<T> {
+    mapping: Box<dyn TextureMapping2D>,
+    tex1: Arc<dyn Texture<T>>,
+    tex2: Arc<dyn Texture<T>>,
+    aa_method: AaMethod
+}
+
+impl<T> Checkerboard2DTexture<T> {
+    pub fn new(mapping: Box<dyn TextureMapping2D>, tex1: Arc<dyn Texture<T>>, tex2: Arc<dyn Texture<T>>,
+               aa_method: AaMethod) -> Self {
+        Checkerboard2DTexture { mapping, tex1, tex2, aa_method }
+    }
+}
+
+impl<T: Add<Output = T> + Mul<f32, Output = T>> Texture<T> for Checkerboard2DTexture<T> {
+    fn evaluate(&self, si: &SurfaceInteraction) -> T {
+        let c = self.mapping.map(si);
+        let st = c.st;
+        let point_sample = || {
+            if (st.x.floor() as i32 + st.y.floor() as i32) % 2 == 0 {
+                self.tex1.evaluate(si)
+            } else {
+                self.tex2.evaluate(si)
+            }
+        };
+        if self.aa_method == AaMethod::None {
+            return point_sample()
+        }
+
+        // Find the box to filter over and check whether it lies within a single check
+        let ds = c.dstdx.x.abs().max(c.dstdy.x.abs());
+        let dt = c.dstdx.y.abs().max(c.dstdy.y.abs());
+        let (s0, s1) = (st.x - ds, st.x + ds);
+        let (t0, t1) = (st.y - dt, st.y + dt);
+        if s0.floor() == s1.floor() && t0.floor() == t1.floor() {
+            return point_sample()
+        }
+
+        // Integrate the fraction of the box covered by the checks of tex2, which is where the periodic step
+        // functions along s and t differ
+        let bump_int = |x: f32| (x / 2.0).floor() + 2.0 * (x / 2.0 - (x / 2.0).floor() - 0.5).max(0.0);
+        let s_int = (bump_int(s1) - bump_int(s0)) / (2.0 * ds);
+        let t_int = (bump_int(t1) - bump_int(t0)) / (2.0 * dt);
+        let mut area2 = s_int + t_int - 2.0 * s_int * t_int;
+        if ds > 1.0 || dt > 1.0 {
+            area2 = 0.5;
+        }
+        self.tex1.evaluate(si) * (1.0 - area2) + self.tex2.evaluate(si) * area2
+    }
+}
+
+/// Alternates between `tex1` and `tex2` in the unit cubes of the texture space, with `tex1` in the cube at the
+/// origin.
+///
+/// The checkerboard is point sampled.
+pub struct Checkerboard3DTexture<T> {
+    mapping: Box<dyn TextureMapping3D>,
+    tex1: Arc<dyn Texture<T>>,
+    tex2: Arc<dyn Texture<T>>
+}
+
+impl<T> Checkerboard3DTexture<T> {
+    pub fn new(mapping: Box<dyn TextureMapping3D>, tex1: Arc<dyn Texture<T>>, tex2: Arc<dyn Texture<T>>) -> Self {
+        Checkerboard3DTexture { mapping, tex1, tex2 }
+    }
+}
+
+impl<T> Texture<T> for Checkerboard3DTexture<T> {
+    fn evaluate(&self, si: &SurfaceInteraction) -> T {
+        let p = self.mapping.map(si).p;
+        if (p.x.floor() as i32 + p.y.floor() as i32 + p.z.floor() as i32) % 2 == 0 {
+            self.tex1.evaluate(si)
+        } else {
+            self.tex2.evaluate(si)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use crate::texture::{ConstantTexture, PointTransformMapping, UvMapping};
+    use crate::{point2, Point3f, Transform};
+    use super::*;
+
+    fn interaction(u: f32, v: f32, footprint: f32) -> SurfaceInteraction {
+        SurfaceInteraction {
+            uv: point2(u, v),
+            dud: Cell::new((footprint, 0.0)),
+            dvd: Cell::new((0.0, footprint)),
+            ..Default::default()
+        }
+    }
+
+    fn checkerboard(aa_method: AaMethod) -> Checkerboard2DTexture<f32> {
+        Checkerboard2DTexture::new(Box::new(UvMapping::new(4.0, 4.0, 0.0, 0.0)),
+                                   Arc::new(ConstantTexture::new(1.0)), Arc::new(ConstantTexture::new(0.0)),
+                                   aa_method)
+    }
+
+    #[test]
+    fn test_point_sampled() {
+        let tex = checkerboard(AaMethod::None);
+        assert_eq!(tex.evaluate(&interaction(0.1, 0.1, 0.0)), 1.0);
+        assert_eq!(tex.evaluate(&interaction(0.3, 0.1, 0.0)), 0.0);
+        assert_eq!(tex.evaluate(&interaction(0.3, 0.3, 0.0)), 1.0);
+        // Large footprints are ignored without filtering
+        assert_eq!(tex.evaluate(&interaction(0.1, 0.1, 1.0)), 1.0);
+    }
+
+    #[test]
+    fn test_closed_form_filtering() {
+        let tex = checkerboard(AaMethod::ClosedForm);
+        // Lookups inside a single check are exact
+        assert_eq!(tex.evaluate(&interaction(0.1, 0.1, 0.01)), 1.0);
+        // A box that covers a whole number of checks averages them
+        assert!((tex.evaluate(&interaction(0.25, 0.25, 0.25)) - 0.5).abs() < 1e-5);
+        assert_eq!(tex.evaluate(&interaction(0.1, 0.1, 1.0)), 0.5);
+        // A box that straddles the edge between two checks is covered half by each of them
+        assert!((tex.evaluate(&interaction(0.25, 0.1, 0.05)) - 0.5).abs() < 1e-4);
+        // Filtered values stay between those of the checks
+        for i in 0..100 {
+            let v = tex.evaluate(&interaction(i as f32 * 0.013, i as f32 * 0.007, 0.04));
+            assert!((0.0..=1.0).contains(&v));
+        }
+    }
+
+    #[test]
+    fn test_checkerboard_3d() {
+        let tex = Checkerboard3DTexture::new(Box::new(PointTransformMapping::new(Transform::scale(2.0, 2.0, 2.0))),
+                                             Arc::new(ConstantTexture::new(1.0)), Arc::new(ConstantTexture::new(0.0)));
+        let at = |x, y, z| tex.evaluate(&SurfaceInteraction { p: Point3f::new(x, y, z), ..Default::default() });
+        assert_eq!(at(0.1, 0.1, 0.1), 1.0);
+        assert_eq!(at(0.6, 0.1, 0.1), 0.0);
+        assert_eq!(at(0.6, 0.6, 0.1), 1.0);
+        assert_eq!(at(-0.1, 0.1, 0.1), 0.0);
+    }
+}
@@ -0,0 +1,21 @@
+use crate::interaction::SurfaceInteraction;
+use crate::texture::Texture;
+
+/// A texture with the same value everywhere.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ConstantTexture<T> {
+    value: T
+}
+
+impl<T> ConstantTexture<T> {
+    pub const fn new(value: T) -> Self {
+        ConstantTexture { value }
+    }
+}
+
+impl<T: Copy + Send + Sync> Texture<T> for ConstantTexture<T> {
+    #[inline]
+    fn evaluate(&self, _si: &SurfaceInteraction) -> T {
+        self.value
+    }
+}
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+use crate::hash::hash;
+use crate::interaction::SurfaceInteraction;
+use crate::texture::{Texture, TextureMapping2D};
+use crate::point2;
+
+/// Polka dots of `inside` over a background of `outside`.
+///
+/// Every other unit square of the texture coordinates holds a dot at a random position inside it, where the squares
+/// that do are chosen by a hash of their coordinates.
+pub struct DotsTexture<T> {
+    mapping: Box<dyn TextureMapping2D>,
+    inside: Arc<dyn Texture<T>>,
+    outside: Arc<dyn Texture<T>>
+}
+
+impl<T> DotsTexture<T> {
+    pub fn new(mapping: Box<dyn TextureMapping2D>, inside: Arc<dyn Texture<T>>, outside: Arc<dyn Texture<T>>) -> Self {
+        DotsTexture { mapping, inside, outside }
+    }
+}
+
+impl<T> Texture<T> for DotsTexture<T> {
+    fn evaluate(&self, si: &SurfaceInteraction) -> T {
+        const RADIUS: f32 = 0.35;
+        const MAX_SHIFT: f32 = 0.5 - RADIUS;
+
+        // Cells are centered on integer coordinates
+        let st = self.mapping.map(si).st;
+        let s_cell = (st.x + 0.5).floor();
+        let t_cell = (st.y + 0.5).floor();
+        let h = hash(&[s_cell as i64 as u64, t_cell as i64 as u64]);
+        if h & 1 == 0 {
+            // Shift the dot by random offsets taken from the remaining bits of the hash
+            let offset = |bits: u64| ((bits & 0xffffff) as f32 / (1 << 24) as f32) * 2.0 - 1.0;
+            let center = point2(s_cell + MAX_SHIFT * offset(h >> 8), t_cell + MAX_SHIFT * offset(h >> 32));
+            if (st - center).length_squared() < RADIUS * RADIUS {
+                return self.inside.evaluate(si)
+            }
+        }
+        self.outside.evaluate(si)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::texture::{ConstantTexture, UvMapping};
+    use super::*;
+
+    #[test]
+    fn test_dots() {
+        let tex = DotsTexture::new(Box::new(UvMapping::new(8.0, 8.0, 0.0, 0.0)), Arc::new(ConstantTexture::new(1.0)),
+                                   Arc::new(ConstantTexture::new(0.0)));
+        let n = 256;
+        let values: Vec<f32> = (0..n * n).map(|i| {
+            let uv = point2(((i % n) as f32 + 0.5) / n as f32, ((i / n) as f32 + 0.5) / n as f32);
+            tex.evaluate(&SurfaceInteraction { uv, ..Default::default() })
+        }).collect();
+        // About half of the cells hold a dot, which covers pi 0.35^2 of the cell
+        let coverage = values.iter().sum::<f32>() / values.len() as f32;
+        assert!(coverage > 0.1 && coverage < 0.3, "{}", coverage);
+        // Points far from any dot are outside
+        assert!(values.iter().any(|&v| v == 0.0));
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::io;
+use std::lazy::SyncLazy;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use crate::image::{Image, WrapMode};
+use crate::interaction::SurfaceInteraction;
+use crate::mipmap::{FilterFunction, MipMap, MipMapFilterOptions, Texel};
+use crate::texture::{Texture, TextureMapping2D};
+use crate::{point2, RgbSpectrum, Spectrum, SpectrumType, Vector2f};
+
+/// Identifies a MIP map in [`MIPMAPS`] by the file of its image and the settings of its lookups.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct MipMapKey {
+    path: PathBuf,
+    filter: FilterFunction,
+    max_anisotropy: u32,
+    wrap_mode: WrapMode
+}
+
+/// The MIP maps of the image textures that have been created, so that textures that use the same image with the
+/// same settings share them.
+static MIPMAPS: SyncLazy<Mutex<HashMap<MipMapKey, Arc<MipMap>>>> = SyncLazy::new(Default::default);
+
+/// A texture given by an image, which is filtered over the footprint of lookups with a [`MipMap`].
+///
+/// The top left corner of the image is at `(s, t) = (0, 1)`, so that images are upright in the `(u, v)`
+/// parameterization of surfaces. Scalar textures use the average of the color channels of RGB images, while
+/// spectral textures convert them to spectra of the type given at construction, which is
+/// [`Reflectance`](SpectrumType::Reflectance) for albedos and [`Unbounded`](SpectrumType::Unbounded) otherwise.
+pub struct ImageTexture {
+    mapping: Box<dyn TextureMapping2D>,
+    mipmap: Arc<MipMap>,
+    scale: f32,
+    spectrum_type: SpectrumType
+}
+
+impl ImageTexture {
+    /// Creates a texture from the image in the file at `path`, whose values are multiplied by `scale`.
+    ///
+    /// Images are only read once for all textures that refer to them with the same filter options and wrap mode.
+    pub fn new<P: AsRef<Path>>(mapping: Box<dyn TextureMapping2D>, path: P, options: MipMapFilterOptions,
+                               wrap_mode: WrapMode, scale: f32, spectrum_type: SpectrumType) -> io::Result<Self> {
+        let path = path.as_ref();
+        let key = MipMapKey {
+            path: path.to_path_buf(),
+            filter: options.filter,
+            max_anisotropy: options.max_anisotropy.to_bits(),
+            wrap_mode
+        };
+        if let Some(mipmap) = MIPMAPS.lock().unwrap().get(&key) {
+            return Ok(ImageTexture { mapping, mipmap: mipmap.clone(), scale, spectrum_type })
+        }
+        // Read the image without holding the lock, so that textures of other images are created meanwhile
+        let mipmap = Arc::new(MipMap::new(Image::read(path)?, wrap_mode, options));
+        // Another thread may have read the same image meanwhile
+        let mipmap = MIPMAPS.lock().unwrap().entry(key).or_insert(mipmap).clone();
+        Ok(ImageTexture { mapping, mipmap, scale, spectrum_type })
+    }
+
+    /// Creates a texture from an image in memory.
+    pub fn from_image(mapping: Box<dyn TextureMapping2D>, image: Image, options: MipMapFilterOptions,
+                      wrap_mode: WrapMode, scale: f32, spectrum_type: SpectrumType) -> Self {
+        ImageTexture { mapping, mipmap: Arc::new(MipMap::new(image, wrap_mode, options)), scale, spectrum_type }
+    }
+
+    fn lookup<T: Texel>(&self, si: &SurfaceInteraction) -> T {
+        let c = self.mapping.map(si);
+        // Flip t so that the top of the image is at t = 1
+        let st = point2(c.st.x, 1.0 - c.st.y);
+        let dst0 = Vector2f::new(c.dstdx.x, -c.dstdx.y);
+        let dst1 = Vector2f::new(c.dstdy.x, -c.dstdy.y);
+        self.mipmap.filter::<T>(st, dst0, dst1) * self.scale
+    }
+}
+
+impl Texture<f32> for ImageTexture {
+    fn evaluate(&self, si: &SurfaceInteraction) -> f32 {
+        self.lookup(si)
+    }
+}
+
+impl Texture<Spectrum> for ImageTexture {
+    fn evaluate(&self, si: &SurfaceInteraction) -> Spectrum {
+        let rgb: RgbSpectrum = self.lookup(si);
+        Spectrum::from_rgb(*rgb.coefficients(), self.spectrum_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::texture::UvMapping;
+    use crate::Point2i;
+    use super::*;
+
+    fn interaction(u: f32, v: f32) -> SurfaceInteraction {
+        SurfaceInteraction { uv: point2(u, v), ..Default::default() }
+    }
+
+    #[test]
+    fn test_image_texture() {
+        // A black top row over a white bottom row
+        let image = Image::new(Point2i::new(2, 2), 1, vec![0.0, 0.0, 1.0, 1.0]);
+        let tex = ImageTexture::from_image(Box::new(UvMapping::default()), image, MipMapFilterOptions::default(),
+                                           WrapMode::Clamp, 0.5, SpectrumType::Reflectance);
+        assert_eq!(Texture::<f32>::evaluate(&tex, &interaction(0.25, 0.9)), 0.0);
+        assert_eq!(Texture::<f32>::evaluate(&tex, &interaction(0.25, 0.1)), 0.5);
+        let s: Spectrum = tex.evaluate(&interaction(0.75, 0.1));
+        assert!((s.y() - 0.5).abs() < 1e-2, "{}", s.y());
+
+        // Spectral reflectances are clamped to one, while unbounded spectra keep the scaled values
+        let texture = |spectrum_type| {
+            let image = Image::new(Point2i::new(1, 1), 1, vec![1.0]);
+            ImageTexture::from_image(Box::new(UvMapping::default()), image, MipMapFilterOptions::default(),
+                                     WrapMode::Clamp, 4.0, spectrum_type)
+        };
+        let reflectance: Spectrum = texture(SpectrumType::Reflectance).evaluate(&interaction(0.5, 0.5));
+        let unbounded: Spectrum = texture(SpectrumType::Unbounded).evaluate(&interaction(0.5, 0.5));
+        if cfg!(feature = "spectral") {
+            assert!((reflectance.y() - 1.0).abs() < 1e-2, "{}", reflectance.y());
+        }
+        assert!((unbounded.y() - 4.0).abs() < 4e-2, "{}", unbounded.y());
+    }
+
+    #[test]
+    fn test_mipmaps_are_shared() {
+        let path = std::env::temp_dir().join(format!("pbr-image-texture-{}.pfm", std::process::id()));
+        let mut bytes = b"Pf\n1 1\n-1.0\n".to_vec();
+        bytes.extend_from_slice(&0.25f32.to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+
+        let options = MipMapFilterOptions { filter: FilterFunction::Ewa, max_anisotropy: 4.0 };
+        let new = |options| {
+            ImageTexture::new(Box::new(UvMapping::default()), &path, options, WrapMode::Repeat, 1.0,
+                              SpectrumType::Reflectance).unwrap()
+        };
+        let (a, b, c) = (new(options), new(options), new(MipMapFilterOptions::default()));
+        fs::remove_file(&path).unwrap();
+        assert!(Arc::ptr_eq(&a.mipmap, &b.mipmap));
+        assert!(!Arc::ptr_eq(&a.mipmap, &c.mipmap));
+        assert_eq!(Texture::<f32>::evaluate(&a, &interaction(0.5, 0.5)), 0.25);
+
+        // Textures whose image is not cached yet read it
+        let missing = ImageTexture::new(Box::new(UvMapping::default()), &path, options, WrapMode::Clamp, 1.0,
+                                        SpectrumType::Reflectance);
+        assert_eq!(missing.err().unwrap().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_mipmaps_are_shared_across_threads() {
+        let path = std::env::temp_dir().join(format!("pbr-image-texture-threads-{}.pfm", std::process::id()));
+        let mut bytes = b"Pf\n1 1\n-1.0\n".to_vec();
+        bytes.extend_from_slice(&0.5f32.to_le_bytes());
+        fs::write(&path, bytes).unwrap();
+
+        let textures: Vec<ImageTexture> = (0..4)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    ImageTexture::new(Box::new(UvMapping::default()), path, MipMapFilterOptions::default(),
+                                      WrapMode::Repeat, 1.0, SpectrumType::Reflectance).unwrap()
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+        fs::remove_file(&path).unwrap();
+        assert!(textures.iter().all(|t| Arc::ptr_eq(&t.mipmap, &textures[0].mipmap)));
+    }
+}
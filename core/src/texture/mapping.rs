@@ -0,0 +1,258 @@
+use std::f32::consts::{FRAC_1_PI, PI};
+use crate::geom::DotProduct;
+use crate::interaction::SurfaceInteraction;
+use crate::math::safe_acos;
+use crate::{point2, Point2f, Point3f, Transform, Vector2f, Vector3f};
+
+/// Texture coordinates `st` of a point on a surface, together with their change for a one pixel step in x and y on
+/// the film, which determines the area of the texture that has to be filtered.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct TexCoord2D {
+    pub st: Point2f,
+    /// The change of `st` for a step in x, i.e. `(ds/dx, dt/dx)`.
+    pub dstdx: Vector2f,
+    /// The change of `st` for a step in y, i.e. `(ds/dy, dt/dy)`.
+    pub dstdy: Vector2f
+}
+
+/// Three-dimensional texture coordinates of a point on a surface, together with their change for a one pixel step
+/// in x and y on the film.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct TexCoord3D {
+    pub p: Point3f,
+    pub dpdx: Vector3f,
+    pub dpdy: Vector3f
+}
+
+/// Computes two-dimensional texture coordinates for points on surfaces.
+pub trait TextureMapping2D: Send + Sync {
+    fn map(&self, si: &SurfaceInteraction) -> TexCoord2D;
+}
+
+/// Computes three-dimensional texture coordinates for points on surfaces.
+pub trait TextureMapping3D: Send + Sync {
+    fn map(&self, si: &SurfaceInteraction) -> TexCoord3D;
+}
+
+/// Maps the `(u, v)` parameterization of the surface to `(su u + du, sv v + dv)`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct UvMapping {
+    su: f32,
+    sv: f32,
+    du: f32,
+    dv: f32
+}
+
+impl UvMapping {
+    pub const fn new(su: f32, sv: f32, du: f32, dv: f32) -> Self {
+        UvMapping { su, sv, du, dv }
+    }
+}
+
+impl Default for UvMapping {
+    fn default() -> Self {
+        UvMapping::new(1.0, 1.0, 0.0, 0.0)
+    }
+}
+
+impl TextureMapping2D for UvMapping {
+    fn map(&self, si: &SurfaceInteraction) -> TexCoord2D {
+        let (dudx, dudy) = si.dud.get();
+        let (dvdx, dvdy) = si.dvd.get();
+        TexCoord2D {
+            st: point2(self.su * si.uv.x + self.du, self.sv * si.uv.y + self.dv),
+            dstdx: Vector2f::new(self.su * dudx, self.sv * dvdx),
+            dstdy: Vector2f::new(self.su * dudy, self.sv * dvdy)
+        }
+    }
+}
+
+/// Maps points to the spherical coordinates `(theta / pi, phi / 2 pi)` of their direction from the origin of the
+/// texture space given by `texture_from_render`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SphericalMapping {
+    texture_from_render: Transform
+}
+
+impl SphericalMapping {
+    pub const fn new(texture_from_render: Transform) -> Self {
+        SphericalMapping { texture_from_render }
+    }
+
+    fn sphere(&self, p: &Point3f) -> Point2f {
+        let v = Vector3f::from(self.texture_from_render.transform_point(p)).normalize();
+        let theta = safe_acos(v.z);
+        let phi = v.y.atan2(v.x);
+        let phi = if phi < 0.0 { phi + 2.0 * PI } else { phi };
+        point2(theta * FRAC_1_PI, phi * (0.5 * FRAC_1_PI))
+    }
+}
+
+impl TextureMapping2D for SphericalMapping {
+    fn map(&self, si: &SurfaceInteraction) -> TexCoord2D {
+        let st = self.sphere(&si.p);
+        let (dstdx, dstdy) = finite_differences(si, st, false, |p| self.sphere(p));
+        TexCoord2D { st, dstdx, dstdy }
+    }
+}
+
+/// Maps points to the angle around and the height along the `z` axis of the texture space given by
+/// `texture_from_render`, with the angle scaled to `[0, 1]`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CylindricalMapping {
+    texture_from_render: Transform
+}
+
+impl CylindricalMapping {
+    pub const fn new(texture_from_render: Transform) -> Self {
+        CylindricalMapping { texture_from_render }
+    }
+
+    fn cylinder(&self, p: &Point3f) -> Point2f {
+        let p = self.texture_from_render.transform_point(p);
+        point2((PI + p.y.atan2(p.x)) * (0.5 * FRAC_1_PI), p.z)
+    }
+}
+
+impl TextureMapping2D for CylindricalMapping {
+    fn map(&self, si: &SurfaceInteraction) -> TexCoord2D {
+        let st = self.cylinder(&si.p);
+        let (dstdx, dstdy) = finite_differences(si, st, true, |p| self.cylinder(p));
+        TexCoord2D { st, dstdx, dstdy }
+    }
+}
+
+/// Projects points onto the plane spanned by `vs` and `vt`, with the offsets `ds` and `dt` added to the
+/// coordinates.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PlanarMapping {
+    vs: Vector3f,
+    vt: Vector3f,
+    ds: f32,
+    dt: f32
+}
+
+impl PlanarMapping {
+    pub const fn new(vs: Vector3f, vt: Vector3f, ds: f32, dt: f32) -> Self {
+        PlanarMapping { vs, vt, ds, dt }
+    }
+}
+
+impl TextureMapping2D for PlanarMapping {
+    fn map(&self, si: &SurfaceInteraction) -> TexCoord2D {
+        let v = Vector3f::from(si.p);
+        let (dpdx, dpdy) = (si.dpdx.get(), si.dpdy.get());
+        TexCoord2D {
+            st: point2(self.ds + v.dot(&self.vs), self.dt + v.dot(&self.vt)),
+            dstdx: Vector2f::new(self.vs.dot(&dpdx), self.vt.dot(&dpdx)),
+            dstdy: Vector2f::new(self.vs.dot(&dpdy), self.vt.dot(&dpdy))
+        }
+    }
+}
+
+/// Maps points to the texture space given by `texture_from_render`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PointTransformMapping {
+    texture_from_render: Transform
+}
+
+impl PointTransformMapping {
+    pub const fn new(texture_from_render: Transform) -> Self {
+        PointTransformMapping { texture_from_render }
+    }
+}
+
+impl TextureMapping3D for PointTransformMapping {
+    fn map(&self, si: &SurfaceInteraction) -> TexCoord3D {
+        TexCoord3D {
+            p: self.texture_from_render.transform_point(&si.p),
+            dpdx: self.texture_from_render.transform_vector(&si.dpdx.get()),
+            dpdy: self.texture_from_render.transform_vector(&si.dpdy.get())
+        }
+    }
+}
+
+/// Estimates the change of the coordinates `st` given by `f` at `si` for a step in x and y with forward
+/// differences, for mappings whose derivatives have no simple closed form.
+///
+/// Either `s` or, if `periodic_s` is not set, `t` is periodic in `[0, 1]`, so that steps across the seam are taken
+/// the short way around.
+fn finite_differences(si: &SurfaceInteraction, st: Point2f, periodic_s: bool, f: impl Fn(&Point3f) -> Point2f)
+                      -> (Vector2f, Vector2f) {
+    const DELTA: f32 = 0.1;
+    let wrap = |d: f32| if d > 0.5 { d - 1.0 } else if d < -0.5 { d + 1.0 } else { d };
+    let difference = |dp: Vector3f| {
+        let d = f(&(si.p + dp * DELTA)) - st;
+        let d = if periodic_s { Vector2f::new(wrap(d.x), d.y) } else { Vector2f::new(d.x, wrap(d.y)) };
+        d * (1.0 / DELTA)
+    };
+    (difference(si.dpdx.get()), difference(si.dpdy.get()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use crate::{vec3, Point3f};
+    use super::*;
+
+    #[test]
+    fn test_uv_mapping() {
+        let si = SurfaceInteraction {
+            uv: point2(0.25, 0.5),
+            dud: Cell::new((0.01, 0.02)),
+            dvd: Cell::new((0.03, 0.04)),
+            ..Default::default()
+        };
+        let c = UvMapping::new(2.0, 4.0, 0.5, -1.0).map(&si);
+        assert_eq!(c.st, point2(1.0, 1.0));
+        assert_eq!(c.dstdx, Vector2f::new(0.02, 0.12));
+        assert_eq!(c.dstdy, Vector2f::new(0.04, 0.16));
+    }
+
+    #[test]
+    fn test_spherical_mapping() {
+        let mapping = SphericalMapping::new(Transform::translate(vec3(0.0, 0.0, -1.0)));
+        let at = |p: Point3f, dpdx: Vector3f| mapping.map(&SurfaceInteraction {
+            p,
+            dpdx: Cell::new(dpdx),
+            ..Default::default()
+        });
+        let c = at(Point3f::new(0.0, 1.0, 1.0), Vector3f::default());
+        assert!((c.st.x - 0.5).abs() < 1e-6 && (c.st.y - 0.25).abs() < 1e-6);
+        // Steps across the seam at phi = 0 are short
+        let c = at(Point3f::new(1.0, -1e-3, 1.0), vec3(0.0, 0.01, 0.0));
+        assert!(c.st.y > 0.99);
+        assert!(c.dstdx.y > 0.0 && c.dstdx.y < 0.01, "{:?}", c.dstdx);
+    }
+
+    #[test]
+    fn test_cylindrical_mapping() {
+        let mapping = CylindricalMapping::new(Transform::translate(vec3(0.0, 0.0, 0.0)));
+        let si = SurfaceInteraction {
+            p: Point3f::new(-1.0, 1e-3, 0.0),
+            dpdx: Cell::new(vec3(0.0, -0.01, 0.0)),
+            ..Default::default()
+        };
+        let c = mapping.map(&si);
+        assert!(c.st.x > 0.99 && c.st.y.abs() < 1e-6);
+        assert!(c.dstdx.x > 0.0 && c.dstdx.x < 0.01, "{:?}", c.dstdx);
+        // The height is not affected by the distance from the axis
+        let si = SurfaceInteraction { p: Point3f::new(2.0, 0.0, 3.0), ..Default::default() };
+        assert_eq!(mapping.map(&si).st, point2(0.5, 3.0));
+    }
+
+    #[test]
+    fn test_planar_mapping() {
+        let mapping = PlanarMapping::new(vec3(1.0, 0.0, 0.0), vec3(0.0, 0.0, 2.0), 0.5, 0.0);
+        let si = SurfaceInteraction {
+            p: Point3f::new(1.0, 2.0, 3.0),
+            dpdx: Cell::new(vec3(0.1, 0.2, 0.3)),
+            dpdy: Cell::new(vec3(0.0, 0.0, -0.1)),
+            ..Default::default()
+        };
+        let c = mapping.map(&si);
+        assert_eq!(c.st, point2(1.5, 6.0));
+        assert!((c.dstdx - Vector2f::new(0.1, 0.6)).length() < 1e-6);
+        assert!((c.dstdy - Vector2f::new(0.0, -0.2)).length() < 1e-6);
+    }
+}
@@ -0,0 +1,44 @@
+use std::ops::{Add, Mul};
+use std::sync::Arc;
+use crate::interaction::SurfaceInteraction;
+use crate::texture::Texture;
+
+/// Linearly interpolates between two textures by the value of a scalar texture, which selects `tex1` where it is
+/// zero and `tex2` where it is one.
+pub struct MixTexture<T> {
+    tex1: Arc<dyn Texture<T>>,
+    tex2: Arc<dyn Texture<T>>,
+    amount: Arc<dyn Texture<f32>>
+}
+
+impl<T> MixTexture<T> {
+    pub fn new(tex1: Arc<dyn Texture<T>>, tex2: Arc<dyn Texture<T>>, amount: Arc<dyn Texture<f32>>) -> Self {
+        MixTexture { tex1, tex2, amount }
+    }
+}
+
+impl<T: Default + Add<Output = T> + Mul<f32, Output = T>> Texture<T> for MixTexture<T> {
+    fn evaluate(&self, si: &SurfaceInteraction) -> T {
+        let amount = self.amount.evaluate(si);
+        // Only evaluate the textures that contribute
+        let t1 = if amount != 1.0 { self.tex1.evaluate(si) * (1.0 - amount) } else { T::default() };
+        let t2 = if amount != 0.0 { self.tex2.evaluate(si) * amount } else { T::default() };
+        t1 + t2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::texture::ConstantTexture;
+    use super::*;
+
+    #[test]
+    fn test_mix() {
+        let si = SurfaceInteraction::default();
+        let mix = |amount| MixTexture::new(Arc::new(ConstantTexture::new(2.0)), Arc::new(ConstantTexture::new(4.0)),
+                                           Arc::new(ConstantTexture::new(amount)));
+        assert_eq!(mix(0.0).evaluate(&si), 2.0);
+        assert_eq!(mix(0.25).evaluate(&si), 2.5);
+        assert_eq!(mix(1.0).evaluate(&si), 4.0);
+    }
+}
@@ -0,0 +1,26 @@
+use std::ops::Mul;
+use std::sync::Arc;
+use crate::interaction::SurfaceInteraction;
+use crate::texture::Texture;
+
+/// The product of a texture and a scalar texture.
+pub struct ScaleTexture<T> {
+    tex: Arc<dyn Texture<T>>,
+    scale: Arc<dyn Texture<f32>>
+}
+
+impl<T> ScaleTexture<T> {
+    pub fn new(tex: Arc<dyn Texture<T>>, scale: Arc<dyn Texture<f32>>) -> Self {
+        ScaleTexture { tex, scale }
+    }
+}
+
+impl<T: Default + Mul<f32, Output = T>> Texture<T> for ScaleTexture<T> {
+    fn evaluate(&self, si: &SurfaceInteraction) -> T {
+        let scale = self.scale.evaluate(si);
+        if scale == 0.0 {
+            return T::default()
+        }
+        self.tex.evaluate(si) * scale
+    }
+}
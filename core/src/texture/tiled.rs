@@ -0,0 +1,152 @@
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use crate::image::WrapMode;
+use crate::interaction::SurfaceInteraction;
+use crate::texcache::{TextureCache, TileSource, TiledImage};
+use crate::texture::{Texture, TextureMapping2D};
+use crate::{point2, Point2f, Point2i, Spectrum, SpectrumType};
+
+/// A texture given by a tiled, MIP mapped image whose tiles are paged in by a [`TextureCache`] as lookups need
+/// them.
+///
+/// Lookups interpolate between the two levels whose texels are closest to the width of the footprint. As for
+/// [`ImageTexture`](crate::texture::ImageTexture), the top left corner of the image is at `(s, t) = (0, 1)`,
+/// scalar textures use the average of the color channels of RGB images and spectral textures convert them to
+/// spectra of the type given at construction.
+pub struct TiledImageTexture {
+    mapping: Box<dyn TextureMapping2D>,
+    cache: Arc<TextureCache>,
+    image: Arc<TiledImage>,
+    wrap_mode: WrapMode,
+    scale: f32,
+    spectrum_type: SpectrumType
+}
+
+impl TiledImageTexture {
+    /// Creates a texture from the tiled EXR file at `path`, whose values are multiplied by `scale`.
+    ///
+    /// Only the header of the file is read here, and files are only opened once per cache.
+    pub fn new<P: AsRef<Path>>(mapping: Box<dyn TextureMapping2D>, cache: Arc<TextureCache>, path: P,
+                               wrap_mode: WrapMode, scale: f32, spectrum_type: SpectrumType) -> io::Result<Self> {
+        let image = cache.open(path)?;
+        Ok(TiledImageTexture { mapping, cache, image, wrap_mode, scale, spectrum_type })
+    }
+
+    /// Creates a texture whose tiles are read from `source`.
+    pub fn from_source(mapping: Box<dyn TextureMapping2D>, cache: Arc<TextureCache>, source: Box<dyn TileSource>,
+                       wrap_mode: WrapMode, scale: f32, spectrum_type: SpectrumType) -> io::Result<Self> {
+        let image = cache.add(source)?;
+        Ok(TiledImageTexture { mapping, cache, image, wrap_mode, scale, spectrum_type })
+    }
+
+    fn lookup(&self, si: &SurfaceInteraction) -> [f32; 3] {
+        let c = self.mapping.map(si);
+        // Flip t so that the top of the image is at t = 1
+        let st = point2(c.st.x, 1.0 - c.st.y);
+
+        // Choose the levels whose texels have about the width of the footprint
+        let width = 2.0 * c.dstdx.x.abs().max(c.dstdx.y.abs()).max(c.dstdy.x.abs()).max(c.dstdy.y.abs());
+        let res = self.image.resolution(0);
+        let level = (width * res.x.max(res.y) as f32).max(1e-8).log2().clamp(0.0, (self.image.levels() - 1) as f32);
+        let i_level = level.floor() as usize;
+        let delta = level - i_level as f32;
+        let mut texel = self.bilerp(i_level, st);
+        if delta > 0.0 {
+            let upper = self.bilerp(i_level + 1, st);
+            for (t, u) in texel.iter_mut().zip(upper) {
+                *t += (u - *t) * delta;
+            }
+        }
+        texel.map(|t| t * self.scale)
+    }
+
+    fn bilerp(&self, level: usize, st: Point2f) -> [f32; 3] {
+        let res = self.image.resolution(level);
+        let x = st.x * res.x as f32 - 0.5;
+        let y = st.y * res.y as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (dx, dy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i32, y0 as i32);
+        let mut texel = [0.0; 3];
+        for (p, w) in [(Point2i::new(x0, y0), (1.0 - dx) * (1.0 - dy)), (Point2i::new(x0 + 1, y0), dx * (1.0 - dy)),
+                       (Point2i::new(x0, y0 + 1), (1.0 - dx) * dy), (Point2i::new(x0 + 1, y0 + 1), dx * dy)] {
+            if let Some(p) = self.wrap_mode.remap(p, res) {
+                for (t, v) in texel.iter_mut().zip(self.cache.texel(&self.image, level, p)) {
+                    *t += v * w;
+                }
+            }
+        }
+        texel
+    }
+}
+
+impl Texture<f32> for TiledImageTexture {
+    fn evaluate(&self, si: &SurfaceInteraction) -> f32 {
+        let rgb = self.lookup(si);
+        if self.image.n_channels() < 3 {
+            return rgb[0]
+        }
+        (rgb[0] + rgb[1] + rgb[2]) / 3.0
+    }
+}
+
+impl Texture<Spectrum> for TiledImageTexture {
+    fn evaluate(&self, si: &SurfaceInteraction) -> Spectrum {
+        Spectrum::from_rgb(self.lookup(si), self.spectrum_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use crate::texture::UvMapping;
+    use super::*;
+
+    /// A 4x4 image whose levels are constant, with the value of each level being its index.
+    struct LevelSource {
+        levels: Vec<Point2i>
+    }
+
+    impl TileSource for LevelSource {
+        fn n_channels(&self) -> usize {
+            1
+        }
+
+        fn tile_size(&self) -> Point2i {
+            Point2i::new(2, 2)
+        }
+
+        fn level_resolutions(&self) -> &[Point2i] {
+            &self.levels
+        }
+
+        fn read_tile(&self, level: usize, _tile: Point2i) -> io::Result<Vec<f32>> {
+            Ok(vec![level as f32; 4])
+        }
+    }
+
+    #[test]
+    fn test_tiled_image_texture() {
+        let source = LevelSource { levels: vec![Point2i::new(4, 4), Point2i::new(2, 2), Point2i::new(1, 1)] };
+        let cache = Arc::new(TextureCache::new(1 << 20));
+        let tex = TiledImageTexture::from_source(Box::new(UvMapping::default()), cache.clone(), Box::new(source),
+                                                 WrapMode::Clamp, 2.0, SpectrumType::Unbounded).unwrap();
+        let at = |width: f32| {
+            let si = SurfaceInteraction {
+                uv: point2(0.3, 0.6),
+                dud: Cell::new((width, 0.0)),
+                ..Default::default()
+            };
+            Texture::<f32>::evaluate(&tex, &si)
+        };
+        // A footprint of a texel of the full resolution image, of two texels and of the whole image
+        assert_eq!(at(0.125), 0.0);
+        assert_eq!(at(0.25), 2.0);
+        assert_eq!(at(1.0), 4.0);
+        assert!((at(0.125 * 2f32.sqrt()) - 1.0).abs() < 1e-5);
+        let stats = cache.stats();
+        assert_eq!(stats.resident_tiles, 4);
+        assert!(stats.hits > stats.misses);
+    }
+}
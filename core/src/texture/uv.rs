@@ -0,0 +1,22 @@
+use crate::interaction::SurfaceInteraction;
+use crate::texture::{Texture, TextureMapping2D};
+use crate::{Spectrum, SpectrumType};
+
+/// A texture for debugging mappings, which shows the fractional parts of the texture coordinates `(s, t)` in the
+/// red and green channels.
+pub struct UvTexture {
+    mapping: Box<dyn TextureMapping2D>
+}
+
+impl UvTexture {
+    pub fn new(mapping: Box<dyn TextureMapping2D>) -> Self {
+        UvTexture { mapping }
+    }
+}
+
+impl Texture<Spectrum> for UvTexture {
+    fn evaluate(&self, si: &SurfaceInteraction) -> Spectrum {
+        let st = self.mapping.map(si).st;
+        Spectrum::from_rgb([st.x - st.x.floor(), st.y - st.y.floor(), 0.0], SpectrumType::Reflectance)
+    }
+}
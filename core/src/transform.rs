@@ -1,6 +1,11 @@
-use crate::math::Matrix4x4;
-use crate::{Vector3, Point3};
+use std::cell::Cell;
+use std::ops::Mul;
+use crate::math::{gamma, Matrix4x4};
+use crate::{Bounds3f, Normal3f, Point3f, Ray, RayDifferential, Vector3f, Vector3, Point3};
+use crate::geom::{DotProduct, Normal3};
+use crate::interaction::{Shading, SurfaceInteraction};
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Transform {
     forward: Matrix4x4,
     inverse: Matrix4x4,
@@ -34,6 +39,15 @@ impl Transform {
     pub const fn transpose(&self) -> Self {
         unsafe { Self::with_inverse_unchecked(self.forward.transpose(), self.inverse.transpose()) }
     }
+
+    /// Returns `true` if applying the transform changes the handedness of the coordinate system.
+    pub fn swaps_handedness(&self) -> bool {
+        let m = &self.forward;
+        let det = m[(0, 0)] * (m[(1, 1)] * m[(2, 2)] - m[(1, 2)] * m[(2, 1)]) -
+            m[(0, 1)] * (m[(1, 0)] * m[(2, 2)] - m[(1, 2)] * m[(2, 0)]) +
+            m[(0, 2)] * (m[(1, 0)] * m[(2, 1)] - m[(1, 1)] * m[(2, 0)]);
+        det < 0.0
+    }
 }
 
 // Constructors for different transforms
@@ -132,6 +146,45 @@ impl Transform {
         );
         Self::new(camera_to_world).inverse()
     }
+
+    /// Creates an orthographic projection that maps z values in `[z_near, z_far]` to `[0, 1]`.
+    pub fn orthographic(z_near: f32, z_far: f32) -> Self {
+        Transform::scale(1.0, 1.0, 1.0 / (z_far - z_near)) * Transform::translate(Vector3::new(0.0, 0.0, -z_near))
+    }
+
+    /// Creates a perspective projection with the given field of view in degrees that maps z values in
+    /// `[near, far]` to `[0, 1]`.
+    #[rustfmt::skip]
+    pub fn perspective(fov: f32, near: f32, far: f32) -> Self {
+        let persp = Matrix4x4::new(
+            1.0, 0.0,                 0.0,                         0.0,
+            0.0, 1.0,                 0.0,                         0.0,
+            0.0, 0.0, far / (far - near), -far * near / (far - near),
+            0.0, 0.0,                 1.0,                         0.0
+        );
+        let inv_tan_ang = 1.0 / (fov.to_radians() / 2.0).tan();
+        Transform::scale(inv_tan_ang, inv_tan_ang, 1.0) * Transform::new(persp)
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    /// Composes two transforms so that `rhs` is applied first.
+    fn mul(self, rhs: Transform) -> Transform {
+        unsafe {
+            // SAFETY: (AB)^-1 = B^-1 A^-1
+            Transform::with_inverse_unchecked(self.forward * rhs.forward, rhs.inverse * self.inverse)
+        }
+    }
+}
+
+impl<'a> Mul<&'a Transform> for &'a Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: &'a Transform) -> Transform {
+        *self * *rhs
+    }
 }
 
 impl Transform {
@@ -149,4 +202,134 @@ impl Transform {
             T::from((xp / wp, yp / wp, zp / wp))
         }
     }
-}
\ No newline at end of file
+}
+impl Transform {
+    pub fn transform_point(&self, p: &Point3f) -> Point3f {
+        self.transform(*p)
+    }
+
+    pub fn transform_vector(&self, v: &Vector3f) -> Vector3f {
+        let m = &self.forward;
+        Vector3::new(
+            m[(0,0)] * v.x + m[(0,1)] * v.y + m[(0,2)] * v.z,
+            m[(1,0)] * v.x + m[(1,1)] * v.y + m[(1,2)] * v.z,
+            m[(2,0)] * v.x + m[(2,1)] * v.y + m[(2,2)] * v.z
+        )
+    }
+
+    /// Transforms a surface normal. Normals are transformed by the inverse transpose of the transformation matrix.
+    pub fn transform_normal(&self, n: &Normal3f) -> Normal3f {
+        let m = &self.inverse;
+        Normal3::new(
+            m[(0,0)] * n.x + m[(1,0)] * n.y + m[(2,0)] * n.z,
+            m[(0,1)] * n.x + m[(1,1)] * n.y + m[(2,1)] * n.z,
+            m[(0,2)] * n.x + m[(1,2)] * n.y + m[(2,2)] * n.z
+        )
+    }
+
+    /// Transforms a point and returns it together with a conservative bound on the absolute rounding error
+    /// introduced by the transformation.
+    pub fn transform_point_with_error(&self, p: &Point3f) -> (Point3f, Vector3f) {
+        let m = &self.forward;
+        let (x, y, z) = (p.x, p.y, p.z);
+        let x_abs_sum = (m[(0,0)] * x).abs() + (m[(0,1)] * y).abs() + (m[(0,2)] * z).abs() + m[(0,3)].abs();
+        let y_abs_sum = (m[(1,0)] * x).abs() + (m[(1,1)] * y).abs() + (m[(1,2)] * z).abs() + m[(1,3)].abs();
+        let z_abs_sum = (m[(2,0)] * x).abs() + (m[(2,1)] * y).abs() + (m[(2,2)] * z).abs() + m[(2,3)].abs();
+        let p_error = Vector3::new(x_abs_sum, y_abs_sum, z_abs_sum) * gamma(3);
+        (self.transform_point(p), p_error)
+    }
+
+    /// Transforms a point that already carries the absolute error `p_error` and returns it together with the
+    /// accumulated error bound.
+    pub fn transform_point_with_abs_error(&self, p: &Point3f, p_error: &Vector3f) -> (Point3f, Vector3f) {
+        let m = &self.forward;
+        let (x, y, z) = (p.x, p.y, p.z);
+        let row_error = |i: usize| {
+            (gamma(3) + 1.0) * (m[(i,0)].abs() * p_error.x + m[(i,1)].abs() * p_error.y + m[(i,2)].abs() * p_error.z) +
+                gamma(3) * ((m[(i,0)] * x).abs() + (m[(i,1)] * y).abs() + (m[(i,2)] * z).abs() + m[(i,3)].abs())
+        };
+        let abs_error = Vector3::new(row_error(0), row_error(1), row_error(2));
+        (self.transform_point(p), abs_error)
+    }
+
+    /// Transforms a vector and returns it together with a conservative bound on the absolute rounding error.
+    pub fn transform_vector_with_error(&self, v: &Vector3f) -> (Vector3f, Vector3f) {
+        let m = &self.forward;
+        let row_error = |i: usize| (m[(i,0)] * v.x).abs() + (m[(i,1)] * v.y).abs() + (m[(i,2)] * v.z).abs();
+        let abs_error = Vector3::new(row_error(0), row_error(1), row_error(2)) * gamma(3);
+        (self.transform_vector(v), abs_error)
+    }
+
+    pub fn transform_ray(&self, r: &Ray) -> Ray {
+        let (ray, _, _) = self.transform_ray_with_error(r);
+        ray
+    }
+
+    /// Transforms a ray and returns it together with the error bounds of its origin and direction.
+    ///
+    /// The origin of the transformed ray is offset to the edge of its error bounds and `tmax` is shortened
+    /// accordingly so that the ray does not start on the wrong side of a surface.
+    pub fn transform_ray_with_error(&self, r: &Ray) -> (Ray, Vector3f, Vector3f) {
+        let (mut o, o_error) = self.transform_point_with_error(&r.o);
+        let (d, d_error) = self.transform_vector_with_error(&r.d);
+        let mut tmax = r.tmax.get();
+        let length_squared = d.length_squared();
+        if length_squared > 0.0 {
+            let dt = d.abs().dot(&o_error) / length_squared;
+            o = o + d * dt;
+            tmax -= dt;
+        }
+        let ray = Ray { o, d, tmax: Cell::new(tmax), time: r.time };
+        (ray, o_error, d_error)
+    }
+
+    pub fn transform_ray_differential(&self, r: &RayDifferential) -> RayDifferential {
+        RayDifferential {
+            ray: self.transform_ray(&r.ray),
+            has_differentials: r.has_differentials,
+            rx_origin: self.transform_point(&r.rx_origin),
+            ry_origin: self.transform_point(&r.ry_origin),
+            rx_direction: self.transform_vector(&r.rx_direction),
+            ry_direction: self.transform_vector(&r.ry_direction)
+        }
+    }
+
+    pub fn transform_bounds(&self, b: &Bounds3f) -> Bounds3f {
+        (1..8).fold(Bounds3f::from(self.transform_point(&b.corner(0))), |bounds, i| {
+            bounds.union_point(&self.transform_point(&b.corner(i)))
+        })
+    }
+
+    pub fn transform_surface_interaction(&self, si: &SurfaceInteraction) -> SurfaceInteraction {
+        let (p, p_error) = self.transform_point_with_abs_error(&si.p, &si.p_error);
+        let n = self.transform_normal(&si.n).normalize();
+        let shading_n = self.transform_normal(&si.shading.n).normalize().face_forward(&n);
+        SurfaceInteraction {
+            p,
+            time: si.time,
+            p_error,
+            wo: self.transform_vector(&si.wo).normalize(),
+            n,
+            uv: si.uv,
+            dpdu: self.transform_vector(&si.dpdu),
+            dpdv: self.transform_vector(&si.dpdv),
+            dndu: self.transform_normal(&si.dndu),
+            dndv: self.transform_normal(&si.dndv),
+            shape: si.shape.clone(),
+            shading: Shading {
+                n: shading_n,
+                dpdu: self.transform_vector(&si.shading.dpdu),
+                dpdv: self.transform_vector(&si.shading.dpdv),
+                dndu: self.transform_normal(&si.shading.dndu),
+                dndv: self.transform_normal(&si.shading.dndv)
+            },
+            material: si.material.clone(),
+            // Scattering functions are only computed in world space
+            bsdf: None,
+            dpdx: Cell::new(self.transform_vector(&si.dpdx.get())),
+            dpdy: Cell::new(self.transform_vector(&si.dpdy.get())),
+            dud: si.dud.clone(),
+            dvd: si.dvd.clone()
+        }
+    }
+}
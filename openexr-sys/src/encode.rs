@@ -229,7 +229,7 @@ pub struct exr_encode_pipeline_t {
     ///
     /// This is allowed to be overridden, but probably is not necessary
     /// in most scenarios.
-    pub write_fn: Option<unsafe extern "C" fn(exr_encode_pipeline_t) -> exr_result_t>,
+    pub write_fn: Option<unsafe extern "C" fn(*mut exr_encode_pipeline_t) -> exr_result_t>,
 
     /// Small stash of channel info values. This is faster than calling
     /// malloc when the channel count in the part is small (RGBAZ),
@@ -281,7 +281,7 @@ extern "C" {
     ) -> exr_result_t;
 
     /// Execute the encoding pipeline.
-    pub fn exr_encoding_run(ctxt: exr_const_context_t, part_index: c_int, encode_pipe: exr_encode_pipeline_t) -> exr_result_t;
+    pub fn exr_encoding_run(ctxt: exr_const_context_t, part_index: c_int, encode_pipe: *mut exr_encode_pipeline_t) -> exr_result_t;
 
     /// Free any intermediate memory in the encoding pipeline.
     ///
@@ -8,8 +8,10 @@ use semver::{BuildMetadata, Prerelease, Version};
 
 use crate::sys::*;
 
+pub mod attr;
 pub mod context;
 pub mod error;
+pub mod frame_buffer;
 mod alloc;
 
 pub fn version() -> Version {
@@ -0,0 +1,226 @@
+use crate::sys::*;
+
+use exr_compression_t::*;
+use exr_pixel_type_t::*;
+use exr_storage_t::*;
+use exr_tile_level_mode_t::*;
+use exr_tile_round_mode_t::*;
+
+/// Compression method used for the pixel data of a part.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum Compression {
+    None,
+    Rle,
+    Zips,
+    Zip,
+    Piz,
+    Pxr24,
+    B44,
+    B44a,
+    Dwaa,
+    Dwab
+}
+
+impl const Default for Compression {
+    fn default() -> Self {
+        Compression::Zip
+    }
+}
+
+impl From<Compression> for exr_compression_t {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::None => EXR_COMPRESSION_NONE,
+            Compression::Rle => EXR_COMPRESSION_RLE,
+            Compression::Zips => EXR_COMPRESSION_ZIPS,
+            Compression::Zip => EXR_COMPRESSION_ZIP,
+            Compression::Piz => EXR_COMPRESSION_PIZ,
+            Compression::Pxr24 => EXR_COMPRESSION_PXR24,
+            Compression::B44 => EXR_COMPRESSION_B44,
+            Compression::B44a => EXR_COMPRESSION_B44A,
+            Compression::Dwaa => EXR_COMPRESSION_DWAA,
+            Compression::Dwab => EXR_COMPRESSION_DWAB
+        }
+    }
+}
+
+/// Data type of the samples of a channel.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum PixelType {
+    Uint,
+    Half,
+    Float
+}
+
+impl PixelType {
+    /// Size of a single sample in bytes.
+    pub const fn size(self) -> usize {
+        match self {
+            PixelType::Half => 2,
+            PixelType::Uint | PixelType::Float => 4
+        }
+    }
+}
+
+impl From<exr_pixel_type_t> for PixelType {
+    fn from(pixel_type: exr_pixel_type_t) -> Self {
+        match pixel_type {
+            EXR_PIXEL_UINT => PixelType::Uint,
+            EXR_PIXEL_HALF => PixelType::Half,
+            EXR_PIXEL_FLOAT | EXR_PIXEL_LAST_TYPE => PixelType::Float
+        }
+    }
+}
+
+impl From<PixelType> for exr_pixel_type_t {
+    fn from(pixel_type: PixelType) -> Self {
+        match pixel_type {
+            PixelType::Uint => EXR_PIXEL_UINT,
+            PixelType::Half => EXR_PIXEL_HALF,
+            PixelType::Float => EXR_PIXEL_FLOAT
+        }
+    }
+}
+
+/// An integer pixel region where both `min` and `max` are inclusive, as used for the data and display windows.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub struct Box2i {
+    pub min: [i32; 2],
+    pub max: [i32; 2]
+}
+
+impl Box2i {
+    pub const fn new(min: [i32; 2], max: [i32; 2]) -> Self {
+        Box2i { min, max }
+    }
+
+    /// Creates the region `(0, 0) - (width - 1, height - 1)`.
+    pub const fn with_size(width: i32, height: i32) -> Self {
+        Box2i { min: [0, 0], max: [width - 1, height - 1] }
+    }
+
+    pub const fn width(&self) -> i32 {
+        self.max[0] - self.min[0] + 1
+    }
+
+    pub const fn height(&self) -> i32 {
+        self.max[1] - self.min[1] + 1
+    }
+}
+
+impl From<Box2i> for exr_attr_box2i_t {
+    fn from(b: Box2i) -> Self {
+        exr_attr_box2i_t {
+            min: exr_attr_v2i_t { x: b.min[0], y: b.min[1] },
+            max: exr_attr_v2i_t { x: b.max[0], y: b.max[1] }
+        }
+    }
+}
+
+impl From<exr_attr_box2i_t> for Box2i {
+    fn from(b: exr_attr_box2i_t) -> Self {
+        Box2i { min: [b.min.x, b.min.y], max: [b.max.x, b.max.y] }
+    }
+}
+
+/// How the pixel data of a part is stored.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum Storage {
+    Scanline,
+    Tiled,
+    DeepScanline,
+    DeepTiled
+}
+
+impl From<exr_storage_t> for Storage {
+    fn from(storage: exr_storage_t) -> Self {
+        match storage {
+            EXR_STORAGE_SCANLINE | EXR_STORAGE_LAST_TYPE => Storage::Scanline,
+            EXR_STORAGE_TILED => Storage::Tiled,
+            EXR_STORAGE_DEEP_SCANLINE => Storage::DeepScanline,
+            EXR_STORAGE_DEEP_TILED => Storage::DeepTiled
+        }
+    }
+}
+
+/// The resolution levels stored by a tiled part.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum LevelMode {
+    /// Only the full resolution image.
+    One,
+    /// Levels that halve the resolution in both directions until it is one pixel.
+    Mipmap,
+    /// Levels that halve the resolution in either direction independently.
+    Ripmap
+}
+
+impl From<LevelMode> for exr_tile_level_mode_t {
+    fn from(mode: LevelMode) -> Self {
+        match mode {
+            LevelMode::One => EXR_TILE_ONE_LEVEL,
+            LevelMode::Mipmap => EXR_TILE_MIPMAP_LEVELS,
+            LevelMode::Ripmap => EXR_TILE_RIPMAP_LEVELS
+        }
+    }
+}
+
+impl From<exr_tile_level_mode_t> for LevelMode {
+    fn from(mode: exr_tile_level_mode_t) -> Self {
+        match mode {
+            EXR_TILE_ONE_LEVEL | EXR_TILE_LAST_TYPE => LevelMode::One,
+            EXR_TILE_MIPMAP_LEVELS => LevelMode::Mipmap,
+            EXR_TILE_RIPMAP_LEVELS => LevelMode::Ripmap
+        }
+    }
+}
+
+/// Whether the size of a level is rounded down or up when the size of the level above is odd.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub enum LevelRoundingMode {
+    Down,
+    Up
+}
+
+impl From<LevelRoundingMode> for exr_tile_round_mode_t {
+    fn from(mode: LevelRoundingMode) -> Self {
+        match mode {
+            LevelRoundingMode::Down => EXR_TILE_ROUND_DOWN,
+            LevelRoundingMode::Up => EXR_TILE_ROUND_UP
+        }
+    }
+}
+
+impl From<exr_tile_round_mode_t> for LevelRoundingMode {
+    fn from(mode: exr_tile_round_mode_t) -> Self {
+        match mode {
+            EXR_TILE_ROUND_DOWN | EXR_TILE_ROUND_LAST_TYPE => LevelRoundingMode::Down,
+            EXR_TILE_ROUND_UP => LevelRoundingMode::Up
+        }
+    }
+}
+
+/// Size of the tiles and the resolution levels of a tiled part.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub struct TileDescription {
+    pub x_size: u32,
+    pub y_size: u32,
+    pub level_mode: LevelMode,
+    pub rounding_mode: LevelRoundingMode
+}
+
+impl TileDescription {
+    pub const fn new(x_size: u32, y_size: u32, level_mode: LevelMode, rounding_mode: LevelRoundingMode) -> Self {
+        TileDescription { x_size, y_size, level_mode, rounding_mode }
+    }
+}
+
+/// A channel of a part.
+#[derive(Debug, Eq, PartialEq, Clone, Hash)]
+pub struct Channel {
+    pub name: String,
+    pub pixel_type: PixelType,
+    /// Whether the values are perceptually linear.
+    pub linear: bool,
+    pub x_sampling: i32,
+    pub y_sampling: i32
+}
@@ -1,11 +1,11 @@
 use std::alloc::{Allocator, Global, Layout};
-use std::ffi::CString;
-use std::{cmp, io, slice};
+use std::ffi::{CStr, CString};
+use std::{cmp, io, ptr, slice};
 use std::io::{Seek, SeekFrom, Write};
 use std::marker::PhantomData;
-use std::mem::MaybeUninit;
+use std::mem::{ManuallyDrop, MaybeUninit};
 use std::path::Path;
-use libc::{c_char, c_void, ENOTSUP, size_t};
+use libc::{c_char, c_int, c_void, ENOTSUP, size_t};
 use parking_lot::Mutex;
 use errno::{Errno, set_errno};
 
@@ -13,7 +13,9 @@ use os_str_bytes::OsStrBytes;
 use log::trace;
 
 use crate::sys::*;
+use super::attr::{Box2i, Channel, Compression, PixelType, Storage, TileDescription};
 use super::error::{Error, Result};
+use super::frame_buffer::{Slice, SliceMut};
 
 use exr_default_write_mode_t::*;
 use openexr_sys::exr_error_code_t::EXR_ERR_WRITE_IO;
@@ -45,6 +47,16 @@ impl RawContext {
     }
 }
 
+impl RawContext {
+    pub fn finish(self) -> Result<()> {
+        let mut this = ManuallyDrop::new(self);
+        unsafe {
+            trace!("exr_finish");
+            Error::from_extern(exr_finish(&mut this.0))
+        }
+    }
+}
+
 impl Drop for RawContext {
     fn drop(&mut self) {
         unsafe {
@@ -73,15 +85,253 @@ impl Into<exr_default_write_mode_t> for DefaultWriteMode {
 #[derive(Default)]
 pub struct ContextInitializer(exr_context_initializer_t);
 
-pub struct WriteContext<W: Write + Seek, A: Allocator = Global> {
-    ctxt: RawContext,
-    writer: W,
-    _phantom: PhantomData<A>
+/// Index of a part within a multi-part file; single part files only have part 0.
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Hash)]
+pub struct Part(c_int);
+
+impl Part {
+    pub const fn new(index: i32) -> Self {
+        Part(index)
+    }
+
+    pub const fn index(self) -> i32 {
+        self.0
+    }
+}
+
+/// A context for reading an EXR file.
+///
+/// Only the header is read when the file is opened. The pixel data is read on request one chunk at a time, so that
+/// the tiles of an image that does not fit in memory can be paged in as they are needed.
+pub struct ReadContext {
+    ctxt: RawContext
+}
+
+// SAFETY: the library guards the chunk table and the stream of a context that is opened for reading, so that it can
+// read chunks for several threads at once, and the context is never modified after the header has been read.
+unsafe impl Send for ReadContext {}
+unsafe impl Sync for ReadContext {}
+
+impl ReadContext {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let initializer = exr_context_initializer_t {
+            alloc_fn: Some(exr_alloc),
+            free_fn: Some(exr_free),
+            ..Default::default()
+        };
+        let ctxt = RawContext::start_read(path.as_ref(), &ContextInitializer(initializer))?;
+        Ok(ReadContext { ctxt })
+    }
+
+    pub fn part_count(&self) -> Result<usize> {
+        let mut count: c_int = 0;
+        unsafe {
+            Error::from_extern(exr_get_count(self.ctxt.0, &mut count))?;
+        }
+        Ok(count as usize)
+    }
+
+    pub fn storage(&self, part: Part) -> Result<Storage> {
+        let mut storage = exr_storage_t::EXR_STORAGE_LAST_TYPE;
+        unsafe {
+            Error::from_extern(exr_get_storage(self.ctxt.0, part.0, &mut storage))?;
+        }
+        Ok(storage.into())
+    }
+
+    pub fn data_window(&self, part: Part) -> Result<Box2i> {
+        let mut data_window: MaybeUninit<exr_attr_box2i_t> = MaybeUninit::uninit();
+        unsafe {
+            Error::from_extern(exr_get_data_window(self.ctxt.0, part.0, data_window.as_mut_ptr()))?;
+            Ok(Box2i::from(data_window.assume_init()))
+        }
+    }
+
+    /// Returns the channels of a part, sorted by name.
+    pub fn channels(&self, part: Part) -> Result<Vec<Channel>> {
+        let mut chlist: *const exr_attr_chlist_t = ptr::null();
+        unsafe {
+            exr_get_channels(self.ctxt.0, part.0, &mut chlist);
+            if chlist.is_null() {
+                return Err(Error::with_message(&"part has no channel list"))
+            }
+            let chlist = &*chlist;
+            let entries = slice::from_raw_parts(chlist.entries, chlist.num_channels as usize);
+            Ok(entries.iter().map(|entry| {
+                let name = slice::from_raw_parts(entry.name.str.cast::<u8>(), entry.name.length as usize);
+                Channel {
+                    name: String::from_utf8_lossy(name).into_owned(),
+                    pixel_type: entry.pixel_type.into(),
+                    linear: entry.p_linear != 0,
+                    x_sampling: entry.x_sampling,
+                    y_sampling: entry.y_sampling
+                }
+            }).collect())
+        }
+    }
+
+    /// Returns the tile size and levels of a tiled part.
+    pub fn tile_description(&self, part: Part) -> Result<TileDescription> {
+        let (mut x_size, mut y_size) = (0, 0);
+        let mut level_mode = exr_tile_level_mode_t::EXR_TILE_LAST_TYPE;
+        let mut rounding_mode = exr_tile_round_mode_t::EXR_TILE_ROUND_LAST_TYPE;
+        unsafe {
+            Error::from_extern(exr_get_tile_descriptor(
+                self.ctxt.0, part.0, &mut x_size, &mut y_size, &mut level_mode, &mut rounding_mode
+            ))?;
+        }
+        Ok(TileDescription::new(x_size, y_size, level_mode.into(), rounding_mode.into()))
+    }
+
+    /// Returns the number of levels in x and y of a tiled part.
+    pub fn tile_levels(&self, part: Part) -> Result<(i32, i32)> {
+        let (mut levels_x, mut levels_y) = (0, 0);
+        unsafe {
+            Error::from_extern(exr_get_tile_levels(self.ctxt.0, part.0, &mut levels_x, &mut levels_y))?;
+        }
+        Ok((levels_x, levels_y))
+    }
+
+    /// Returns the width and height of the level `(level_x, level_y)` of a tiled part.
+    pub fn level_size(&self, part: Part, level_x: i32, level_y: i32) -> Result<(i32, i32)> {
+        let (mut width, mut height) = (0, 0);
+        unsafe {
+            Error::from_extern(exr_get_level_sizes(self.ctxt.0, part.0, level_x, level_y, &mut width, &mut height))?;
+        }
+        Ok((width, height))
+    }
+
+    /// Reads the tile `(tile_x, tile_y)` of the level `(level_x, level_y)` of a tiled part, writing the samples of
+    /// each channel to the slice with the same name.
+    ///
+    /// The slices are indexed relative to the top left pixel of the tile. Tiles at the right and bottom edges of a
+    /// level only cover the part of the tile size that lies within the level, and channels without a slice are
+    /// skipped.
+    pub fn read_tile(&self, part: Part, tile_x: i32, tile_y: i32, level_x: i32, level_y: i32,
+                     slices: &mut [SliceMut<'_>]) -> Result<()> {
+        let ctxt = self.ctxt.0;
+        unsafe {
+            let mut cinfo: exr_chunk_info_t = MaybeUninit::zeroed().assume_init();
+            Error::from_extern(exr_read_tile_chunk_info(ctxt, part.0, tile_x, tile_y, level_x, level_y, &mut cinfo))?;
+            if cinfo.width <= 0 || cinfo.height <= 0 {
+                return Err(Error::with_message(&"tile is empty"))
+            }
+            for slice in slices.iter() {
+                if !slice_covers(slice.data.len(), cinfo.width, cinfo.height, slice.x_stride, slice.y_stride) {
+                    return Err(Error::with_message(&"slice is too small for the tile"))
+                }
+            }
+
+            // SAFETY: the pipeline is plain data that the library expects to be zero-initialized, and destroying it
+            // is safe even if the initialization failed part way
+            let mut decoder: exr_decode_pipeline_t = MaybeUninit::zeroed().assume_init();
+            let result = Self::decode_chunk(ctxt, part, &cinfo, [cinfo.start_x, cinfo.start_y], slices, &mut decoder);
+            trace!("exr_decoding_destroy");
+            exr_decoding_destroy(ctxt, &mut decoder);
+            result
+        }
+    }
+
+    /// Reads the complete data window of a scanline part, writing the samples of each channel to the slice with the
+    /// same name.
+    ///
+    /// The slices are indexed relative to the top left pixel of the data window, and channels without a slice are
+    /// skipped.
+    pub fn read_scanlines(&self, part: Part, slices: &mut [SliceMut<'_>]) -> Result<()> {
+        let ctxt = self.ctxt.0;
+        let data_window = self.data_window(part)?;
+        let mut lines_per_chunk: i32 = 0;
+        unsafe {
+            Error::from_extern(exr_get_scanlines_per_chunk(ctxt, part.0, &mut lines_per_chunk))?;
+        }
+
+        let (width, height) = (data_window.width(), data_window.height());
+        if width <= 0 || height <= 0 {
+            return Err(Error::with_message(&"data window is empty"))
+        }
+        for slice in slices.iter() {
+            if !slice_covers(slice.data.len(), width, height, slice.x_stride, slice.y_stride) {
+                return Err(Error::with_message(&"slice is too small for the data window"))
+            }
+        }
+
+        let origin = [data_window.min[0], data_window.min[1]];
+        let mut y = data_window.min[1];
+        while y <= data_window.max[1] {
+            unsafe {
+                let mut cinfo: exr_chunk_info_t = MaybeUninit::zeroed().assume_init();
+                Error::from_extern(exr_read_scanline_chunk_info(ctxt, part.0, y, &mut cinfo))?;
+                // SAFETY: as for tiles, the pipeline is zero-initialized and may be destroyed after a failure
+                let mut decoder: exr_decode_pipeline_t = MaybeUninit::zeroed().assume_init();
+                let result = Self::decode_chunk(ctxt, part, &cinfo, origin, slices, &mut decoder);
+                trace!("exr_decoding_destroy");
+                exr_decoding_destroy(ctxt, &mut decoder);
+                result?;
+            }
+            y += lines_per_chunk;
+        }
+        Ok(())
+    }
+
+    /// Decodes a chunk into the slices, whose first samples belong to the pixel `origin`.
+    unsafe fn decode_chunk(
+        ctxt: exr_context_t,
+        part: Part,
+        cinfo: &exr_chunk_info_t,
+        origin: [i32; 2],
+        slices: &mut [SliceMut<'_>],
+        decoder: &mut exr_decode_pipeline_t
+    ) -> Result<()> {
+        trace!("exr_decoding_initialize");
+        Error::from_extern(exr_decoding_initialize(ctxt, part.0, cinfo, decoder))?;
+
+        let channels = slice::from_raw_parts_mut(decoder.channels, decoder.channel_count as usize);
+        for channel in channels {
+            let name = CStr::from_ptr(channel.channel_name).to_bytes();
+            match slices.iter_mut().find(|slice| slice.name.as_bytes() == name) {
+                Some(slice) => {
+                    let size = slice.data.pixel_type().size();
+                    let offset = (cinfo.start_y - origin[1]) as usize * slice.y_stride +
+                        (cinfo.start_x - origin[0]) as usize * slice.x_stride;
+                    channel.user_data_type = exr_pixel_type_t::from(slice.data.pixel_type()) as u16;
+                    channel.user_bytes_per_element = size as i16;
+                    channel.user_pixel_stride = (slice.x_stride * size) as i32;
+                    channel.user_line_stride = (slice.y_stride * size) as i32;
+                    channel.ptr = slice.data.as_mut_ptr().add(offset * size);
+                },
+                // The library does not decode channels without a destination
+                None => channel.ptr = ptr::null_mut()
+            }
+        }
+
+        Error::from_extern(exr_decoding_choose_default_routines(ctxt, part.0, decoder))?;
+        trace!("exr_decoding_run");
+        Error::from_extern(exr_decoding_run(ctxt, part.0, decoder))
+    }
 }
 
-impl<W: Write + Seek> WriteContext<W, Global> {
+/// Checks that a slice of `len` samples covers a non-empty window of `width` by `height` pixels with the given
+/// strides, treating lengths that overflow as not covered.
+fn slice_covers(len: usize, width: i32, height: i32, x_stride: usize, y_stride: usize) -> bool {
+    debug_assert!(width > 0 && height > 0);
+    let required = || {
+        let y = usize::try_from(height - 1).ok()?.checked_mul(y_stride)?;
+        let x = usize::try_from(width - 1).ok()?.checked_mul(x_stride)?;
+        y.checked_add(x)?.checked_add(1)
+    };
+    matches!(required(), Some(required) if len >= required)
+}
 
+/// A context for writing an EXR file to a seekable stream.
+///
+/// Parts and their channels are defined first, after which `write_header` commits the header and the pixel data
+/// of each part can be written in order.
+pub struct WriteContext<W: Write + Seek, A: Allocator = Global> {
+    ctxt: RawContext,
+    _phantom: PhantomData<(W, A)>
+}
 
+impl<W: Write + Seek + Send> WriteContext<W, Global> {
     pub fn new(writer: W) -> Result<Self> {
         let writer = Box::into_raw(Box::new(Mutex::new(writer)));
         let mut initializer = exr_context_initializer_t::default();
@@ -89,7 +339,152 @@ impl<W: Write + Seek> WriteContext<W, Global> {
         initializer.free_fn = Some(exr_free);
         initializer.user_data = writer.cast();
         initializer.write_fn = Some(<W as WriteContextOps>::write);
-        todo!()
+        initializer.destroy_fn = Some(<W as WriteContextOps>::destroy);
+        // The filename is informational only when a custom write function is provided. If the context can not
+        // be created the writer is leaked rather than risking a double free through destroy_fn.
+        let ctxt = RawContext::start_write(Path::new("stream"), &ContextInitializer(initializer))?;
+        Ok(WriteContext { ctxt, _phantom: PhantomData })
+    }
+}
+
+impl<W: Write + Seek, A: Allocator> WriteContext<W, A> {
+    /// Adds a scanline image part with all required attributes initialized.
+    ///
+    /// The name is optional for single part files.
+    pub fn add_scanline_part(&mut self, name: Option<&str>, data_window: Box2i, display_window: Box2i, compression: Compression) -> Result<Part> {
+        let name = name.map(|name| CString::new(name).unwrap());
+        let mut index: c_int = 0;
+        unsafe {
+            trace!("exr_add_part");
+            let name_ptr = name.as_ref().map_or(ptr::null(), |name| name.as_ptr());
+            Error::from_extern(exr_add_part(self.ctxt.0, name_ptr, exr_storage_t::EXR_STORAGE_SCANLINE, &mut index))?;
+            trace!("exr_initialize_required_attr");
+            Error::from_extern(exr_initialize_required_attr(
+                self.ctxt.0,
+                index,
+                &display_window.into(),
+                &data_window.into(),
+                1.0,
+                &exr_attr_v2f_t { x: 0.0, y: 0.0 },
+                1.0,
+                exr_lineorder_t::EXR_LINEORDER_INCREASING_Y,
+                compression.into()
+            ))?;
+        }
+        Ok(Part(index))
+    }
+
+    /// Adds a channel to a part.
+    ///
+    /// `linear` hints lossy compression methods that the values are perceptually linear, which is normally not the
+    /// case for color channels.
+    pub fn add_channel(&mut self, part: Part, name: &str, pixel_type: PixelType, linear: bool) -> Result<()> {
+        let name = CString::new(name).unwrap();
+        let percept = if linear {
+            exr_perceptual_treatment_t::EXR_PERCEPTUALLY_LINEAR
+        } else {
+            exr_perceptual_treatment_t::EXR_PERCEPTUALLY_LOGARITHMIC
+        };
+        unsafe {
+            trace!("exr_add_channel");
+            Error::from_extern(exr_add_channel(self.ctxt.0, part.0, name.as_ptr(), pixel_type.into(), percept, 1, 1))
+        }
+    }
+
+    /// Writes the header, after which the part definitions can no longer be changed.
+    pub fn write_header(&mut self) -> Result<()> {
+        unsafe {
+            trace!("exr_write_header");
+            Error::from_extern(exr_write_header(self.ctxt.0))
+        }
+    }
+
+    /// Writes the complete data window of a scanline part, reading the samples of each channel from the slice
+    /// with the same name.
+    pub fn write_scanlines(&mut self, part: Part, slices: &[Slice<'_>]) -> Result<()> {
+        let ctxt = self.ctxt.0;
+        let mut data_window: MaybeUninit<exr_attr_box2i_t> = MaybeUninit::uninit();
+        let mut lines_per_chunk: i32 = 0;
+        let data_window = unsafe {
+            Error::from_extern(exr_get_data_window(ctxt, part.0, data_window.as_mut_ptr()))?;
+            Error::from_extern(exr_get_scanlines_per_chunk(ctxt, part.0, &mut lines_per_chunk))?;
+            Box2i::from(data_window.assume_init())
+        };
+
+        let (width, height) = (data_window.width(), data_window.height());
+        if width <= 0 || height <= 0 {
+            return Err(Error::with_message(&"data window is empty"))
+        }
+        for slice in slices {
+            if !slice_covers(slice.data.len(), width, height, slice.x_stride, slice.y_stride) {
+                return Err(Error::with_message(&"slice is too small for the data window"))
+            }
+        }
+
+        unsafe {
+            // SAFETY: the pipeline is plain data that the library expects to be zero-initialized
+            let mut encoder: exr_encode_pipeline_t = MaybeUninit::zeroed().assume_init();
+            let mut initialized = false;
+            let result = Self::encode_chunks(ctxt, part, &data_window, lines_per_chunk, slices, &mut encoder, &mut initialized);
+            if initialized {
+                trace!("exr_encoding_destroy");
+                exr_encoding_destroy(ctxt, &mut encoder);
+            }
+            result
+        }
+    }
+
+    unsafe fn encode_chunks(
+        ctxt: exr_context_t,
+        part: Part,
+        data_window: &Box2i,
+        lines_per_chunk: i32,
+        slices: &[Slice<'_>],
+        encoder: &mut exr_encode_pipeline_t,
+        initialized: &mut bool
+    ) -> Result<()> {
+        let mut y = data_window.min[1];
+        while y <= data_window.max[1] {
+            let mut cinfo: exr_chunk_info_t = MaybeUninit::zeroed().assume_init();
+            Error::from_extern(exr_write_scanline_chunk_info(ctxt, part.0, y, &mut cinfo))?;
+            if *initialized {
+                Error::from_extern(exr_encoding_update(ctxt, part.0, &cinfo, encoder))?;
+            } else {
+                trace!("exr_encoding_initialize");
+                Error::from_extern(exr_encoding_initialize(ctxt, part.0, &cinfo, encoder))?;
+                *initialized = true;
+            }
+
+            let channels = slice::from_raw_parts_mut(encoder.channels, encoder.channel_count as usize);
+            for channel in channels {
+                let name = CStr::from_ptr(channel.channel_name).to_bytes();
+                let slice = slices.iter()
+                    .find(|slice| slice.name.as_bytes() == name)
+                    .ok_or(Error::with_message(&"no slice for channel"))?;
+                let size = slice.data.pixel_type().size();
+                let offset = (cinfo.start_y - data_window.min[1]) as usize * slice.y_stride +
+                    (cinfo.start_x - data_window.min[0]) as usize * slice.x_stride;
+                channel.user_data_type = exr_pixel_type_t::from(slice.data.pixel_type()) as u16;
+                channel.user_bytes_per_element = size as i16;
+                channel.user_pixel_stride = (slice.x_stride * size) as i32;
+                channel.user_line_stride = (slice.y_stride * size) as i32;
+                channel.ptr = slice.data.as_ptr().add(offset * size) as *mut u8;
+            }
+
+            if encoder.convert_and_pack_fn.is_none() {
+                Error::from_extern(exr_encoding_choose_default_routines(ctxt, part.0, encoder))?;
+            }
+            Error::from_extern(exr_encoding_run(ctxt, part.0, encoder))?;
+            y += lines_per_chunk;
+        }
+        Ok(())
+    }
+
+    /// Flushes the chunk offset tables and closes the stream.
+    ///
+    /// Dropping the context also finishes the file, but any error is lost.
+    pub fn finish(self) -> Result<()> {
+        self.ctxt.finish()
     }
 }
 
@@ -102,6 +497,8 @@ trait WriteContextOps {
         offset: u64,
         error_cb: exr_stream_error_func_ptr_t
     ) -> i64;
+
+    unsafe extern "C" fn destroy(ctxt: exr_const_context_t, userdata: *mut c_void, failed: c_int);
 }
 
 impl<W: Write + Seek> WriteContextOps for W {
@@ -123,15 +520,35 @@ impl<W: Write + Seek> WriteContextOps for W {
             (error_cb.unwrap_unchecked())(ctxt, EXR_ERR_WRITE_IO as exr_result_t, message.as_ptr());
             return -1;
         }
-        match writer.write(src) {
-            Ok(written) => written as i64,
+        match writer.write_all(src) {
+            Ok(()) => src.len() as i64,
             Err(err) => {
                 // Release the mutex in case error_cb unwinds
                 drop(writer);
                 let message = CString::new(err.to_string()).unwrap();
                 (error_cb.unwrap_unchecked())(ctxt, EXR_ERR_WRITE_IO as exr_result_t, message.as_ptr());
-                return -1;
+                -1
             }
         }
     }
-}
\ No newline at end of file
+
+    default unsafe extern "C" fn destroy(_ctxt: exr_const_context_t, userdata: *mut c_void, _failed: c_int) {
+        let mut writer = Box::from_raw(userdata.cast::<Mutex<W>>());
+        let _ = writer.get_mut().flush();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_slice_covers_window() {
+        assert!(slice_covers(12, 4, 3, 1, 4));
+        assert!(!slice_covers(11, 4, 3, 1, 4));
+        // Interleaved channels only need to reach the last sample
+        assert!(slice_covers(34, 4, 3, 3, 12));
+        assert!(slice_covers(1, 1, 1, 0, 0));
+        assert!(!slice_covers(usize::MAX, 2, i32::MAX, 1, usize::MAX / 2));
+    }
+}
@@ -68,6 +68,10 @@ impl Error {
         }
         Err(Error { repr: Repr::ErrorCode(ErrorCode::from_repr(code).unwrap_or(ErrorCode::Unknown)) })
     }
+
+    pub(crate) const fn with_message(message: &'static &'static str) -> Error {
+        Error { repr: Repr::ConstMessage(message) }
+    }
 }
 
 impl fmt::Debug for Error {
@@ -102,6 +106,12 @@ impl const From<ErrorCode> for Error {
     }
 }
 
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error { repr: Repr::Io(err) }
+    }
+}
+
 enum Repr {
     ErrorCode(ErrorCode),
     Io(io::Error),
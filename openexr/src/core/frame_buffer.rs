@@ -0,0 +1,117 @@
+use super::attr::PixelType;
+
+/// Typed pixel data of a single channel.
+#[derive(Debug, Copy, Clone)]
+pub enum SliceData<'a> {
+    /// Half-precision samples stored as their raw bits.
+    Half(&'a [u16]),
+    Float(&'a [f32]),
+    Uint(&'a [u32])
+}
+
+impl<'a> SliceData<'a> {
+    pub const fn pixel_type(&self) -> PixelType {
+        match self {
+            SliceData::Half(_) => PixelType::Half,
+            SliceData::Float(_) => PixelType::Float,
+            SliceData::Uint(_) => PixelType::Uint
+        }
+    }
+
+    pub const fn len(&self) -> usize {
+        match self {
+            SliceData::Half(data) => data.len(),
+            SliceData::Float(data) => data.len(),
+            SliceData::Uint(data) => data.len()
+        }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) const fn as_ptr(&self) -> *const u8 {
+        match self {
+            SliceData::Half(data) => data.as_ptr().cast(),
+            SliceData::Float(data) => data.as_ptr().cast(),
+            SliceData::Uint(data) => data.as_ptr().cast()
+        }
+    }
+}
+
+/// Source of the samples of one channel when writing an image.
+///
+/// The sample of the pixel at `(x, y)` of the data window is read from index
+/// `(y - data_window.min.y) * y_stride + (x - data_window.min.x) * x_stride` of `data`, where the strides are
+/// counted in samples. The samples are converted to the pixel type of the channel if it differs.
+#[derive(Debug, Copy, Clone)]
+pub struct Slice<'a> {
+    pub name: &'a str,
+    pub data: SliceData<'a>,
+    pub x_stride: usize,
+    pub y_stride: usize
+}
+
+impl<'a> Slice<'a> {
+    /// Creates a slice for densely packed samples of an image `width` pixels wide with `channels` interleaved
+    /// channels, starting at the sample of channel `offset`.
+    pub fn interleaved(name: &'a str, data: SliceData<'a>, width: usize, channels: usize, offset: usize) -> Self {
+        let data = match data {
+            SliceData::Half(data) => SliceData::Half(&data[offset..]),
+            SliceData::Float(data) => SliceData::Float(&data[offset..]),
+            SliceData::Uint(data) => SliceData::Uint(&data[offset..])
+        };
+        Slice { name, data, x_stride: channels, y_stride: width * channels }
+    }
+}
+
+/// Typed destination for the pixel data of a single channel.
+#[derive(Debug)]
+pub enum SliceDataMut<'a> {
+    /// Half-precision samples stored as their raw bits.
+    Half(&'a mut [u16]),
+    Float(&'a mut [f32]),
+    Uint(&'a mut [u32])
+}
+
+impl<'a> SliceDataMut<'a> {
+    pub const fn pixel_type(&self) -> PixelType {
+        match self {
+            SliceDataMut::Half(_) => PixelType::Half,
+            SliceDataMut::Float(_) => PixelType::Float,
+            SliceDataMut::Uint(_) => PixelType::Uint
+        }
+    }
+
+    pub const fn len(&self) -> usize {
+        match self {
+            SliceDataMut::Half(data) => data.len(),
+            SliceDataMut::Float(data) => data.len(),
+            SliceDataMut::Uint(data) => data.len()
+        }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut u8 {
+        match self {
+            SliceDataMut::Half(data) => data.as_mut_ptr().cast(),
+            SliceDataMut::Float(data) => data.as_mut_ptr().cast(),
+            SliceDataMut::Uint(data) => data.as_mut_ptr().cast()
+        }
+    }
+}
+
+/// Destination of the samples of one channel when reading an image, laid out like a [`Slice`].
+///
+/// The samples are converted from the pixel type of the channel if it differs. As the slices of a read borrow their
+/// data mutably, each channel is read to a separate buffer.
+#[derive(Debug)]
+pub struct SliceMut<'a> {
+    pub name: &'a str,
+    pub data: SliceDataMut<'a>,
+    pub x_stride: usize,
+    pub y_stride: usize
+}